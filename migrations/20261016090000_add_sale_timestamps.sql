@@ -0,0 +1,9 @@
+-- Store the full, timezone-aware timestamp of a sale instead of only the
+-- civil date. Existing sales are assumed to have happened at noon UTC, which
+-- preserves their booking date.
+
+alter table sales add column created_at text not null default '';
+
+update sales set created_at = date || 'T12:00:00+00:00[UTC]';
+
+alter table sales drop column date;
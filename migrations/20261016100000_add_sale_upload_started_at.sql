@@ -0,0 +1,5 @@
+-- Remember when the upload of a sale to Vereinsflieger was started, so that
+-- an upload that was interrupted (e.g. by a crash or power loss) is not
+-- blindly repeated, which could book the sale twice.
+
+alter table sales add column upload_started_at text;
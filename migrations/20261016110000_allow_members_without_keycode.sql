@@ -0,0 +1,24 @@
+-- Allow storing members without RFID keycodes (using an empty keycode), so
+-- that they can log in with other means, e.g. a printed member card.
+
+create table members_new
+(
+    keycode text not null,
+    id text not null,
+    firstname text not null,
+    lastname text not null,
+    nickname text not null
+);
+
+insert into members_new (keycode, id, firstname, lastname, nickname)
+select keycode, id, firstname, lastname, nickname
+from members;
+
+drop table members;
+
+alter table members_new rename to members;
+
+-- Note that queries need to include `keycode != ''` for this index to be used.
+create unique index members_keycode_uindex on members (keycode) where keycode != '';
+
+create index members_id_index on members (id);
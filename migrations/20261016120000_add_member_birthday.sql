@@ -0,0 +1,3 @@
+-- Store the birthday of members to enforce age restrictions of articles.
+
+alter table members add column birthday text;
@@ -0,0 +1,10 @@
+-- Keep uploaded sales in the database for a while instead of deleting them
+-- right away, so that the purchase history of members is available locally
+-- (e.g. to enforce daily purchase limits). The unit price at the time of the
+-- sale is stored as well, since article prices might change.
+
+alter table sales add column uploaded_at text;
+
+alter table sales add column unit_price text;
+
+create index sales_member_id_index on sales (member_id);
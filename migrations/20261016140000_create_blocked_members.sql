@@ -0,0 +1,10 @@
+-- Locally maintained list of members that are not allowed to buy anything,
+-- e.g. because of unpaid bills.
+
+create table blocked_members
+(
+    member_id text not null
+        constraint blocked_members_pk
+            primary key,
+    blocked_at text not null
+);
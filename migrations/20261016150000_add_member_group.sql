@@ -0,0 +1,4 @@
+-- Store the member group (aka. "Mitgliedsstatus") of members to select
+-- group-specific article prices.
+
+alter table members add column member_group text not null default '';
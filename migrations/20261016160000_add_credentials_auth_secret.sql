@@ -0,0 +1,4 @@
+-- Store the two-factor authentication secret for Vereinsflieger API accounts
+-- that have 2FA enabled.
+
+alter table credentials add column auth_secret text;
@@ -0,0 +1,7 @@
+-- Allow storing credentials for multiple clubs, but only one set of
+-- credentials per club.
+
+delete from credentials
+where rowid not in (select min(rowid) from credentials group by club_id);
+
+create unique index credentials_club_id_uindex on credentials (club_id);
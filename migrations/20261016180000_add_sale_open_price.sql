@@ -0,0 +1,4 @@
+-- Mark sales of the open-price article, where the member entered the price
+-- manually and the total price has to be sent to Vereinsflieger explicitly.
+
+alter table sales add column open_price boolean not null default false;
@@ -0,0 +1,15 @@
+-- Persist the logged-in member and the in-progress cart, so that a purchase
+-- can be restored after a crash or power loss. The table contains at most
+-- one row.
+
+create table session
+(
+    id integer not null
+        constraint session_pk
+            primary key
+        check (id = 0),
+    member_id text not null,
+    refund boolean not null,
+    cart blob not null,
+    updated_at text not null
+);
@@ -0,0 +1,4 @@
+-- Store the transaction ID of sales that were paid by card (e.g. by guests
+-- on a SumUp terminal) instead of being booked to a member account.
+
+alter table sales add column payment_reference text;
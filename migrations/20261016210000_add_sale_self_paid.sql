@@ -0,0 +1,4 @@
+-- Mark sales of guests that paid by bank transfer or PayPal themselves, so
+-- that these payments can be reconciled with the bank statements.
+
+alter table sales add column self_paid boolean not null default false;
@@ -0,0 +1,14 @@
+-- Sales that were paid in cash are not uploaded to Vereinsflieger, but
+-- recorded in a local ledger, which is used to calculate the expected
+-- contents of the cash box.
+
+create table cash_ledger
+(
+    id text not null primary key,
+    created_at text not null,
+    member_id text not null,
+    -- `null` for entries that record the emptying of the cash box
+    article_id text,
+    amount integer not null,
+    total text not null
+);
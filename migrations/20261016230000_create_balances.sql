@@ -0,0 +1,9 @@
+-- Store the prepaid balance of members, which is topped up by buying
+-- credit articles.
+
+create table balances
+(
+    member_id text not null primary key,
+    balance text not null,
+    updated_at text not null
+);
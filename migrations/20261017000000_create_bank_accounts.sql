@@ -0,0 +1,8 @@
+-- Store the IBANs of members for SEPA direct debits. These are kept separate
+-- from the `members` table, so that they do not end up in log output.
+
+create table bank_accounts
+(
+    member_id text not null primary key,
+    iban text not null
+);
@@ -0,0 +1,9 @@
+-- Keycodes that were registered manually, e.g. because Vereinsflieger stores
+-- them in an unparsable format. These are kept separate from the `members`
+-- table, so that they are not lost on synchronization.
+
+create table manual_keycodes
+(
+    keycode text not null primary key,
+    member_id text not null
+);
@@ -0,0 +1,11 @@
+-- Store vouchers (e.g. for welcome drinks of new members), which reduce the
+-- total of a purchase by their value and can only be redeemed once.
+
+create table vouchers
+(
+    barcode text not null primary key,
+    value text not null,
+    valid_from text,
+    valid_until text,
+    redeemed_at text
+);
@@ -0,0 +1,19 @@
+-- Track the stock of articles in the fridge, which is reduced by sales, and
+-- store the results of stocktakings to find out about shrinkage.
+
+create table stock
+(
+    article_id text not null primary key,
+    amount integer not null,
+    updated_at text not null
+);
+
+create table stocktakings
+(
+    id text not null,
+    created_at text not null,
+    article_id text not null,
+    expected integer not null,
+    counted integer not null,
+    primary key (id, article_id)
+);
@@ -0,0 +1,10 @@
+-- Log who restocked which articles and when.
+
+create table restocks
+(
+    id text not null primary key,
+    created_at text not null,
+    member_id text not null,
+    article_id text not null,
+    amount integer not null
+);
@@ -0,0 +1,3 @@
+-- Store the best-before date of restocked batches of perishable articles.
+
+alter table restocks add column best_before text;
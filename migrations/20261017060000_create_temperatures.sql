@@ -0,0 +1,7 @@
+-- Log the readings of the fridge temperature sensor.
+
+create table temperatures
+(
+    measured_at text not null,
+    temperature real not null
+);
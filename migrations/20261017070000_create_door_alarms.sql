@@ -0,0 +1,7 @@
+-- Log the times at which the fridge door was left open for too long.
+
+create table door_alarms
+(
+    opened_at text not null,
+    closed_at text not null
+);
@@ -0,0 +1,25 @@
+-- Record all significant actions for dispute resolution. The log is
+-- append-only, so existing entries can not be changed or deleted.
+
+create table audit_log
+(
+    id         integer primary key autoincrement,
+    created_at text not null,
+    action     text not null,
+    member_id  text,
+    details    text not null default ''
+);
+
+create trigger audit_log_no_update
+    before update
+    on audit_log
+begin
+    select raise(abort, 'the audit log is append-only');
+end;
+
+create trigger audit_log_no_delete
+    before delete
+    on audit_log
+begin
+    select raise(abort, 'the audit log is append-only');
+end;
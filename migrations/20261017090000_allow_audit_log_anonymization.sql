@@ -0,0 +1,16 @@
+-- Allow removing the member IDs from old audit log entries for data
+-- minimization, while still rejecting any other changes.
+
+drop trigger audit_log_no_update;
+
+create trigger audit_log_no_update
+    before update
+    on audit_log
+    when new.id is not old.id
+        or new.created_at is not old.created_at
+        or new.action is not old.action
+        or new.details is not old.details
+        or new.member_id is not null
+begin
+    select raise(abort, 'the audit log is append-only');
+end;
@@ -0,0 +1,4 @@
+-- Count the failed upload attempts of each sale, so that sales that are
+-- repeatedly rejected by Vereinsflieger can be reported.
+
+alter table sales add column upload_failures integer not null default 0;
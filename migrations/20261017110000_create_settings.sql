@@ -0,0 +1,10 @@
+-- Store settings that are chosen in the UI instead of with command line
+-- options, e.g. whether the installation runs without Vereinsflieger.
+
+create table settings
+(
+    key   text not null
+        constraint settings_pk
+            primary key,
+    value text not null
+);
@@ -0,0 +1,28 @@
+-- Allow members to park their cart and resume it on their next login. The
+-- parked carts are stored next to the active session (which keeps the ID 0),
+-- with at most one parked cart per member.
+
+create table session_new
+(
+    id integer not null
+        constraint session_pk
+            primary key,
+    member_id text not null,
+    refund boolean not null,
+    cart blob not null,
+    updated_at text not null,
+    parked_at text,
+    check ((id = 0) = (parked_at is null))
+);
+
+insert into session_new (id, member_id, refund, cart, updated_at)
+select id, member_id, refund, cart, updated_at
+from session;
+
+drop table session;
+
+alter table session_new rename to session;
+
+create unique index session_parked_member_id_uindex
+    on session (member_id)
+    where parked_at is not null;
@@ -0,0 +1,4 @@
+-- Remember the Vereinsflieger cost type of sales that were booked to a club
+-- account or event cost center instead of the buying member.
+
+alter table sales add column cost_type text;
@@ -0,0 +1,10 @@
+-- Locally maintained nicknames that override the (often missing or stale)
+-- nicknames from Vereinsflieger.
+
+create table member_nicknames
+(
+    member_id text not null
+        constraint member_nicknames_pk
+            primary key,
+    nickname text not null
+);
@@ -0,0 +1,5 @@
+-- The category of the article (e.g. drinks or snacks), which is used to
+-- group the cart.
+
+alter table articles
+    add column category text;
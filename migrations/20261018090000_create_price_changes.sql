@@ -0,0 +1,13 @@
+-- Price changes of articles that were detected on synchronization, so that
+-- unexpected changes in Vereinsflieger are noticed by the admins.
+
+create table price_changes
+(
+    id          integer primary key autoincrement,
+    changed_at  text    not null,
+    article_id  text    not null,
+    designation text    not null,
+    old_price   text,
+    new_price   text,
+    seen        boolean not null default false
+);
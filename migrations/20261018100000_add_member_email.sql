@@ -0,0 +1,5 @@
+-- The email addresses of the members, if they may be stored, so that they
+-- can be contacted directly (e.g. for digital receipts or balance warnings).
+
+alter table members
+    add column email text;
@@ -0,0 +1,5 @@
+-- The Vereinsflieger booking account of the article (aka. "Sachkonto"),
+-- which is used as the default account of the DATEV export.
+
+alter table articles
+    add column account text;
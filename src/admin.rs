@@ -0,0 +1,1160 @@
+use crate::currency;
+use crate::database;
+use crate::state::Message;
+use crate::system::{self, SystemInfo};
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Row, Text};
+use iced::Length::Fixed;
+use iced::{color, Center, Element, Fill, Right};
+use rust_decimal::Decimal;
+use ulid::Ulid;
+
+/// The admin screen, which is opened by entering the admin PIN while no
+/// member is logged in.
+#[derive(Debug, Default)]
+pub struct Admin {
+    /// The current member search query.
+    pub search_query: String,
+    /// The members matching the current search query.
+    pub search_results: Vec<database::Member>,
+    /// The expected contents of the cash box, if cash payments are enabled.
+    pub cash_balance: Option<Decimal>,
+    /// Whether monthly statements can be exported.
+    pub statements_enabled: bool,
+    /// Whether members can be imported from a CSV file.
+    pub member_import_enabled: bool,
+    /// Whether members can book purchases to a cost center, or `None` if no
+    /// cost centers are configured.
+    pub cost_centers_enabled: Option<bool>,
+    /// The ongoing stocktaking ("Inventur"), if it was started.
+    pub stocktaking: Option<Stocktaking>,
+    /// The ongoing restocking ("Auffüllen"), if it was started.
+    pub restocking: Option<Restocking>,
+    /// The most recent audit log entries, if the audit log is shown.
+    pub audit_log: Option<Vec<database::AuditEntry>>,
+    /// The most recent price changes, if they are shown.
+    pub price_changes: Option<Vec<database::PriceChange>>,
+    /// The system diagnostics, if the system screen is shown.
+    pub system_info: Option<SystemInfo>,
+    /// The sales that have not been uploaded yet, if they are shown.
+    pub pending_sales: Option<PendingSales>,
+    /// The member whose local nickname is currently edited, and the entered
+    /// nickname.
+    pub nickname_edit: Option<(String, String)>,
+}
+
+/// An action that has to be confirmed with a fresh admin code, e.g. opening
+/// the admin screen or emptying the cash box, even if the admin screen is
+/// already open.
+#[derive(Debug)]
+pub struct Confirmation {
+    /// The message that runs the action once it was confirmed.
+    pub action: Message,
+    /// The entered admin code.
+    pub code: String,
+}
+
+impl Admin {
+    /// Whether scanned input is currently processed on the admin screen,
+    /// instead of being ignored.
+    pub fn is_scanning(&self) -> bool {
+        let restocking = self.restocking.as_ref();
+        self.stocktaking.is_some()
+            || restocking.is_some_and(|restocking| restocking.article.is_none())
+    }
+}
+
+/// A stocktaking, in which every physical item in the fridge is scanned
+/// once to count the stock of all articles.
+#[derive(Debug, Default)]
+pub struct Stocktaking {
+    /// The scanned articles and their counted amount, in the order in which
+    /// they were first scanned.
+    pub counted: Vec<(database::Article, i64)>,
+    /// The differences to the tracked stock together with the article
+    /// designations, once the stocktaking is finished.
+    pub report: Option<Vec<(String, database::StockDifference)>>,
+}
+
+/// A restocking, in which the member who fills the fridge scans their RFID
+/// chip and then each article, followed by the number of units.
+#[derive(Debug, Default)]
+pub struct Restocking {
+    /// The member who restocks the fridge, once they scanned their chip.
+    pub member: Option<database::Member>,
+    /// The scanned article, while its number of units is entered.
+    pub article: Option<database::Article>,
+    /// The number of units entered on the numpad.
+    pub quantity: String,
+    /// The best-before date of the scanned article as `DDMMYY`, while it is
+    /// entered on the numpad.
+    pub best_before: Option<String>,
+    /// The designations and amounts of the restocked articles.
+    pub restocked: Vec<(String, i64)>,
+}
+
+/// The maximum number of digits of the restocked number of units.
+const MAX_QUANTITY_DIGITS: usize = 3;
+
+impl Restocking {
+    /// Parse the entered number of units, which must be positive.
+    pub fn parse_quantity(input: &str) -> Option<i64> {
+        if input.is_empty() || input.len() > MAX_QUANTITY_DIGITS {
+            return None;
+        }
+
+        input.parse().ok().filter(|quantity| *quantity > 0)
+    }
+
+    /// Parse the entered best-before date in the `DDMMYY` format.
+    pub fn parse_best_before(input: &str) -> Option<jiff::civil::Date> {
+        if input.len() != 6 || !input.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let day = input[0..2].parse().ok()?;
+        let month = input[2..4].parse().ok()?;
+        let year = 2000 + input[4..6].parse::<i16>().ok()?;
+        jiff::civil::Date::new(year, month, day).ok()
+    }
+}
+
+/// The sales that have not been uploaded to Vereinsflieger yet, so that
+/// obvious mistakes can be corrected before they are booked.
+#[derive(Debug, Default)]
+pub struct PendingSales {
+    /// The pending sales together with their article designations, oldest
+    /// first.
+    pub sales: Vec<(database::Sale, String)>,
+    /// The sale that is currently edited.
+    pub editing: Option<PendingSaleEdit>,
+}
+
+/// The new values of an edited pending sale.
+#[derive(Debug)]
+pub struct PendingSaleEdit {
+    pub id: Ulid,
+    /// The amount of articles, which keeps the sign of the original amount.
+    pub amount: i32,
+    pub member_id: String,
+}
+
+/// The result of saving an edited pending sale.
+#[derive(Debug, Clone, Copy)]
+pub enum PendingSaleUpdate {
+    Saved,
+    /// There is no member with the entered member ID.
+    UnknownMember,
+    /// The sale was uploaded or its upload started in the meantime.
+    Uploading,
+}
+
+impl PendingSales {
+    /// Whether the sale can be edited. Sales that were paid by card, bank
+    /// transfer or PayPal are not booked to a member account and their
+    /// payment can't be changed anymore.
+    pub fn is_editable(sale: &database::Sale) -> bool {
+        sale.upload_started_at.is_none()
+            && sale.payment_reference.is_none()
+            && !sale.self_paid
+            && !sale.member_id.is_empty()
+    }
+
+    /// Describe the changes of the edited sale for the audit log, e.g.
+    /// `01JN… Cola: 2x → 3x, Mitglied 11011 → 11012`.
+    pub fn describe_edit(&self) -> Option<String> {
+        let edit = self.editing.as_ref()?;
+        let (sale, designation) = self.sales.iter().find(|(sale, _)| *sale.id == edit.id)?;
+
+        let mut changes = Vec::new();
+        if sale.amount != edit.amount {
+            changes.push(format!("{}x → {}x", sale.amount, edit.amount));
+        }
+        if sale.member_id != edit.member_id {
+            changes.push(format!("Mitglied {} → {}", sale.member_id, edit.member_id));
+        }
+
+        Some(format!("{} {designation}: {}", edit.id, changes.join(", ")))
+    }
+}
+
+/// Render the pending sales with the controls to edit one of them.
+fn pending_sales_view(pending: &PendingSales) -> Element<'_, Message> {
+    let title = text("Offene Verkäufe").size(36).width(Fill);
+
+    let rows = column(pending.sales.iter().map(|(sale, designation)| {
+        let created_at = sale.created_at.strftime("%d.%m. %H:%M").to_string();
+        let created_at = text(created_at).size(24).width(Fixed(150.));
+        let designation = text(designation).size(24).width(Fill);
+
+        let edit = pending.editing.as_ref().filter(|edit| edit.id == *sale.id);
+        let Some(edit) = edit else {
+            if sale.upload_started_at.is_some() {
+                return interrupted_sale_row(sale, created_at, designation);
+            }
+
+            let edit_button = PendingSales::is_editable(sale).then(|| {
+                button(text("Ändern").color(color!(0xffffff)).size(18))
+                    .style(button::secondary)
+                    .padding([5, 10])
+                    .on_press(Message::EditPendingSale(*sale.id))
+            });
+
+            return Row::with_capacity(5)
+                .push(created_at)
+                .push(designation)
+                .push(text(format!("{}x", sale.amount)).size(24).width(Fixed(80.)))
+                .push(text(&sale.member_id).size(24).width(Fixed(100.)))
+                .extend(edit_button.map(Into::into))
+                .spacing(20)
+                .align_y(Center)
+                .into();
+        };
+
+        // The amount can't become zero, refunds stay refunds
+        let decrement = edit.amount - 1;
+        let decrement_button = button(text("−").color(color!(0xffffff)).size(24))
+            .style(button::secondary)
+            .padding([0, 15])
+            .on_press_maybe((decrement != 0).then_some(Message::SetPendingSaleAmount(decrement)));
+
+        let increment = edit.amount + 1;
+        let increment_button = button(text("+").color(color!(0xffffff)).size(24))
+            .style(button::secondary)
+            .padding([0, 15])
+            .on_press_maybe((increment != 0).then_some(Message::SetPendingSaleAmount(increment)));
+
+        let member_input = text_input("Mitgliedsnummer", &edit.member_id)
+            .on_input(Message::SetPendingSaleMember)
+            .on_submit(Message::SavePendingSale)
+            .size(24)
+            .width(Fixed(150.));
+
+        let save_button = button(text("Speichern").color(color!(0xffffff)).size(18))
+            .style(button::primary)
+            .padding([5, 10])
+            .on_press(Message::SavePendingSale);
+
+        let cancel_button = button(text("Abbrechen").color(color!(0xffffff)).size(18))
+            .style(button::secondary)
+            .padding([5, 10])
+            .on_press(Message::CancelPendingSaleEdit);
+
+        row![
+            created_at,
+            designation,
+            decrement_button,
+            text(format!("{}x", edit.amount)).size(24),
+            increment_button,
+            member_input,
+            save_button,
+            cancel_button,
+        ]
+        .spacing(20)
+        .align_y(Center)
+        .into()
+    }))
+    .spacing(5);
+
+    let back_button = button(
+        text("Zurück")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::ClosePendingSales);
+
+    column![
+        title,
+        scrollable(rows).height(Fill).width(Fill),
+        back_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// Ask for a fresh admin code before running an admin action.
+pub fn confirmation_view(confirmation: &Confirmation) -> Element<'_, Message> {
+    let title = text("Bestätigung erforderlich").size(36).width(Fill);
+    let hint = text("Bitte einen neuen Admin-Code eingeben").size(24);
+
+    let code_input = text_input("Admin-Code", &confirmation.code)
+        .on_input(Message::SetConfirmationCode)
+        .on_submit(Message::SubmitConfirmationCode)
+        .secure(true)
+        .size(24)
+        .width(Fixed(300.));
+
+    let confirm_button = button(text("Bestätigen").color(color!(0xffffff)).size(24))
+        .style(button::danger)
+        .padding([10, 20])
+        .on_press(Message::SubmitConfirmationCode);
+
+    let cancel_button = button(text("Abbrechen").color(color!(0xffffff)).size(24))
+        .style(button::secondary)
+        .padding([10, 20])
+        .on_press(Message::CancelConfirmation);
+
+    column![
+        title,
+        hint,
+        code_input,
+        row![confirm_button, cancel_button].spacing(20),
+    ]
+    .spacing(20)
+    .padding([20, 30])
+    .into()
+}
+
+/// Render a sale whose upload was interrupted, so that an admin can check
+/// in Vereinsflieger whether it arrived and decide how to continue.
+fn interrupted_sale_row<'a>(
+    sale: &'a database::Sale,
+    created_at: Text<'a>,
+    designation: Text<'a>,
+) -> Element<'a, Message> {
+    let resend_button = button(text("Erneut senden").color(color!(0xffffff)).size(18))
+        .style(button::primary)
+        .padding([5, 10])
+        .on_press(Message::ResolveInterruptedSale(*sale.id, true));
+
+    let discard_button = button(text("Verwerfen").color(color!(0xffffff)).size(18))
+        .style(button::danger)
+        .padding([5, 10])
+        .on_press(Message::ResolveInterruptedSale(*sale.id, false));
+
+    row![
+        created_at,
+        designation,
+        text(format!("{}x", sale.amount)).size(24).width(Fixed(80.)),
+        text(&sale.member_id).size(24).width(Fixed(100.)),
+        text("Upload unterbrochen").size(18).color(color!(0xff8800)),
+        resend_button,
+        discard_button,
+    ]
+    .spacing(20)
+    .align_y(Center)
+    .into()
+}
+
+/// Render the most recent audit log entries, newest first.
+fn audit_log_view(entries: &[database::AuditEntry]) -> Element<'_, Message> {
+    let title = text("Protokoll").size(36).width(Fill);
+
+    let rows = column(entries.iter().map(|entry| {
+        let created_at = entry.created_at.to_zoned(jiff::tz::TimeZone::system());
+        row![
+            text(created_at.strftime("%d.%m. %H:%M:%S").to_string())
+                .size(18)
+                .width(Fixed(150.)),
+            text(&entry.action).size(18).width(Fixed(180.)),
+            text(entry.member_id.as_deref().unwrap_or_default())
+                .size(18)
+                .width(Fixed(100.)),
+            text(&entry.details).size(18).width(Fill),
+        ]
+        .spacing(20)
+        .into()
+    }))
+    .spacing(5);
+
+    let back_button = button(
+        text("Zurück")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::CloseAuditLog);
+
+    column![
+        title,
+        scrollable(rows).height(Fill).width(Fill),
+        back_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// Render the most recent price changes, newest first.
+fn price_changes_view(changes: &[database::PriceChange]) -> Element<'_, Message> {
+    let title = text("Preisänderungen").size(36).width(Fill);
+
+    let rows = column(changes.iter().map(|change| {
+        let changed_at = change.changed_at.to_zoned(jiff::tz::TimeZone::system());
+        row![
+            text(changed_at.strftime("%d.%m. %H:%M").to_string())
+                .size(18)
+                .width(Fixed(150.)),
+            text(&change.article_id).size(18).width(Fixed(180.)),
+            text(change.label()).size(18).width(Fill),
+        ]
+        .spacing(20)
+        .into()
+    }))
+    .spacing(5);
+
+    let back_button = button(
+        text("Zurück")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::ClosePriceChanges);
+
+    column![
+        title,
+        scrollable(rows).height(Fill).width(Fill),
+        back_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// Render the system diagnostics.
+fn system_view(info: &SystemInfo) -> Element<'_, Message> {
+    let title = text("System").size(36).width(Fill);
+
+    let timestamp = |timestamp: Option<jiff::Timestamp>| match timestamp {
+        Some(timestamp) => {
+            let timestamp = timestamp.to_zoned(jiff::tz::TimeZone::system());
+            timestamp.strftime("%d.%m. %H:%M").to_string()
+        }
+        None => "noch nicht".to_string(),
+    };
+    let bytes = |bytes: Option<u64>| bytes.map_or("unbekannt".to_string(), system::format_bytes);
+
+    let health = &info.health;
+    let uptime = jiff::Timestamp::now().duration_since(info.started_at);
+    let system_uptime = info
+        .system_uptime
+        .map_or("unbekannt".to_string(), system::format_duration);
+    let memory = format!(
+        "{} belegt, {} verfügbar",
+        bytes(info.memory_usage),
+        bytes(info.memory_available)
+    );
+    let update_check = match &info.update_check {
+        Some(check) => format!("{} ({})", timestamp(Some(check.checked_at)), check.result),
+        None => "noch nicht".to_string(),
+    };
+    let vereinsflieger = match (info.online, health.rate_limited_until) {
+        (false, _) => "nicht erreichbar".to_string(),
+        (true, Some(until)) => format!("Limit erreicht, pausiert bis {}", timestamp(Some(until))),
+        (true, None) => "erreichbar".to_string(),
+    };
+    let pending_sales = health
+        .pending_sales
+        .map_or("unbekannt".to_string(), |count| count.to_string());
+    let logins = info
+        .usage
+        .as_ref()
+        .map_or("unbekannt".to_string(), |usage| {
+            let days = usage
+                .logins_per_day
+                .iter()
+                .map(|(date, count)| format!("{}: {count}", date.strftime("%d.%m.")));
+            days.collect::<Vec<_>>().join(", ")
+        });
+    let busiest_hours = info
+        .usage
+        .as_ref()
+        .map_or("unbekannt".to_string(), |usage| {
+            let hours = usage.busiest_hours(3);
+            if hours.is_empty() {
+                return "noch keine Verkäufe".to_string();
+            }
+            let hours = hours
+                .iter()
+                .map(|(hour, amount)| format!("{hour}–{} Uhr ({amount})", hour + 1));
+            hours.collect::<Vec<_>>().join(", ")
+        });
+
+    let rows = [
+        ("Version", format!("v{}", health.version)),
+        ("Laufzeit", system::format_duration(uptime)),
+        ("System-Laufzeit", system_uptime),
+        ("Datenbank", bytes(info.database_size)),
+        ("Freier Speicherplatz", bytes(info.free_disk_space)),
+        ("Arbeitsspeicher", memory),
+        ("Update-Prüfung", update_check),
+        ("Vereinsflieger", vereinsflieger),
+        ("Artikel-Sync", timestamp(health.last_article_sync)),
+        ("Mitglieder-Sync", timestamp(health.last_member_sync)),
+        ("Verkäufe hochgeladen", timestamp(health.last_sales_upload)),
+        ("Offene Verkäufe", pending_sales),
+        ("Anmeldungen", logins),
+        ("Stoßzeiten", busiest_hours),
+    ];
+
+    let rows = column(rows.into_iter().map(|(label, value)| {
+        row![
+            text(label).size(24).width(Fixed(300.)),
+            text(value).size(24).width(Fill),
+        ]
+        .spacing(20)
+        .into()
+    }))
+    .spacing(5);
+
+    let back_button = button(
+        text("Zurück")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::CloseSystemInfo);
+
+    column![
+        title,
+        scrollable(rows).height(Fill).width(Fill),
+        back_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+impl Stocktaking {
+    /// Count the given number of units of a scanned article.
+    pub fn count(&mut self, article: database::Article, amount: i64) {
+        match self
+            .counted
+            .iter_mut()
+            .find(|(counted_article, _)| counted_article.id == article.id)
+        {
+            Some((_, counted)) => *counted += amount,
+            None => self.counted.push((article, amount)),
+        }
+    }
+}
+
+impl Admin {
+    /// Render the admin screen, showing a warning if the Vereinsflieger sync
+    /// is paused because of rate limiting or articles expire soon, and a
+    /// notification about unseen price changes.
+    pub fn view<'a>(
+        &'a self,
+        rate_limited_until: Option<jiff::Timestamp>,
+        expiring_batches: &'a [database::ExpiringBatch],
+        unseen_price_changes: u32,
+    ) -> Element<'a, Message> {
+        if let Some(stocktaking) = &self.stocktaking {
+            return stocktaking.view();
+        }
+        if let Some(restocking) = &self.restocking {
+            return restocking.view();
+        }
+        if let Some(entries) = &self.audit_log {
+            return audit_log_view(entries);
+        }
+        if let Some(changes) = &self.price_changes {
+            return price_changes_view(changes);
+        }
+        if let Some(info) = &self.system_info {
+            return system_view(info);
+        }
+        if let Some(pending) = &self.pending_sales {
+            return pending_sales_view(pending);
+        }
+
+        let title = text("Administration").size(36).width(Fill);
+
+        let rate_limit_warning = rate_limited_until.map(|until| {
+            let until = until.to_zoned(jiff::tz::TimeZone::system());
+            text(format!(
+                "Vereinsflieger-Limit erreicht, Sync pausiert bis {}",
+                until.strftime("%H:%M")
+            ))
+            .color(color!(0xffee12))
+            .size(24)
+        });
+
+        let expiry_warnings = expiring_batches.iter().map(|batch| {
+            text(format!("Läuft bald ab: {}", batch.label()))
+                .color(color!(0xffee12))
+                .size(24)
+                .into()
+        });
+
+        let price_change_notice = (unseen_price_changes > 0).then(|| {
+            let label = match unseen_price_changes {
+                1 => "1 Preis geändert".to_string(),
+                count => format!("{count} Preise geändert"),
+            };
+            let show_button = button(text("Anzeigen").color(color!(0xffffff)).size(18))
+                .style(button::secondary)
+                .padding([5, 10])
+                .on_press(Message::ShowPriceChanges);
+
+            row![
+                text(label).color(color!(0xffee12)).size(24).width(Fill),
+                show_button,
+            ]
+            .spacing(20)
+            .align_y(Center)
+        });
+
+        let search_input = text_input("Mitglied suchen (Name)", &self.search_query)
+            .on_input(Message::SetMemberSearch)
+            .size(24)
+            .width(Fill);
+
+        let audit_log_button = button(text("Protokoll").color(color!(0xffffff)).size(18))
+            .style(button::secondary)
+            .padding([5, 10])
+            .on_press(Message::ShowAuditLog);
+
+        let pending_sales_button = button(text("Offene Verkäufe").color(color!(0xffffff)).size(18))
+            .style(button::secondary)
+            .padding([5, 10])
+            .on_press(Message::ShowPendingSales);
+
+        let system_button = button(text("System").color(color!(0xffffff)).size(18))
+            .style(button::secondary)
+            .padding([5, 10])
+            .on_press(Message::ShowSystemInfo);
+
+        let screenshot_button = button(text("Bildschirmfoto").color(color!(0xffffff)).size(18))
+            .style(button::secondary)
+            .padding([5, 10])
+            .on_press(Message::TakeScreenshot);
+
+        let nickname_edit = self.nickname_edit.as_ref();
+        let results = self.search_results.iter().map(|member| {
+            match nickname_edit.filter(|(member_id, _)| *member_id == member.id) {
+                Some((_, nickname)) => nickname_row(member, nickname),
+                None => member_row(member),
+            }
+        });
+        let results = column(results).spacing(10);
+
+        let back_button = button(
+            text("Zurück")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::danger)
+        .padding([10, 20])
+        .on_press(Message::CloseAdmin);
+
+        let add_club_button = button(
+            text("Verein hinzufügen")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::secondary)
+        .padding([10, 20])
+        .on_press(Message::AddClub);
+
+        let cash_row = self.cash_balance.map(|balance| {
+            let empty_button = button(text("Kasse geleert").color(color!(0xffffff)).size(18))
+                .style(button::danger)
+                .padding([5, 10])
+                .on_press(Message::EmptyCashBox);
+
+            row![
+                text(format!("Kassenbestand: {}", currency::format(balance)))
+                    .size(24)
+                    .width(Fill),
+                empty_button,
+            ]
+            .spacing(20)
+            .align_y(Center)
+        });
+
+        let cost_center_row = self.cost_centers_enabled.map(|enabled| {
+            let (status, label) = match enabled {
+                true => ("Kostenstellen: freigegeben", "Sperren"),
+                false => ("Kostenstellen: gesperrt", "Freigeben"),
+            };
+            let toggle_button = button(text(label).color(color!(0xffffff)).size(18))
+                .style(button::secondary)
+                .padding([5, 10])
+                .on_press(Message::ToggleCostCenters);
+
+            row![text(status).size(24).width(Fill), toggle_button]
+                .spacing(20)
+                .align_y(Center)
+        });
+
+        let statements_button = self.statements_enabled.then(|| {
+            button(
+                text("Monatsabrechnung")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press(Message::ExportStatements)
+        });
+
+        let member_import_button = self.member_import_enabled.then(|| {
+            button(
+                text("Mitglieder importieren")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press(Message::ImportMembers)
+        });
+
+        let stocktaking_button = button(
+            text("Inventur")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::secondary)
+        .padding([10, 20])
+        .on_press(Message::StartStocktaking);
+
+        let restocking_button = button(
+            text("Auffüllen")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::secondary)
+        .padding([10, 20])
+        .on_press(Message::StartRestocking);
+
+        let buttons = Row::with_capacity(6)
+            .push(add_club_button)
+            .push(restocking_button)
+            .push(stocktaking_button)
+            .extend(statements_button.map(Into::into))
+            .extend(member_import_button.map(Into::into))
+            .push(back_button)
+            .spacing(10);
+
+        column![title]
+            .extend(rate_limit_warning.map(Into::into))
+            .extend(expiry_warnings)
+            .extend(price_change_notice.map(Into::into))
+            .push(
+                row![
+                    search_input,
+                    audit_log_button,
+                    pending_sales_button,
+                    system_button,
+                    screenshot_button
+                ]
+                .spacing(20)
+                .align_y(Center),
+            )
+            .push(scrollable(results).height(Fill).width(Fill))
+            .extend(cash_row.map(Into::into))
+            .extend(cost_center_row.map(Into::into))
+            .push(buttons)
+            .spacing(10)
+            .padding([20, 30])
+            .into()
+    }
+}
+
+impl Stocktaking {
+    /// Render the list of counted articles, or the shrinkage report once
+    /// the stocktaking is finished.
+    fn view(&self) -> Element<'_, Message> {
+        let title = text("Inventur").size(36).width(Fill);
+
+        let Some(report) = &self.report else {
+            let hint = text("Bitte alle Artikel im Kühlschrank einzeln scannen")
+                .size(24)
+                .color(color!(0x888888));
+
+            let rows = column(self.counted.iter().map(|(article, counted)| {
+                row![
+                    text(format!("{counted}x"))
+                        .size(24)
+                        .width(Fixed(60.))
+                        .align_x(Right),
+                    text(&article.designation).size(24).width(Fill),
+                ]
+                .spacing(20)
+                .into()
+            }))
+            .spacing(10);
+
+            let finish_button = button(
+                text("Abschließen")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::success)
+            .padding([10, 20])
+            .on_press(Message::FinishStocktaking);
+
+            let cancel_button = button(
+                text("Abbrechen")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::danger)
+            .padding([10, 20])
+            .on_press(Message::CloseStocktaking);
+
+            return column![
+                title,
+                hint,
+                scrollable(rows).height(Fill).width(Fill),
+                row![finish_button, cancel_button].spacing(10),
+            ]
+            .spacing(10)
+            .padding([20, 30])
+            .into();
+        };
+
+        let rows = column(report.iter().map(|(designation, difference)| {
+            let shrinkage = difference.shrinkage();
+            let shrinkage_color = match shrinkage {
+                1.. => color!(0xff4444),
+                _ => color!(0x888888),
+            };
+
+            row![
+                text(designation).size(24).width(Fill),
+                text(format!("Soll {}", difference.expected))
+                    .size(24)
+                    .width(Fixed(120.)),
+                text(format!("Ist {}", difference.counted))
+                    .size(24)
+                    .width(Fixed(120.)),
+                text(format!("Differenz {:+}", -shrinkage))
+                    .size(24)
+                    .color(shrinkage_color)
+                    .width(Fixed(160.)),
+            ]
+            .spacing(20)
+            .into()
+        }))
+        .spacing(10);
+
+        let done_button = button(
+            text("Fertig")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::primary)
+        .padding([10, 20])
+        .on_press(Message::CloseStocktaking);
+
+        column![
+            title,
+            scrollable(rows).height(Fill).width(Fill),
+            done_button
+        ]
+        .spacing(10)
+        .padding([20, 30])
+        .into()
+    }
+}
+
+impl Restocking {
+    /// Render the prompt for the next scan, or the numpad to enter the
+    /// number of units of the scanned article.
+    fn view(&self) -> Element<'_, Message> {
+        if let Some(article) = &self.article {
+            return restock_numpad_view(self, article);
+        }
+
+        let title = text("Auffüllen").size(36).width(Fill);
+
+        let hint = match &self.member {
+            None => "Bitte RFID Chip scannen".to_string(),
+            Some(member) => format!(
+                "{} {} – Artikel scannen bitte",
+                member.firstname, member.lastname
+            ),
+        };
+        let hint = text(hint).size(24).color(color!(0x888888));
+
+        let rows = column(self.restocked.iter().map(|(designation, amount)| {
+            row![
+                text(format!("+{amount}"))
+                    .size(24)
+                    .width(Fixed(60.))
+                    .align_x(Right),
+                text(designation).size(24).width(Fill),
+            ]
+            .spacing(20)
+            .into()
+        }))
+        .spacing(10);
+
+        let done_button = button(
+            text("Fertig")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::primary)
+        .padding([10, 20])
+        .on_press(Message::CloseRestocking);
+
+        column![
+            title,
+            hint,
+            scrollable(rows).height(Fill).width(Fill),
+            done_button
+        ]
+        .spacing(10)
+        .padding([20, 30])
+        .into()
+    }
+}
+
+/// The numpad that is used to enter the number of restocked units of the
+/// scanned article, followed by its best-before date if expiry tracking
+/// is enabled.
+fn restock_numpad_view<'a>(
+    restocking: &'a Restocking,
+    article: &'a database::Article,
+) -> Element<'a, Message> {
+    let (title, display, numpad, valid) = match &restocking.best_before {
+        Some(input) => (
+            format!("{} – Mindesthaltbarkeit (TTMMJJ)", article.designation),
+            format_best_before_input(input),
+            numpad(
+                input,
+                |value| value.len() <= 6 && value.chars().all(|c| c.is_ascii_digit()),
+                Message::SetRestockBestBefore,
+            ),
+            Restocking::parse_best_before(input).is_some(),
+        ),
+        None => {
+            let input = &restocking.quantity;
+            (
+                format!("{} – Anzahl eingeben", article.designation),
+                if input.is_empty() { "0" } else { input }.to_string(),
+                numpad(
+                    input,
+                    |value| value.is_empty() || Restocking::parse_quantity(value).is_some(),
+                    Message::SetRestockQuantity,
+                ),
+                Restocking::parse_quantity(input).is_some(),
+            )
+        }
+    };
+
+    let title = text(title).size(36).width(Fill);
+    let display = text(display).size(48).width(Fill).align_x(Right);
+
+    let cancel_button = button(
+        text("Abbruch")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::danger)
+    .padding([10, 20])
+    .on_press(Message::CancelRestockArticle);
+
+    let skip_button = restocking.best_before.as_ref().map(|_| {
+        button(
+            text("Ohne MHD")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::secondary)
+        .padding([10, 20])
+        .on_press(Message::SkipRestockBestBefore)
+    });
+
+    let confirm_button = button(
+        text("Auffüllen")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::success)
+    .padding([10, 20])
+    .on_press_maybe(valid.then_some(Message::ConfirmRestock));
+
+    let buttons = Row::with_capacity(3)
+        .push(cancel_button)
+        .extend(skip_button.map(Into::into))
+        .push(confirm_button)
+        .spacing(10);
+
+    column![
+        title,
+        display,
+        container(numpad).width(Fill).height(Fill).align_x(Center),
+        buttons,
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// A numpad with digits and a backspace key, which only allows inputs
+/// that pass the `is_valid` check.
+fn numpad<'a>(
+    input: &str,
+    is_valid: fn(&str) -> bool,
+    on_input: fn(String) -> Message,
+) -> Element<'a, Message> {
+    let key = |label: &'static str, value: String| {
+        let valid = is_valid(&value);
+        button(text(label).size(36).width(Fill).align_x(Center))
+            .width(Fixed(120.))
+            .padding([10, 20])
+            .style(button::secondary)
+            .on_press_maybe(valid.then(|| on_input(value)))
+    };
+
+    let digit = |label: &'static str| key(label, format!("{input}{label}"));
+
+    let mut backspace = input.chars();
+    backspace.next_back();
+
+    column![
+        row![digit("7"), digit("8"), digit("9")].spacing(10),
+        row![digit("4"), digit("5"), digit("6")].spacing(10),
+        row![digit("1"), digit("2"), digit("3")].spacing(10),
+        row![digit("0"), key("⌫", backspace.as_str().to_string())].spacing(10),
+    ]
+    .spacing(10)
+    .into()
+}
+
+/// Format a (partially) entered best-before date as `TT.MM.JJ`, with
+/// placeholders for the missing digits.
+fn format_best_before_input(input: &str) -> String {
+    let mut digits = input.chars();
+    let mut display = String::new();
+    for (i, placeholder) in "TTMMJJ".chars().enumerate() {
+        if i == 2 || i == 4 {
+            display.push('.');
+        }
+        display.push(digits.next().unwrap_or(placeholder));
+    }
+    display
+}
+
+fn member_row(member: &database::Member) -> Element<'_, Message> {
+    let name = if member.nickname.is_empty() {
+        format!("{} {}", member.firstname, member.lastname)
+    } else {
+        format!(
+            "{} {} ({})",
+            member.firstname, member.lastname, member.nickname
+        )
+    };
+
+    let login_button = button(text("Anmelden").color(color!(0xffffff)).size(18))
+        .style(button::primary)
+        .padding([5, 10])
+        .on_press(Message::AdminLogin(member.clone()));
+
+    let refund_button = button(text("Erstatten").color(color!(0xffffff)).size(18))
+        .style(button::secondary)
+        .padding([5, 10])
+        .on_press(Message::AdminRefund(member.clone()));
+
+    let nickname_button = button(text("Spitzname").color(color!(0xffffff)).size(18))
+        .style(button::secondary)
+        .padding([5, 10])
+        .on_press(Message::EditNickname(member.clone()));
+
+    let block_label = if member.blocked {
+        "Entsperren"
+    } else {
+        "Sperren"
+    };
+    let block_button = button(text(block_label).color(color!(0xffffff)).size(18))
+        .style(button::danger)
+        .padding([5, 10])
+        .on_press(Message::SetMemberBlocked {
+            member_id: member.id.clone(),
+            blocked: !member.blocked,
+        });
+
+    container(
+        row![
+            text(name).size(24).width(Fill),
+            text(&member.id)
+                .size(24)
+                .color(color!(0x888888))
+                .width(Fixed(100.)),
+            nickname_button,
+            block_button,
+            refund_button,
+            login_button,
+        ]
+        .spacing(20)
+        .align_y(Center),
+    )
+    .into()
+}
+
+/// The row of a member whose local nickname is edited. An empty nickname
+/// restores the nickname from Vereinsflieger.
+fn nickname_row<'a>(member: &'a database::Member, nickname: &'a str) -> Element<'a, Message> {
+    let name = format!("{} {}", member.firstname, member.lastname);
+
+    let nickname_input = text_input("Spitzname", nickname)
+        .on_input(Message::SetNickname)
+        .on_submit(Message::SaveNickname)
+        .size(24)
+        .width(Fill);
+
+    let save_button = button(text("Speichern").color(color!(0xffffff)).size(18))
+        .style(button::primary)
+        .padding([5, 10])
+        .on_press(Message::SaveNickname);
+
+    let cancel_button = button(text("Abbrechen").color(color!(0xffffff)).size(18))
+        .style(button::secondary)
+        .padding([5, 10])
+        .on_press(Message::CancelNicknameEdit);
+
+    container(
+        row![
+            text(name).size(24).width(Fill),
+            nickname_input,
+            cancel_button,
+            save_button,
+        ]
+        .spacing(20)
+        .align_y(Center),
+    )
+    .into()
+}
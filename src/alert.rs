@@ -0,0 +1,49 @@
+use anyhow::Context;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::info;
+
+/// Send an email with the local `sendmail` command (e.g. from `msmtp` or
+/// `postfix`), which has to be configured to relay the mail.
+pub async fn send_email(to: String, subject: String, body: String) -> anyhow::Result<()> {
+    let message = format_email(&to, &subject, &body);
+
+    tokio::task::spawn_blocking(move || {
+        info!("Sending alert email to {to}…");
+        let mut child = Command::new("sendmail")
+            .arg("-t")
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to run sendmail")?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .context("Failed to open sendmail input")?;
+        stdin.write_all(message.as_bytes())?;
+        drop(stdin);
+
+        let status = child.wait()?;
+        anyhow::ensure!(status.success(), "sendmail failed with {status}");
+        Ok(())
+    })
+    .await?
+}
+
+fn format_email(to: &str, subject: &str, body: &str) -> String {
+    format!("To: {to}\nSubject: {subject}\nContent-Type: text/plain; charset=utf-8\n\n{body}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_email() {
+        let message = format_email("a@example.com", "Test", "Hallo");
+        assert_eq!(
+            message,
+            "To: a@example.com\nSubject: Test\nContent-Type: text/plain; charset=utf-8\n\nHallo\n"
+        );
+    }
+}
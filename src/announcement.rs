@@ -0,0 +1,52 @@
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The file extensions of announcements that are shown as images.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// An announcement that is shown on the idle screen, e.g. the next club
+/// event or upcoming price changes.
+///
+/// This is parsed from either a text or the path of a PNG or JPEG image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Announcement {
+    Text(String),
+    Image(PathBuf),
+}
+
+impl FromStr for Announcement {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        let is_image = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&&*extension.to_lowercase()));
+
+        Ok(match is_image {
+            true => Self::Image(path),
+            false => Self::Text(s.replace("\\n", "\n")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_announcement() {
+        assert_eq!(
+            "Arbeitsdienst am Samstag\\nab 9 Uhr".parse(),
+            Ok(Announcement::Text(
+                "Arbeitsdienst am Samstag\nab 9 Uhr".to_string()
+            ))
+        );
+        assert_eq!(
+            "slides/fest.PNG".parse(),
+            Ok(Announcement::Image(PathBuf::from("slides/fest.PNG")))
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::Context;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, SqliteConnection, SqlitePool};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// The number of database backups that are kept.
+const MAX_BACKUPS: usize = 5;
+
+/// The directory in which the backups of the database at `path` are stored.
+fn backup_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join("backups")
+}
+
+/// Back up the database at `path` into a timestamped file in the `backups`
+/// directory next to it, if there are any migrations that have not been
+/// applied yet.
+///
+/// This allows a bad migration to be rolled back. Only the most recent
+/// backups are kept. Returns the path of the backup, if one was created.
+pub async fn create_before_migrations(
+    pool: &SqlitePool,
+    path: &Path,
+) -> anyhow::Result<Option<PathBuf>> {
+    // A new database without any migrations does not need a backup
+    let Ok(applied) = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations")
+        .fetch_all(pool)
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let has_pending_migrations = sqlx::migrate!()
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .any(|migration| !applied.contains(&migration.version));
+    if !has_pending_migrations {
+        return Ok(None);
+    }
+
+    let dir = backup_dir(path);
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = jiff::Zoned::now().strftime("%Y%m%dT%H%M%S");
+    let backup = dir.join(format!("{stem}-{timestamp}.db"));
+
+    info!("Backing up database to {}…", backup.display());
+    sqlx::query("VACUUM INTO $1")
+        .bind(backup.to_string_lossy())
+        .execute(pool)
+        .await?;
+
+    prune(path, MAX_BACKUPS).await?;
+
+    Ok(Some(backup))
+}
+
+/// Delete all but the `keep` most recent backups of the database at `path`.
+pub async fn prune(path: &Path, keep: usize) -> std::io::Result<()> {
+    let backups = list(path).await?;
+    for old_backup in backups.iter().rev().skip(keep) {
+        info!("Deleting old database backup {}", old_backup.display());
+        tokio::fs::remove_file(old_backup).await?;
+    }
+
+    Ok(())
+}
+
+/// Replace the corrupted database with its most recent backup.
+///
+/// The sales of the corrupted database are merged into the backup first,
+/// including their upload state, so that newer sales are neither lost nor
+/// uploaded twice. Only then is the corrupted database moved next to the
+/// original file with a `.corrupted` extension and replaced by the merged
+/// backup. The backup file is removed, so that a bad backup is not restored
+/// twice.
+///
+/// Returns the number of merged sales.
+pub async fn restore_latest(
+    pool: SqlitePool,
+    options: &SqliteConnectOptions,
+) -> anyhow::Result<u64> {
+    let path = options.get_filename();
+    let Some(backup) = list(path).await?.pop() else {
+        anyhow::bail!("No database backup found");
+    };
+
+    // Closing the last connection also checkpoints the write-ahead log
+    pool.close().await;
+
+    info!("Restoring database from {}…", backup.display());
+    let restoring = path.with_extension("restoring");
+    tokio::fs::copy(&backup, &restoring).await?;
+
+    let restoring_options = options.clone().filename(&restoring);
+    let merged = match merge_sales(restoring_options, path).await {
+        Ok(merged) => merged,
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&restoring).await;
+            return Err(err.context(format!("Failed to merge sales from {}", path.display())));
+        }
+    };
+
+    let corrupted = path.with_extension("corrupted");
+    warn!("Keeping corrupted database as {}", corrupted.display());
+    tokio::fs::rename(path, &corrupted).await?;
+    for suffix in ["-wal", "-shm"] {
+        let with_suffix = |path: &Path| {
+            let mut journal = path.as_os_str().to_owned();
+            journal.push(suffix);
+            PathBuf::from(journal)
+        };
+        let _ = tokio::fs::rename(with_suffix(path), with_suffix(&corrupted)).await;
+    }
+    tokio::fs::rename(&restoring, path).await?;
+    tokio::fs::remove_file(&backup).await?;
+
+    Ok(merged)
+}
+
+/// Migrate the database with the given options and merge the sales of the
+/// corrupted database at `corrupted` into it.
+///
+/// Sales that only exist in the corrupted database are added, and the
+/// upload state of the corrupted database wins for sales that exist in
+/// both. Pending sales that are missing from the corrupted database were
+/// deleted or pruned after their upload, so they are removed.
+async fn merge_sales(options: SqliteConnectOptions, corrupted: &Path) -> anyhow::Result<u64> {
+    let mut connection = SqliteConnection::connect_with(&options).await?;
+    sqlx::migrate!().run(&mut connection).await?;
+
+    sqlx::query("ATTACH DATABASE $1 AS corrupted")
+        .bind(corrupted.to_string_lossy())
+        .execute(&mut connection)
+        .await
+        .context("Failed to open corrupted database")?;
+
+    let mut transaction = connection.begin().await?;
+
+    let merged = sqlx::query(
+        r#"
+        INSERT INTO sales (id, created_at, member_id, article_id, amount, unit_price, open_price,
+            payment_reference, self_paid, cost_type, upload_started_at, uploaded_at,
+            upload_failures)
+        SELECT id, created_at, member_id, article_id, amount, unit_price, open_price,
+            payment_reference, self_paid, cost_type, upload_started_at, uploaded_at,
+            upload_failures
+        FROM corrupted.sales
+        WHERE true
+        ON CONFLICT (id) DO UPDATE SET
+            upload_started_at = excluded.upload_started_at,
+            uploaded_at = excluded.uploaded_at,
+            upload_failures = excluded.upload_failures
+        WHERE sales.upload_started_at IS NOT excluded.upload_started_at
+            OR sales.uploaded_at IS NOT excluded.uploaded_at
+            OR sales.upload_failures IS NOT excluded.upload_failures
+        "#,
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    sqlx::query(
+        r#"
+        DELETE FROM sales
+        WHERE uploaded_at IS NULL AND id NOT IN (SELECT id FROM corrupted.sales)
+        "#,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    sqlx::query("DETACH DATABASE corrupted")
+        .execute(&mut connection)
+        .await?;
+    connection.close().await?;
+
+    Ok(merged)
+}
+
+/// List the backups of the database at `path`, from oldest to newest.
+async fn list(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let prefix = format!("{stem}-");
+
+    let mut backups = Vec::new();
+    let mut entries = match tokio::fs::read_dir(backup_dir(path)).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(backups),
+        Err(err) => return Err(err),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&prefix) && name.ends_with(".db") {
+            backups.push(entry.path());
+        }
+    }
+
+    // The timestamps in the file names sort chronologically
+    backups.sort();
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Sale;
+
+    #[tokio::test]
+    async fn test_backup_before_migrations() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("clubfridge-{}", ulid::Ulid::new()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join("clubfridge.db");
+
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await?;
+
+        // Neither a new nor a fully migrated database is backed up
+        assert_eq!(create_before_migrations(&pool, &path).await?, None);
+        sqlx::migrate!().run(&pool).await?;
+        assert_eq!(create_before_migrations(&pool, &path).await?, None);
+
+        // Pretend that the latest migration has not been applied yet
+        sqlx::query(
+            r#"
+            DELETE FROM _sqlx_migrations
+            WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        let backup = create_before_migrations(&pool, &path).await?;
+        assert!(backup.is_some_and(|backup| backup.exists()));
+        assert_eq!(list(&path).await?.len(), 1);
+
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_latest() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("clubfridge-{}", ulid::Ulid::new()));
+        tokio::fs::create_dir_all(dir.join("backups")).await?;
+        let path = dir.join("clubfridge.db");
+
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options.clone()).await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let sales = vec![Sale::test("1"), Sale::test("2"), Sale::test("3")];
+        let (uploaded_id, deleted_id, pending_id) = (*sales[0].id, *sales[1].id, *sales[2].id);
+        Sale::insert_all(pool.clone(), sales).await?;
+
+        let backup = dir.join("backups").join("clubfridge-20250301T120000.db");
+        sqlx::query("VACUUM INTO $1")
+            .bind(backup.to_string_lossy())
+            .execute(&pool)
+            .await?;
+
+        // Changes after the backup are merged from the corrupted database
+        Sale::mark_uploaded(&pool, uploaded_id).await?;
+        sqlx::query("DELETE FROM sales WHERE id = $1")
+            .bind(deleted_id.to_string())
+            .execute(&pool)
+            .await?;
+        let new_sale = Sale::test("4");
+        let new_id = *new_sale.id;
+        Sale::insert_all(pool.clone(), vec![new_sale]).await?;
+
+        assert_eq!(restore_latest(pool, &options).await?, 2);
+        assert!(path.exists());
+        assert!(path.with_extension("corrupted").exists());
+        assert!(!backup.exists());
+
+        let pool = SqlitePool::connect_with(options.clone()).await?;
+        let pending = Sale::load_all(pool.clone()).await?;
+        let pending_ids = pending.iter().map(|sale| *sale.id).collect::<Vec<_>>();
+        assert_eq!(pending_ids, vec![pending_id, new_id]);
+
+        let uploaded_at: Option<String> =
+            sqlx::query_scalar("SELECT uploaded_at FROM sales WHERE id = $1")
+                .bind(uploaded_id.to_string())
+                .fetch_one(&pool)
+                .await?;
+        assert!(uploaded_at.is_some());
+
+        assert!(restore_latest(pool, &options).await.is_err());
+
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+}
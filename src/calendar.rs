@@ -0,0 +1,182 @@
+use anyhow::Context;
+use tracing::{debug, info};
+
+/// An event from the club calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// The local start time of the event.
+    pub start: jiff::civil::DateTime,
+    /// Whether the event lasts all day, in which case the start time is
+    /// midnight.
+    pub all_day: bool,
+    pub summary: String,
+}
+
+impl Event {
+    /// Format the start of the event for the idle screen, e.g.
+    /// `Sa 01.03. 09:00`.
+    pub fn format_start(&self) -> String {
+        const WEEKDAYS: [&str; 7] = ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"];
+        let weekday = WEEKDAYS[self.start.weekday().to_monday_zero_offset() as usize];
+
+        match self.all_day {
+            true => format!("{weekday} {}", self.start.strftime("%d.%m.")),
+            false => format!("{weekday} {}", self.start.strftime("%d.%m. %H:%M")),
+        }
+    }
+}
+
+/// Download the iCal feed at the given URL and return the next `count`
+/// events that have not started yet.
+pub async fn load_upcoming(url: &str, count: usize) -> anyhow::Result<Vec<Event>> {
+    info!("Loading calendar…");
+    let ics = reqwest::get(url)
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+        .context("Failed to read calendar")?;
+
+    let events = parse(&ics);
+    debug!("Found {} events in calendar", events.len());
+
+    let now = jiff::Zoned::now().datetime();
+    Ok(upcoming(events, now, count))
+}
+
+/// Select the next `count` events that start after `now`, including all-day
+/// events of the current day, in order.
+fn upcoming(mut events: Vec<Event>, now: jiff::civil::DateTime, count: usize) -> Vec<Event> {
+    events
+        .retain(|event| event.start >= now || (event.all_day && event.start.date() == now.date()));
+    events.sort_by_key(|event| event.start);
+    events.truncate(count);
+    events
+}
+
+/// Parse the events of an iCalendar (RFC 5545) file.
+///
+/// Only the start time and summary of the events are read. Recurring events
+/// are only included with their first occurrence.
+fn parse(ics: &str) -> Vec<Event> {
+    // Long lines are folded by inserting a line break followed by a space
+    let unfolded = ics
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut start = None;
+    let mut summary = None;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+
+        match name {
+            "BEGIN" if value == "VEVENT" => {
+                start = None;
+                summary = None;
+            }
+            "DTSTART" => start = parse_start(params, value),
+            "SUMMARY" => summary = Some(unescape(value)),
+            "END" if value == "VEVENT" => {
+                if let (Some((start, all_day)), Some(summary)) = (start.take(), summary.take()) {
+                    events.push(Event {
+                        start,
+                        all_day,
+                        summary,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Parse the value of a `DTSTART` property into a local time, and whether
+/// it is a date without time.
+fn parse_start(params: &str, value: &str) -> Option<(jiff::civil::DateTime, bool)> {
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        let date = jiff::civil::Date::strptime("%Y%m%d", value).ok()?;
+        return Some((date.to_datetime(jiff::civil::Time::midnight()), true));
+    }
+
+    if let Some(value) = value.strip_suffix('Z') {
+        let datetime = jiff::civil::DateTime::strptime("%Y%m%dT%H%M%S", value).ok()?;
+        let timestamp = datetime.to_zoned(jiff::tz::TimeZone::UTC).ok()?.timestamp();
+        return Some((
+            timestamp.to_zoned(jiff::tz::TimeZone::system()).datetime(),
+            false,
+        ));
+    }
+
+    let datetime = jiff::civil::DateTime::strptime("%Y%m%dT%H%M%S", value).ok()?;
+    let tz = params
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .and_then(|tzid| jiff::tz::TimeZone::get(tzid).ok());
+
+    // Floating times and unknown time zones are interpreted as local time
+    let Some(tz) = tz else {
+        return Some((datetime, false));
+    };
+    let zoned = datetime.to_zoned(tz).ok()?;
+    Some((
+        zoned
+            .with_time_zone(jiff::tz::TimeZone::system())
+            .datetime(),
+        false,
+    ))
+}
+
+/// Remove the escaping of special characters in iCalendar text values.
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_calendar() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20250301\r\n\
+            SUMMARY:Arbeitsdienst\\, Halle\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20250215T180000\r\n\
+            SUMMARY:Mitgliederversammlung im Clubhaus mit anschließendem\r\n  Grillen\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Europe/Berlin:20250101T120000\r\n\
+            SUMMARY:Neujahrsempfang\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let events = parse(ics);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].summary, "Arbeitsdienst, Halle");
+        assert!(events[0].all_day);
+        assert_eq!(
+            events[1].summary,
+            "Mitgliederversammlung im Clubhaus mit anschließendem Grillen"
+        );
+
+        let now = jiff::civil::date(2025, 2, 1).at(12, 0, 0, 0);
+        let events = upcoming(events, now, 5);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].format_start(), "Sa 15.02. 18:00");
+        assert_eq!(events[1].format_start(), "Sa 01.03.");
+    }
+}
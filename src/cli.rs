@@ -0,0 +1,305 @@
+use crate::backup;
+use crate::database;
+use crate::import;
+use crate::qr_login;
+use crate::running::select_client;
+use crate::state::Options;
+use crate::statement;
+use crate::sync;
+use crate::totp;
+use rust_decimal::Decimal;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::types::Text;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+/// Commands that run without the GUI, e.g. over SSH when the screen is
+/// broken.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum Command {
+    /// List the sales that have not been uploaded to Vereinsflieger yet
+    Pending,
+    /// Load the articles and members from Vereinsflieger
+    Sync,
+    /// Upload the pending sales to Vereinsflieger
+    Upload,
+    /// Check the integrity of the local database
+    CheckDb,
+    /// Replace a corrupted database with its latest backup, keeping the
+    /// sales of the corrupted database
+    RestoreBackup,
+    /// Register an additional RFID keycode for a member
+    AddKeycode {
+        /// The member ID (aka. "Mitgliedsnummer")
+        member_id: String,
+        /// The 10-digit numeric or 7-digit hexadecimal keycode
+        keycode: String,
+    },
+    /// Replace the members with the members from a CSV file with the
+    /// columns `keycode;member ID;name;nickname;email`, for offline installations
+    ImportMembers {
+        /// The path of the CSV file
+        path: PathBuf,
+    },
+    /// Replace the bank accounts for SEPA direct debits with the accounts
+    /// from a CSV file with the columns `member ID;IBAN`
+    ImportBankAccounts {
+        /// The path of the CSV file
+        path: PathBuf,
+    },
+    /// Print the login QR code content of a member, which can be shown on
+    /// their phone instead of scanning the RFID chip
+    QrLoginCode {
+        /// The member ID (aka. "Mitgliedsnummer")
+        member_id: String,
+    },
+    /// Generate a new TOTP secret for opening the admin screen, which
+    /// replaces the admin PIN and any previous secret
+    AdminTotp,
+    /// Print the audit log as CSV
+    ExportAuditLog,
+    /// Create a voucher that reduces the total of a purchase once
+    AddVoucher {
+        /// The barcode that is printed on the voucher
+        barcode: String,
+        /// The value of the voucher in Euro
+        value: Decimal,
+        /// The last day on which the voucher can be redeemed
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        valid_until: Option<jiff::civil::Date>,
+    },
+}
+
+/// Run a headless command and return once it is finished.
+pub fn run(command: Command, options: &Options) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    runtime.block_on(async {
+        let pool = SqlitePoolOptions::default()
+            .connect_with(options.database())
+            .await?;
+
+        // The integrity check and the restore should also work if the
+        // migrations fail because of a corrupt database
+        match command {
+            Command::CheckDb => return check_db(&pool).await,
+            Command::RestoreBackup => return restore_backup(pool, options).await,
+            _ => {}
+        }
+
+        sqlx::migrate!().run(&pool).await?;
+
+        match command {
+            Command::Pending => pending(&pool).await,
+            Command::Sync => {
+                let (article_client, sales_client) = clients(&pool, options).await?;
+                if let Some(vereinsflieger) = article_client {
+                    let categories = options.article_categories.clone();
+                    sync::sync_articles(vereinsflieger, pool.clone(), categories).await?;
+                }
+                if let Some(vereinsflieger) = sales_client {
+                    let purge_removed = options.purge_removed_members;
+                    let store_emails = options.store_member_emails;
+                    sync::sync_members(vereinsflieger, pool.clone(), purge_removed, store_emails)
+                        .await?;
+                }
+                Ok(())
+            }
+            Command::Upload => {
+                let (_, sales_client) = clients(&pool, options).await?;
+                let Some(vereinsflieger) = sales_client else {
+                    anyhow::bail!("No credentials found for the sales club");
+                };
+                let credentials = database::Credentials::find_all(pool.clone()).await?;
+                let verifier = options.upload_verifier(&credentials);
+                sync::upload_sales(vereinsflieger, pool.clone(), verifier.as_ref()).await
+            }
+            Command::AddKeycode { member_id, keycode } => {
+                add_keycode(&pool, &member_id, &keycode).await
+            }
+            Command::ImportMembers { path } => {
+                import_members(&pool, &path, options.store_member_emails).await
+            }
+            Command::ImportBankAccounts { path } => import_bank_accounts(&pool, &path).await,
+            Command::QrLoginCode { member_id } => qr_login_code(&pool, options, &member_id).await,
+            Command::AdminTotp => admin_totp(&pool).await,
+            Command::ExportAuditLog => export_audit_log(&pool).await,
+            Command::AddVoucher {
+                barcode,
+                value,
+                valid_until,
+            } => add_voucher(&pool, barcode, value, valid_until).await,
+            Command::CheckDb | Command::RestoreBackup => unreachable!(),
+        }
+    })
+}
+
+/// Create the Vereinsflieger clients for the article and the sales club
+/// from the stored credentials.
+async fn clients(
+    pool: &SqlitePool,
+    options: &Options,
+) -> anyhow::Result<(
+    Option<vereinsflieger::Client>,
+    Option<vereinsflieger::Client>,
+)> {
+    let credentials = database::Credentials::find_all(pool.clone()).await?;
+    anyhow::ensure!(!credentials.is_empty(), "No credentials found in database");
+
+    let clients = credentials
+        .into_iter()
+        .map(|credentials| {
+            let club_id = credentials.club_id;
+            (club_id, vereinsflieger::Client::new(credentials.into()))
+        })
+        .collect::<Vec<_>>();
+
+    let article_client = select_client(&clients, options.article_club);
+    let sales_client = select_client(&clients, options.sales_club);
+    Ok((article_client, sales_client))
+}
+
+async fn pending(pool: &SqlitePool) -> anyhow::Result<()> {
+    let sales = database::Sale::load_all(pool.clone()).await?;
+
+    for sale in &sales {
+        let total = sale.total().map(|total| format!("{total:.2}€"));
+        let interrupted = match sale.upload_started_at {
+            Some(_) => " (upload interrupted)",
+            None => "",
+        };
+
+        println!(
+            "{}\t{}\t{}\t{}x {}\t{}{interrupted}",
+            *sale.id,
+            sale.created_at.strftime("%Y-%m-%d %H:%M"),
+            sale.member_id,
+            sale.amount,
+            sale.article_id,
+            total.unwrap_or_default(),
+        );
+    }
+
+    println!("{} pending sales", sales.len());
+    Ok(())
+}
+
+async fn add_keycode(pool: &SqlitePool, member_id: &str, keycode: &str) -> anyhow::Result<()> {
+    let Some(keycode) = database::Member::normalize_keycode(keycode) else {
+        anyhow::bail!("Expected a 10-digit numeric or 7-digit hexadecimal keycode");
+    };
+
+    if !database::Member::add_keycode(pool.clone(), member_id, &keycode).await? {
+        anyhow::bail!("Member {member_id} not found in database");
+    }
+
+    println!("Keycode {keycode} registered for member {member_id}");
+    Ok(())
+}
+
+async fn import_members(pool: &SqlitePool, path: &Path, store_emails: bool) -> anyhow::Result<()> {
+    let members = import::read_members(path, store_emails).await?;
+    let count = members.len();
+    database::Member::save_all(pool.clone(), members).await?;
+
+    println!("{count} members imported from {}", path.display());
+    Ok(())
+}
+
+async fn import_bank_accounts(pool: &SqlitePool, path: &Path) -> anyhow::Result<()> {
+    let accounts = import::read_bank_accounts(path).await?;
+    let count = accounts.len();
+    database::BankAccount::save_all(pool, accounts).await?;
+
+    println!("{count} bank accounts imported from {}", path.display());
+    Ok(())
+}
+
+async fn qr_login_code(
+    pool: &SqlitePool,
+    options: &Options,
+    member_id: &str,
+) -> anyhow::Result<()> {
+    let Some(secret) = &options.qr_login_secret else {
+        anyhow::bail!("No QR login secret configured, use `--qr-login-secret`");
+    };
+    if database::Member::find_by_id(pool.clone(), member_id)
+        .await?
+        .is_none()
+    {
+        anyhow::bail!("Member {member_id} not found in database");
+    }
+
+    println!("{}", qr_login::code(secret.as_bytes(), member_id));
+    Ok(())
+}
+
+async fn admin_totp(pool: &SqlitePool) -> anyhow::Result<()> {
+    let secret = totp::generate_secret()?;
+    database::Settings::set_admin_totp_secret(pool, &secret).await?;
+
+    let entry = database::AuditEntry::new("admin_totp_rotate", None, "");
+    database::AuditEntry::insert_all(pool, vec![entry]).await?;
+
+    println!("Secret: {secret}");
+    println!("{}", totp::uri(&secret, "admin"));
+    println!("Please restart the application to use the new secret");
+    Ok(())
+}
+
+async fn export_audit_log(pool: &SqlitePool) -> anyhow::Result<()> {
+    println!("Zeitpunkt;Aktion;Mitgliedsnummer;Details");
+    for entry in database::AuditEntry::load_all(pool).await? {
+        println!(
+            "{};{};{};{}",
+            *entry.created_at,
+            statement::escape(&entry.action),
+            statement::escape(entry.member_id.as_deref().unwrap_or_default()),
+            statement::escape(&entry.details),
+        );
+    }
+
+    Ok(())
+}
+
+async fn add_voucher(
+    pool: &SqlitePool,
+    barcode: String,
+    value: Decimal,
+    valid_until: Option<jiff::civil::Date>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(value > Decimal::ZERO, "The voucher value must be positive");
+
+    let voucher = database::Voucher {
+        barcode,
+        value: Text(value),
+        valid_from: None,
+        valid_until: valid_until.map(Text),
+        redeemed_at: None,
+    };
+    voucher.insert(pool).await?;
+
+    println!("Voucher {} over {value:.2}€ created", voucher.barcode);
+    Ok(())
+}
+
+async fn restore_backup(pool: SqlitePool, options: &Options) -> anyhow::Result<()> {
+    let merged = backup::restore_latest(pool, &options.database()).await?;
+    println!("Database backup restored, {merged} sales merged from the corrupted database");
+    Ok(())
+}
+
+async fn check_db(pool: &SqlitePool) -> anyhow::Result<()> {
+    let problems = database::integrity_check(pool).await?;
+    if problems.is_empty() {
+        println!("Database integrity check passed");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    anyhow::bail!("Database integrity check found {} problems", problems.len());
+}
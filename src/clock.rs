@@ -0,0 +1,75 @@
+use anyhow::Context;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// The time after which an unanswered NTP request is considered failed.
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of seconds between the NTP epoch (1900-01-01) and the Unix
+/// epoch (1970-01-01).
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// Query the given (S)NTP server and return the offset of the server clock
+/// relative to the local clock.
+///
+/// A positive result means that the local clock is behind.
+pub async fn query_skew(server: &str) -> anyhow::Result<jiff::SignedDuration> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket
+        .connect(server)
+        .await
+        .with_context(|| format!("Failed to resolve time server {server}"))?;
+
+    // LI = 0 (no warning), VN = 3, Mode = 3 (client)
+    let mut request = [0; 48];
+    request[0] = 0x1B;
+
+    let sent_at = jiff::Timestamp::now();
+    socket.send(&request).await?;
+
+    let mut response = [0; 48];
+    let len = tokio::time::timeout(NTP_TIMEOUT, socket.recv(&mut response))
+        .await
+        .context("Time server did not respond")??;
+    let received_at = jiff::Timestamp::now();
+
+    anyhow::ensure!(len == response.len(), "Invalid NTP response length: {len}");
+
+    let server_time = parse_ntp_timestamp(&response[40..48])?;
+
+    // Assume that the request and response took the same amount of time.
+    let round_trip = received_at.duration_since(sent_at);
+    let local_time = sent_at.checked_add(round_trip / 2)?;
+
+    Ok(server_time.duration_since(local_time))
+}
+
+/// Parse a 64-bit NTP timestamp (seconds and fraction since 1900).
+fn parse_ntp_timestamp(bytes: &[u8]) -> anyhow::Result<jiff::Timestamp> {
+    let seconds = u32::from_be_bytes(bytes[0..4].try_into()?);
+    let fraction = u32::from_be_bytes(bytes[4..8].try_into()?);
+    anyhow::ensure!(seconds != 0, "Time server returned an empty timestamp");
+
+    let seconds = seconds as i64 - NTP_UNIX_OFFSET;
+    let nanoseconds = (fraction as u64 * 1_000_000_000) >> 32;
+
+    Ok(jiff::Timestamp::new(seconds, nanoseconds as i32)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ntp_timestamp() {
+        // 2025-01-01T00:00:00.5Z
+        let seconds = (1_735_689_600 + NTP_UNIX_OFFSET) as u32;
+        let mut bytes = seconds.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&0x8000_0000u32.to_be_bytes());
+
+        let timestamp = parse_ntp_timestamp(&bytes).unwrap();
+        assert_eq!(timestamp.to_string(), "2025-01-01T00:00:00.5Z");
+
+        assert!(parse_ntp_timestamp(&[0; 8]).is_err());
+    }
+}
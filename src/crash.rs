@@ -0,0 +1,86 @@
+use crate::paths;
+use std::os::unix::process::CommandExt;
+use std::panic::PanicHookInfo;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// The minimum time the application has to run before it is restarted
+/// after a panic, to avoid a restart loop if it panics during startup.
+const MIN_UPTIME_FOR_RESTART: Duration = Duration::from_secs(60);
+
+/// A panic of a previous run of the application, which is shown on a
+/// full-screen error screen after the restart.
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub message: String,
+}
+
+/// Install a panic hook that logs the panic and saves it to the crash
+/// report file, so that it can be shown after the application restarted.
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = describe(info);
+        error!("Application panicked: {message}");
+
+        let timestamp = jiff::Zoned::now().strftime("%d.%m.%Y %H:%M:%S");
+        let report = format!("{timestamp}: {message}");
+        if let Err(err) = std::fs::write(paths::crash_report(), report) {
+            error!("Failed to save crash report: {err}");
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// The panic message and the source location of the panic.
+fn describe(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+
+    match info.location() {
+        Some(location) => format!("{message} ({location})"),
+        None => message.to_string(),
+    }
+}
+
+/// Load and remove the crash report of a previous run, if there is one.
+pub fn take_report() -> Option<CrashReport> {
+    let path = paths::crash_report();
+    let message = std::fs::read_to_string(&path).ok()?;
+    if let Err(err) = std::fs::remove_file(&path) {
+        error!("Failed to remove crash report: {err}");
+    }
+
+    Some(CrashReport { message })
+}
+
+/// Replace the current process with a new instance of the application
+/// with the same arguments, unless the application panicked shortly after
+/// it was started.
+///
+/// This only returns if the restart was not possible.
+pub fn restart(started_at: Instant) {
+    if started_at.elapsed() < MIN_UPTIME_FOR_RESTART {
+        error!("Not restarting, since the application panicked right after it was started");
+        return;
+    }
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            error!("Failed to determine executable for restart: {err}");
+            return;
+        }
+    };
+
+    error!("Restarting after panic…");
+    let err = std::process::Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    error!("Failed to restart after panic: {err}");
+}
@@ -0,0 +1,57 @@
+use rust_decimal::Decimal;
+use std::sync::OnceLock;
+
+/// How prices are formatted on the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CurrencyFormat {
+    /// Decimal comma and trailing currency symbol, e.g. `1,50 €`.
+    #[default]
+    German,
+    /// Decimal point and leading currency symbol, e.g. `€1.50`.
+    English,
+}
+
+static FORMAT: OnceLock<CurrencyFormat> = OnceLock::new();
+
+/// Set the format that is used by [`format`].
+pub fn init(format: CurrencyFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Format the given amount in Euro with two decimal places, using the
+/// configured format.
+pub fn format(amount: Decimal) -> String {
+    format_with(amount, FORMAT.get().copied().unwrap_or_default())
+}
+
+fn format_with(amount: Decimal, format: CurrencyFormat) -> String {
+    let sign = if amount.is_sign_negative() && !amount.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    let amount = format!("{:.2}", amount.abs());
+
+    match format {
+        CurrencyFormat::German => format!("{sign}{} €", amount.replace('.', ",")),
+        CurrencyFormat::English => format!("{sign}€{amount}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let german = |cents| format_with(Decimal::new(cents, 2), CurrencyFormat::German);
+        assert_eq!(german(150), "1,50 €");
+        assert_eq!(german(-150), "-1,50 €");
+        assert_eq!(german(0), "0,00 €");
+        assert_eq!(german(123456), "1234,56 €");
+
+        let english = |cents| format_with(Decimal::new(cents, 2), CurrencyFormat::English);
+        assert_eq!(english(150), "€1.50");
+        assert_eq!(english(-150), "-€1.50");
+    }
+}
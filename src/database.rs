@@ -1,14 +1,22 @@
+use crate::currency;
+use crate::logging;
 use rust_decimal::Decimal;
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::types::Text;
-use sqlx::{SqliteConnection, SqlitePool};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tracing::{info, warn};
 use ulid::Ulid;
 
+/// The maximum number of rows that are inserted with a single statement, to
+/// stay well below the SQLite limit for the number of bind parameters.
+const BULK_INSERT_ROWS: usize = 500;
+
 /// The Vereinsflieger credentials used to access the API.
 ///
 /// These are saved in the `credentials` database table and queried
-/// upon startup.
+/// upon startup. The table contains at most one set of credentials per club.
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct Credentials {
     /// The club ID in Vereinsflieger.
@@ -32,6 +40,10 @@ pub struct Credentials {
     /// The password of the account used to access the Vereinsflieger API.
     #[sqlx(try_from = "String")]
     pub password: SecretString,
+
+    /// The two-factor authentication secret of the account, if 2FA is
+    /// enabled for it.
+    pub auth_secret: Option<String>,
 }
 
 impl From<Credentials> for vereinsflieger::Credentials {
@@ -41,38 +53,45 @@ impl From<Credentials> for vereinsflieger::Credentials {
             app_key: credentials.app_key.clone(),
             username: credentials.username.clone(),
             password: credentials.password.expose_secret().into(),
-            auth_secret: None,
+            auth_secret: credentials.auth_secret,
         }
     }
 }
 
 impl Credentials {
-    /// Find the "first" set of credentials in the database. If multiple
-    /// credentials are stored, a random one is returned. In other words,
-    /// we expect only one set of credentials to be stored.
-    pub async fn find_first(pool: SqlitePool) -> sqlx::Result<Option<Self>> {
+    /// Find all sets of credentials in the database, in the order in which
+    /// they were added.
+    pub async fn find_all(pool: SqlitePool) -> sqlx::Result<Vec<Self>> {
         sqlx::query_as(
             r#"
-            SELECT club_id, app_key, username, password
+            SELECT club_id, app_key, username, password, auth_secret
             FROM credentials
+            ORDER BY rowid
             "#,
         )
-        .fetch_optional(&pool)
+        .fetch_all(&pool)
         .await
     }
 
-    /// Insert the credentials into the database.
+    /// Insert the credentials into the database, replacing any existing
+    /// credentials for the same club.
     pub async fn insert(&self, pool: SqlitePool) -> sqlx::Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO credentials (club_id, app_key, username, password)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO credentials (club_id, app_key, username, password, auth_secret)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (club_id) DO UPDATE SET
+                app_key = excluded.app_key,
+                username = excluded.username,
+                password = excluded.password,
+                auth_secret = excluded.auth_secret
             "#,
         )
         .bind(self.club_id)
         .bind(&self.app_key)
         .bind(&self.username)
         .bind(self.password.expose_secret())
+        .bind(&self.auth_secret)
         .execute(&pool)
         .await
         .map(|_| ())
@@ -81,16 +100,18 @@ impl Credentials {
 
 /// A member of the club.
 ///
-/// Note that the primary key of the `members` table is the `keycode` field,
+/// Note that the `members` table has a unique index on the `keycode` field,
 /// so a member might exist multiple times in the database if they have
 /// multiple keycodes. It is implemented this way to optimize the query
-/// performance when looking up a member by their keycode.
+/// performance when looking up a member by their keycode. Members without
+/// any keycode are stored once with an empty keycode.
 #[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
 pub struct Member {
     /// The RFID keycode of the member.
     ///
     /// This currently accepts 10-digit numeric keycodes and 7-digit hexadecimal
-    /// keycodes. The latter are converted to the former.
+    /// keycodes. The latter are converted to the former. This is empty for
+    /// members without any keycode.
     pub keycode: String,
 
     /// The member ID inside the club (aka. "Mitgliedsnummer").
@@ -103,7 +124,30 @@ pub struct Member {
     pub lastname: String,
 
     /// The nickname of the member. (might be empty)
+    ///
+    /// A nickname that was set locally by an admin is stored in the separate
+    /// `member_nicknames` table and takes precedence over the synchronized
+    /// nickname.
     pub nickname: String,
+
+    /// The birthday of the member, if known.
+    pub birthday: Option<Text<jiff::civil::Date>>,
+
+    /// The member group (aka. "Mitgliedsstatus") of the member, which is used
+    /// to select group-specific article prices. (might be empty)
+    pub member_group: String,
+
+    /// The email address of the member, if it may be stored, e.g. for
+    /// digital receipts or balance warnings.
+    pub email: Option<String>,
+
+    /// Whether the member is on the local blocklist and is not allowed to
+    /// buy anything (e.g. because of unpaid bills).
+    ///
+    /// This is not stored in the `members` table, but in the separate
+    /// `blocked_members` table, so that it is not lost on synchronization.
+    #[sqlx(default)]
+    pub blocked: bool,
 }
 
 impl Member {
@@ -111,9 +155,14 @@ impl Member {
     pub async fn find_by_keycode(pool: SqlitePool, keycode: &str) -> sqlx::Result<Option<Self>> {
         sqlx::query_as(
             r#"
-            SELECT keycode, id, firstname, lastname, nickname
+            SELECT keycode, id, firstname, lastname, birthday, member_group, email,
+                COALESCE(
+                    (SELECT nickname FROM member_nicknames WHERE member_id = members.id),
+                    nickname
+                ) AS nickname,
+                EXISTS(SELECT 1 FROM blocked_members WHERE member_id = members.id) AS blocked
             FROM members
-            WHERE keycode = $1
+            WHERE keycode = $1 AND keycode != ''
             "#,
         )
         .bind(keycode)
@@ -121,6 +170,104 @@ impl Member {
         .await
     }
 
+    /// Find a member by their member ID (aka. "Mitgliedsnummer").
+    pub async fn find_by_id(pool: SqlitePool, id: &str) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as(
+            r#"
+            SELECT keycode, id, firstname, lastname, birthday, member_group, email,
+                COALESCE(
+                    (SELECT nickname FROM member_nicknames WHERE member_id = members.id),
+                    nickname
+                ) AS nickname,
+                EXISTS(SELECT 1 FROM blocked_members WHERE member_id = members.id) AS blocked
+            FROM members
+            WHERE id = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+    }
+
+    /// Find members whose name or nickname contains the given query.
+    ///
+    /// Members with multiple keycodes are only returned once. The results are
+    /// sorted by name and limited to a reasonable number of entries.
+    pub async fn search_by_name(pool: SqlitePool, query: &str) -> sqlx::Result<Vec<Self>> {
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{escaped}%");
+
+        sqlx::query_as(
+            r#"
+            SELECT keycode, id, firstname, lastname, birthday, member_group, email,
+                COALESCE(
+                    (SELECT nickname FROM member_nicknames WHERE member_id = members.id),
+                    nickname
+                ) AS nickname,
+                EXISTS(SELECT 1 FROM blocked_members WHERE member_id = members.id) AS blocked
+            FROM members
+            WHERE firstname || ' ' || lastname LIKE $1 ESCAPE '\'
+                OR members.nickname LIKE $1 ESCAPE '\'
+                OR EXISTS(
+                    SELECT 1 FROM member_nicknames
+                    WHERE member_id = members.id AND member_nicknames.nickname LIKE $1 ESCAPE '\'
+                )
+            GROUP BY id
+            ORDER BY lastname, firstname
+            LIMIT 20
+            "#,
+        )
+        .bind(pattern)
+        .fetch_all(&pool)
+        .await
+    }
+
+    /// Add the member with the given ID to the blocklist, or remove them
+    /// from it.
+    pub async fn set_blocked(pool: SqlitePool, id: &str, blocked: bool) -> sqlx::Result<()> {
+        let query = if blocked {
+            sqlx::query(
+                r#"
+                INSERT INTO blocked_members (member_id, blocked_at)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(id)
+            .bind(jiff::Timestamp::now().to_string())
+        } else {
+            sqlx::query("DELETE FROM blocked_members WHERE member_id = $1").bind(id)
+        };
+
+        query.execute(&pool).await.map(|_| ())
+    }
+
+    /// Set the local nickname of the member with the given ID, which
+    /// overrides the synchronized nickname. An empty nickname removes the
+    /// override again.
+    pub async fn set_nickname(pool: SqlitePool, id: &str, nickname: &str) -> sqlx::Result<()> {
+        let nickname = nickname.trim();
+        let query = if nickname.is_empty() {
+            sqlx::query("DELETE FROM member_nicknames WHERE member_id = $1").bind(id)
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO member_nicknames (member_id, nickname)
+                VALUES ($1, $2)
+                ON CONFLICT (member_id) DO UPDATE SET nickname = excluded.nickname
+                "#,
+            )
+            .bind(id)
+            .bind(nickname)
+        };
+
+        query.execute(&pool).await.map(|_| ())
+    }
+
     /// Delete all members from the database.
     ///
     /// This should usually be used inside a transaction in combination with
@@ -136,8 +283,9 @@ impl Member {
     async fn insert(&self, connection: &mut SqliteConnection) -> sqlx::Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO members (keycode, id, firstname, lastname, nickname)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO members
+                (keycode, id, firstname, lastname, nickname, birthday, member_group, email)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
         )
         .bind(&self.keycode)
@@ -145,6 +293,9 @@ impl Member {
         .bind(&self.firstname)
         .bind(&self.lastname)
         .bind(&self.nickname)
+        .bind(self.birthday)
+        .bind(&self.member_group)
+        .bind(&self.email)
         .execute(connection)
         .await
         .map(|_| ())
@@ -163,25 +314,204 @@ impl Member {
         for member in members {
             if let Err(error) = member.insert(&mut transaction).await {
                 warn!(
-                    "Failed to insert member {} {} with keycode {}: {error}",
-                    member.firstname, member.lastname, member.keycode
+                    member_id = %member.id,
+                    "Failed to insert member {:?}: {error}",
+                    logging::member(&member)
                 );
             }
         }
 
+        Self::apply_manual_keycodes(&mut transaction).await?;
+
         transaction.commit().await
     }
 
+    /// Delete the blocklist entries, local nicknames and manual keycodes of
+    /// members that are not in the members list anymore, e.g. because they
+    /// left the club.
+    ///
+    /// Prepaid balances are kept, since they are still owed to the member.
+    /// Returns the number of deleted rows.
+    pub async fn purge_removed(pool: &SqlitePool) -> sqlx::Result<u64> {
+        let mut transaction = pool.begin().await?;
+
+        let mut count = 0;
+        for table in ["blocked_members", "member_nicknames", "manual_keycodes"] {
+            let query =
+                format!("DELETE FROM {table} WHERE member_id NOT IN (SELECT id FROM members)");
+            let result = sqlx::query(&query).execute(&mut *transaction).await?;
+            count += result.rows_affected();
+        }
+
+        transaction.commit().await?;
+        Ok(count)
+    }
+
+    /// Register an additional keycode for the member with the given ID.
+    ///
+    /// The keycode is stored separately, so that it is still assigned to the
+    /// member after the next synchronization. If the keycode was assigned to
+    /// another member before, it is reassigned. Returns `false` if the member
+    /// does not exist.
+    pub async fn add_keycode(pool: SqlitePool, id: &str, keycode: &str) -> sqlx::Result<bool> {
+        let mut transaction = pool.begin().await?;
+
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM members WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await?;
+        if !exists {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO manual_keycodes (keycode, member_id)
+            VALUES ($1, $2)
+            ON CONFLICT (keycode) DO UPDATE SET member_id = excluded.member_id
+            "#,
+        )
+        .bind(keycode)
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+
+        // Keep the previous owner of the keycode without any keycode, if it
+        // was their only one
+        sqlx::query(
+            r#"
+            INSERT INTO members
+                (keycode, id, firstname, lastname, nickname, birthday, member_group, email)
+            SELECT '', id, firstname, lastname, nickname, birthday, member_group, email
+            FROM members
+            WHERE keycode = $1
+                AND NOT EXISTS(SELECT 1 FROM members AS other
+                    WHERE other.id = members.id AND other.keycode != $1)
+            "#,
+        )
+        .bind(keycode)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM members WHERE keycode = $1")
+            .bind(keycode)
+            .execute(&mut *transaction)
+            .await?;
+
+        Self::apply_manual_keycodes(&mut transaction).await?;
+
+        transaction.commit().await.map(|_| true)
+    }
+
+    /// Insert a copy of the member for each manually registered keycode
+    /// and remove the keycode-less entries of these members.
+    async fn apply_manual_keycodes(connection: &mut SqliteConnection) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO members
+                (keycode, id, firstname, lastname, nickname, birthday, member_group, email)
+            SELECT manual_keycodes.keycode, members.id, firstname, lastname, nickname,
+                birthday, member_group, email
+            FROM manual_keycodes
+            JOIN members ON members.id = manual_keycodes.member_id
+            GROUP BY manual_keycodes.keycode
+            "#,
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM members
+            WHERE keycode = '' AND id IN (SELECT member_id FROM manual_keycodes)
+            "#,
+        )
+        .execute(connection)
+        .await
+        .map(|_| ())
+    }
+
+    /// A pseudo member for guests, who pay by card instead of booking to a
+    /// member account.
+    pub fn guest() -> Self {
+        Self {
+            keycode: String::new(),
+            id: String::new(),
+            firstname: "Gast".to_string(),
+            lastname: String::new(),
+            nickname: "Gast".to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        }
+    }
+
+    /// Whether this is the pseudo member for guests.
+    pub fn is_guest(&self) -> bool {
+        self.id.is_empty()
+    }
+
+    /// Whether the given date is the birthday of the member.
+    pub fn has_birthday_on(&self, date: jiff::civil::Date) -> bool {
+        let Some(birthday) = self.birthday.as_deref() else {
+            return false;
+        };
+
+        // Members born on February 29 celebrate on February 28 in other years
+        let is_leap_day = (birthday.month(), birthday.day()) == (2, 29);
+        if is_leap_day && !date.in_leap_year() {
+            return (date.month(), date.day()) == (2, 28);
+        }
+
+        (date.month(), date.day()) == (birthday.month(), birthday.day())
+    }
+
+    /// Get the age of the member in full years on the given date.
+    ///
+    /// Returns `None` if the birthday of the member is unknown.
+    pub fn age_on(&self, date: jiff::civil::Date) -> Option<i16> {
+        let birthday = *self.birthday?;
+
+        let mut age = date.year() - birthday.year();
+        if (date.month(), date.day()) < (birthday.month(), birthday.day()) {
+            age -= 1;
+        }
+
+        Some(age)
+    }
+
+    /// Check whether the member is at least the given age on the given date.
+    ///
+    /// Returns `None` if the birthday of the member is unknown.
+    pub fn is_of_age(&self, minimum_age: i16, date: jiff::civil::Date) -> Option<bool> {
+        self.age_on(date).map(|age| age >= minimum_age)
+    }
+
+    /// Parse a Vereinsflieger birthday, which is either in `YYYY-MM-DD` or
+    /// in `DD.MM.YYYY` format. Returns `None` for empty or invalid values.
+    pub fn parse_birthday(birthday: &str) -> Option<jiff::civil::Date> {
+        birthday
+            .parse()
+            .or_else(|_| jiff::civil::Date::strptime("%d.%m.%Y", birthday))
+            .ok()
+    }
+
     /// Parse a Vereinsflieger keycode into a normalized format.
     ///
     /// This function accepts both the 10-digit numeric format and the 7-digit
     /// hexadecimal format. It returns the 10-digit numeric format.
     pub fn parse_keycode(key: vereinsflieger::Key) -> Option<String> {
-        let key = key.name;
+        Self::normalize_keycode(&key.name)
+    }
+
+    /// Convert a keycode in the 10-digit numeric or the 7-digit hexadecimal
+    /// format into the 10-digit numeric format.
+    pub fn normalize_keycode(key: &str) -> Option<String> {
         if key.len() == 10 && key.chars().all(|c| c.is_ascii_digit()) {
-            Some(key)
+            Some(key.to_string())
         } else if key.len() == 7 && key.chars().all(|c| c.is_ascii_hexdigit()) {
-            let key = u32::from_str_radix(&key, 16).ok()?;
+            let key = u32::from_str_radix(key, 16).ok()?;
             Some(format!("{key:0>10}"))
         } else {
             None
@@ -190,7 +520,7 @@ impl Member {
 }
 
 /// An article that can be sold in the club.
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
 pub struct Article {
     /// The article ID (aka. "Artikelnummer").
     ///
@@ -204,6 +534,16 @@ pub struct Article {
     /// A mapping of date ranges to prices.
     #[sqlx(json)]
     pub prices: Vec<Price>,
+
+    /// The category of the article (e.g. "Getränke"), which is synced from
+    /// the cost type in Vereinsflieger unless it is overridden locally.
+    #[serde(default)]
+    pub category: Option<String>,
+
+    /// The booking account of the article (aka. "Sachkonto"), if it is set
+    /// in Vereinsflieger.
+    #[serde(default)]
+    pub account: Option<String>,
 }
 
 impl TryFrom<vereinsflieger::Article> for Article {
@@ -218,10 +558,76 @@ impl TryFrom<vereinsflieger::Article> for Article {
                 .into_iter()
                 .map(Price::try_from)
                 .collect::<Result<_, _>>()?,
+            // The cost type (aka. "Gebührenbereich") groups the articles,
+            // since the `vereinsflieger` client does not expose the article
+            // groups
+            category: Some(article.cost_type).filter(|cost_type| !cost_type.is_empty()),
+            account: Some(article.account).filter(|account| !account.is_empty()),
         })
     }
 }
 
+/// An in-memory copy of the articles table, so that scanned barcodes can be
+/// looked up without querying the database, e.g. while a synchronization
+/// holds a write transaction.
+///
+/// The articles are loaded on the first lookup. The cache has to be
+/// invalidated with [`ArticleCache::invalidate()`] whenever the articles in
+/// the database change.
+#[derive(Debug, Clone, Default)]
+pub struct ArticleCache(Arc<Mutex<ArticleCacheInner>>);
+
+#[derive(Debug, Default)]
+struct ArticleCacheInner {
+    articles: Option<Arc<HashMap<String, Article>>>,
+    /// Incremented on every invalidation, so that articles that were loaded
+    /// before an invalidation are not cached.
+    generation: u64,
+}
+
+impl ArticleCache {
+    /// Find an article by its barcode (i.e. article ID), loading all
+    /// articles from the database if they are not cached yet.
+    pub async fn find_by_barcode(
+        &self,
+        pool: SqlitePool,
+        barcode: &str,
+    ) -> sqlx::Result<Option<Article>> {
+        let (articles, generation) = {
+            let inner = self.0.lock().unwrap();
+            (inner.articles.clone(), inner.generation)
+        };
+
+        let articles = match articles {
+            Some(articles) => articles,
+            None => {
+                let articles = Article::load_all(&pool).await?;
+                let articles = articles
+                    .into_iter()
+                    .map(|article| (article.id.clone(), article))
+                    .collect::<HashMap<_, _>>();
+                let articles = Arc::new(articles);
+
+                let mut inner = self.0.lock().unwrap();
+                if inner.generation == generation {
+                    inner.articles = Some(articles.clone());
+                }
+                articles
+            }
+        };
+
+        Ok(articles.get(barcode).cloned())
+    }
+
+    /// Drop the cached articles, so that they are loaded from the database
+    /// again on the next lookup.
+    pub fn invalidate(&self) {
+        let mut inner = self.0.lock().unwrap();
+        inner.articles = None;
+        inner.generation += 1;
+    }
+}
+
 /// A price for an article that is valid within a certain date range.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Price {
@@ -233,6 +639,22 @@ pub struct Price {
 
     /// The unit price of the article.
     pub unit_price: Decimal,
+
+    /// The member group that this price applies to, or `None` if it applies
+    /// to all members without a more specific price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_group: Option<String>,
+
+    /// The sales tax rate in percent (aka. "Mehrwertsteuer"), if it is set
+    /// in Vereinsflieger.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sales_tax: Option<Decimal>,
+}
+
+impl Price {
+    fn is_valid_on(&self, date: &jiff::civil::Date) -> bool {
+        self.valid_from <= *date && self.valid_to >= *date
+    }
 }
 
 impl TryFrom<vereinsflieger::Price> for Price {
@@ -243,23 +665,69 @@ impl TryFrom<vereinsflieger::Price> for Price {
             valid_from: price.valid_from.parse()?,
             valid_to: price.valid_to.parse()?,
             unit_price: price.unit_price.parse()?,
+            member_group: None,
+            sales_tax: price.sales_tax.parse().ok(),
         })
     }
 }
 
 impl Article {
-    /// Find an article by its barcode (i.e. article ID).
-    pub async fn find_by_barcode(pool: SqlitePool, barcode: &str) -> sqlx::Result<Option<Self>> {
-        sqlx::query_as(
-            r#"
-            SELECT id, designation, prices
-            FROM articles
-            WHERE id = $1
-            "#,
-        )
-        .bind(barcode)
-        .fetch_optional(&pool)
-        .await
+    /// Load the designations of all articles, keyed by article ID.
+    pub async fn load_designations(pool: &SqlitePool) -> sqlx::Result<HashMap<String, String>> {
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT id, designation FROM articles")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Load all articles from the database.
+    pub async fn load_all(pool: &SqlitePool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT id, designation, prices, category, account FROM articles")
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Count the articles in the database.
+    pub async fn count(pool: &SqlitePool) -> sqlx::Result<u32> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM articles")
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Create an article with a price that is always valid, for articles
+    /// that are not loaded from Vereinsflieger.
+    pub fn with_fixed_price(id: String, designation: String, unit_price: Decimal) -> Self {
+        Self {
+            id,
+            designation,
+            prices: vec![Price {
+                valid_from: jiff::civil::Date::constant(2000, 1, 1),
+                valid_to: jiff::civil::Date::constant(2999, 12, 31),
+                unit_price,
+                member_group: None,
+                sales_tax: None,
+            }],
+            category: None,
+            account: None,
+        }
+    }
+
+    /// Insert articles into the database, replacing existing articles with
+    /// the same ID, but keeping all other articles.
+    pub async fn upsert_all(pool: &SqlitePool, articles: &[Self]) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        for article in articles {
+            sqlx::query("DELETE FROM articles WHERE id = $1")
+                .bind(&article.id)
+                .execute(&mut *transaction)
+                .await?;
+
+            article.insert(&mut transaction).await?;
+        }
+
+        transaction.commit().await
     }
 
     /// Delete all articles from the database.
@@ -281,33 +749,70 @@ impl Article {
 
         sqlx::query(
             r#"
-            INSERT INTO articles (id, designation, prices)
-            VALUES ($1, $2, $3)
+            INSERT INTO articles (id, designation, prices, category, account)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
         .bind(&self.id)
         .bind(&self.designation)
         .bind(prices)
+        .bind(&self.category)
+        .bind(&self.account)
         .execute(connection)
         .await
         .map(|_| ())
     }
 
+    /// Insert articles into the database with multi-row `INSERT` statements,
+    /// which is much faster than inserting them one by one.
+    async fn insert_bulk(connection: &mut SqliteConnection, articles: &[Self]) -> sqlx::Result<()> {
+        for chunk in articles.chunks(BULK_INSERT_ROWS) {
+            let prices = chunk
+                .iter()
+                .map(|article| serde_json::to_string(&article.prices))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Into::into)
+                .map_err(sqlx::Error::Encode)?;
+
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO articles (id, designation, prices, category, account) ",
+            );
+            query.push_values(chunk.iter().zip(prices), |mut row, (article, prices)| {
+                row.push_bind(&article.id)
+                    .push_bind(&article.designation)
+                    .push_bind(prices)
+                    .push_bind(&article.category)
+                    .push_bind(&article.account);
+            });
+            query.build().execute(&mut *connection).await?;
+        }
+
+        Ok(())
+    }
+
     /// Remove all articles from the database and insert a new set of articles.
     ///
-    /// If any article fails to insert, a warning is logged, but the transaction
-    /// is still committed. This ensures that we still insert as many articles as
-    /// possible, even if some of them e.g. share the same barcode causing a
-    /// unique constraint violation.
+    /// If multiple articles share the same barcode, only the first one is
+    /// inserted and a warning is logged for the others. This ensures that we
+    /// still insert as many articles as possible, instead of failing the
+    /// whole synchronization because of a unique constraint violation.
     pub async fn save_all(pool: SqlitePool, articles: Vec<Self>) -> sqlx::Result<()> {
+        let mut ids = HashSet::new();
+        let articles = articles
+            .into_iter()
+            .filter(|article| {
+                let unique = ids.insert(article.id.clone());
+                if !unique {
+                    warn!("Failed to insert article: duplicate barcode {}", article.id);
+                }
+                unique
+            })
+            .collect::<Vec<_>>();
+
         let mut transaction = pool.begin().await?;
 
         Self::delete_all(&mut transaction).await?;
-        for article in articles {
-            if let Err(error) = article.insert(&mut transaction).await {
-                warn!("Failed to insert article: {error}");
-            }
-        }
+        Self::insert_bulk(&mut transaction, &articles).await?;
 
         transaction.commit().await
     }
@@ -326,8 +831,142 @@ impl Article {
     pub fn price_for_date(&self, date: &jiff::civil::Date) -> Option<Decimal> {
         self.prices
             .iter()
-            .find(|price| price.valid_from <= *date && price.valid_to >= *date)
+            .filter(|price| price.member_group.is_none())
+            .find(|price| price.is_valid_on(date))
+            .map(|price| price.unit_price)
+    }
+
+    /// Get the price of the article for a specific date and member group.
+    ///
+    /// If there is no price for the member group, the general price for the
+    /// date is returned.
+    pub fn price_for_member_group(
+        &self,
+        date: &jiff::civil::Date,
+        member_group: &str,
+    ) -> Option<Decimal> {
+        self.prices
+            .iter()
+            .filter(|price| price.member_group.as_deref() == Some(member_group))
+            .find(|price| price.is_valid_on(date))
             .map(|price| price.unit_price)
+            .or_else(|| self.price_for_date(date))
+    }
+}
+
+/// A change of the current price of an article, which was detected when
+/// the articles were synchronized.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PriceChange {
+    pub changed_at: Text<jiff::Timestamp>,
+    pub article_id: String,
+    pub designation: String,
+    /// The price before the synchronization, or `None` if the article had
+    /// no price for the date.
+    pub old_price: Option<Text<Decimal>>,
+    /// The price after the synchronization, or `None` if the article has
+    /// no price for the date anymore.
+    pub new_price: Option<Text<Decimal>>,
+}
+
+impl PriceChange {
+    /// Compare the prices of the articles on the given date before and after
+    /// a synchronization.
+    ///
+    /// Articles that were added or removed are not considered price changes.
+    pub fn diff(previous: &[Article], current: &[Article], date: jiff::civil::Date) -> Vec<Self> {
+        let previous = previous
+            .iter()
+            .map(|article| (article.id.as_str(), article.price_for_date(&date)))
+            .collect::<HashMap<_, _>>();
+
+        let changed_at = jiff::Timestamp::now();
+        current
+            .iter()
+            .filter_map(|article| {
+                let old_price = *previous.get(article.id.as_str())?;
+                let new_price = article.price_for_date(&date);
+                (old_price != new_price).then(|| Self {
+                    changed_at: Text(changed_at),
+                    article_id: article.id.clone(),
+                    designation: article.designation.clone(),
+                    old_price: old_price.map(Text),
+                    new_price: new_price.map(Text),
+                })
+            })
+            .collect()
+    }
+
+    /// Save detected price changes, which are unseen until they are loaded
+    /// with [`PriceChange::load_recent()`].
+    pub async fn insert_all(pool: &SqlitePool, changes: &[Self]) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        for change in changes {
+            sqlx::query(
+                r#"
+                INSERT INTO price_changes
+                    (changed_at, article_id, designation, old_price, new_price)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(change.changed_at)
+            .bind(&change.article_id)
+            .bind(&change.designation)
+            .bind(change.old_price)
+            .bind(change.new_price)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// Count the price changes that an admin has not seen yet.
+    pub async fn count_unseen(pool: &SqlitePool) -> sqlx::Result<u32> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM price_changes WHERE NOT seen")
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Load the most recent price changes, newest first, and mark all
+    /// changes as seen.
+    pub async fn load_recent(pool: &SqlitePool, limit: u32) -> sqlx::Result<Vec<Self>> {
+        let mut transaction = pool.begin().await?;
+
+        let changes = sqlx::query_as(
+            r#"
+            SELECT changed_at, article_id, designation, old_price, new_price
+            FROM price_changes
+            ORDER BY id DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        sqlx::query("UPDATE price_changes SET seen = true WHERE NOT seen")
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        Ok(changes)
+    }
+
+    /// A short description of the change, e.g. `Cola: 1,50 € → 1,80 €`.
+    pub fn label(&self) -> String {
+        let format = |price: Option<Text<Decimal>>| match price {
+            Some(price) => currency::format(*price),
+            None => "kein Preis".to_string(),
+        };
+
+        format!(
+            "{}: {} → {}",
+            self.designation,
+            format(self.old_price),
+            format(self.new_price)
+        )
     }
 }
 
@@ -338,96 +977,2242 @@ impl Article {
 /// connection and upload the sales later. This also works around the 500
 /// request limit per day, since the remaining sales can be synchronized on
 /// the next day.
-#[derive(Debug, sqlx::FromRow)]
+///
+/// After the upload, sales are kept for a while as purchase history, until
+/// they are removed by [`Sale::delete_uploaded_before()`].
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct Sale {
     /// The unique ID of the sale.
     pub id: Text<Ulid>,
-    /// The date of the sale.
-    pub date: Text<jiff::civil::Date>,
+    /// The time of the sale, including the time zone of the device.
+    pub created_at: Text<jiff::Zoned>,
     /// The member ID of the buyer (aka. "Mitgliedsnummer").
     pub member_id: String,
     /// The article ID of the sold article (aka. "Artikelnummer").
     pub article_id: String,
-    /// The amount of articles sold.
-    pub amount: u32,
+    /// The amount of articles sold, which is negative for refunds.
+    pub amount: i32,
+    /// The unit price of the article at the time of the sale.
+    ///
+    /// This is `None` for sales that were recorded before the unit price
+    /// was stored.
+    pub unit_price: Option<Text<Decimal>>,
+    /// Whether the price of the sale was entered manually by the member,
+    /// in which case the total price is sent to Vereinsflieger explicitly.
+    pub open_price: bool,
+    /// The transaction ID of the card payment, if the sale was paid by card
+    /// instead of being booked to a member account.
+    pub payment_reference: Option<String>,
+    /// Whether a guest paid the sale themselves by bank transfer or PayPal.
+    pub self_paid: bool,
+    /// The Vereinsflieger cost type, if the sale was booked to a cost center.
+    pub cost_type: Option<String>,
+    /// The time at which the upload of this sale to Vereinsflieger was
+    /// started, or `None` if no upload is currently in progress.
+    ///
+    /// If this is set when the sales are loaded for the next upload, the
+    /// previous upload was interrupted and it is unknown whether the sale
+    /// has been booked in Vereinsflieger.
+    pub upload_started_at: Option<Text<jiff::Timestamp>>,
 }
 
 impl Sale {
-    /// Load all sales from the database.
+    /// Load all sales that have not been uploaded yet from the database,
+    /// ordered by the time of the sale.
     pub async fn load_all(pool: SqlitePool) -> sqlx::Result<Vec<Self>> {
+        let mut sales: Vec<Self> = sqlx::query_as(
+            r#"
+            SELECT id, created_at, member_id, article_id, amount, unit_price, open_price,
+                payment_reference, self_paid, cost_type, upload_started_at
+            FROM sales
+            WHERE uploaded_at IS NULL
+            "#,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        sales.sort_by_key(|sale| sale.created_at.timestamp());
+
+        Ok(sales)
+    }
+
+    /// Load all sales (including uploaded ones) of a member with the given
+    /// booking date.
+    pub async fn load_for_member_on(
+        pool: SqlitePool,
+        member_id: &str,
+        date: jiff::civil::Date,
+    ) -> sqlx::Result<Vec<Self>> {
+        // `created_at` starts with the civil date in the time zone of the
+        // sale, which is the booking date.
         sqlx::query_as(
             r#"
-            SELECT id, date, member_id, article_id, amount
+            SELECT id, created_at, member_id, article_id, amount, unit_price, open_price,
+                payment_reference, self_paid, cost_type, upload_started_at
             FROM sales
+            WHERE member_id = $1 AND substr(created_at, 1, 10) = $2
             "#,
         )
+        .bind(member_id)
+        .bind(date.to_string())
         .fetch_all(&pool)
         .await
     }
 
-    /// Insert a sale into the database.
-    async fn insert(&self, connection: &mut SqliteConnection) -> sqlx::Result<()> {
-        sqlx::query(
+    /// Load the sales of the most recent purchase of a member (including
+    /// uploaded ones), ignoring refunds.
+    pub async fn load_last_purchase(pool: &SqlitePool, member_id: &str) -> sqlx::Result<Vec<Self>> {
+        // All sales of a purchase are saved with the same timestamp, and the
+        // IDs are ULIDs, which sort by the time at which they were created.
+        sqlx::query_as(
             r#"
-            INSERT INTO sales (id, date, member_id, article_id, amount)
-            VALUES ($1, $2, $3, $4, $5)
+            SELECT id, created_at, member_id, article_id, amount, unit_price, open_price,
+                payment_reference, self_paid, cost_type, upload_started_at
+            FROM sales
+            WHERE member_id = $1 AND created_at = (
+                SELECT created_at FROM sales
+                WHERE member_id = $1 AND amount > 0
+                ORDER BY id DESC
+                LIMIT 1
+            )
+            ORDER BY id
             "#,
         )
-        .bind(self.id)
-        .bind(self.date)
-        .bind(&self.member_id)
-        .bind(&self.article_id)
-        .bind(self.amount)
-        .execute(connection)
+        .bind(member_id)
+        .fetch_all(pool)
         .await
-        .map(|_| ())
     }
 
-    /// Insert multiple sales into the database.
-    #[tracing::instrument(skip(pool))]
-    pub async fn insert_all(pool: SqlitePool, sales: Vec<Sale>) -> sqlx::Result<()> {
-        info!("Adding sales to database…");
+    /// Load all sales (including uploaded ones) with a booking date in the
+    /// given range, ordered by member and time.
+    pub async fn load_between(
+        pool: &SqlitePool,
+        start: jiff::civil::Date,
+        end: jiff::civil::Date,
+    ) -> sqlx::Result<Vec<Self>> {
+        let mut sales: Vec<Self> = sqlx::query_as(
+            r#"
+            SELECT id, created_at, member_id, article_id, amount, unit_price, open_price,
+                payment_reference, self_paid, cost_type, upload_started_at
+            FROM sales
+            WHERE substr(created_at, 1, 10) BETWEEN $1 AND $2
+            "#,
+        )
+        .bind(start.to_string())
+        .bind(end.to_string())
+        .fetch_all(pool)
+        .await?;
 
-        let mut transaction = pool.begin().await?;
+        sales.sort_by(|a, b| {
+            let a_key = (&a.member_id, a.created_at.timestamp());
+            a_key.cmp(&(&b.member_id, b.created_at.timestamp()))
+        });
 
-        for sale in sales {
-            sale.insert(&mut transaction).await?;
+        Ok(sales)
+    }
+
+    /// The booking date of the sale in Vereinsflieger, which is the civil
+    /// date in the time zone where the sale happened.
+    pub fn booking_date(&self) -> jiff::civil::Date {
+        self.created_at.date()
+    }
+
+    /// The total price of the sale, if the unit price is known.
+    pub fn total(&self) -> Option<Decimal> {
+        self.unit_price
+            .map(|unit_price| Decimal::from(self.amount) * *unit_price)
+    }
+
+    /// Count the sales that have not been uploaded yet.
+    pub async fn count(pool: &SqlitePool) -> sqlx::Result<u32> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM sales WHERE uploaded_at IS NULL")
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Insert multiple sales into the database with multi-row `INSERT`
+    /// statements, and update the stock once per article.
+    ///
+    /// This runs on an existing connection, e.g. within the transaction of
+    /// a [`Checkout`].
+    #[tracing::instrument(skip(connection))]
+    async fn insert_rows(connection: &mut SqliteConnection, sales: &[Sale]) -> sqlx::Result<()> {
+        info!("Adding sales to database…");
+
+        for chunk in sales.chunks(BULK_INSERT_ROWS) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                r#"
+                INSERT INTO sales (
+                    id, created_at, member_id, article_id, amount, unit_price, open_price,
+                    payment_reference, self_paid, cost_type
+                )
+                "#,
+            );
+            query.push_values(chunk, |mut row, sale| {
+                row.push_bind(sale.id)
+                    .push_bind(&sale.created_at)
+                    .push_bind(&sale.member_id)
+                    .push_bind(&sale.article_id)
+                    .push_bind(sale.amount)
+                    .push_bind(sale.unit_price)
+                    .push_bind(sale.open_price)
+                    .push_bind(&sale.payment_reference)
+                    .push_bind(sale.self_paid)
+                    .push_bind(&sale.cost_type);
+            });
+            query.build().execute(&mut *connection).await?;
+        }
+
+        // Discounts and vouchers are booked with a negative price and do
+        // not take an article out of the fridge
+        let mut sold = HashMap::<&str, i32>::new();
+        for sale in sales {
+            if sale
+                .unit_price
+                .is_none_or(|price| !price.is_sign_negative())
+            {
+                *sold.entry(sale.article_id.as_str()).or_default() += sale.amount;
+            }
+        }
+        for (article_id, amount) in sold {
+            Stock::remove_sold(connection, article_id, amount).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The comment that is attached to the booking in Vereinsflieger.
+    ///
+    /// This contains the unique sale ID, so that sales can be matched
+    /// with their bookings in Vereinsflieger.
+    pub fn comment(&self) -> String {
+        match &self.payment_reference {
+            Some(reference) => format!("clubfridge-neo {} (Karte {reference})", *self.id),
+            None if self.self_paid => format!("clubfridge-neo {} (Selbstzahler)", *self.id),
+            None => format!("clubfridge-neo {}", *self.id),
+        }
+    }
+
+    /// Remember that the upload of the sale with the given ID has started.
+    pub async fn mark_upload_started(pool: &SqlitePool, id: Ulid) -> sqlx::Result<()> {
+        sqlx::query("UPDATE sales SET upload_started_at = $2 WHERE id = $1")
+            .bind(id.to_string())
+            .bind(jiff::Timestamp::now().to_string())
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Forget that the upload of the sale with the given ID has started,
+    /// because the upload definitely failed and can be retried.
+    ///
+    /// This also counts the failed attempt for [`Sale::count_stuck()`].
+    pub async fn mark_upload_failed(pool: &SqlitePool, id: Ulid) -> sqlx::Result<()> {
+        sqlx::query(
+            "UPDATE sales
+             SET upload_started_at = NULL, upload_failures = upload_failures + 1
+             WHERE id = $1",
+        )
+        .bind(id.to_string())
+        .execute(pool)
+        .await
+        .map(|_| ())
+    }
+
+    /// Decide about a sale whose upload was interrupted, after an admin
+    /// checked whether it arrived in Vereinsflieger. The sale is either
+    /// uploaded again or marked as uploaded without sending it again.
+    ///
+    /// Returns `false` if the sale is not waiting for a decision anymore.
+    pub async fn resolve_interrupted(
+        pool: &SqlitePool,
+        id: Ulid,
+        resend: bool,
+    ) -> sqlx::Result<bool> {
+        let query = match resend {
+            true => {
+                "UPDATE sales SET upload_started_at = NULL
+                 WHERE id = $1 AND uploaded_at IS NULL AND upload_started_at IS NOT NULL"
+            }
+            false => {
+                "UPDATE sales SET uploaded_at = $2
+                 WHERE id = $1 AND uploaded_at IS NULL AND upload_started_at IS NOT NULL"
+            }
+        };
+
+        sqlx::query(query)
+            .bind(id.to_string())
+            .bind(jiff::Timestamp::now().to_string())
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Count the sales that have not been uploaded yet although their upload
+    /// failed at least `max_failures` times or they are older than `max_age`.
+    pub async fn count_stuck(
+        pool: &SqlitePool,
+        max_failures: u32,
+        max_age: jiff::SignedDuration,
+    ) -> sqlx::Result<u32> {
+        let sales: Vec<(Text<jiff::Zoned>, u32)> = sqlx::query_as(
+            "SELECT created_at, upload_failures FROM sales WHERE uploaded_at IS NULL",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let cutoff = jiff::Timestamp::now() - max_age;
+        let stuck = sales.iter().filter(|(created_at, failures)| {
+            *failures >= max_failures || created_at.timestamp() < cutoff
+        });
+
+        Ok(stuck.count() as u32)
+    }
+
+    /// Change the member and amount of a sale whose upload has not started
+    /// yet, and adjust the stock of the article by the difference.
+    ///
+    /// Returns `false` if the sale was uploaded or its upload started in
+    /// the meantime.
+    pub async fn update_pending(
+        pool: &SqlitePool,
+        id: Ulid,
+        member_id: &str,
+        amount: i32,
+    ) -> sqlx::Result<bool> {
+        let mut transaction = pool.begin().await?;
+
+        let sale: Option<(String, i32, Option<Text<Decimal>>)> = sqlx::query_as(
+            r#"
+            SELECT article_id, amount, unit_price
+            FROM sales
+            WHERE id = $1 AND uploaded_at IS NULL AND upload_started_at IS NULL
+            "#,
+        )
+        .bind(id.to_string())
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let Some((article_id, previous_amount, unit_price)) = sale else {
+            return Ok(false);
+        };
+
+        sqlx::query("UPDATE sales SET member_id = $2, amount = $3 WHERE id = $1")
+            .bind(id.to_string())
+            .bind(member_id)
+            .bind(amount)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Same as in `insert_all()`, discounts and vouchers have no stock
+        if unit_price.is_none_or(|price| !price.is_sign_negative()) {
+            let difference = amount - previous_amount;
+            Stock::remove_sold(&mut transaction, &article_id, difference).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Remember that the sale with the given ID was successfully uploaded.
+    pub async fn mark_uploaded(pool: &SqlitePool, id: Ulid) -> sqlx::Result<()> {
+        sqlx::query("UPDATE sales SET uploaded_at = $2 WHERE id = $1")
+            .bind(id.to_string())
+            .bind(jiff::Timestamp::now().to_string())
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Delete all sales that were uploaded before the given time.
+    ///
+    /// Returns the number of deleted sales.
+    pub async fn delete_uploaded_before(
+        pool: &SqlitePool,
+        timestamp: jiff::Timestamp,
+    ) -> sqlx::Result<u64> {
+        sqlx::query("DELETE FROM sales WHERE uploaded_at IS NOT NULL AND uploaded_at < $1")
+            .bind(timestamp.to_string())
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+    }
+}
+
+/// A paid cart, which is saved in a single transaction, so that e.g. a
+/// voucher is never redeemed without its sales or the other way around.
+#[derive(Debug, Default)]
+pub struct Checkout {
+    pub sales: Vec<Sale>,
+    /// Whether the cart was paid in cash, which records the sales in the
+    /// cash ledger instead of booking them in Vereinsflieger.
+    pub cash: bool,
+    /// The barcodes of the vouchers that were redeemed in the cart.
+    pub vouchers: Vec<String>,
+    /// The total of the credit articles in the cart, which tops up the
+    /// prepaid balance of the member.
+    pub credit: Decimal,
+    /// The article to which the part of the total that is paid from the
+    /// prepaid balance is booked with a negative price, if the cart is
+    /// paid from the balance at all.
+    pub balance_article: Option<String>,
+}
+
+impl Checkout {
+    /// Save the sales, redeem the vouchers and update the prepaid balance
+    /// of the member. If any of this fails, nothing is saved.
+    ///
+    /// The purchases are paid from the prepaid balance as far as possible,
+    /// so that only the remainder is booked to the member account. Returns
+    /// the amount that was paid from the balance.
+    pub async fn save(mut self, pool: &SqlitePool) -> sqlx::Result<Decimal> {
+        let mut transaction = pool.begin().await?;
+
+        let member_id = self.sales.first().map(|sale| sale.member_id.clone());
+        let member_id = member_id.filter(|member_id| !member_id.is_empty());
+
+        let mut debit = Decimal::ZERO;
+        if let Some(member_id) = &member_id {
+            if !self.credit.is_zero() {
+                Balance::add(&mut transaction, member_id, self.credit).await?;
+            }
+
+            if let Some(article_id) = self.balance_article.take() {
+                let total = self.sales.iter().filter_map(Sale::total).sum::<Decimal>();
+                let purchases = total - self.credit;
+                debit = Balance::debit(&mut transaction, member_id, purchases).await?;
+                if !debit.is_zero() {
+                    self.sales.push(Sale {
+                        id: Text(Ulid::new()),
+                        article_id,
+                        amount: 1,
+                        unit_price: Some(Text(-debit)),
+                        open_price: true,
+                        ..self.sales[0].clone()
+                    });
+                }
+            }
+        }
+
+        if self.cash {
+            let entries = self.sales.iter().map(CashEntry::from).collect();
+            CashEntry::insert_rows(&mut transaction, entries).await?;
+        } else {
+            Sale::insert_rows(&mut transaction, &self.sales).await?;
+        }
+
+        Voucher::redeem_all(&mut transaction, &self.vouchers).await?;
+
+        transaction.commit().await?;
+
+        Ok(debit)
+    }
+}
+
+#[cfg(test)]
+impl Sale {
+    /// Insert the sales in their own transaction.
+    pub async fn insert_all(pool: SqlitePool, sales: Vec<Sale>) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+        Self::insert_rows(&mut transaction, &sales).await?;
+        transaction.commit().await
+    }
+
+    /// Create a pending sale of one article by member `1` for tests, without
+    /// a unit price. The other fields can be changed with the `with_*()`
+    /// methods.
+    pub fn test(article_id: &str) -> Self {
+        Self {
+            id: Text(Ulid::new()),
+            created_at: Text(jiff::Zoned::now()),
+            member_id: "1".to_string(),
+            article_id: article_id.to_string(),
+            amount: 1,
+            unit_price: None,
+            open_price: false,
+            payment_reference: None,
+            self_paid: false,
+            cost_type: None,
+            upload_started_at: None,
+        }
+    }
+
+    pub fn with_member(mut self, member_id: &str) -> Self {
+        self.member_id = member_id.to_string();
+        self
+    }
+
+    pub fn with_amount(mut self, amount: i32) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Set the unit price in cents.
+    pub fn with_unit_price(mut self, cents: i64) -> Self {
+        self.unit_price = Some(Text(Decimal::new(cents, 2)));
+        self
+    }
+
+    pub fn with_created_at(mut self, created_at: &str) -> Self {
+        self.created_at = Text(created_at.parse().unwrap());
+        self
+    }
+}
+
+/// The logged-in member and their in-progress cart.
+///
+/// This is saved in the `session` table on every change, so that an
+/// unfinished purchase can be restored after a crash or power loss.
+///
+/// Carts that were parked by their member are stored in the same table,
+/// until the member logs in again.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Session {
+    /// The member ID of the logged-in member.
+    pub member_id: String,
+    /// Whether the cart is booked as a refund.
+    pub refund: bool,
+    /// The articles in the cart.
+    #[sqlx(json)]
+    pub cart: Vec<crate::running::Sale>,
+}
+
+impl Session {
+    /// Load the saved session, if there is one.
+    pub async fn load(pool: &SqlitePool) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as("SELECT member_id, refund, cart FROM session WHERE id = 0")
+            .fetch_optional(pool)
+            .await
+    }
+
+    fn cart_json(&self) -> sqlx::Result<String> {
+        serde_json::to_string(&self.cart)
+            .map_err(Into::into)
+            .map_err(sqlx::Error::Encode)
+    }
+
+    /// Save the session, replacing any previously saved session.
+    pub async fn save(&self, pool: &SqlitePool) -> sqlx::Result<()> {
+        let cart = self.cart_json()?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session (id, member_id, refund, cart, updated_at)
+            VALUES (0, $1, $2, $3, $4)
+            "#,
+        )
+        .bind(&self.member_id)
+        .bind(self.refund)
+        .bind(cart)
+        .bind(Text(jiff::Timestamp::now()))
+        .execute(pool)
+        .await
+        .map(|_| ())
+    }
+
+    /// Delete the saved session.
+    pub async fn clear(pool: &SqlitePool) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM session WHERE id = 0")
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Park the cart until the member logs in again, replacing any cart that
+    /// the member parked before, and delete the saved session.
+    pub async fn park(&self, pool: &SqlitePool) -> sqlx::Result<()> {
+        let cart = self.cart_json()?;
+        let now = Text(jiff::Timestamp::now());
+
+        let mut transaction = pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session (member_id, refund, cart, updated_at, parked_at)
+            VALUES ($1, $2, $3, $4, $4)
+            "#,
+        )
+        .bind(&self.member_id)
+        .bind(self.refund)
+        .bind(cart)
+        .bind(now)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM session WHERE id = 0")
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await
+    }
+
+    /// Remove the parked cart of the given member from the database and
+    /// return it, if it was parked after the given time.
+    ///
+    /// Carts that were parked before the given time are deleted.
+    pub async fn take_parked(
+        pool: &SqlitePool,
+        member_id: &str,
+        parked_after: jiff::Timestamp,
+    ) -> sqlx::Result<Option<Self>> {
+        let mut transaction = pool.begin().await?;
+        let session = sqlx::query_as(
+            r#"
+            SELECT member_id, refund, cart FROM session
+            WHERE member_id = $1 AND parked_at IS NOT NULL AND parked_at >= $2
+            "#,
+        )
+        .bind(member_id)
+        .bind(parked_after.to_string())
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM session
+            WHERE parked_at IS NOT NULL AND (member_id = $1 OR parked_at < $2)
+            "#,
+        )
+        .bind(member_id)
+        .bind(parked_after.to_string())
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(session)
+    }
+}
+
+/// Settings that are chosen in the UI and stored in the `settings` table.
+pub struct Settings;
+
+impl Settings {
+    async fn get(pool: &SqlitePool, key: &str) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar("SELECT value FROM settings WHERE key = $1")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn set(pool: &SqlitePool, key: &str, value: &str) -> sqlx::Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES ($1, $2)")
+            .bind(key)
+            .bind(value)
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Whether the installation was set up without Vereinsflieger on the
+    /// setup screen.
+    pub async fn offline(pool: &SqlitePool) -> sqlx::Result<bool> {
+        let value = Self::get(pool, "offline").await?;
+        Ok(value.as_deref() == Some("true"))
+    }
+
+    /// Remember whether the installation runs without Vereinsflieger.
+    pub async fn set_offline(pool: &SqlitePool, offline: bool) -> sqlx::Result<()> {
+        Self::set(pool, "offline", &offline.to_string()).await
+    }
+
+    /// The base32-encoded TOTP secret for opening the admin screen, if one
+    /// was provisioned.
+    pub async fn admin_totp_secret(pool: &SqlitePool) -> sqlx::Result<Option<String>> {
+        Self::get(pool, "admin_totp_secret").await
+    }
+
+    /// Replace the TOTP secret for opening the admin screen.
+    pub async fn set_admin_totp_secret(pool: &SqlitePool, secret: &str) -> sqlx::Result<()> {
+        Self::set(pool, "admin_totp_secret", secret).await
+    }
+}
+
+/// An entry of the local cash ledger.
+///
+/// Sales that were paid in cash are recorded here instead of the `sales`
+/// table, so that they are not booked to a member account. Emptying the
+/// cash box is recorded as an entry without article, which resets the
+/// balance to zero.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CashEntry {
+    /// The unique ID of the entry.
+    pub id: Text<Ulid>,
+    /// The time of the entry, including the time zone of the device.
+    pub created_at: Text<jiff::Zoned>,
+    /// The member ID of the buyer, which is empty for guests.
+    pub member_id: String,
+    /// The article ID of the sold article, or `None` if the cash box
+    /// was emptied.
+    pub article_id: Option<String>,
+    /// The amount of articles sold.
+    pub amount: i32,
+    /// The amount of money that was added to (or removed from) the cash box.
+    pub total: Text<Decimal>,
+}
+
+impl From<&Sale> for CashEntry {
+    fn from(sale: &Sale) -> Self {
+        Self {
+            id: sale.id,
+            created_at: sale.created_at.clone(),
+            member_id: sale.member_id.clone(),
+            article_id: Some(sale.article_id.clone()),
+            amount: sale.amount,
+            total: Text(sale.total().unwrap_or_default()),
+        }
+    }
+}
+
+impl CashEntry {
+    /// Insert multiple entries into the cash ledger.
+    pub async fn insert_all(pool: &SqlitePool, entries: Vec<Self>) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+        Self::insert_rows(&mut transaction, entries).await?;
+        transaction.commit().await
+    }
+
+    /// Insert the entries and update the stock on an existing connection,
+    /// e.g. within the transaction of a [`Checkout`].
+    async fn insert_rows(
+        connection: &mut SqliteConnection,
+        entries: Vec<Self>,
+    ) -> sqlx::Result<()> {
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO cash_ledger (id, created_at, member_id, article_id, amount, total)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(entry.id)
+            .bind(&entry.created_at)
+            .bind(&entry.member_id)
+            .bind(&entry.article_id)
+            .bind(entry.amount)
+            .bind(entry.total)
+            .execute(&mut *connection)
+            .await?;
+
+            if let Some(article_id) = &entry.article_id {
+                // Discounts and vouchers reduce the total of a sale
+                let is_discount = entry.total.is_sign_negative() != (entry.amount < 0);
+                if !is_discount {
+                    Stock::remove_sold(connection, article_id, entry.amount).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The expected contents of the cash box.
+    pub async fn balance(pool: &SqlitePool) -> sqlx::Result<Decimal> {
+        let totals: Vec<Text<Decimal>> = sqlx::query_scalar("SELECT total FROM cash_ledger")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(totals.into_iter().map(|total| *total).sum())
+    }
+
+    /// Record that the cash box was emptied, which resets the balance
+    /// to zero.
+    pub async fn empty_cash_box(pool: &SqlitePool) -> sqlx::Result<()> {
+        let balance = Self::balance(pool).await?;
+
+        let entry = Self {
+            id: Text(Ulid::new()),
+            created_at: Text(jiff::Zoned::now()),
+            member_id: String::new(),
+            article_id: None,
+            amount: 0,
+            total: Text(-balance),
+        };
+
+        Self::insert_all(pool, vec![entry]).await
+    }
+}
+
+/// The bank account of a member, which is used for SEPA direct debits.
+#[derive(Clone, sqlx::FromRow)]
+pub struct BankAccount {
+    /// The member ID of the account holder.
+    pub member_id: String,
+    /// The IBAN of the account.
+    pub iban: String,
+}
+
+impl BankAccount {
+    /// Load the bank accounts of all members, keyed by member ID.
+    pub async fn load_all(pool: &SqlitePool) -> sqlx::Result<HashMap<String, String>> {
+        let accounts: Vec<Self> = sqlx::query_as("SELECT member_id, iban FROM bank_accounts")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(accounts
+            .into_iter()
+            .map(|account| (account.member_id, account.iban))
+            .collect())
+    }
+
+    /// Remove all bank accounts from the database and insert a new set
+    /// of accounts.
+    pub async fn save_all(pool: &SqlitePool, accounts: Vec<Self>) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("DELETE FROM bank_accounts")
+            .execute(&mut *transaction)
+            .await?;
+
+        for account in accounts {
+            sqlx::query("INSERT OR REPLACE INTO bank_accounts (member_id, iban) VALUES ($1, $2)")
+                .bind(&account.member_id)
+                .bind(&account.iban)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await
+    }
+}
+
+/// The prepaid balance of a member, which is topped up by buying
+/// credit articles.
+pub struct Balance;
+
+impl Balance {
+    /// Load the balance of the given member, which is zero if the member
+    /// never topped up their balance.
+    pub async fn load(pool: &SqlitePool, member_id: &str) -> sqlx::Result<Decimal> {
+        let balance: Option<Text<Decimal>> =
+            sqlx::query_scalar("SELECT balance FROM balances WHERE member_id = $1")
+                .bind(member_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(balance.map(|balance| *balance).unwrap_or_default())
+    }
+
+    /// Add the given amount to the balance of the member, returning the
+    /// new balance.
+    async fn add(
+        connection: &mut SqliteConnection,
+        member_id: &str,
+        amount: Decimal,
+    ) -> sqlx::Result<Decimal> {
+        let balance: Option<Text<Decimal>> =
+            sqlx::query_scalar("SELECT balance FROM balances WHERE member_id = $1")
+                .bind(member_id)
+                .fetch_optional(&mut *connection)
+                .await?;
+
+        let balance = balance.map(|balance| *balance).unwrap_or_default() + amount;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO balances (member_id, balance, updated_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(member_id)
+        .bind(Text(balance))
+        .bind(Text(jiff::Timestamp::now()))
+        .execute(&mut *connection)
+        .await?;
+
+        info!(%member_id, "Prepaid balance changed to {balance}€");
+
+        Ok(balance)
+    }
+
+    /// Take up to `amount` from the balance of the member, without letting
+    /// the balance become negative. Returns the amount that was taken.
+    async fn debit(
+        connection: &mut SqliteConnection,
+        member_id: &str,
+        amount: Decimal,
+    ) -> sqlx::Result<Decimal> {
+        let balance: Option<Text<Decimal>> =
+            sqlx::query_scalar("SELECT balance FROM balances WHERE member_id = $1")
+                .bind(member_id)
+                .fetch_optional(&mut *connection)
+                .await?;
+
+        let balance = balance.map(|balance| *balance).unwrap_or_default();
+        let debit = amount.min(balance).max(Decimal::ZERO);
+        if !debit.is_zero() {
+            Self::add(connection, member_id, -debit).await?;
+        }
+
+        Ok(debit)
+    }
+}
+
+/// A voucher (e.g. for a welcome drink of new members or an event
+/// giveaway), which reduces the total of a purchase by its value.
+///
+/// Vouchers are created with the `add-voucher` command and can only be
+/// redeemed once.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Voucher {
+    /// The barcode that is printed on the voucher.
+    pub barcode: String,
+    /// The amount of money by which the total is reduced.
+    pub value: Text<Decimal>,
+    /// The first day on which the voucher can be redeemed, if limited.
+    pub valid_from: Option<Text<jiff::civil::Date>>,
+    /// The last day on which the voucher can be redeemed, if limited.
+    pub valid_until: Option<Text<jiff::civil::Date>>,
+    /// The time at which the voucher was redeemed, if it was.
+    pub redeemed_at: Option<Text<jiff::Timestamp>>,
+}
+
+impl Voucher {
+    /// Find a voucher by its barcode.
+    pub async fn find_by_barcode(pool: SqlitePool, barcode: &str) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as(
+            r#"
+            SELECT barcode, value, valid_from, valid_until, redeemed_at
+            FROM vouchers
+            WHERE barcode = $1
+            "#,
+        )
+        .bind(barcode)
+        .fetch_optional(&pool)
+        .await
+    }
+
+    /// Whether the voucher was not redeemed yet and is valid on the
+    /// given day.
+    pub fn is_valid_on(&self, date: jiff::civil::Date) -> bool {
+        self.redeemed_at.is_none()
+            && self.valid_from.is_none_or(|valid_from| *valid_from <= date)
+            && self
+                .valid_until
+                .is_none_or(|valid_until| date <= *valid_until)
+    }
+
+    /// Insert a new voucher, replacing any existing voucher with the
+    /// same barcode.
+    pub async fn insert(&self, pool: &SqlitePool) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO vouchers (barcode, value, valid_from, valid_until, redeemed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&self.barcode)
+        .bind(self.value)
+        .bind(self.valid_from)
+        .bind(self.valid_until)
+        .bind(self.redeemed_at)
+        .execute(pool)
+        .await
+        .map(|_| ())
+    }
+
+    /// Mark the vouchers with the given barcodes as redeemed.
+    ///
+    /// Fails with [`sqlx::Error::RowNotFound`] if one of the vouchers does
+    /// not exist or was already redeemed in the meantime.
+    async fn redeem_all(
+        connection: &mut SqliteConnection,
+        barcodes: &[String],
+    ) -> sqlx::Result<()> {
+        let now = Text(jiff::Timestamp::now());
+        for barcode in barcodes {
+            let result = sqlx::query(
+                "UPDATE vouchers SET redeemed_at = $1 WHERE barcode = $2 AND redeemed_at IS NULL",
+            )
+            .bind(now)
+            .bind(barcode)
+            .execute(&mut *connection)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(sqlx::Error::RowNotFound);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The tracked stock of the articles in the fridge.
+///
+/// Only articles that were counted during a stocktaking are tracked. Their
+/// stock is reduced whenever they are sold.
+pub struct Stock;
+
+/// The difference between the tracked and the counted stock of an article,
+/// which was found during a stocktaking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StockDifference {
+    pub article_id: String,
+    /// The tracked stock before the stocktaking.
+    pub expected: i64,
+    /// The number of units that were found in the fridge.
+    pub counted: i64,
+}
+
+impl StockDifference {
+    /// The number of units that went missing, which is negative if more
+    /// units were found than expected.
+    pub fn shrinkage(&self) -> i64 {
+        self.expected - self.counted
+    }
+}
+
+/// A restocked batch of an article that is still in the fridge and expires
+/// soon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpiringBatch {
+    pub article_id: String,
+    pub designation: String,
+    /// The estimated number of units of the batch that are still in the
+    /// fridge.
+    pub amount: i64,
+    pub best_before: jiff::civil::Date,
+}
+
+impl ExpiringBatch {
+    /// A short description of the batch, e.g. `6x Cola (MHD 01.03.)`.
+    pub fn label(&self) -> String {
+        let best_before = self.best_before.strftime("%d.%m.");
+        format!("{}x {} (MHD {best_before})", self.amount, self.designation)
+    }
+}
+
+impl Stock {
+    /// Load the tracked stock of all articles, keyed by article ID.
+    pub async fn load_all(pool: &SqlitePool) -> sqlx::Result<HashMap<String, i64>> {
+        Self::load_all_with(&mut *pool.acquire().await?).await
+    }
+
+    async fn load_all_with(
+        connection: &mut SqliteConnection,
+    ) -> sqlx::Result<HashMap<String, i64>> {
+        let stock: Vec<(String, i64)> = sqlx::query_as("SELECT article_id, amount FROM stock")
+            .fetch_all(connection)
+            .await?;
+
+        Ok(stock.into_iter().collect())
+    }
+
+    /// Reduce the stock of a tracked article by the sold amount, which
+    /// increases the stock for refunds.
+    async fn remove_sold(
+        connection: &mut SqliteConnection,
+        article_id: &str,
+        amount: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query("UPDATE stock SET amount = amount - $1, updated_at = $2 WHERE article_id = $3")
+            .bind(amount)
+            .bind(Text(jiff::Timestamp::now()))
+            .bind(article_id)
+            .execute(connection)
+            .await
+            .map(|_| ())
+    }
+
+    /// Add restocked units of an article to its stock and log who restocked
+    /// it, together with the best-before date of perishable articles.
+    /// Returns the new stock.
+    ///
+    /// Articles that were not tracked before are tracked from now on,
+    /// starting with the restocked amount.
+    pub async fn restock(
+        pool: &SqlitePool,
+        member_id: &str,
+        article_id: &str,
+        amount: i64,
+        best_before: Option<jiff::civil::Date>,
+    ) -> sqlx::Result<i64> {
+        let mut transaction = pool.begin().await?;
+        let now = Text(jiff::Zoned::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO restocks (id, created_at, member_id, article_id, amount, best_before)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Text(Ulid::new()))
+        .bind(&now)
+        .bind(member_id)
+        .bind(article_id)
+        .bind(amount)
+        .bind(best_before.map(Text))
+        .execute(&mut *transaction)
+        .await?;
+
+        let stock = sqlx::query_scalar(
+            r#"
+            INSERT INTO stock (article_id, amount, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (article_id) DO UPDATE
+            SET amount = amount + excluded.amount, updated_at = excluded.updated_at
+            RETURNING amount
+            "#,
+        )
+        .bind(article_id)
+        .bind(amount)
+        .bind(Text(now.timestamp()))
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(stock)
+    }
+
+    /// Find the batches of articles in the fridge that expire on or before
+    /// the given day, ordered by their best-before date.
+    ///
+    /// The batches in the fridge are estimated from the tracked stock,
+    /// assuming that the oldest units are sold first.
+    pub async fn expiring_until(
+        pool: &SqlitePool,
+        until: jiff::civil::Date,
+    ) -> sqlx::Result<Vec<ExpiringBatch>> {
+        let stock = Self::load_all(pool).await?;
+        let designations = Article::load_designations(pool).await?;
+
+        // Newer restocks have a larger row ID
+        let restocks: Vec<(String, i64, Option<Text<jiff::civil::Date>>)> = sqlx::query_as(
+            "SELECT article_id, amount, best_before FROM restocks ORDER BY rowid DESC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut unassigned = stock;
+        let mut batches = Vec::new();
+        for (article_id, amount, best_before) in restocks {
+            let Some(remaining) = unassigned.get_mut(&article_id).filter(|stock| **stock > 0)
+            else {
+                continue;
+            };
+
+            let amount = amount.min(*remaining);
+            *remaining -= amount;
+
+            if let Some(best_before) = best_before.filter(|date| **date <= until) {
+                batches.push(ExpiringBatch {
+                    designation: designations.get(&article_id).cloned().unwrap_or_default(),
+                    article_id,
+                    amount,
+                    best_before: *best_before,
+                });
+            }
+        }
+
+        batches.sort_by_key(|batch| batch.best_before);
+        Ok(batches)
+    }
+
+    /// Save the result of a stocktaking and replace the tracked stock with
+    /// the counted stock.
+    ///
+    /// Tracked articles that were not counted are assumed to be out of
+    /// stock. Returns the differences for all counted and tracked articles,
+    /// ordered by article ID.
+    pub async fn save_stocktaking(
+        pool: &SqlitePool,
+        counted: &HashMap<String, i64>,
+    ) -> sqlx::Result<Vec<StockDifference>> {
+        let mut transaction = pool.begin().await?;
+
+        let expected = Self::load_all_with(&mut transaction).await?;
+
+        let mut article_ids = expected.keys().chain(counted.keys()).collect::<Vec<_>>();
+        article_ids.sort();
+        article_ids.dedup();
+
+        let differences = article_ids
+            .into_iter()
+            .map(|article_id| StockDifference {
+                article_id: article_id.clone(),
+                expected: expected.get(article_id).copied().unwrap_or_default(),
+                counted: counted.get(article_id).copied().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
+        let id = Text(Ulid::new());
+        let now = Text(jiff::Zoned::now());
+        for difference in &differences {
+            sqlx::query(
+                r#"
+                INSERT INTO stocktakings (id, created_at, article_id, expected, counted)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(id)
+            .bind(&now)
+            .bind(&difference.article_id)
+            .bind(difference.expected)
+            .bind(difference.counted)
+            .execute(&mut *transaction)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO stock (article_id, amount, updated_at)
+                VALUES ($1, $2, $3)
+                "#,
+            )
+            .bind(&difference.article_id)
+            .bind(difference.counted)
+            .bind(Text(now.timestamp()))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(differences)
+    }
+}
+
+/// The readings of the fridge temperature sensor.
+pub struct Temperature;
+
+/// The time for which temperature readings are kept.
+const TEMPERATURE_HISTORY_RETENTION: jiff::SignedDuration =
+    jiff::SignedDuration::from_hours(90 * 24);
+
+impl Temperature {
+    /// Log a temperature reading in °C and delete old readings.
+    pub async fn insert(pool: &SqlitePool, temperature: f64) -> sqlx::Result<()> {
+        let now = jiff::Timestamp::now();
+
+        sqlx::query("INSERT INTO temperatures (measured_at, temperature) VALUES ($1, $2)")
+            .bind(Text(now))
+            .bind(temperature)
+            .execute(pool)
+            .await?;
+
+        sqlx::query("DELETE FROM temperatures WHERE measured_at < $1")
+            .bind(Text(now - TEMPERATURE_HISTORY_RETENTION))
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// An entry of the append-only audit log, which records all significant
+/// actions (e.g. logins, scans, payments and admin actions) for dispute
+/// resolution.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub created_at: Text<jiff::Timestamp>,
+    /// The kind of action (e.g. `login` or `pay`).
+    pub action: String,
+    /// The member that the action concerns, if any.
+    pub member_id: Option<String>,
+    /// A human-readable description of the action (e.g. the scanned
+    /// article or the total of a payment).
+    pub details: String,
+}
+
+impl AuditEntry {
+    pub fn new(action: &str, member_id: Option<&str>, details: impl Into<String>) -> Self {
+        Self {
+            created_at: Text(jiff::Timestamp::now()),
+            action: action.to_string(),
+            member_id: member_id.map(ToString::to_string),
+            details: details.into(),
+        }
+    }
+
+    /// Append entries to the audit log.
+    pub async fn insert_all(pool: &SqlitePool, entries: Vec<Self>) -> sqlx::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO audit_log (created_at, action, member_id, details)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(entry.created_at)
+            .bind(entry.action)
+            .bind(entry.member_id)
+            .bind(entry.details)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// Load the most recent entries of the audit log, newest first.
+    pub async fn load_recent(pool: &SqlitePool, limit: u32) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as(
+            r#"
+            SELECT created_at, action, member_id, details
+            FROM audit_log
+            ORDER BY id DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Load all entries of the audit log, oldest first.
+    pub async fn load_all(pool: &SqlitePool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as(
+            r#"
+            SELECT created_at, action, member_id, details
+            FROM audit_log
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+/// Usage metrics that are computed from the audit log and the sales
+/// history, to help clubs with stocking and opening hours.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    /// The number of logins on each of the last [`Usage::LOGIN_DAYS`] days,
+    /// oldest first.
+    pub logins_per_day: Vec<(jiff::civil::Date, u32)>,
+    /// The number of sold units per hour of the day over the last
+    /// [`Usage::SALES_DAYS`] days.
+    pub sales_per_hour: [u32; 24],
+}
+
+impl Usage {
+    pub const LOGIN_DAYS: i64 = 7;
+    pub const SALES_DAYS: i64 = 30;
+
+    /// Compute the usage metrics up to the current date.
+    pub async fn load(pool: &SqlitePool) -> sqlx::Result<Self> {
+        let now = jiff::Zoned::now();
+
+        let since = now.timestamp() - jiff::SignedDuration::from_hours(24 * Self::LOGIN_DAYS);
+        let logins: Vec<Text<jiff::Timestamp>> = sqlx::query_scalar(
+            "SELECT created_at FROM audit_log WHERE action = 'login' AND created_at >= $1",
+        )
+        .bind(Text(since))
+        .fetch_all(pool)
+        .await?;
+
+        // Sales are stored with their local date first, so they can be
+        // filtered by comparing the strings
+        let since = now.date() - jiff::Span::new().days(Self::SALES_DAYS);
+        let sales: Vec<(Text<jiff::Zoned>, i32)> =
+            sqlx::query_as("SELECT created_at, amount FROM sales WHERE created_at >= $1")
+                .bind(since.to_string())
+                .fetch_all(pool)
+                .await?;
+
+        let logins = logins.into_iter().map(|Text(timestamp)| timestamp);
+        let sales = sales
+            .into_iter()
+            .map(|(Text(created_at), amount)| (created_at, amount));
+        Ok(Self::from_events(&now, logins, sales))
+    }
+
+    /// Count the logins per day and the sold units per hour. Refunds are
+    /// not counted.
+    fn from_events(
+        now: &jiff::Zoned,
+        logins: impl IntoIterator<Item = jiff::Timestamp>,
+        sales: impl IntoIterator<Item = (jiff::Zoned, i32)>,
+    ) -> Self {
+        let today = now.date();
+        let mut logins_per_day = (0..Self::LOGIN_DAYS)
+            .rev()
+            .map(|days| (today - jiff::Span::new().days(days), 0))
+            .collect::<Vec<_>>();
+
+        for login in logins {
+            let date = login.to_zoned(now.time_zone().clone()).date();
+            if let Some((_, count)) = logins_per_day.iter_mut().find(|(day, _)| *day == date) {
+                *count += 1;
+            }
+        }
+
+        let mut sales_per_hour = [0; 24];
+        for (created_at, amount) in sales {
+            if amount > 0 {
+                sales_per_hour[created_at.hour() as usize] += amount as u32;
+            }
+        }
+
+        Self {
+            logins_per_day,
+            sales_per_hour,
+        }
+    }
+
+    /// The hours of the day with the most sold units, busiest first.
+    pub fn busiest_hours(&self, count: usize) -> Vec<(usize, u32)> {
+        let mut hours = self
+            .sales_per_hour
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, amount)| *amount > 0)
+            .collect::<Vec<_>>();
+
+        hours.sort_by_key(|(hour, amount)| (std::cmp::Reverse(*amount), *hour));
+        hours.truncate(count);
+        hours
+    }
+}
+
+/// The times at which the fridge door was left open for too long.
+pub struct DoorAlarm;
+
+impl DoorAlarm {
+    /// Log a door alarm once the door was closed again.
+    pub async fn insert(
+        pool: &SqlitePool,
+        opened_at: jiff::Timestamp,
+        closed_at: jiff::Timestamp,
+    ) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO door_alarms (opened_at, closed_at) VALUES ($1, $2)")
+            .bind(Text(opened_at))
+            .bind(Text(closed_at))
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Remove the member IDs from uploaded sales, cash ledger entries, restocks
+/// and audit log entries that are older than `before`, so that
+/// member-identifying data is not kept longer than necessary.
+///
+/// Pending sales are never changed. Returns the number of anonymized rows.
+pub async fn anonymize_member_data(
+    pool: &SqlitePool,
+    before: jiff::Timestamp,
+) -> sqlx::Result<u64> {
+    // `created_at` of cash ledger entries and restocks starts with the civil
+    // date in the local time zone
+    let before_date = before.to_zoned(jiff::tz::TimeZone::system()).date();
+
+    let mut transaction = pool.begin().await?;
+
+    let queries = [
+        (
+            "UPDATE sales SET member_id = '' \
+             WHERE member_id != '' AND uploaded_at IS NOT NULL AND uploaded_at < $1",
+            before.to_string(),
+        ),
+        (
+            "UPDATE cash_ledger SET member_id = '' \
+             WHERE member_id != '' AND substr(created_at, 1, 10) < $1",
+            before_date.to_string(),
+        ),
+        (
+            "UPDATE restocks SET member_id = '' \
+             WHERE member_id != '' AND substr(created_at, 1, 10) < $1",
+            before_date.to_string(),
+        ),
+        (
+            "UPDATE audit_log SET member_id = NULL \
+             WHERE member_id IS NOT NULL AND created_at < $1",
+            before.to_string(),
+        ),
+    ];
+
+    let mut count = 0;
+    for (query, before) in queries {
+        let result = sqlx::query(query)
+            .bind(before)
+            .execute(&mut *transaction)
+            .await?;
+        count += result.rows_affected();
+    }
+
+    transaction.commit().await?;
+    Ok(count)
+}
+
+/// Run the SQLite integrity check on the database.
+///
+/// Returns the list of problems, which is empty if the database is intact.
+pub async fn integrity_check(pool: &SqlitePool) -> sqlx::Result<Vec<String>> {
+    let messages: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(messages
+        .into_iter()
+        .filter(|message| message != "ok")
+        .collect())
+}
+
+/// The tables whose contents are synchronized from Vereinsflieger and can
+/// therefore be cleared to repair a corrupted database.
+const SYNCED_TABLES: &[&str] = &["members", "articles"];
+
+/// Try to repair a corrupted database.
+///
+/// This first rebuilds all indexes, which fixes the most common kind of
+/// corruption. If the database is still corrupted, the synchronized tables
+/// are cleared, so that they are loaded from Vereinsflieger again, and the
+/// database file is rebuilt. Sales and other local data are kept.
+///
+/// Offline installations maintain these tables locally, so they are only
+/// cleared if Vereinsflieger credentials are configured.
+///
+/// Returns the list of remaining problems, which is empty if the repair
+/// was successful.
+pub async fn repair(pool: &SqlitePool) -> sqlx::Result<Vec<String>> {
+    warn!("Rebuilding database indexes…");
+    sqlx::query("REINDEX").execute(pool).await?;
+
+    let problems = integrity_check(pool).await?;
+    if problems.is_empty() {
+        return Ok(problems);
+    }
+
+    if !can_sync(pool).await {
+        warn!("Keeping members and articles, since they can't be loaded from Vereinsflieger");
+        for problem in &problems {
+            warn!("Remaining database integrity problem: {problem}");
+        }
+        return Ok(problems);
+    }
+
+    for table in SYNCED_TABLES {
+        warn!("Clearing synchronized table `{table}`…");
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = $1)")
+                .bind(table)
+                .fetch_one(pool)
+                .await?;
+        if exists {
+            sqlx::query(&format!("DELETE FROM {table}"))
+                .execute(pool)
+                .await?;
         }
+    }
+
+    warn!("Rebuilding database file…");
+    sqlx::query("VACUUM").execute(pool).await?;
+
+    integrity_check(pool).await
+}
+
+/// Check whether Vereinsflieger credentials are configured and the offline
+/// mode is disabled, so that the synchronized tables can be loaded again.
+///
+/// This is `false` if the database can't tell, e.g. because the tables are
+/// missing or corrupted.
+async fn can_sync(pool: &SqlitePool) -> bool {
+    let credentials = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM credentials")
+        .fetch_one(pool)
+        .await;
+    let offline = Settings::offline(pool).await;
+
+    matches!((credentials, offline), (Ok(count), Ok(false)) if count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keycode_conversion() {
+        let check = |input: &str, expected| {
+            let key = vereinsflieger::Key {
+                name: input.to_string(),
+                title: "".to_string(),
+            };
+
+            assert_eq!(Member::parse_keycode(key).as_deref(), expected);
+        };
+
+        check("0005635570", Some("0005635570"));
+        check("055FDF2", Some("0005635570"));
+        check("S2017, A2711, 20€", None);
+        check("20 Euro", None);
+    }
+
+    #[test]
+    fn test_price_changes() {
+        let date = jiff::civil::date(2025, 6, 1);
+        let article = |id: &str, unit_price: Option<i64>| {
+            let mut article = Article::with_fixed_price(
+                id.to_string(),
+                format!("Article {id}"),
+                Decimal::new(unit_price.unwrap_or_default(), 2),
+            );
+            if unit_price.is_none() {
+                article.prices.clear();
+            }
+            article
+        };
+
+        let previous = vec![
+            article("1", Some(150)),
+            article("2", Some(200)),
+            article("3", Some(100)),
+            article("4", Some(100)),
+        ];
+        let current = vec![
+            article("1", Some(150)),
+            article("2", Some(250)),
+            article("3", None),
+            article("5", Some(100)),
+        ];
+
+        let changes = PriceChange::diff(&previous, &current, date);
+        let changes = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.article_id.as_str(),
+                    change.old_price,
+                    change.new_price,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "2",
+                    Some(Text(Decimal::new(200, 2))),
+                    Some(Text(Decimal::new(250, 2)))
+                ),
+                ("3", Some(Text(Decimal::new(100, 2))), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_article_from_vereinsflieger() {
+        let article = |cost_type: &str, account: &str| vereinsflieger::Article {
+            article_id: "1".to_string(),
+            designation: "Test Artikel".to_string(),
+            description: String::new(),
+            unit_type: "Stück".to_string(),
+            cost_type: cost_type.to_string(),
+            sphere: "1".to_string(),
+            account: account.to_string(),
+            prices: vec![vereinsflieger::Price {
+                valid_from: "2025-01-01".to_string(),
+                valid_to: "2025-12-31".to_string(),
+                sales_tax: "19.00".to_string(),
+                unit_price: "1.50".to_string(),
+            }],
+        };
+
+        let synced = Article::try_from(article("Getränke", "8400")).unwrap();
+        assert_eq!(synced.category.as_deref(), Some("Getränke"));
+        assert_eq!(synced.account.as_deref(), Some("8400"));
+        assert_eq!(synced.prices[0].sales_tax, Some(Decimal::new(1900, 2)));
+
+        let synced = Article::try_from(article("", "")).unwrap();
+        assert_eq!(synced.category, None);
+        assert_eq!(synced.account, None);
+    }
+
+    #[test]
+    fn test_member_group_prices() {
+        let price = |unit_price, member_group: Option<&str>| Price {
+            valid_from: jiff::civil::date(2025, 1, 1),
+            valid_to: jiff::civil::date(2025, 12, 31),
+            unit_price: Decimal::new(unit_price, 2),
+            member_group: member_group.map(ToString::to_string),
+            sales_tax: None,
+        };
+
+        let article = Article {
+            id: "1".to_string(),
+            designation: "Test Artikel".to_string(),
+            prices: vec![price(100, Some("Jugend")), price(150, None)],
+            category: None,
+            account: None,
+        };
+
+        let date = jiff::civil::date(2025, 3, 1);
+        let check = |member_group, expected| {
+            let price = article.price_for_member_group(&date, member_group);
+            assert_eq!(price, Some(Decimal::new(expected, 2)));
+        };
+
+        check("Jugend", 100);
+        check("Aktiv", 150);
+        check("", 150);
+        assert_eq!(article.price_for_date(&date), Some(Decimal::new(150, 2)));
+
+        // Outside of the date range of the group price, the general price
+        // applies, e.g. for group prices that are scheduled ahead
+        let mut group_price = price(80, Some("Jugend"));
+        group_price.valid_from = jiff::civil::date(2025, 6, 1);
+        let mut general_price = price(150, None);
+        general_price.valid_to = jiff::civil::date(2025, 6, 30);
+        let article = Article {
+            prices: vec![group_price, general_price],
+            ..article
+        };
+
+        let check = |date, expected: Option<i64>| {
+            let price = article.price_for_member_group(&date, "Jugend");
+            assert_eq!(price, expected.map(|cents| Decimal::new(cents, 2)));
+        };
+
+        check(jiff::civil::date(2025, 3, 1), Some(150));
+        check(jiff::civil::date(2025, 6, 1), Some(80));
+        check(jiff::civil::date(2025, 7, 1), Some(80));
+        check(jiff::civil::date(2026, 1, 1), None);
+    }
+
+    #[test]
+    fn test_member_age() {
+        let check = |birthday: &str, expected| {
+            let member = Member {
+                keycode: "".to_string(),
+                id: "1".to_string(),
+                firstname: "John".to_string(),
+                lastname: "Doe".to_string(),
+                nickname: "".to_string(),
+                birthday: Member::parse_birthday(birthday).map(Text),
+                member_group: String::new(),
+                email: None,
+                blocked: false,
+            };
+
+            let date = jiff::civil::date(2025, 3, 1);
+            assert_eq!(member.age_on(date), expected);
+            assert_eq!(member.is_of_age(18, date), expected.map(|age| age >= 18));
+        };
+
+        check("2007-03-01", Some(18));
+        check("2007-03-02", Some(17));
+        check("01.03.2009", Some(16));
+        check("", None);
+        check("0000-00-00", None);
+    }
+
+    #[test]
+    fn test_member_birthday() {
+        let member = |birthday: &str| Member {
+            keycode: "".to_string(),
+            id: "1".to_string(),
+            firstname: "John".to_string(),
+            lastname: "Doe".to_string(),
+            nickname: "".to_string(),
+            birthday: Member::parse_birthday(birthday).map(Text),
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        };
+
+        assert!(member("2007-03-01").has_birthday_on(jiff::civil::date(2025, 3, 1)));
+        assert!(!member("2007-03-02").has_birthday_on(jiff::civil::date(2025, 3, 1)));
+        assert!(!member("").has_birthday_on(jiff::civil::date(2025, 3, 1)));
+        assert!(member("2004-02-29").has_birthday_on(jiff::civil::date(2025, 2, 28)));
+        assert!(!member("2004-02-29").has_birthday_on(jiff::civil::date(2024, 2, 28)));
+        assert!(member("2004-02-29").has_birthday_on(jiff::civil::date(2024, 2, 29)));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_for_multiple_clubs() -> anyhow::Result<()> {
+        let credentials = |club_id, username: &str| Credentials {
+            club_id,
+            app_key: "appkey".to_string(),
+            username: username.to_string(),
+            password: "secret".to_string().into(),
+            auth_secret: None,
+        };
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        credentials(42, "alice").insert(pool.clone()).await?;
+        credentials(7, "bob").insert(pool.clone()).await?;
+        credentials(42, "carol").insert(pool.clone()).await?;
+
+        let all = Credentials::find_all(pool).await?;
+        let all = all
+            .iter()
+            .map(|c| (c.club_id, c.username.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(all, vec![(42, "carol"), (7, "bob")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert!(Session::load(&pool).await?.is_none());
+
+        let article = Article {
+            id: "1".to_string(),
+            designation: "Test Artikel".to_string(),
+            prices: vec![],
+            category: None,
+            account: None,
+        };
+
+        let mut session = Session {
+            member_id: "1".to_string(),
+            refund: false,
+            cart: vec![],
+        };
+        session.save(&pool).await?;
+
+        session.cart.push(crate::running::Sale {
+            amount: 2,
+            article,
+            unit_price: Decimal::new(150, 2),
+            open_price: false,
+            discount: false,
+            voucher: None,
+        });
+        session.save(&pool).await?;
+
+        let loaded = Session::load(&pool).await?.unwrap();
+        assert_eq!(loaded.member_id, "1");
+        assert_eq!(loaded.cart.len(), 1);
+        assert_eq!(loaded.cart[0].amount, 2);
+        assert_eq!(loaded.cart[0].total(), Decimal::new(300, 2));
+
+        Session::clear(&pool).await?;
+        assert!(Session::load(&pool).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parked_session() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let session = |member_id: &str| Session {
+            member_id: member_id.to_string(),
+            refund: false,
+            cart: vec![],
+        };
+
+        let before = jiff::Timestamp::now() - jiff::SignedDuration::from_secs(60);
+        session("1").save(&pool).await?;
+        session("1").park(&pool).await?;
+        session("2").park(&pool).await?;
+        session("2").park(&pool).await?;
+        assert!(Session::load(&pool).await?.is_none());
+
+        // The active session is independent of the parked carts
+        session("3").save(&pool).await?;
+
+        let parked = Session::take_parked(&pool, "1", before).await?;
+        assert_eq!(parked.unwrap().member_id, "1");
+        assert!(Session::take_parked(&pool, "1", before).await?.is_none());
+
+        // Expired carts are not restored
+        let after = jiff::Timestamp::now() + jiff::SignedDuration::from_secs(60);
+        assert!(Session::take_parked(&pool, "2", after).await?.is_none());
+        assert!(Session::take_parked(&pool, "2", before).await?.is_none());
+
+        assert_eq!(Session::load(&pool).await?.unwrap().member_id, "3");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_settings() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert!(!Settings::offline(&pool).await?);
+        Settings::set_offline(&pool, true).await?;
+        assert!(Settings::offline(&pool).await?);
+        Settings::set_offline(&pool, false).await?;
+        assert!(!Settings::offline(&pool).await?);
+
+        assert_eq!(Settings::admin_totp_secret(&pool).await?, None);
+        Settings::set_admin_totp_secret(&pool, "JBSWY3DPEHPK3PXP").await?;
+        let secret = Settings::admin_totp_secret(&pool).await?;
+        assert_eq!(secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cash_ledger() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert_eq!(CashEntry::balance(&pool).await?, Decimal::ZERO);
+
+        let sale = |amount, cents| Sale::test("2").with_amount(amount).with_unit_price(cents);
+
+        let entries = vec![(&sale(2, 150)).into(), (&sale(1, 250)).into()];
+        CashEntry::insert_all(&pool, entries).await?;
+        assert_eq!(CashEntry::balance(&pool).await?, Decimal::new(550, 2));
+
+        CashEntry::empty_cash_box(&pool).await?;
+        assert_eq!(CashEntry::balance(&pool).await?, Decimal::ZERO);
+
+        CashEntry::insert_all(&pool, vec![(&sale(1, 100)).into()]).await?;
+        assert_eq!(CashEntry::balance(&pool).await?, Decimal::new(100, 2));
+
+        // Cash sales are not uploaded to Vereinsflieger
+        assert_eq!(Sale::count(&pool).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_balance() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert_eq!(Balance::load(&pool, "1").await?, Decimal::ZERO);
+
+        let mut connection = pool.acquire().await?;
+        let balance = Balance::add(&mut connection, "1", Decimal::new(10, 0)).await?;
+        assert_eq!(balance, Decimal::new(10, 0));
+
+        let balance = Balance::add(&mut connection, "1", Decimal::new(5, 0)).await?;
+        assert_eq!(balance, Decimal::new(15, 0));
+
+        assert_eq!(Balance::load(&pool, "1").await?, Decimal::new(15, 0));
+        assert_eq!(Balance::load(&pool, "2").await?, Decimal::ZERO);
+
+        // The balance never becomes negative
+        let debit = Balance::debit(&mut connection, "1", Decimal::new(4, 0)).await?;
+        assert_eq!(debit, Decimal::new(4, 0));
+        let debit = Balance::debit(&mut connection, "1", Decimal::new(20, 0)).await?;
+        assert_eq!(debit, Decimal::new(11, 0));
+        assert_eq!(Balance::load(&pool, "1").await?, Decimal::ZERO);
+
+        let debit = Balance::debit(&mut connection, "2", Decimal::new(4, 0)).await?;
+        assert_eq!(debit, Decimal::ZERO);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vouchers() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let voucher = Voucher {
+            barcode: "V123".to_string(),
+            value: Text(Decimal::new(2, 0)),
+            valid_from: None,
+            valid_until: Some(Text(jiff::civil::date(2025, 3, 31))),
+            redeemed_at: None,
+        };
+        voucher.insert(&pool).await?;
+
+        assert!(Voucher::find_by_barcode(pool.clone(), "V124")
+            .await?
+            .is_none());
+
+        let voucher = Voucher::find_by_barcode(pool.clone(), "V123")
+            .await?
+            .unwrap();
+        assert_eq!(*voucher.value, Decimal::new(2, 0));
+        assert!(voucher.is_valid_on(jiff::civil::date(2025, 3, 31)));
+        assert!(!voucher.is_valid_on(jiff::civil::date(2025, 4, 1)));
+
+        let mut connection = pool.acquire().await?;
+        Voucher::redeem_all(&mut connection, &["V123".to_string()]).await?;
+
+        let voucher = Voucher::find_by_barcode(pool.clone(), "V123")
+            .await?
+            .unwrap();
+        assert!(voucher.redeemed_at.is_some());
+        assert!(!voucher.is_valid_on(jiff::civil::date(2025, 3, 1)));
+
+        // Vouchers can only be redeemed once
+        let result = Voucher::redeem_all(&mut connection, &["V123".to_string()]).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_checkout() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let voucher = Voucher {
+            barcode: "V123".to_string(),
+            value: Text(Decimal::new(2, 0)),
+            valid_from: None,
+            valid_until: None,
+            redeemed_at: None,
+        };
+        voucher.insert(&pool).await?;
 
-        transaction.commit().await?;
+        let checkout = || Checkout {
+            sales: vec![Sale::test("1").with_unit_price(150)],
+            vouchers: vec!["V123".to_string()],
+            ..Default::default()
+        };
+        checkout().save(&pool).await?;
+        assert_eq!(Sale::count(&pool).await?, 1);
+
+        // The sales are not saved if the voucher was already redeemed
+        let result = checkout().save(&pool).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(Sale::count(&pool).await?, 1);
 
         Ok(())
     }
 
-    /// Delete a sale by its ID.
-    pub async fn delete_by_id(pool: &SqlitePool, id: Ulid) -> sqlx::Result<()> {
-        sqlx::query("DELETE FROM sales WHERE id = $1")
-            .bind(id.to_string())
-            .execute(pool)
-            .await
-            .map(|_| ())
+    #[tokio::test]
+    async fn test_checkout_balance() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let credit = Sale::test("credit").with_unit_price(1000);
+        let cola = |amount| Sale::test("cola").with_amount(amount).with_unit_price(150);
+
+        // The top-up pays for the purchases in the same cart
+        let checkout = Checkout {
+            sales: vec![credit, cola(2)],
+            credit: Decimal::new(10, 0),
+            balance_article: Some("credit".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(checkout.save(&pool).await?, Decimal::new(3, 0));
+        assert_eq!(Balance::load(&pool, "1").await?, Decimal::new(7, 0));
+
+        let sales = Sale::load_all(pool.clone()).await?;
+        let total = sales.iter().filter_map(Sale::total).sum::<Decimal>();
+        assert_eq!(total, Decimal::new(10, 0));
+
+        // Only the remainder is booked to the member account
+        let checkout = || Checkout {
+            sales: vec![cola(3)],
+            balance_article: Some("credit".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(checkout().save(&pool).await?, Decimal::new(45, 1));
+        assert_eq!(checkout().save(&pool).await?, Decimal::new(25, 1));
+        assert_eq!(checkout().save(&pool).await?, Decimal::ZERO);
+        assert_eq!(Balance::load(&pool, "1").await?, Decimal::ZERO);
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_stocktaking() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let counted = HashMap::from([("1".to_string(), 10), ("2".to_string(), 5)]);
+        let differences = Stock::save_stocktaking(&pool, &counted).await?;
+        assert_eq!(differences.len(), 2);
+        assert_eq!(differences[0].shrinkage(), -10);
+
+        let sale = |article_id, amount, cents| {
+            Sale::test(article_id)
+                .with_amount(amount)
+                .with_unit_price(cents)
+        };
+
+        // Untracked articles and discounts do not change the stock
+        let sales = vec![
+            sale("1", 3, 150),
+            sale("1", 1, -150),
+            sale("2", -1, 100),
+            sale("3", 1, 100),
+        ];
+        Sale::insert_all(pool.clone(), sales).await?;
+
+        let stock = Stock::load_all(&pool).await?;
+        assert_eq!(stock.len(), 2);
+        assert_eq!(stock["1"], 7);
+        assert_eq!(stock["2"], 6);
+
+        let counted = HashMap::from([("1".to_string(), 6)]);
+        let differences = Stock::save_stocktaking(&pool, &counted).await?;
+        let shrinkage = differences
+            .iter()
+            .map(StockDifference::shrinkage)
+            .collect::<Vec<_>>();
+        assert_eq!(shrinkage, vec![1, 6]);
+
+        let stock = Stock::load_all(&pool).await?;
+        assert_eq!(stock["2"], 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restock() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert_eq!(Stock::restock(&pool, "11011", "1", 24, None).await?, 24);
+        assert_eq!(Stock::restock(&pool, "11012", "1", 12, None).await?, 36);
+        assert_eq!(Stock::load_all(&pool).await?["1"], 36);
+
+        let restocks: Vec<(String, i64)> =
+            sqlx::query_as("SELECT member_id, amount FROM restocks ORDER BY id")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(restocks.len(), 2);
+        assert!(restocks.contains(&("11012".to_string(), 12)));
+
+        Ok(())
+    }
 
     #[test]
-    fn test_keycode_conversion() {
-        let check = |input: &str, expected| {
-            let key = vereinsflieger::Key {
-                name: input.to_string(),
-                title: "".to_string(),
-            };
+    fn test_usage() -> anyhow::Result<()> {
+        let now: jiff::Zoned = "2025-06-10T20:00:00+02:00[Europe/Berlin]".parse()?;
+        let logins: [jiff::Timestamp; 4] = [
+            "2025-06-10T17:00:00Z".parse()?,
+            "2025-06-10T18:00:00Z".parse()?,
+            // 00:30 local time on June 9th
+            "2025-06-08T22:30:00Z".parse()?,
+            "2025-06-01T12:00:00Z".parse()?,
+        ];
+        let sales: [(jiff::Zoned, i32); 4] = [
+            ("2025-06-10T19:15:00+02:00[Europe/Berlin]".parse()?, 2),
+            ("2025-06-09T19:45:00+02:00[Europe/Berlin]".parse()?, 1),
+            ("2025-06-09T18:00:00+02:00[Europe/Berlin]".parse()?, 1),
+            ("2025-06-09T18:05:00+02:00[Europe/Berlin]".parse()?, -1),
+        ];
+
+        let usage = Usage::from_events(&now, logins, sales);
+        assert_eq!(usage.logins_per_day.len(), 7);
+        assert_eq!(usage.logins_per_day[0], (jiff::civil::date(2025, 6, 4), 0));
+        assert_eq!(usage.logins_per_day[5], (jiff::civil::date(2025, 6, 9), 1));
+        assert_eq!(usage.logins_per_day[6], (jiff::civil::date(2025, 6, 10), 2));
+        assert_eq!(usage.sales_per_hour[19], 3);
+        assert_eq!(usage.sales_per_hour[18], 1);
+        assert_eq!(usage.busiest_hours(5), vec![(19, 3), (18, 1)]);
+        assert_eq!(usage.busiest_hours(1), vec![(19, 3)]);
 
-            assert_eq!(Member::parse_keycode(key).as_deref(), expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expiring_batches() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let date = jiff::civil::date;
+        Stock::restock(&pool, "11011", "1", 6, Some(date(2025, 3, 1))).await?;
+        Stock::restock(&pool, "11011", "1", 6, Some(date(2025, 3, 10))).await?;
+        Stock::restock(&pool, "11011", "2", 6, None).await?;
+
+        let batches = Stock::expiring_until(&pool, date(2025, 3, 5)).await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].article_id, "1");
+        assert_eq!(batches[0].amount, 6);
+
+        // The units of the older batch are assumed to be sold first
+        let counted = HashMap::from([("1".to_string(), 8), ("2".to_string(), 6)]);
+        Stock::save_stocktaking(&pool, &counted).await?;
+
+        let batches = Stock::expiring_until(&pool, date(2025, 3, 31)).await?;
+        let amounts = batches.iter().map(|batch| batch.amount).collect::<Vec<_>>();
+        assert_eq!(amounts, vec![2, 6]);
+
+        let counted = HashMap::from([("1".to_string(), 5)]);
+        Stock::save_stocktaking(&pool, &counted).await?;
+        assert!(Stock::expiring_until(&pool, date(2025, 3, 5))
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let entries = vec![
+            AuditEntry::new("login", Some("11011"), ""),
+            AuditEntry::new("pay", Some("11011"), "2 Artikel, 3.00€"),
+        ];
+        AuditEntry::insert_all(&pool, entries.clone()).await?;
+
+        let recent = AuditEntry::load_recent(&pool, 1).await?;
+        assert_eq!(recent, vec![entries[1].clone()]);
+        assert_eq!(AuditEntry::load_all(&pool).await?, entries);
+
+        // The audit log is append-only
+        assert!(sqlx::query("DELETE FROM audit_log")
+            .execute(&pool)
+            .await
+            .is_err());
+        assert!(sqlx::query("UPDATE audit_log SET details = ''")
+            .execute(&pool)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_article_insertion() -> anyhow::Result<()> {
+        let articles = (0..BULK_INSERT_ROWS * 2 + 1)
+            .map(|index| {
+                let id = format!("{index:04}");
+                Article::with_fixed_price(id.clone(), id, Decimal::new(150, 2))
+            })
+            .collect::<Vec<_>>();
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Article::save_all(pool.clone(), articles).await?;
+
+        assert_eq!(
+            Article::count(&pool).await?,
+            BULK_INSERT_ROWS as u32 * 2 + 1
+        );
+
+        let cache = ArticleCache::default();
+        let article = cache.find_by_barcode(pool, "1000").await?.unwrap();
+        assert_eq!(article.current_price(), Some(Decimal::new(150, 2)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_article_cache() -> anyhow::Result<()> {
+        let article = |designation: &str| {
+            let designation = designation.to_string();
+            Article::with_fixed_price("1".to_string(), designation, Decimal::new(150, 2))
         };
 
-        check("0005635570", Some("0005635570"));
-        check("055FDF2", Some("0005635570"));
-        check("S2017, A2711, 20€", None);
-        check("20 Euro", None);
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Article::save_all(pool.clone(), vec![article("Cola")]).await?;
+
+        let cache = ArticleCache::default();
+        let found = cache.find_by_barcode(pool.clone(), "1").await?;
+        assert_eq!(found.unwrap().designation, "Cola");
+        assert!(cache.find_by_barcode(pool.clone(), "2").await?.is_none());
+
+        // Changes are only visible after the cache was invalidated
+        Article::save_all(pool.clone(), vec![article("Cola Zero")]).await?;
+        let found = cache.find_by_barcode(pool.clone(), "1").await?;
+        assert_eq!(found.unwrap().designation, "Cola");
+
+        cache.invalidate();
+        let found = cache.find_by_barcode(pool.clone(), "1").await?;
+        assert_eq!(found.unwrap().designation, "Cola Zero");
+
+        Ok(())
     }
 
     #[tokio::test]
@@ -436,12 +3221,16 @@ mod tests {
             id: "1".to_string(),
             designation: "Test Artikel 1".to_string(),
             prices: vec![],
+            category: None,
+            account: None,
         };
 
         let article2 = Article {
             id: "1".to_string(),
             designation: "Test Artikel 2".to_string(),
             prices: vec![],
+            category: None,
+            account: None,
         };
 
         let articles = vec![article1, article2];
@@ -468,6 +3257,10 @@ mod tests {
             firstname: "John".to_string(),
             lastname: "Doe".to_string(),
             nickname: "".to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
         };
 
         let member2 = Member {
@@ -476,6 +3269,10 @@ mod tests {
             firstname: "Jane".to_string(),
             lastname: "Doe".to_string(),
             nickname: "".to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
         };
 
         let members = vec![member1, member2];
@@ -493,4 +3290,398 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_members_without_keycode() -> anyhow::Result<()> {
+        let member = |keycode: &str, id: &str| Member {
+            keycode: keycode.to_string(),
+            id: id.to_string(),
+            firstname: "John".to_string(),
+            lastname: "Doe".to_string(),
+            nickname: "".to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        };
+
+        let members = vec![member("", "1"), member("", "2"), member("0005635570", "3")];
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Member::save_all(pool.clone(), members).await?;
+
+        assert_eq!(
+            Member::find_by_id(pool.clone(), "2").await?,
+            Some(member("", "2"))
+        );
+        assert_eq!(Member::find_by_keycode(pool.clone(), "").await?, None);
+
+        let results = Member::search_by_name(pool.clone(), "n do").await?;
+        assert_eq!(results.len(), 3);
+        assert!(Member::search_by_name(pool.clone(), "%").await?.is_empty());
+
+        Member::set_blocked(pool.clone(), "3", true).await?;
+        let blocked = Member::find_by_keycode(pool.clone(), "0005635570").await?;
+        assert!(blocked.is_some_and(|member| member.blocked));
+
+        Member::set_blocked(pool.clone(), "3", false).await?;
+        let unblocked = Member::find_by_keycode(pool.clone(), "0005635570").await?;
+        assert!(unblocked.is_some_and(|member| !member.blocked));
+        assert_eq!(
+            Member::find_by_keycode(pool.clone(), "0005635570").await?,
+            Some(member("0005635570", "3"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_member_nicknames() -> anyhow::Result<()> {
+        let member = |id: &str, nickname: &str| Member {
+            keycode: format!("000563557{id}"),
+            id: id.to_string(),
+            firstname: "John".to_string(),
+            lastname: "Doe".to_string(),
+            nickname: nickname.to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        };
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Member::save_all(pool.clone(), vec![member("1", "Jo"), member("2", "")]).await?;
+
+        Member::set_nickname(pool.clone(), "1", " Johnny ").await?;
+        let found = Member::find_by_keycode(pool.clone(), "0005635571").await?;
+        assert_eq!(found, Some(member("1", "Johnny")));
+
+        // The local nickname survives the next synchronization
+        Member::save_all(pool.clone(), vec![member("1", "Jo"), member("2", "")]).await?;
+        let found = Member::find_by_id(pool.clone(), "1").await?;
+        assert_eq!(found, Some(member("1", "Johnny")));
+
+        let results = Member::search_by_name(pool.clone(), "johnny").await?;
+        assert_eq!(results, vec![member("1", "Johnny")]);
+
+        Member::set_nickname(pool.clone(), "1", "").await?;
+        let found = Member::find_by_id(pool.clone(), "1").await?;
+        assert_eq!(found, Some(member("1", "Jo")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manual_keycodes() -> anyhow::Result<()> {
+        let member = |keycode: &str, id: &str| Member {
+            keycode: keycode.to_string(),
+            id: id.to_string(),
+            firstname: "John".to_string(),
+            lastname: "Doe".to_string(),
+            nickname: "".to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        };
+
+        let members = vec![member("", "1"), member("0005635570", "2")];
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Member::save_all(pool.clone(), members.clone()).await?;
+
+        assert!(!Member::add_keycode(pool.clone(), "3", "0000000001").await?);
+        assert!(Member::add_keycode(pool.clone(), "1", "0000000001").await?);
+        assert!(Member::add_keycode(pool.clone(), "2", "0000000002").await?);
+
+        let expected = Some(member("0000000001", "1"));
+        assert_eq!(
+            Member::find_by_keycode(pool.clone(), "0000000001").await?,
+            expected
+        );
+        assert_eq!(Member::find_by_id(pool.clone(), "1").await?, expected);
+
+        // Manual keycodes survive the next synchronization
+        Member::save_all(pool.clone(), members).await?;
+        assert_eq!(
+            Member::find_by_keycode(pool.clone(), "0000000001").await?,
+            expected
+        );
+        assert_eq!(
+            Member::find_by_keycode(pool.clone(), "0000000002").await?,
+            Some(member("0000000002", "2"))
+        );
+        assert!(Member::find_by_keycode(pool.clone(), "0005635570")
+            .await?
+            .is_some());
+
+        // Keycodes can be reassigned to another member
+        assert!(Member::add_keycode(pool.clone(), "2", "0000000001").await?);
+        assert_eq!(
+            Member::find_by_keycode(pool.clone(), "0000000001").await?,
+            Some(member("0000000001", "2"))
+        );
+        assert_eq!(
+            Member::find_by_id(pool.clone(), "1").await?,
+            Some(member("", "1"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sale_timestamp_roundtrip() -> anyhow::Result<()> {
+        let created_at = "2025-03-01T23:30:00+01:00[+01:00]";
+        let earlier = "2025-03-01T20:00:00+00:00[UTC]";
+
+        let sales = vec![
+            Sale::test("1").with_created_at(created_at),
+            Sale::test("2").with_created_at(earlier),
+        ];
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Sale::insert_all(pool.clone(), sales).await?;
+
+        let sales = Sale::load_all(pool).await?;
+        assert_eq!(sales.len(), 2);
+        assert_eq!(*sales[0].created_at, earlier.parse()?);
+        assert_eq!(*sales[1].created_at, created_at.parse()?);
+        assert_eq!(sales[1].booking_date(), jiff::civil::date(2025, 3, 1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sales_history() -> anyhow::Result<()> {
+        let sale = |created_at, member_id| {
+            Sale::test("1")
+                .with_created_at(created_at)
+                .with_member(member_id)
+                .with_amount(2)
+                .with_unit_price(150)
+        };
+
+        let mut sales = vec![
+            sale("2025-03-01T23:30:00+01:00[+01:00]", "1"),
+            sale("2025-03-02T00:30:00+01:00[+01:00]", "1"),
+            sale("2025-03-01T12:00:00+01:00[+01:00]", "2"),
+        ];
+        let uploaded_id = *sales[0].id;
+        // The IDs of later sales are sorted after the IDs of earlier ones
+        sales[1].id = Text(Ulid::from_parts(uploaded_id.timestamp_ms() + 1, 0));
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Sale::insert_all(pool.clone(), sales).await?;
+        Sale::mark_uploaded(&pool, uploaded_id).await?;
+
+        assert_eq!(Sale::count(&pool).await?, 2);
+        assert_eq!(Sale::load_all(pool.clone()).await?.len(), 2);
+
+        let date = jiff::civil::date(2025, 3, 1);
+        let history = Sale::load_for_member_on(pool.clone(), "1", date).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(*history[0].id, uploaded_id);
+        assert_eq!(history[0].total(), Some(Decimal::new(300, 2)));
+
+        let last_purchase = Sale::load_last_purchase(&pool, "1").await?;
+        assert_eq!(last_purchase.len(), 1);
+        assert_ne!(*last_purchase[0].id, uploaded_id);
+        assert!(Sale::load_last_purchase(&pool, "3").await?.is_empty());
+
+        let deleted = Sale::delete_uploaded_before(&pool, jiff::Timestamp::MAX).await?;
+        assert_eq!(deleted, 1);
+        assert_eq!(Sale::count(&pool).await?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stuck_sales() -> anyhow::Result<()> {
+        let old = jiff::Zoned::now().checked_sub(jiff::SignedDuration::from_hours(5 * 24))?;
+        let old = old.to_string();
+        let sales = vec![
+            Sale::test("1"),
+            Sale::test("1"),
+            Sale::test("1").with_created_at(&old),
+        ];
+        let failed_id = *sales[0].id;
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+        Sale::insert_all(pool.clone(), sales).await?;
+
+        let max_age = jiff::SignedDuration::from_hours(3 * 24);
+        assert_eq!(Sale::count_stuck(&pool, 2, max_age).await?, 1);
+
+        Sale::mark_upload_failed(&pool, failed_id).await?;
+        assert_eq!(Sale::count_stuck(&pool, 2, max_age).await?, 1);
+        Sale::mark_upload_failed(&pool, failed_id).await?;
+        assert_eq!(Sale::count_stuck(&pool, 2, max_age).await?, 2);
+
+        Sale::mark_uploaded(&pool, failed_id).await?;
+        assert_eq!(Sale::count_stuck(&pool, 2, max_age).await?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_pending_sale() -> anyhow::Result<()> {
+        let sale = || Sale::test("1").with_amount(2).with_unit_price(150);
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+        Stock::restock(&pool, "1", "1", 10, None).await?;
+
+        let sales = vec![sale(), sale()];
+        let (pending_id, uploading_id) = (*sales[0].id, *sales[1].id);
+        Sale::insert_all(pool.clone(), sales).await?;
+        assert_eq!(Stock::load_all(&pool).await?["1"], 6);
+
+        assert!(Sale::update_pending(&pool, pending_id, "2", 3).await?);
+        assert_eq!(Stock::load_all(&pool).await?["1"], 5);
+
+        let sales = Sale::load_all(pool.clone()).await?;
+        let updated = sales.iter().find(|sale| *sale.id == pending_id).unwrap();
+        assert_eq!(updated.member_id, "2");
+        assert_eq!(updated.amount, 3);
+
+        Sale::mark_upload_started(&pool, uploading_id).await?;
+        assert!(!Sale::update_pending(&pool, uploading_id, "2", 1).await?);
+        assert_eq!(Stock::load_all(&pool).await?["1"], 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_interrupted_sale() -> anyhow::Result<()> {
+        let sales = vec![Sale::test("1"), Sale::test("1"), Sale::test("1")];
+        let (pending_id, resend_id, discard_id) = (*sales[0].id, *sales[1].id, *sales[2].id);
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+        Sale::insert_all(pool.clone(), sales).await?;
+
+        // Only sales with an interrupted upload wait for a decision
+        assert!(!Sale::resolve_interrupted(&pool, pending_id, true).await?);
+
+        Sale::mark_upload_started(&pool, resend_id).await?;
+        Sale::mark_upload_started(&pool, discard_id).await?;
+        assert!(Sale::resolve_interrupted(&pool, resend_id, true).await?);
+        assert!(Sale::resolve_interrupted(&pool, discard_id, false).await?);
+        assert!(!Sale::resolve_interrupted(&pool, discard_id, true).await?);
+
+        let sales = Sale::load_all(pool.clone()).await?;
+        let mut ids = sales.iter().map(|sale| *sale.id).collect::<Vec<_>>();
+        ids.sort();
+        let mut expected = vec![pending_id, resend_id];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert!(sales.iter().all(|sale| sale.upload_started_at.is_none()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_anonymize_member_data() -> anyhow::Result<()> {
+        let sale = |member_id| Sale::test("1").with_member(member_id).with_unit_price(150);
+
+        let sales = vec![sale("1"), sale("2")];
+        let uploaded_id = *sales[0].id;
+
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        Sale::insert_all(pool.clone(), sales).await?;
+        Sale::mark_uploaded(&pool, uploaded_id).await?;
+        let entry = AuditEntry::new("login", Some("1"), "");
+        AuditEntry::insert_all(&pool, vec![entry]).await?;
+
+        let before = jiff::Timestamp::now() - jiff::SignedDuration::from_hours(1);
+        assert_eq!(anonymize_member_data(&pool, before).await?, 0);
+
+        // Pending sales keep their member ID until they are uploaded
+        let before = jiff::Timestamp::now() + jiff::SignedDuration::from_hours(1);
+        assert_eq!(anonymize_member_data(&pool, before).await?, 2);
+
+        let member_ids: Vec<String> = sqlx::query_scalar("SELECT member_id FROM sales ORDER BY id")
+            .fetch_all(&pool)
+            .await?;
+        assert_eq!(member_ids.len(), 2);
+        assert!(member_ids.contains(&String::new()));
+        assert!(member_ids.contains(&"2".to_string()));
+
+        let entries = AuditEntry::load_all(&pool).await?;
+        assert_eq!(entries[0].member_id, None);
+
+        // Other changes to the audit log are still rejected
+        assert!(sqlx::query("UPDATE audit_log SET member_id = '2'")
+            .execute(&pool)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_removed_members() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let member = Member {
+            keycode: "0000000001".to_string(),
+            id: "1".to_string(),
+            firstname: "John".to_string(),
+            lastname: "Doe".to_string(),
+            nickname: String::new(),
+            birthday: None,
+            member_group: String::new(),
+            email: None,
+            blocked: false,
+        };
+        Member::save_all(pool.clone(), vec![member]).await?;
+        Member::set_blocked(pool.clone(), "1", true).await?;
+        Member::add_keycode(pool.clone(), "1", "0000000002").await?;
+
+        assert_eq!(Member::purge_removed(&pool).await?, 0);
+
+        Member::save_all(pool.clone(), vec![]).await?;
+        assert_eq!(Member::purge_removed(&pool).await?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        assert!(integrity_check(&pool).await?.is_empty());
+        assert!(repair(&pool).await?.is_empty());
+
+        assert!(!can_sync(&pool).await);
+        let credentials = Credentials {
+            club_id: 1,
+            app_key: "key".to_string(),
+            username: "user".to_string(),
+            password: SecretString::from("secret"),
+            auth_secret: None,
+        };
+        credentials.insert(pool.clone()).await?;
+        assert!(can_sync(&pool).await);
+
+        Settings::set_offline(&pool, true).await?;
+        assert!(!can_sync(&pool).await);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,291 @@
+use crate::database;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+/// The booking account and tax rate of an article in the accounting export.
+///
+/// This is parsed from `<article ID>=<account>:<tax rate>`, e.g.
+/// `1234=8400:19`. The article ID `*` applies to all articles without a
+/// more specific entry and without an account in Vereinsflieger.
+#[derive(Debug, Clone)]
+pub struct DatevAccount {
+    pub article_id: String,
+    pub account: String,
+    pub tax_rate: u8,
+}
+
+impl FromStr for DatevAccount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((article_id, right)) = s.split_once('=') else {
+            anyhow::bail!("Expected `<article ID>=<account>:<tax rate>`");
+        };
+        let Some((account, tax_rate)) = right.split_once(':') else {
+            anyhow::bail!("Expected `<article ID>=<account>:<tax rate>`");
+        };
+
+        let tax_rate = tax_rate.parse()?;
+        anyhow::ensure!(
+            matches!(tax_rate, 0 | 7 | 19),
+            "Unsupported tax rate: {tax_rate}%"
+        );
+
+        Ok(Self {
+            article_id: article_id.to_string(),
+            account: account.to_string(),
+            tax_rate,
+        })
+    }
+}
+
+/// The configuration of the DATEV export.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub accounts: Vec<DatevAccount>,
+    /// The account against which all sales are booked (e.g. receivables
+    /// from members).
+    pub contra_account: String,
+    /// The DATEV consultant number (aka. "Beraternummer").
+    pub consultant: u32,
+    /// The DATEV client number (aka. "Mandantennummer").
+    pub client: u32,
+}
+
+impl Config {
+    /// Find the booking account and tax rate of a sale.
+    ///
+    /// An explicit entry for the article takes precedence over the account
+    /// and sales tax that are synced from Vereinsflieger, which in turn take
+    /// precedence over the `*` entry.
+    fn account_for(
+        &self,
+        sale: &database::Sale,
+        article: Option<&database::Article>,
+    ) -> Option<(String, u8)> {
+        let find = |id: &str| self.accounts.iter().find(|acc| acc.article_id == id);
+        let configured = |account: &DatevAccount| (account.account.clone(), account.tax_rate);
+
+        find(&sale.article_id)
+            .map(configured)
+            .or_else(|| synced_account(sale, article?))
+            .or_else(|| find("*").map(configured))
+    }
+}
+
+/// The booking account of the article and the sales tax of its price at the
+/// time of the sale, as synced from Vereinsflieger.
+fn synced_account(sale: &database::Sale, article: &database::Article) -> Option<(String, u8)> {
+    let date = sale.booking_date();
+    let price = article
+        .prices
+        .iter()
+        .filter(|price| price.member_group.is_none())
+        .find(|price| price.valid_from <= date && price.valid_to >= date)?;
+
+    let tax_rate = price.sales_tax?.to_u8()?;
+    let account = article.account.clone()?;
+    matches!(tax_rate, 0 | 7 | 19).then_some((account, tax_rate))
+}
+
+/// Export the sales in the month of the given date, aggregated per booking
+/// account and tax rate, as DATEV "Buchungsstapel" CSV file into
+/// `<dir>/<YYYY-MM>/datev.csv`.
+pub async fn export_month(
+    pool: &SqlitePool,
+    dir: &Path,
+    month: jiff::civil::Date,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let start = month.first_of_month();
+    let end = month.last_of_month();
+
+    info!("Exporting DATEV bookings for {start} to {end}…");
+    let sales = database::Sale::load_between(pool, start, end).await?;
+    let articles = database::Article::load_all(pool).await?;
+    let articles = articles
+        .into_iter()
+        .map(|article| (article.id.clone(), article))
+        .collect::<HashMap<_, _>>();
+    let totals = aggregate(config, &articles, &sales)?;
+    let csv = to_csv(config, start, &totals, &jiff::Zoned::now());
+
+    let dir = dir.join(start.strftime("%Y-%m").to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join("datev.csv"), encode_latin1(&csv)).await?;
+
+    info!(
+        "Exported {} DATEV bookings to {}",
+        totals.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+/// Sum up the sales per booking account and tax rate.
+///
+/// This fails if any sale has no booking account, since the export would
+/// otherwise under-report the revenue.
+fn aggregate(
+    config: &Config,
+    articles: &HashMap<String, database::Article>,
+    sales: &[database::Sale],
+) -> anyhow::Result<BTreeMap<(String, u8), Decimal>> {
+    let mut totals = BTreeMap::new();
+    let mut unmapped = BTreeSet::new();
+    for sale in sales {
+        let article = articles.get(&sale.article_id);
+        let Some(key) = config.account_for(sale, article) else {
+            unmapped.insert(sale.article_id.as_str());
+            continue;
+        };
+
+        *totals.entry(key).or_default() += sale.total().unwrap_or_default();
+    }
+
+    if !unmapped.is_empty() {
+        let article_ids = unmapped.into_iter().collect::<Vec<_>>().join(", ");
+        anyhow::bail!("No DATEV account for the articles {article_ids}, use `--datev-account`");
+    }
+
+    Ok(totals)
+}
+
+/// Format the bookings in the DATEV "Buchungsstapel" format (version 700).
+fn to_csv(
+    config: &Config,
+    month: jiff::civil::Date,
+    totals: &BTreeMap<(String, u8), Decimal>,
+    now: &jiff::Zoned,
+) -> String {
+    let start = month.first_of_month();
+    let end = month.last_of_month();
+    let description = format!("Clubfridge {}", start.strftime("%Y-%m"));
+
+    let mut csv = String::new();
+    let _ = writeln!(
+        csv,
+        "\"EXTF\";700;21;\"Buchungsstapel\";13;{created};;\"\";\"\";\"\";{consultant};{client};\
+         {fiscal_year}0101;{account_length};{from};{to};\"{description}\";\"\";1;0;0;\"EUR\"",
+        created = now.strftime("%Y%m%d%H%M%S000"),
+        consultant = config.consultant,
+        client = config.client,
+        fiscal_year = start.year(),
+        account_length = config.contra_account.len(),
+        from = start.strftime("%Y%m%d"),
+        to = end.strftime("%Y%m%d"),
+    );
+
+    csv.push_str(
+        "Umsatz (ohne Soll/Haben-Kz);Soll/Haben-Kennzeichen;WKZ Umsatz;Kurs;Basis-Umsatz;\
+         WKZ Basis-Umsatz;Konto;Gegenkonto (ohne BU-Schlüssel);BU-Schlüssel;Belegdatum;\
+         Belegfeld 1;Belegfeld 2;Skonto;Buchungstext\n",
+    );
+
+    for ((account, tax_rate), total) in totals {
+        if total.is_zero() {
+            continue;
+        }
+
+        let amount = format!("{:.2}", total.abs()).replace('.', ",");
+        let debit_credit = if total.is_sign_negative() { "H" } else { "S" };
+        let tax_key = match tax_rate {
+            7 => "2",
+            19 => "3",
+            _ => "",
+        };
+
+        let _ = writeln!(
+            csv,
+            "{amount};\"{debit_credit}\";\"EUR\";;;;{contra_account};{account};\"{tax_key}\";\
+             {date};\"{reference}\";;;\"{description}\"",
+            contra_account = config.contra_account,
+            date = end.strftime("%d%m"),
+            reference = start.strftime("CF%Y%m"),
+        );
+    }
+
+    csv
+}
+
+/// Encode the CSV file as Latin-1, which matches the Windows-1252 encoding
+/// expected by DATEV for umlauts. Other characters are replaced by `?`.
+fn encode_latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datev_export() {
+        let config = Config {
+            accounts: vec!["1234=8300:7".parse().unwrap(), "*=8400:19".parse().unwrap()],
+            contra_account: "1400".to_string(),
+            consultant: 1001,
+            client: 1,
+        };
+
+        let sale = |article_id, amount, cents| {
+            database::Sale::test(article_id)
+                .with_created_at("2025-03-01T12:00:00+01:00[Europe/Berlin]")
+                .with_member("11011")
+                .with_amount(amount)
+                .with_unit_price(cents)
+        };
+
+        let mut synced = database::Article::with_fixed_price(
+            "3".to_string(),
+            "Kaffee".to_string(),
+            Decimal::new(100, 2),
+        );
+        synced.account = Some("8300".to_string());
+        synced.prices[0].sales_tax = Some(Decimal::new(700, 2));
+        let articles = HashMap::from([(synced.id.clone(), synced)]);
+
+        let sales = vec![
+            sale("1234", 2, 150),
+            sale("1", 1, 250),
+            sale("2", 1, 100),
+            sale("3", 1, 100),
+        ];
+        let totals = aggregate(&config, &articles, &sales).unwrap();
+
+        let month = jiff::civil::date(2025, 3, 1);
+        let now = "2025-04-01T12:00:00+02:00[Europe/Berlin]".parse().unwrap();
+        let csv = to_csv(&config, month, &totals, &now);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("\"EXTF\";700;21;\"Buchungsstapel\";13;20250401120000000;"));
+        assert!(lines[0].contains(";1001;1;20250101;4;20250301;20250331;"));
+        assert_eq!(
+            lines[2],
+            "4,00;\"S\";\"EUR\";;;;1400;8300;\"2\";3103;\"CF202503\";;;\"Clubfridge 2025-03\""
+        );
+        assert_eq!(
+            lines[3],
+            "3,50;\"S\";\"EUR\";;;;1400;8400;\"3\";3103;\"CF202503\";;;\"Clubfridge 2025-03\""
+        );
+
+        // Without the `*` entry, the sales of unmapped articles fail the export
+        let config = Config {
+            accounts: vec!["1234=8300:7".parse().unwrap()],
+            ..config
+        };
+        let err = aggregate(&config, &articles, &sales).unwrap_err();
+        assert!(err.to_string().contains("articles 1, 2,"));
+
+        assert!("1234=8400:16".parse::<DatevAccount>().is_err());
+        assert!("1234=8400".parse::<DatevAccount>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::database::{Article, Member, Price};
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tracing::info;
+
+/// The fake members of the demo mode as `(keycode, ID, first name, last name)`.
+const MEMBERS: &[(&str, &str, &str, &str)] = &[
+    ("0000000001", "90001", "Erika", "Mustermann"),
+    ("0000000002", "90002", "Max", "Mustermann"),
+    ("0000000003", "90003", "Otto", "Normalverbraucher"),
+    ("0000000004", "90004", "Lieschen", "Müller"),
+];
+
+/// The fake articles of the demo mode as `(barcode, designation, cents)`.
+const ARTICLES: &[(&str, &str, i64)] = &[
+    ("4029764001807", "Club-Mate", 150),
+    ("40822938", "Wasser", 80),
+    ("5449000000996", "Cola", 120),
+    ("4008400401621", "Schokoriegel", 100),
+    ("4001686301265", "Gummibärchen", 90),
+];
+
+/// Fill the (temporary) database with the fake members and articles.
+pub async fn seed(pool: &SqlitePool) -> sqlx::Result<()> {
+    info!("Seeding database with demo data…");
+
+    let members = (0..MEMBERS.len()).map(member).collect();
+    Member::save_all(pool.clone(), members).await?;
+
+    let articles = (0..ARTICLES.len()).map(article).collect();
+    Article::save_all(pool.clone(), articles).await
+}
+
+/// The fake member for an unknown keycode, so that any card can be used to
+/// log in.
+pub fn member_for_keycode(keycode: &str) -> Member {
+    Member {
+        keycode: keycode.to_string(),
+        ..member(pick(keycode, MEMBERS.len()))
+    }
+}
+
+/// The fake article for an unknown barcode, so that any product can be
+/// scanned.
+pub fn article_for_barcode(barcode: &str) -> Article {
+    article(pick(barcode, ARTICLES.len()))
+}
+
+/// Deterministically pick an index for the given input, so that scanning
+/// the same code again yields the same member or article.
+fn pick(input: &str, len: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    (hasher.finish() % len as u64) as usize
+}
+
+fn member(index: usize) -> Member {
+    let (keycode, id, firstname, lastname) = MEMBERS[index];
+    Member {
+        keycode: keycode.to_string(),
+        id: id.to_string(),
+        firstname: firstname.to_string(),
+        lastname: lastname.to_string(),
+        nickname: String::new(),
+        birthday: None,
+        member_group: String::new(),
+        email: None,
+        blocked: false,
+    }
+}
+
+fn article(index: usize) -> Article {
+    let (id, designation, cents) = ARTICLES[index];
+    Article {
+        id: id.to_string(),
+        designation: designation.to_string(),
+        prices: vec![Price {
+            valid_from: jiff::civil::Date::constant(2000, 1, 1),
+            valid_to: jiff::civil::Date::constant(2999, 12, 31),
+            unit_price: Decimal::new(cents, 2),
+            member_group: None,
+            sales_tax: None,
+        }],
+        category: None,
+        account: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_demo_data() -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(":memory:").await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        seed(&pool).await?;
+
+        let member = Member::find_by_keycode(pool.clone(), "0000000002").await?;
+        assert_eq!(member.unwrap().firstname, "Max");
+
+        let cache = crate::database::ArticleCache::default();
+        let article = cache.find_by_barcode(pool.clone(), "40822938").await?;
+        assert_eq!(article.unwrap().designation, "Wasser");
+
+        let member = member_for_keycode("1234567890");
+        assert_eq!(member.keycode, "1234567890");
+        assert_eq!(member, member_for_keycode("1234567890"));
+
+        let article = article_for_barcode("foo");
+        assert_eq!(article.id, article_for_barcode("foo").id);
+
+        Ok(())
+    }
+}
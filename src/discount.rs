@@ -0,0 +1,164 @@
+use crate::database;
+use crate::running::Sale;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// The prefix of the designation of discount lines in the cart.
+const DESIGNATION_PREFIX: &str = "Rabatt";
+
+/// A volume discount for an article.
+///
+/// This is parsed from `<article ID>*<amount>=free`, which makes every n-th
+/// unit free (e.g. `1234*10=free` for every 10th coffee), or from
+/// `<article ID>*<amount>=<price>`, which reduces the unit price when at
+/// least that amount is bought (e.g. `1234*12=0.90` for the crate price).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscountRule {
+    pub article_id: String,
+    pub amount: u16,
+    pub kind: DiscountKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscountKind {
+    /// Every n-th unit is free.
+    EveryNthFree,
+    /// The unit price when at least n units are bought.
+    UnitPrice(Decimal),
+}
+
+impl FromStr for DiscountRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((left, value)) = s.rsplit_once('=') else {
+            anyhow::bail!("Expected `<article ID>*<amount>=<price or \"free\">`");
+        };
+        let Some((article_id, amount)) = left.rsplit_once('*') else {
+            anyhow::bail!("Expected `<article ID>*<amount>=<price or \"free\">`");
+        };
+
+        let amount = amount.parse()?;
+        anyhow::ensure!(amount > 0, "Discount amount must be positive");
+
+        let kind = match value {
+            "free" => DiscountKind::EveryNthFree,
+            price => DiscountKind::UnitPrice(price.parse()?),
+        };
+
+        Ok(Self {
+            article_id: article_id.to_string(),
+            amount,
+            kind,
+        })
+    }
+}
+
+impl DiscountRule {
+    /// Calculate the discount for the given amount and unit price, as a
+    /// positive value.
+    fn discount(&self, amount: u16, unit_price: Decimal) -> Decimal {
+        match self.kind {
+            DiscountKind::EveryNthFree => Decimal::from(amount / self.amount) * unit_price,
+            DiscountKind::UnitPrice(price) if amount >= self.amount && price < unit_price => {
+                Decimal::from(amount) * (unit_price - price)
+            }
+            DiscountKind::UnitPrice(_) => Decimal::ZERO,
+        }
+    }
+}
+
+/// Recalculate the discount lines of the cart after it has changed.
+///
+/// Each discount is added as a separate line for the dedicated discount
+/// article with a negative price, so that it is visible to the member and
+/// is subtracted from the total that is uploaded to Vereinsflieger, without
+/// booking additional units of the discounted article.
+pub fn apply(sales: &mut Vec<Sale>, rules: &[DiscountRule], article_id: &str) {
+    sales.retain(|sale| !sale.discount);
+
+    let mut discounts = Vec::new();
+    for rule in rules {
+        // Articles with a manually entered or free price are not discounted
+        let Some(sale) = sales
+            .iter()
+            .find(|sale| !sale.open_price && sale.article.id == rule.article_id)
+        else {
+            continue;
+        };
+
+        let discount = rule.discount(sale.amount, sale.unit_price);
+        if discount > Decimal::ZERO {
+            let designation = &sale.article.designation;
+            let article = database::Article {
+                id: article_id.to_string(),
+                designation: format!("{DESIGNATION_PREFIX} {designation}"),
+                prices: vec![],
+                category: None,
+                account: None,
+            };
+
+            discounts.push(Sale {
+                amount: 1,
+                article,
+                unit_price: -discount,
+                open_price: true,
+                discount: true,
+                voucher: None,
+            });
+        }
+    }
+
+    sales.extend(discounts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_discount_rule() {
+        let rule: DiscountRule = "1234*10=free".parse().unwrap();
+        assert_eq!(rule.article_id, "1234");
+        assert_eq!(rule.amount, 10);
+        assert_eq!(rule.kind, DiscountKind::EveryNthFree);
+
+        let rule: DiscountRule = "1234*12=0.90".parse().unwrap();
+        assert_eq!(rule.kind, DiscountKind::UnitPrice(Decimal::new(90, 2)));
+
+        assert!("1234=free".parse::<DiscountRule>().is_err());
+        assert!("1234*0=free".parse::<DiscountRule>().is_err());
+        assert!("1234*10=gratis".parse::<DiscountRule>().is_err());
+    }
+
+    #[test]
+    fn test_apply_discounts() {
+        let sale = |id, amount, cents| {
+            let mut sale = Sale::test(id, amount, cents);
+            sale.article.designation = "Kaffee".to_string();
+            sale
+        };
+
+        let rules: [DiscountRule; 2] = ["1*10=free".parse().unwrap(), "2*12=0.90".parse().unwrap()];
+
+        let mut sales = vec![sale("1", 9, 100), sale("2", 11, 120)];
+        apply(&mut sales, &rules, "99");
+        assert_eq!(sales.len(), 2);
+
+        sales[0].amount = 21;
+        sales[1].amount = 12;
+        apply(&mut sales, &rules, "99");
+        assert_eq!(sales.len(), 4);
+        assert_eq!(sales[2].article.id, "99");
+        assert_eq!(sales[2].article.designation, "Rabatt Kaffee");
+        assert_eq!(sales[2].total(), Decimal::new(-200, 2));
+        assert_eq!(sales[3].total(), Decimal::new(-360, 2));
+
+        // Applying the rules again replaces the existing discount lines
+        apply(&mut sales, &rules, "99");
+        assert_eq!(sales.len(), 4);
+
+        let total = sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+        assert_eq!(total, Decimal::new(2980, 2));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{backup, logging};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Determine the free space in bytes on the partition that contains `path`.
+///
+/// This uses the POSIX `df` command, since the standard library has no
+/// portable way to query it.
+pub async fn free_space(path: &Path) -> anyhow::Result<u64> {
+    let path = path.to_path_buf();
+    let output = tokio::task::spawn_blocking(move || {
+        std::process::Command::new("df")
+            .arg("-Pk")
+            .arg(&path)
+            .output()
+    })
+    .await?
+    .context("Failed to run `df`")?;
+
+    anyhow::ensure!(output.status.success(), "`df` failed: {}", output.status);
+
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the available space in bytes from the output of `df -Pk`.
+fn parse_df(output: &str) -> anyhow::Result<u64> {
+    let line = output.lines().nth(1).context("Missing `df` output")?;
+    let available = line
+        .split_whitespace()
+        .nth(3)
+        .context("Missing available space in `df` output")?;
+
+    Ok(available.parse::<u64>()? * 1024)
+}
+
+/// Free up disk space by deleting all but the most recent log files and
+/// database backup of the database at `database`.
+pub async fn cleanup(database: Option<PathBuf>) {
+    info!("Deleting old log files and backups to free up disk space…");
+    if let Err(err) = logging::prune(1).await {
+        warn!("Failed to delete old log files: {err}");
+    }
+
+    if let Some(database) = database {
+        if let Err(err) = backup::prune(&database, 1).await {
+            warn!("Failed to delete old database backups: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "\
+Filesystem     1024-blocks    Used Available Capacity Mounted on
+/dev/mmcblk0p2    29348240 5123456  22987654      19% /
+";
+        assert_eq!(parse_df(output).unwrap(), 22987654 * 1024);
+
+        assert!(parse_df("").is_err());
+        assert!(parse_df("Filesystem\n/dev/sda1 1 2\n").is_err());
+    }
+}
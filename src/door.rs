@@ -0,0 +1,50 @@
+use anyhow::Context;
+use std::path::Path;
+use tracing::{debug, error};
+
+/// Read whether the fridge door is open from the value file of a GPIO pin
+/// with a door contact (e.g. `/sys/class/gpio/gpio17/value`).
+///
+/// The door is open if the value is `1`, or `0` if the contact is
+/// `active_low`.
+pub async fn is_open(path: &Path, active_low: bool) -> anyhow::Result<bool> {
+    let value = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read door sensor {}", path.display()))?;
+
+    Ok(parse(&value)? != active_low)
+}
+
+fn parse(value: &str) -> anyhow::Result<bool> {
+    match value.trim() {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        value => anyhow::bail!("Invalid GPIO value: {value:?}"),
+    }
+}
+
+/// Play the alarm sound with `aplay` in the background.
+pub fn play_alarm(sound: &Path) {
+    debug!("Playing door alarm sound {}", sound.display());
+    let result = std::process::Command::new("aplay")
+        .arg("-q")
+        .arg(sound)
+        .spawn();
+
+    if let Err(err) = result {
+        error!("Failed to play door alarm sound: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpio_value() {
+        assert!(parse("1\n").unwrap());
+        assert!(!parse("0\n").unwrap());
+        assert!(parse("").is_err());
+        assert!(parse("high").is_err());
+    }
+}
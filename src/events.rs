@@ -0,0 +1,306 @@
+use crate::http;
+use crate::running::Sale;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+/// The number of events that are buffered for slow clients before they
+/// start missing events.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// The GUID that is appended to the client key in the WebSocket handshake
+/// (see RFC 6455, section 1.3).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Sender for the events that are streamed to WebSocket clients.
+///
+/// This is cheap to clone. Events that are sent while no client is
+/// connected are dropped.
+#[derive(Debug, Clone)]
+pub struct EventStream(broadcast::Sender<Arc<str>>);
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self(broadcast::channel(CHANNEL_CAPACITY).0)
+    }
+}
+
+impl EventStream {
+    /// Send an event to all connected clients.
+    pub fn send(&self, event: Event<'_>) {
+        if self.0.receiver_count() == 0 {
+            return;
+        }
+
+        match serde_json::to_string(&event) {
+            Ok(json) => {
+                let _ = self.0.send(json.into());
+            }
+            Err(err) => debug!("Failed to serialize event: {err}"),
+        }
+    }
+}
+
+/// An event that is streamed to WebSocket clients as JSON.
+///
+/// Member IDs and names are deliberately not included, because the stream
+/// is meant for public displays.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// The cart has changed, or has been cleared because the member
+    /// logged out.
+    Cart {
+        logged_in: bool,
+        refund: bool,
+        items: Vec<Item<'a>>,
+        total: Decimal,
+    },
+    /// The cart has been paid.
+    Sale {
+        refund: bool,
+        payment: &'a str,
+        items: Vec<Item<'a>>,
+        total: Decimal,
+    },
+}
+
+impl<'a> Event<'a> {
+    pub fn cart(logged_in: bool, refund: bool, sales: &'a [Sale]) -> Self {
+        let (items, total) = items(sales);
+        Self::Cart {
+            logged_in,
+            refund,
+            items,
+            total,
+        }
+    }
+
+    pub fn sale(refund: bool, payment: &'a str, sales: &'a [Sale]) -> Self {
+        let (items, total) = items(sales);
+        Self::Sale {
+            refund,
+            payment,
+            items,
+            total,
+        }
+    }
+}
+
+/// A line of the cart or sale.
+#[derive(Debug, Serialize)]
+pub struct Item<'a> {
+    article_id: &'a str,
+    designation: &'a str,
+    amount: u16,
+    unit_price: Decimal,
+    total: Decimal,
+}
+
+fn items(sales: &[Sale]) -> (Vec<Item<'_>>, Decimal) {
+    let items = sales
+        .iter()
+        .map(|sale| Item {
+            article_id: &sale.article.id,
+            designation: &sale.article.designation,
+            amount: sale.amount,
+            unit_price: sale.unit_price,
+            total: sale.total(),
+        })
+        .collect::<Vec<_>>();
+
+    let total = items.iter().map(|item| item.total).sum();
+    (items, total)
+}
+
+/// Serve the `/events` WebSocket endpoint on the given address.
+///
+/// This only returns if the address can't be bound.
+pub async fn serve(addr: SocketAddr, events: EventStream) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Event stream listening on ws://{addr}/events");
+
+    http::serve(listener, move |stream, request| {
+        handle_request(stream, request, events.0.subscribe())
+    })
+    .await;
+
+    Ok(())
+}
+
+async fn handle_request(
+    mut stream: TcpStream,
+    request: Vec<u8>,
+    mut receiver: broadcast::Receiver<Arc<str>>,
+) -> anyhow::Result<()> {
+    let Some(key) = websocket_key(&request) else {
+        return http::not_found(stream).await;
+    };
+
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    stream.write_all(response.as_bytes()).await?;
+    debug!("Event stream client connected");
+
+    // Frames sent by the client are ignored, but they have to be read so
+    // that we notice when the client closes the connection.
+    let (mut reader, mut writer) = stream.into_split();
+    let reader = tokio::spawn(async move {
+        let mut chunk = [0; 1024];
+        while reader.read(&mut chunk).await.is_ok_and(|n| n > 0) {}
+    });
+
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Event stream client missed {skipped} events");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        if reader.is_finished() {
+            break;
+        }
+
+        writer.write_all(&text_frame(&event)).await?;
+    }
+
+    debug!("Event stream client disconnected");
+    Ok(())
+}
+
+/// Extract the `Sec-WebSocket-Key` of a WebSocket upgrade request for the
+/// `/events` path from the raw request head.
+fn websocket_key(request: &[u8]) -> Option<&str> {
+    if http::request_path(request)? != "/events" {
+        return None;
+    }
+
+    let request = std::str::from_utf8(request).ok()?;
+    let mut upgrade = false;
+    let mut key = None;
+    for line in request.lines().skip(1) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("upgrade") {
+            upgrade = value.eq_ignore_ascii_case("websocket");
+        } else if name.eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value);
+        }
+    }
+
+    key.filter(|_| upgrade)
+}
+
+/// Calculate the `Sec-WebSocket-Accept` header value for the given client key.
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    base64(&hasher.finalize())
+}
+
+/// Encode the given text as an unmasked, unfragmented WebSocket text frame.
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x81);
+
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Encode the given bytes as padded standard base64.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, byte)| {
+            value | (*byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (value >> (18 - 6 * i)) & 0x3f;
+                output.push(ALPHABET[index as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_key() {
+        let check = |input: &str, expected| assert_eq!(websocket_key(input.as_bytes()), expected);
+
+        let request = "GET /events HTTP/1.1\r\nHost: x\r\nUpgrade: websocket\r\n\
+            Connection: Upgrade\r\nSec-WebSocket-Key: abc==\r\n\r\n";
+        check(request, Some("abc=="));
+        check(
+            "GET /events HTTP/1.1\r\nSec-WebSocket-Key: abc==\r\n\r\n",
+            None,
+        );
+        check(
+            "GET /healthz HTTP/1.1\r\nUpgrade: websocket\r\nSec-WebSocket-Key: abc==\r\n\r\n",
+            None,
+        );
+        check("", None);
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3
+        let accept_key = accept_key("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(accept_key, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_text_frame() {
+        assert_eq!(text_frame("Hello"), b"\x81\x05Hello");
+
+        let frame = text_frame(&"a".repeat(300));
+        assert_eq!(&frame[..4], &[0x81, 126, 0x01, 0x2c]);
+        assert_eq!(frame.len(), 304);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,69 @@
+use crate::paths;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Load the given font files and all font files in the font directory.
+///
+/// Minimal Raspberry Pi OS images only ship few system fonts, so that
+/// umlauts or emoji in article designations may not be rendered. Glyphs that
+/// are missing in the default font are taken from any of the loaded fonts.
+///
+/// Fonts that can not be read are skipped.
+pub fn load(paths: &[PathBuf]) -> Vec<Vec<u8>> {
+    let mut paths = paths.to_vec();
+    paths.extend(bundled());
+
+    paths
+        .iter()
+        .filter_map(|path| {
+            std::fs::read(path)
+                .inspect(|_| info!("Loaded font {}", path.display()))
+                .inspect_err(|err| warn!("Failed to load font {}: {err}", path.display()))
+                .ok()
+        })
+        .collect()
+}
+
+/// The directory in which additional fonts can be installed, which are
+/// loaded automatically.
+pub fn font_dir() -> PathBuf {
+    paths::data_dir().join("fonts")
+}
+
+/// The font files in the font directory, sorted by name.
+fn bundled() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(font_dir()) else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_font_file(path))
+        .collect::<Vec<_>>();
+
+    paths.sort();
+    paths
+}
+
+fn is_font_file(path: &Path) -> bool {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    extension.is_some_and(|extension| {
+        let extension = extension.to_ascii_lowercase();
+        matches!(extension.as_str(), "ttf" | "otf" | "ttc")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_font_file() {
+        assert!(is_font_file(Path::new("NotoColorEmoji.ttf")));
+        assert!(is_font_file(Path::new("/usr/share/fonts/DejaVuSans.TTF")));
+        assert!(is_font_file(Path::new("Inter.otf")));
+        assert!(!is_font_file(Path::new("README.md")));
+        assert!(!is_font_file(Path::new("fonts")));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::database;
+use crate::http;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// Shared state that is reported by the `/healthz` endpoint.
+///
+/// This is cheap to clone and can be updated from the UI state machine and
+/// from background tasks alike.
+#[derive(Debug, Clone, Default)]
+pub struct HealthStatus(Arc<Mutex<Inner>>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    pool: Option<SqlitePool>,
+    started_at: Option<jiff::Timestamp>,
+    last_article_sync: Option<jiff::Timestamp>,
+    last_member_sync: Option<jiff::Timestamp>,
+    last_sale: Option<jiff::Timestamp>,
+    last_sales_upload: Option<jiff::Timestamp>,
+    rate_limited_until: Option<jiff::Timestamp>,
+    temperature: Option<f64>,
+}
+
+impl HealthStatus {
+    /// Set the database connection pool once it becomes available.
+    pub fn set_pool(&self, pool: SqlitePool) {
+        self.0.lock().unwrap().pool = Some(pool);
+    }
+
+    /// Record the time at which the application was started.
+    pub fn set_started_at(&self, started_at: jiff::Timestamp) {
+        self.0.lock().unwrap().started_at = Some(started_at);
+    }
+
+    /// Record a successful synchronization of the articles list.
+    pub fn article_sync_finished(&self) {
+        self.0.lock().unwrap().last_article_sync = Some(jiff::Timestamp::now());
+    }
+
+    /// Record a successful synchronization of the members list.
+    pub fn member_sync_finished(&self) {
+        self.0.lock().unwrap().last_member_sync = Some(jiff::Timestamp::now());
+    }
+
+    /// Record a successful upload of the pending sales.
+    pub fn sales_upload_finished(&self) {
+        self.0.lock().unwrap().last_sales_upload = Some(jiff::Timestamp::now());
+    }
+
+    /// Record that a sale was saved to the local database.
+    pub fn sale_saved(&self) {
+        self.0.lock().unwrap().last_sale = Some(jiff::Timestamp::now());
+    }
+
+    /// Record until when the Vereinsflieger sync is paused because of
+    /// rate limiting, or `None` if it is not paused.
+    pub fn set_rate_limited_until(&self, until: Option<jiff::Timestamp>) {
+        self.0.lock().unwrap().rate_limited_until = until;
+    }
+
+    /// Record the current fridge temperature in °C, or `None` if the sensor
+    /// could not be read.
+    pub fn set_temperature(&self, temperature: Option<f64>) {
+        self.0.lock().unwrap().temperature = temperature;
+    }
+
+    /// Collect the current health report, querying the database if it
+    /// is available.
+    pub async fn report(&self) -> HealthReport {
+        let (pool, mut report) = {
+            let inner = self.0.lock().unwrap();
+            let report = HealthReport {
+                version: env!("CARGO_PKG_VERSION"),
+                started_at: inner.started_at,
+                database: false,
+                pending_sales: None,
+                last_article_sync: inner.last_article_sync,
+                last_member_sync: inner.last_member_sync,
+                last_sale: inner.last_sale,
+                last_sales_upload: inner.last_sales_upload,
+                rate_limited_until: inner.rate_limited_until,
+                temperature: inner.temperature,
+            };
+            (inner.pool.clone(), report)
+        };
+
+        let pending_sales = match &pool {
+            Some(pool) => database::Sale::count(pool)
+                .await
+                .inspect_err(|err| warn!("Health check failed to count sales: {err}"))
+                .ok(),
+            None => None,
+        };
+
+        report.database = pending_sales.is_some();
+        report.pending_sales = pending_sales;
+        report
+    }
+
+    /// Compute the usage metrics, or `None` if the database is not
+    /// available.
+    pub async fn usage(&self) -> Option<database::Usage> {
+        let pool = self.0.lock().unwrap().pool.clone()?;
+        database::Usage::load(&pool)
+            .await
+            .inspect_err(|err| warn!("Failed to compute usage metrics: {err}"))
+            .ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// The version of the running application.
+    pub version: &'static str,
+    /// The time at which the application was started.
+    pub started_at: Option<jiff::Timestamp>,
+    /// Whether the database is connected and responding to queries.
+    pub database: bool,
+    /// The number of sales that have not been uploaded yet.
+    pub pending_sales: Option<u32>,
+    /// The time of the last successful article synchronization.
+    pub last_article_sync: Option<jiff::Timestamp>,
+    /// The time of the last successful member synchronization.
+    pub last_member_sync: Option<jiff::Timestamp>,
+    /// The time at which the last sale was saved since the start of the
+    /// application.
+    pub last_sale: Option<jiff::Timestamp>,
+    /// The time of the last successful upload of the pending sales.
+    pub last_sales_upload: Option<jiff::Timestamp>,
+    /// The time until which the Vereinsflieger sync is paused because of
+    /// rate limiting.
+    pub rate_limited_until: Option<jiff::Timestamp>,
+    /// The current fridge temperature in °C, if a sensor is configured.
+    pub temperature: Option<f64>,
+}
+
+/// Format the health report and the usage metrics in the Prometheus text
+/// format for the `/metrics` endpoint.
+fn metrics(report: &HealthReport, usage: Option<&database::Usage>, now: jiff::Timestamp) -> String {
+    let mut metrics = String::new();
+    let mut metric = |name: &str, help: &str, values: &[(String, String)]| {
+        metrics.push_str(&format!("# HELP clubfridge_{name} {help}\n"));
+        metrics.push_str(&format!("# TYPE clubfridge_{name} gauge\n"));
+        for (labels, value) in values {
+            metrics.push_str(&format!("clubfridge_{name}{labels} {value}\n"));
+        }
+    };
+
+    if let Some(started_at) = report.started_at {
+        let uptime = now.duration_since(started_at).as_secs();
+        let values = [(String::new(), uptime.to_string())];
+        metric(
+            "uptime_seconds",
+            "Time since the application was started.",
+            &values,
+        );
+    }
+
+    let database = [(String::new(), u8::from(report.database).to_string())];
+    metric(
+        "database_up",
+        "Whether the database is responding.",
+        &database,
+    );
+
+    if let Some(pending_sales) = report.pending_sales {
+        let values = [(String::new(), pending_sales.to_string())];
+        metric(
+            "pending_sales",
+            "Sales that have not been uploaded yet.",
+            &values,
+        );
+    }
+
+    if let Some(usage) = usage {
+        let values = usage
+            .logins_per_day
+            .iter()
+            .map(|(date, count)| (format!("{{date=\"{date}\"}}"), count.to_string()))
+            .collect::<Vec<_>>();
+        metric("logins", "Logins per day over the last days.", &values);
+
+        let values = usage
+            .sales_per_hour
+            .iter()
+            .enumerate()
+            .map(|(hour, amount)| (format!("{{hour=\"{hour}\"}}"), amount.to_string()))
+            .collect::<Vec<_>>();
+        metric(
+            "sold_units",
+            "Sold units per hour of the day over the last days.",
+            &values,
+        );
+    }
+
+    metrics
+}
+
+/// Serve the `/healthz` and `/metrics` endpoints on the given address.
+///
+/// This only returns if the address can't be bound.
+pub async fn serve(addr: SocketAddr, status: HealthStatus) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Health endpoint listening on http://{addr}/healthz");
+
+    http::serve(listener, move |stream, request| {
+        handle_request(stream, request, status.clone())
+    })
+    .await;
+
+    Ok(())
+}
+
+async fn handle_request(
+    stream: TcpStream,
+    request: Vec<u8>,
+    status: HealthStatus,
+) -> anyhow::Result<()> {
+    let json = "application/json";
+    let (status_line, content_type, body) = match http::request_path(&request) {
+        Some("/healthz") => {
+            let report = status.report().await;
+            let status_line = match report.database {
+                true => "200 OK",
+                false => "503 Service Unavailable",
+            };
+            (status_line, json, serde_json::to_string(&report)?)
+        }
+        Some("/metrics") => {
+            let report = status.report().await;
+            let usage = status.usage().await;
+            let body = metrics(&report, usage.as_ref(), jiff::Timestamp::now());
+            ("200 OK", "text/plain; version=0.0.4", body)
+        }
+        _ => return http::not_found(stream).await,
+    };
+
+    http::respond(stream, status_line, content_type, &body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let now: jiff::Timestamp = "2025-06-10T18:00:00Z".parse().unwrap();
+        let report = HealthReport {
+            version: "1.0.0",
+            started_at: Some("2025-06-10T17:00:00Z".parse().unwrap()),
+            database: true,
+            pending_sales: Some(3),
+            last_article_sync: None,
+            last_member_sync: None,
+            last_sale: None,
+            last_sales_upload: None,
+            rate_limited_until: None,
+            temperature: None,
+        };
+        let mut usage = database::Usage {
+            logins_per_day: vec![(jiff::civil::date(2025, 6, 10), 4)],
+            sales_per_hour: [0; 24],
+        };
+        usage.sales_per_hour[19] = 12;
+
+        let metrics = metrics(&report, Some(&usage), now);
+        let lines = metrics.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"# TYPE clubfridge_uptime_seconds gauge"));
+        assert!(lines.contains(&"clubfridge_uptime_seconds 3600"));
+        assert!(lines.contains(&"clubfridge_database_up 1"));
+        assert!(lines.contains(&"clubfridge_pending_sales 3"));
+        assert!(lines.contains(&r#"clubfridge_logins{date="2025-06-10"} 4"#));
+        assert!(lines.contains(&r#"clubfridge_sold_units{hour="19"} 12"#));
+        assert!(lines.contains(&r#"clubfridge_sold_units{hour="0"} 0"#));
+    }
+}
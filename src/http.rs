@@ -0,0 +1,113 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+/// The maximum size of an HTTP request head that we are willing to read.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// The pause after a failed `accept()`, so that e.g. running out of file
+/// descriptors does not turn into a busy loop.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(500);
+
+/// Accept connections on the listener and pass each connection with its
+/// request head to the handler, in a separate task per connection.
+///
+/// Failed accepts are logged and don't stop the server, so this never
+/// returns.
+pub async fn serve<H, F>(listener: TcpListener, handler: H)
+where
+    H: Fn(TcpStream, Vec<u8>) -> F + Clone + Send + 'static,
+    F: Future<Output = anyhow::Result<()>> + Send,
+{
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                warn!("Failed to accept connection: {err}");
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
+
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let result = async move {
+                let request = read_request_head(&mut stream).await?;
+                handler(stream, request).await
+            };
+
+            if let Err(err) = result.await {
+                debug!(%peer, "Failed to handle request: {err}");
+            }
+        });
+    }
+}
+
+/// Read the request line and headers of an HTTP request.
+async fn read_request_head(stream: &mut TcpStream) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buffer.len() + n > MAX_REQUEST_SIZE {
+            anyhow::bail!("Incomplete or oversized request");
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+
+    Ok(buffer)
+}
+
+/// Send a complete response and close the connection.
+pub async fn respond(
+    mut stream: TcpStream,
+    status_line: &str,
+    content_type: &str,
+    body: &str,
+) -> anyhow::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+/// Send a JSON `404 Not Found` response and close the connection.
+pub async fn not_found(stream: TcpStream) -> anyhow::Result<()> {
+    let body = r#"{"error":"not found"}"#;
+    respond(stream, "404 Not Found", "application/json", body).await
+}
+
+/// Extract the path of a `GET` request from the raw request head.
+pub fn request_path(request: &[u8]) -> Option<&str> {
+    let request = std::str::from_utf8(request).ok()?;
+    let request_line = request.lines().next()?;
+
+    let mut parts = request_line.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+
+    let path = parts.next()?;
+    Some(path.split('?').next().unwrap_or(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_path() {
+        let check = |input: &str, expected| assert_eq!(request_path(input.as_bytes()), expected);
+
+        check("GET /healthz HTTP/1.1\r\nHost: x\r\n\r\n", Some("/healthz"));
+        check("GET /healthz?verbose=1 HTTP/1.1\r\n\r\n", Some("/healthz"));
+        check("POST /healthz HTTP/1.1\r\n\r\n", None);
+        check("", None);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::database::{Article, BankAccount, Member};
+use anyhow::Context;
+use rust_decimal::Decimal;
+use std::path::Path;
+
+/// Read articles from a CSV file, e.g. for offline installations without
+/// Vereinsflieger.
+pub async fn read_articles(path: &Path) -> anyhow::Result<Vec<Article>> {
+    let csv = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    parse_articles(&csv)
+}
+
+/// Parse articles from CSV lines in the format
+/// `<article ID>;<designation>;<price>`, which matches the format of the
+/// member statements.
+///
+/// A header line and empty lines are skipped. Prices may use a decimal
+/// comma (e.g. `1,50`).
+pub fn parse_articles(csv: &str) -> anyhow::Result<Vec<Article>> {
+    let mut articles = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = line.split(';').map(str::trim).collect::<Vec<_>>();
+        let [id, designation, price] = fields[..] else {
+            anyhow::bail!(
+                "Line {}: Expected 3 fields, found {}",
+                index + 1,
+                fields.len()
+            );
+        };
+
+        let price = parse_price(price);
+        if index == 0 && price.is_none() {
+            continue;
+        }
+
+        let price = price.with_context(|| format!("Line {}: Invalid price", index + 1))?;
+        anyhow::ensure!(!id.is_empty(), "Line {}: Missing article ID", index + 1);
+
+        let article = Article::with_fixed_price(id.to_string(), designation.to_string(), price);
+        articles.push(article);
+    }
+
+    Ok(articles)
+}
+
+/// Read members from a CSV file, e.g. for offline installations without
+/// Vereinsflieger.
+///
+/// The email addresses are dropped unless `store_emails` is set.
+pub async fn read_members(path: &Path, store_emails: bool) -> anyhow::Result<Vec<Member>> {
+    let csv = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut members = parse_members(&csv)?;
+    if !store_emails {
+        for member in &mut members {
+            member.email = None;
+        }
+    }
+
+    Ok(members)
+}
+
+/// Parse members from CSV lines in the format
+/// `<keycode>;<member ID>;<name>;<nickname>;<email>`.
+///
+/// The name is split into first and last name at the first space. The
+/// keycode, nickname and email may be empty, and members with multiple
+/// keycodes are listed once per keycode. A header line and empty lines are
+/// skipped.
+pub fn parse_members(csv: &str) -> anyhow::Result<Vec<Member>> {
+    let mut members = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = line.split(';').map(str::trim).collect::<Vec<_>>();
+        let (keycode, id, name, nickname, email) = match fields[..] {
+            [keycode, id, name] => (keycode, id, name, "", ""),
+            [keycode, id, name, nickname] => (keycode, id, name, nickname, ""),
+            [keycode, id, name, nickname, email] => (keycode, id, name, nickname, email),
+            _ => anyhow::bail!(
+                "Line {}: Expected 5 fields, found {}",
+                index + 1,
+                fields.len()
+            ),
+        };
+
+        let keycode = match keycode {
+            "" => Some(String::new()),
+            keycode => Member::normalize_keycode(keycode),
+        };
+        if index == 0 && keycode.is_none() {
+            continue;
+        }
+
+        let keycode = keycode.with_context(|| format!("Line {}: Invalid keycode", index + 1))?;
+        anyhow::ensure!(!id.is_empty(), "Line {}: Missing member ID", index + 1);
+
+        let email = (!email.is_empty()).then(|| email.to_string());
+        if let Some(email) = &email {
+            let valid = email.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace)
+            });
+            anyhow::ensure!(valid, "Line {}: Invalid email address", index + 1);
+        }
+
+        let (firstname, lastname) = name.split_once(' ').unwrap_or((name, ""));
+        members.push(Member {
+            keycode,
+            id: id.to_string(),
+            firstname: firstname.to_string(),
+            lastname: lastname.trim().to_string(),
+            nickname: nickname.to_string(),
+            birthday: None,
+            member_group: String::new(),
+            email,
+            blocked: false,
+        });
+    }
+
+    Ok(members)
+}
+
+/// Read the bank accounts of the members from a CSV file, since the
+/// Vereinsflieger API does not expose the IBANs.
+pub async fn read_bank_accounts(path: &Path) -> anyhow::Result<Vec<BankAccount>> {
+    let csv = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    parse_bank_accounts(&csv)
+}
+
+/// Parse bank accounts from CSV lines in the format `<member ID>;<IBAN>`.
+///
+/// Spaces in the IBAN are removed. A header line and empty lines are
+/// skipped.
+pub fn parse_bank_accounts(csv: &str) -> anyhow::Result<Vec<BankAccount>> {
+    let mut accounts = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = line.split(';').map(str::trim).collect::<Vec<_>>();
+        let [member_id, iban] = fields[..] else {
+            anyhow::bail!(
+                "Line {}: Expected 2 fields, found {}",
+                index + 1,
+                fields.len()
+            );
+        };
+
+        let iban = iban.replace(' ', "").to_uppercase();
+        let valid = iban.len() >= 15
+            && iban.len() <= 34
+            && iban[..2].chars().all(|c| c.is_ascii_alphabetic())
+            && iban[2..].chars().all(|c| c.is_ascii_alphanumeric());
+        if index == 0 && !valid {
+            continue;
+        }
+
+        anyhow::ensure!(valid, "Line {}: Invalid IBAN", index + 1);
+        anyhow::ensure!(
+            !member_id.is_empty(),
+            "Line {}: Missing member ID",
+            index + 1
+        );
+
+        accounts.push(BankAccount {
+            member_id: member_id.to_string(),
+            iban,
+        });
+    }
+
+    Ok(accounts)
+}
+
+/// Parse a price with a decimal point or comma.
+pub fn parse_price(price: &str) -> Option<Decimal> {
+    let price = price.trim().trim_end_matches('€').trim_end();
+    let price: Decimal = price.replace(',', ".").parse().ok()?;
+    (!price.is_sign_negative()).then_some(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_articles() {
+        let csv = "\
+Artikelnummer;Bezeichnung;Preis
+40822938;Wasser;0,80
+
+4029764001807;Club-Mate;1.50€
+";
+        let articles = parse_articles(csv).unwrap();
+        assert_eq!(articles.len(), 2);
+        assert_eq!(articles[0].id, "40822938");
+        assert_eq!(articles[0].designation, "Wasser");
+        assert_eq!(articles[0].current_price(), Some(Decimal::new(80, 2)));
+        assert_eq!(articles[1].current_price(), Some(Decimal::new(150, 2)));
+
+        assert!(parse_articles("1;Wasser").is_err());
+        assert!(parse_articles("1;Wasser;1\n2;Cola;teuer").is_err());
+        assert!(parse_articles("1;Wasser;1\n2;Cola;-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_members() {
+        let csv = "\
+Keycode;Mitgliedsnummer;Name;Spitzname;E-Mail
+0001234567;11;Max Mustermann;Maxi;max@example.com
+012d687;11;Max Mustermann;Maxi
+;12;Erika Gabler Mustermann
+";
+        let members = parse_members(csv).unwrap();
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[0].keycode, "0001234567");
+        assert_eq!(members[0].id, "11");
+        assert_eq!(members[0].firstname, "Max");
+        assert_eq!(members[0].lastname, "Mustermann");
+        assert_eq!(members[0].nickname, "Maxi");
+        assert_eq!(members[0].email.as_deref(), Some("max@example.com"));
+        assert_eq!(members[1].keycode, "0001234567");
+        assert_eq!(members[2].keycode, "");
+        assert_eq!(members[2].lastname, "Gabler Mustermann");
+        assert_eq!(members[2].nickname, "");
+        assert_eq!(members[2].email, None);
+
+        assert!(parse_members("0001234567;11").is_err());
+        assert!(parse_members(";11;Max\nabc;12;Erika").is_err());
+        assert!(parse_members(";11;Max;;max").is_err());
+        assert!(parse_members(";11;Max;;max@localhost").is_err());
+    }
+
+    #[test]
+    fn test_parse_bank_accounts() {
+        let csv = "\
+Mitgliedsnummer;IBAN
+11;DE89 3704 0044 0532 0130 00
+
+12;de02120300000000202051
+";
+        let accounts = parse_bank_accounts(csv).unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].member_id, "11");
+        assert_eq!(accounts[0].iban, "DE89370400440532013000");
+        assert_eq!(accounts[1].iban, "DE02120300000000202051");
+
+        assert!(parse_bank_accounts("11").is_err());
+        assert!(parse_bank_accounts("11;DE89370400440532013000\n12;kaputt").is_err());
+        assert!(parse_bank_accounts("11;DE89370400440532013000\n;DE02120300000000202051").is_err());
+    }
+}
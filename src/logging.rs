@@ -1,3 +1,11 @@
+use crate::database::Member;
+use crate::paths;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
 use tracing_subscriber::filter::Targets;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -5,32 +13,200 @@ use tracing_subscriber::Layer;
 
 const DEFAULT_TARGETS: &str = "warn,clubfridge_neo=debug";
 
-pub fn init() -> anyhow::Result<()> {
+/// The target of the log events that are written to the separate
+/// Vereinsflieger debug log file.
+pub const VF_DEBUG_TARGET: &str = "vf_debug";
+
+/// The format in which log lines are written to stdout and the log files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable, compact log lines.
+    #[default]
+    Text,
+    /// One JSON object per log line, including all structured fields (e.g.
+    /// `sale_id` or `member_id`). Suitable for log aggregation systems.
+    Json,
+}
+
+/// Whether member names and keycodes are replaced with hashes in log
+/// messages.
+static PSEUDONYMIZE: AtomicBool = AtomicBool::new(false);
+
+/// Initialize logging to stdout and the log files.
+///
+/// If `vf_debug` is set, the Vereinsflieger API calls are additionally
+/// written to separate `vf-debug` log files. If `pseudonymize` is set,
+/// member data that is formatted with [`member`], [`keycode`] and [`key`]
+/// is replaced with hashes.
+pub fn init(format: LogFormat, vf_debug: bool, pseudonymize: bool) -> anyhow::Result<()> {
+    PSEUDONYMIZE.store(pseudonymize, Ordering::Relaxed);
+
     let targets = targets_from_env();
 
-    let stdout_layer = tracing_subscriber::fmt::layer()
-        .compact()
-        .with_filter(targets.clone());
+    let stdout_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .compact()
+            .with_filter(targets.clone())
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_filter(targets.clone())
+            .boxed(),
+    };
 
     let file_appender = tracing_appender::rolling::Builder::new()
         .rotation(tracing_appender::rolling::Rotation::DAILY)
         .filename_prefix("clubfridge-neo")
         .filename_suffix("log")
         .max_log_files(7)
-        .build("logs")?;
+        .build(paths::log_dir())?;
 
-    let logfile_layer = tracing_subscriber::fmt::layer()
-        .compact()
-        .with_ansi(false)
-        .with_writer(file_appender)
-        .with_filter(targets);
+    let logfile_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .compact()
+            .with_ansi(false)
+            .with_writer(file_appender)
+            .with_filter(targets)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_ansi(false)
+            .with_writer(file_appender)
+            .with_filter(targets)
+            .boxed(),
+    };
+
+    let vf_debug_layer = match vf_debug {
+        true => {
+            let file_appender = tracing_appender::rolling::Builder::new()
+                .rotation(tracing_appender::rolling::Rotation::DAILY)
+                .filename_prefix("vf-debug")
+                .filename_suffix("log")
+                .max_log_files(7)
+                .build(paths::log_dir())?;
+
+            let targets = Targets::new().with_target(VF_DEBUG_TARGET, tracing::Level::TRACE);
+
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_ansi(false)
+                .with_writer(file_appender)
+                .with_filter(targets);
+
+            Some(layer)
+        }
+        false => None,
+    };
 
     Ok(tracing_subscriber::registry()
         .with(stdout_layer)
         .with(logfile_layer)
+        .with(vf_debug_layer)
         .try_init()?)
 }
 
+/// Delete all but the `keep` most recent files of each log in the log
+/// directory.
+pub async fn prune(keep: usize) -> std::io::Result<()> {
+    let mut entries = tokio::fs::read_dir(paths::log_dir()).await?;
+
+    // The log files are named `<prefix>.<date>.log`
+    let mut logs: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some((prefix, _)) = name.split_once('.') {
+            logs.entry(prefix.to_string())
+                .or_default()
+                .push(entry.path());
+        }
+    }
+
+    for mut files in logs.into_values() {
+        // The dates in the file names sort chronologically
+        files.sort();
+        for old_file in files.iter().rev().skip(keep) {
+            info!("Deleting old log file {}", old_file.display());
+            tokio::fs::remove_file(old_file).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn pseudonymize() -> bool {
+    PSEUDONYMIZE.load(Ordering::Relaxed)
+}
+
+/// Hash a piece of member data, so that log lines about the same member can
+/// still be correlated.
+fn hash(value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    format!("#{:08x}", hasher.finish() as u32)
+}
+
+/// Format a member for log messages.
+pub fn member(member: &Member) -> impl Debug + '_ {
+    struct Logged<'a>(&'a Member);
+
+    impl Debug for Logged<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let member = self.0;
+            if !pseudonymize() {
+                return Debug::fmt(member, f);
+            }
+
+            let name = format!(
+                "{} {} {}",
+                member.firstname, member.lastname, member.nickname
+            );
+            f.debug_struct("Member")
+                .field("keycode", &hash(&member.keycode))
+                .field("id", &member.id)
+                .field("name", &hash(&name))
+                .field("member_group", &member.member_group)
+                .field("blocked", &member.blocked)
+                .finish_non_exhaustive()
+        }
+    }
+
+    Logged(member)
+}
+
+/// Format a keycode (or any other scanned input that might be one) for
+/// log messages.
+pub fn keycode(keycode: &str) -> impl Display + '_ {
+    struct Logged<'a>(&'a str);
+
+    impl Display for Logged<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match pseudonymize() {
+                true => f.write_str(&hash(self.0)),
+                false => f.write_str(self.0),
+            }
+        }
+    }
+
+    Logged(keycode)
+}
+
+/// Format a single pressed key for log messages, which is hidden entirely
+/// since it could be part of a keycode.
+pub fn key(key: char) -> impl Debug {
+    struct Logged(char);
+
+    impl Debug for Logged {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match pseudonymize() {
+                true => f.write_str("'*'"),
+                false => Debug::fmt(&self.0, f),
+            }
+        }
+    }
+
+    Logged(key)
+}
+
 fn targets_from_env() -> Targets {
     let targets = match std::env::var("RUST_LOG") {
         Ok(value) => value,
@@ -59,4 +235,11 @@ mod tests {
     fn test_default_targets_does_not_panic() {
         default_targets();
     }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash("0001234567"), hash("0001234567"));
+        assert_ne!(hash("0001234567"), hash("0001234568"));
+        assert_eq!(hash("").len(), 9);
+    }
 }
@@ -1,18 +1,89 @@
+mod admin;
+mod alert;
+mod announcement;
+mod backup;
+mod calendar;
+mod cli;
+mod clock;
+mod crash;
+mod currency;
 mod database;
+mod datev;
+mod demo;
+mod discount;
+mod disk;
+mod door;
+mod events;
+mod fonts;
+mod health;
+mod http;
+mod import;
 mod logging;
+mod mqtt;
+mod network;
+mod offline_setup;
+mod paths;
 mod popup;
+mod qr_login;
+mod receipt;
+mod replay;
 mod running;
+mod scanner;
+mod screenshot;
+mod sepa;
 mod setup;
 mod starting;
 mod state;
+mod statement;
+mod sumup;
+mod sync;
+mod system;
+mod temperature;
+mod texts;
+mod theme;
+mod totp;
+mod transfer;
 mod ui;
+mod update;
+mod verify;
 
-use crate::state::ClubFridge;
+use crate::state::{ClubFridge, Options};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
 
 pub fn main() -> anyhow::Result<()> {
-    logging::init()?;
+    let options = <Options as clap::Parser>::parse();
 
-    ClubFridge::run()?;
+    paths::migrate_legacy_files(options.database.is_none())?;
+
+    logging::init(
+        options.log_format,
+        options.vf_debug,
+        options.pseudonymize_logs,
+    )?;
+
+    currency::init(options.currency_format);
+
+    if let Some(proxy) = &options.proxy {
+        network::use_proxy(proxy);
+    }
+
+    if let Some(command) = options.command.clone() {
+        return cli::run(command, &options);
+    }
+
+    crash::install_hook();
+    let started_at = Instant::now();
+
+    // A panic ends the event loop, which cannot be started again in the
+    // same process, so the application is restarted instead
+    match panic::catch_unwind(AssertUnwindSafe(|| ClubFridge::run(options))) {
+        Ok(result) => result?,
+        Err(panic) => {
+            crash::restart(started_at);
+            panic::resume_unwind(panic);
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,258 @@
+use crate::health::HealthStatus;
+use serde_json::json;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// The interval at which the sensor states are published.
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The time after which a connection attempt to the broker is aborted.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The keep-alive interval that is announced to the broker. Since we
+/// disconnect after each publish, this only has to cover a single round.
+const KEEP_ALIVE_SECS: u16 = 60;
+
+/// The connection details of the MQTT broker.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    /// The address of the broker (e.g. `homeassistant.local:1883`).
+    pub address: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// The prefix of the Home Assistant discovery topics.
+    pub discovery_prefix: String,
+    /// The ID of this fridge, used as client ID, device identifier and
+    /// prefix of the state topic.
+    pub node_id: String,
+}
+
+impl MqttConfig {
+    fn state_topic(&self) -> String {
+        format!("{}/state", self.node_id)
+    }
+}
+
+/// A sensor that is announced via Home Assistant MQTT discovery.
+struct Sensor {
+    /// The key of the value in the health report.
+    key: &'static str,
+    name: &'static str,
+    device_class: Option<&'static str>,
+    unit: Option<&'static str>,
+}
+
+const SENSORS: &[Sensor] = &[
+    Sensor {
+        key: "pending_sales",
+        name: "Offene Verkäufe",
+        device_class: None,
+        unit: None,
+    },
+    Sensor {
+        key: "temperature",
+        name: "Temperatur",
+        device_class: Some("temperature"),
+        unit: Some("°C"),
+    },
+    Sensor {
+        key: "last_sale",
+        name: "Letzter Verkauf",
+        device_class: Some("timestamp"),
+        unit: None,
+    },
+    Sensor {
+        key: "version",
+        name: "Version",
+        device_class: None,
+        unit: None,
+    },
+];
+
+/// Periodically publish the discovery messages and the current sensor
+/// states to the MQTT broker. This never returns.
+pub async fn run(config: MqttConfig, health: HealthStatus) {
+    info!(address = %config.address, "Publishing sensors to MQTT broker");
+
+    loop {
+        if let Err(err) = publish(&config, &health).await {
+            warn!("Failed to publish sensors to MQTT broker: {err}");
+        }
+
+        tokio::time::sleep(PUBLISH_INTERVAL).await;
+    }
+}
+
+/// Connect to the broker, publish the discovery messages and the current
+/// sensor states, and disconnect again.
+///
+/// The discovery messages are published every time, so that Home Assistant
+/// picks the sensors up again after the broker lost its retained messages.
+async fn publish(config: &MqttConfig, health: &HealthStatus) -> anyhow::Result<()> {
+    let report = health.report().await;
+    let state = serde_json::to_vec(&report)?;
+
+    let connect = TcpStream::connect(&config.address);
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, connect).await??;
+
+    stream.write_all(&connect_packet(config)).await?;
+
+    let mut connack = [0; 4];
+    tokio::time::timeout(CONNECT_TIMEOUT, stream.read_exact(&mut connack)).await??;
+    if connack[0] != 0x20 || connack[3] != 0 {
+        anyhow::bail!("Broker refused connection (return code {})", connack[3]);
+    }
+
+    for sensor in SENSORS {
+        let topic = format!(
+            "{}/sensor/{}/{}/config",
+            config.discovery_prefix, config.node_id, sensor.key
+        );
+        let payload = discovery_payload(config, sensor, report.version);
+        stream.write_all(&publish_packet(&topic, &payload)).await?;
+    }
+
+    let state_topic = config.state_topic();
+    stream
+        .write_all(&publish_packet(&state_topic, &state))
+        .await?;
+
+    // DISCONNECT
+    stream.write_all(&[0xe0, 0x00]).await?;
+    stream.shutdown().await?;
+
+    debug!("Published sensors to MQTT broker");
+    Ok(())
+}
+
+/// Build the Home Assistant discovery message for the given sensor.
+fn discovery_payload(config: &MqttConfig, sensor: &Sensor, version: &str) -> Vec<u8> {
+    let mut payload = json!({
+        "name": sensor.name,
+        "unique_id": format!("{}_{}", config.node_id, sensor.key),
+        "state_topic": config.state_topic(),
+        "value_template": format!("{{{{ value_json.{} }}}}", sensor.key),
+        "device": {
+            "identifiers": [config.node_id],
+            "name": "Clubfridge",
+            "manufacturer": "clubfridge-neo",
+            "sw_version": version,
+        },
+    });
+
+    if let Some(device_class) = sensor.device_class {
+        payload["device_class"] = device_class.into();
+    }
+    if let Some(unit) = sensor.unit {
+        payload["unit_of_measurement"] = unit.into();
+        payload["state_class"] = "measurement".into();
+    }
+
+    payload.to_string().into_bytes()
+}
+
+/// Encode an MQTT 3.1.1 CONNECT packet with a clean session.
+fn connect_packet(config: &MqttConfig) -> Vec<u8> {
+    let mut flags = 0x02;
+    let mut body = Vec::new();
+    write_string(&mut body, "MQTT");
+    body.push(0x04);
+    body.push(0); // connect flags, set below
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+
+    write_string(&mut body, &config.node_id);
+    if let Some(username) = &config.username {
+        flags |= 0x80;
+        write_string(&mut body, username);
+    }
+    if let Some(password) = &config.password {
+        flags |= 0x40;
+        write_string(&mut body, password);
+    }
+    body[7] = flags;
+
+    packet(0x10, &body)
+}
+
+/// Encode an MQTT PUBLISH packet with QoS 0 and the retain flag set.
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    write_string(&mut body, topic);
+    body.extend_from_slice(payload);
+
+    packet(0x31, &body)
+}
+
+/// Prepend the fixed header with the variable-length remaining length.
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    packet.push(header);
+
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Write a length-prefixed UTF-8 string.
+fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MqttConfig {
+        MqttConfig {
+            address: "localhost:1883".to_string(),
+            username: Some("user".to_string()),
+            password: Some("pw".to_string()),
+            discovery_prefix: "homeassistant".to_string(),
+            node_id: "clubfridge".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_connect_packet() {
+        let packet = connect_packet(&config());
+        let mut expected = vec![0x10, 32, 0, 4, b'M', b'Q', b'T', b'T', 4, 0xc2, 0, 60];
+        expected.extend_from_slice(b"\x00\x0aclubfridge\x00\x04user\x00\x02pw");
+        assert_eq!(packet, expected);
+    }
+
+    #[test]
+    fn test_publish_packet() {
+        assert_eq!(publish_packet("a/b", b"42"), b"\x31\x07\x00\x03a/b42");
+
+        let packet = publish_packet("t", &[0; 200]);
+        assert_eq!(&packet[..3], &[0x31, 0xcb, 0x01]);
+        assert_eq!(packet.len(), 206);
+    }
+
+    #[test]
+    fn test_discovery_payload() {
+        let sensor = &SENSORS[1];
+        let payload = discovery_payload(&config(), sensor, "1.2.3");
+        let payload: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(payload["unique_id"], "clubfridge_temperature");
+        assert_eq!(payload["state_topic"], "clubfridge/state");
+        assert_eq!(payload["value_template"], "{{ value_json.temperature }}");
+        assert_eq!(payload["unit_of_measurement"], "°C");
+        assert_eq!(payload["device"]["sw_version"], "1.2.3");
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// The address that is used to check whether Vereinsflieger is reachable.
+const VEREINSFLIEGER_ADDRESS: &str = "www.vereinsflieger.de:443";
+
+/// The time after which an unanswered connection attempt is considered
+/// failed.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The `host:port` address that is checked to decide whether Vereinsflieger
+/// is reachable. Behind a proxy, only the proxy can be reached directly.
+pub fn connectivity_address() -> String {
+    match https_proxy() {
+        Some(proxy) => url_address(&proxy),
+        None => VEREINSFLIEGER_ADDRESS.to_string(),
+    }
+}
+
+/// Send all HTTP and HTTPS requests through the given proxy URL (e.g.
+/// `http://proxy.example.com:3128`).
+///
+/// This sets the environment variables that are read by all HTTP clients,
+/// including the ones of the Vereinsflieger API and the self-update, so it
+/// has to be called before any other threads are started.
+pub fn use_proxy(proxy: &str) {
+    std::env::set_var("HTTP_PROXY", proxy);
+    std::env::set_var("HTTPS_PROXY", proxy);
+}
+
+/// The proxy for HTTPS requests from the environment variables, like
+/// `reqwest` reads it.
+fn https_proxy() -> Option<String> {
+    ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|proxy| !proxy.is_empty())
+}
+
+/// The `host:port` address of the server of a URL, without credentials.
+fn url_address(url: &str) -> String {
+    let (default_port, rest) = match url.split_once("://") {
+        Some(("http", rest)) => (80, rest),
+        Some((_, rest)) => (443, rest),
+        None => (443, url),
+    };
+
+    let authority = rest.split('/').next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    match authority.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => authority.to_string(),
+        _ => format!("{authority}:{default_port}"),
+    }
+}
+
+/// Check whether a TCP connection to the given `host:port` address can be
+/// established, which means that the network and the server are available.
+pub async fn is_reachable(address: &str) -> bool {
+    let result = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address)).await;
+    match result {
+        Ok(Ok(_)) => true,
+        Ok(Err(err)) => {
+            debug!("Failed to connect to {address}: {err}");
+            false
+        }
+        Err(_) => {
+            debug!("Connection to {address} timed out");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_address() {
+        assert_eq!(url_address("http://proxy:3128"), "proxy:3128");
+        assert_eq!(url_address("http://localhost"), "localhost:80");
+        assert_eq!(
+            url_address("https://proxy.example.com/path"),
+            "proxy.example.com:443"
+        );
+        assert_eq!(url_address("http://user:pw@proxy:3128/"), "proxy:3128");
+        assert_eq!(url_address("proxy.example.com"), "proxy.example.com:443");
+    }
+}
@@ -0,0 +1,234 @@
+use crate::currency;
+use crate::database::Article;
+use crate::import;
+use crate::setup::input_field;
+use crate::state::{GlobalState, Message};
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::Length::Fixed;
+use iced::{color, Center, Element, Fill, Subscription, Task};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// The first-run screen in offline mode, which is shown if there are no
+/// articles yet, so that they can be created or imported from a CSV file.
+#[derive(Debug)]
+pub struct OfflineSetup {
+    pool: SqlitePool,
+    /// The articles that were created or imported on this screen.
+    articles: Vec<Article>,
+    article_id: String,
+    designation: String,
+    price: String,
+    csv_path: String,
+}
+
+impl OfflineSetup {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            articles: Vec::new(),
+            article_id: String::new(),
+            designation: String::new(),
+            price: String::new(),
+            csv_path: String::new(),
+        }
+    }
+
+    /// The article that was entered in the form, if it is complete.
+    fn article(&self) -> Option<Article> {
+        let article_id = self.article_id.trim();
+        let designation = self.designation.trim();
+        if article_id.is_empty() || designation.is_empty() {
+            return None;
+        }
+
+        let price = import::parse_price(&self.price)?;
+        let article =
+            Article::with_fixed_price(article_id.to_string(), designation.to_string(), price);
+
+        Some(article)
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        Subscription::none()
+    }
+
+    pub fn update(&mut self, message: Message, global_state: &mut GlobalState) -> Task<Message> {
+        match message {
+            Message::SetOfflineArticleId(article_id) => self.article_id = article_id,
+            Message::SetOfflineDesignation(designation) => self.designation = designation,
+            Message::SetOfflinePrice(price) => {
+                if price
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+                {
+                    self.price = price;
+                }
+            }
+            Message::SetOfflineCsvPath(csv_path) => self.csv_path = csv_path,
+            Message::AddOfflineArticle => {
+                let Some(article) = self.article() else {
+                    return Task::none();
+                };
+
+                info!(article_id = %article.id, "Creating article…");
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let articles = vec![article];
+                    let result = Article::upsert_all(&pool, &articles).await;
+                    let result = result.map(|_| articles).map_err(anyhow::Error::from);
+                    Message::OfflineArticlesSaved(result.map_err(Arc::new))
+                });
+            }
+            Message::ImportOfflineArticles => {
+                let path = PathBuf::from(self.csv_path.trim());
+                info!("Importing articles from {}…", path.display());
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = async {
+                        let articles = import::read_articles(&path).await?;
+                        Article::upsert_all(&pool, &articles).await?;
+                        Ok::<_, anyhow::Error>(articles)
+                    };
+
+                    Message::OfflineArticlesSaved(result.await.map_err(Arc::new))
+                });
+            }
+            Message::OfflineArticlesSaved(result) => match result {
+                Ok(articles) => {
+                    info!("Saved {} articles", articles.len());
+                    global_state.show_success(format!("{} Artikel gespeichert", articles.len()));
+
+                    self.articles
+                        .retain(|article| !articles.iter().any(|new| new.id == article.id));
+                    self.articles.extend(articles);
+
+                    self.article_id.clear();
+                    self.designation.clear();
+                    self.price.clear();
+                }
+                Err(err) => {
+                    let error_id = global_state.show_internal_error("Fehler beim Speichern");
+                    warn!(%error_id, "Failed to save articles: {err:#}");
+                }
+            },
+            Message::FinishOfflineSetup if !self.articles.is_empty() => {
+                info!("Offline setup finished");
+                let pool = self.pool.clone();
+                return Task::done(Message::StartupComplete(pool, Vec::new()));
+            }
+            _ => {}
+        }
+
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let title = text("ClubFridge neo – Ersteinrichtung")
+            .size(36)
+            .width(Fill)
+            .align_x(Center);
+
+        let hint = text(
+            "Es sind noch keine Artikel vorhanden. Lege Artikel an oder importiere \
+             eine CSV-Datei mit den Spalten Artikelnummer;Bezeichnung;Preis.",
+        )
+        .size(18)
+        .color(color!(0x888888))
+        .align_x(Center);
+
+        let add_fn = self.article().map(|_| Message::AddOfflineArticle);
+        let inputs = column![
+            input_field(
+                "Artikelnummer",
+                &self.article_id,
+                false,
+                Message::SetOfflineArticleId,
+                add_fn.clone()
+            ),
+            input_field(
+                "Bezeichnung",
+                &self.designation,
+                false,
+                Message::SetOfflineDesignation,
+                add_fn.clone()
+            ),
+            input_field(
+                "Preis",
+                &self.price,
+                false,
+                Message::SetOfflinePrice,
+                add_fn.clone()
+            ),
+        ]
+        .spacing(20)
+        .width(Fixed(400.));
+
+        let add_button = button(text("Artikel anlegen").size(24).color(color!(0xffffff)))
+            .on_press_maybe(add_fn)
+            .padding([10, 20])
+            .style(button::secondary);
+
+        let import_fn =
+            (!self.csv_path.trim().is_empty()).then_some(Message::ImportOfflineArticles);
+        let import = row![
+            container(input_field(
+                "CSV-Datei",
+                &self.csv_path,
+                false,
+                Message::SetOfflineCsvPath,
+                import_fn.clone()
+            ))
+            .width(Fixed(400.)),
+            button(text("Importieren").size(24).color(color!(0xffffff)))
+                .on_press_maybe(import_fn)
+                .padding([10, 20])
+                .style(button::secondary),
+        ]
+        .spacing(20)
+        .align_y(Center);
+
+        let articles = self.articles.iter().map(|article| {
+            let price = article.current_price().unwrap_or_default();
+            text(format!(
+                "{} – {} – {}",
+                article.id,
+                article.designation,
+                currency::format(price)
+            ))
+            .size(18)
+            .into()
+        });
+        let articles = scrollable(column(articles).spacing(5)).height(Fill);
+
+        let finish_fn = (!self.articles.is_empty()).then_some(Message::FinishOfflineSetup);
+        let finish_button = button(
+            text("Einrichtung abschließen")
+                .size(24)
+                .color(color!(0xffffff)),
+        )
+        .on_press_maybe(finish_fn)
+        .padding([10, 20])
+        .style(button::primary);
+
+        container(
+            column![
+                title,
+                hint,
+                inputs,
+                add_button,
+                import,
+                articles,
+                finish_button
+            ]
+            .spacing(20)
+            .align_x(Center),
+        )
+        .height(Fill)
+        .padding([20, 30])
+        .into()
+    }
+}
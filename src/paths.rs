@@ -0,0 +1,177 @@
+use anyhow::Context;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// The name of the directory inside the platform data directory.
+const APP_DIR: &str = "clubfridge-neo";
+
+/// The file name of the database in the data directory.
+const DATABASE_FILE: &str = "clubfridge.db";
+
+/// The directory in which the database, its backups and the log files
+/// are stored by default.
+///
+/// This is `$XDG_DATA_HOME/clubfridge-neo`, or
+/// `~/.local/share/clubfridge-neo` if `XDG_DATA_HOME` is not set. If
+/// neither `XDG_DATA_HOME` nor `HOME` are set, the current working
+/// directory is used.
+pub fn data_dir() -> PathBuf {
+    data_dir_from(std::env::var_os("XDG_DATA_HOME"), std::env::var_os("HOME"))
+}
+
+fn data_dir_from(xdg_data_home: Option<OsString>, home: Option<OsString>) -> PathBuf {
+    // Relative paths in `XDG_DATA_HOME` are invalid and should be ignored
+    let xdg_data_home = xdg_data_home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+
+    let home = home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .map(|home| home.join(".local").join("share"));
+
+    match xdg_data_home.or(home) {
+        Some(dir) => dir.join(APP_DIR),
+        None => PathBuf::new(),
+    }
+}
+
+/// The directory to which the log files are written.
+pub fn log_dir() -> PathBuf {
+    data_dir().join("logs")
+}
+
+/// The directory to which screenshots from the admin screen are saved.
+pub fn screenshot_dir() -> PathBuf {
+    data_dir().join("screenshots")
+}
+
+/// The file that contains the version the application was updated to,
+/// until its release notes were shown.
+pub fn release_notes_marker() -> PathBuf {
+    data_dir().join("show_release_notes")
+}
+
+/// The file to which a panic is saved, so that it can be shown after the
+/// application restarted.
+pub fn crash_report() -> PathBuf {
+    data_dir().join("crash_report")
+}
+
+/// The file to which successful card payments are appended before their
+/// sales are saved, so that they can be booked manually if that fails.
+pub fn card_payment_log() -> PathBuf {
+    data_dir().join("card_payments.log")
+}
+
+/// The connect options of the database in the data directory, which is
+/// created if it does not exist yet.
+pub fn default_database() -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(data_dir().join(DATABASE_FILE))
+        .create_if_missing(true)
+}
+
+/// Create the data directory and move the files of older versions, which
+/// were stored in the current working directory, into it.
+///
+/// The database and its backups are only moved if `database` is set,
+/// i.e. if no database was configured explicitly. Files are never
+/// overwritten, and a failure is returned as an error, since continuing
+/// would silently create a new empty database.
+pub fn migrate_legacy_files(database: bool) -> anyhow::Result<()> {
+    migrate(Path::new("."), &data_dir(), database)
+}
+
+fn migrate(from: &Path, to: &Path, database: bool) -> anyhow::Result<()> {
+    // Without a home directory, the working directory is the data directory
+    if to.as_os_str().is_empty() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    if from.canonicalize()? == to.canonicalize()? {
+        return Ok(());
+    }
+
+    let mut names = vec![OsString::from("logs")];
+    if database {
+        for suffix in ["", "-wal", "-shm"] {
+            names.push(OsString::from(format!("{DATABASE_FILE}{suffix}")));
+        }
+        names.push(OsString::from("backups"));
+    }
+
+    for name in names {
+        let source = from.join(&name);
+        let target = to.join(&name);
+        if !source.exists() || target.exists() {
+            continue;
+        }
+
+        // Logging is not initialized yet
+        eprintln!("Moving {} to {}", source.display(), target.display());
+        std::fs::rename(&source, &target).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir() {
+        let check = |xdg: Option<&str>, home: Option<&str>, expected: &str| {
+            let dir = data_dir_from(xdg.map(OsString::from), home.map(OsString::from));
+            assert_eq!(dir, PathBuf::from(expected));
+        };
+
+        check(Some("/data"), Some("/home/pi"), "/data/clubfridge-neo");
+        check(
+            None,
+            Some("/home/pi"),
+            "/home/pi/.local/share/clubfridge-neo",
+        );
+        check(
+            Some("data"),
+            Some("/home/pi"),
+            "/home/pi/.local/share/clubfridge-neo",
+        );
+        check(None, None, "");
+    }
+
+    #[test]
+    fn test_migrate() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("clubfridge-{}", ulid::Ulid::new()));
+        let from = dir.join("cwd");
+        let to = dir.join("data");
+        std::fs::create_dir_all(from.join("logs"))?;
+        std::fs::write(from.join(DATABASE_FILE), "old")?;
+
+        // The database is left alone if it was configured explicitly
+        migrate(&from, &to, false)?;
+        assert!(to.join("logs").is_dir());
+        assert!(!to.join(DATABASE_FILE).exists());
+
+        migrate(&from, &to, true)?;
+        assert_eq!(std::fs::read_to_string(to.join(DATABASE_FILE))?, "old");
+        assert!(!from.join(DATABASE_FILE).exists());
+
+        // Existing files in the data directory are never overwritten
+        std::fs::write(from.join(DATABASE_FILE), "older")?;
+        migrate(&from, &to, true)?;
+        assert_eq!(std::fs::read_to_string(to.join(DATABASE_FILE))?, "old");
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,40 +1,217 @@
 use crate::state::Message;
 use iced::border::rounded;
-use iced::futures::FutureExt;
 use iced::widget::{container, text};
-use iced::{color, Element, Task, Theme};
-use std::time::Duration;
+use iced::{color, Color, Element, Theme};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tracing::debug;
 
-/// The time after which the popup is automatically hidden.
-const POPUP_TIMEOUT: Duration = Duration::from_secs(3);
+/// The severity of a popup, which determines its colors and how long it
+/// is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Severity {
+    #[default]
+    Info,
+    Success,
+    Error,
+}
+
+impl Severity {
+    /// The time after which a popup of this severity is automatically hidden.
+    fn timeout(self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(3),
+            Severity::Error => Duration::from_secs(6),
+        }
+    }
+
+    /// The background and text colors of a popup of this severity.
+    fn colors(self) -> (Color, Color) {
+        match self {
+            Severity::Info => (color!(0xffffff), color!(0x000000)),
+            Severity::Success => (color!(0x4BD130), color!(0x000000)),
+            Severity::Error => (color!(0xD32F2F), color!(0xffffff)),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Popup {
     pub message: String,
-    _timeout_handle: Option<iced::task::Handle>,
+    pub severity: Severity,
+    /// The time after which the popup is automatically hidden, or `None`
+    /// if it stays visible until it is replaced by another popup.
+    timeout: Option<Duration>,
+    /// The time at which the popup was shown, or `None` if it is queued.
+    shown_at: Option<Instant>,
 }
 
 impl Popup {
-    pub fn new(message: String) -> Self {
+    pub fn new(message: impl Into<String>, severity: Severity) -> Self {
         Self {
-            message,
-            _timeout_handle: None,
+            message: message.into(),
+            severity,
+            timeout: Some(severity.timeout()),
+            shown_at: None,
         }
     }
 
-    pub fn with_timeout(mut self) -> (Self, Task<Message>) {
-        let timeout_future = tokio::time::sleep(POPUP_TIMEOUT);
-        let timeout_task = Task::future(timeout_future.map(|_| Message::PopupTimeoutReached));
-        let (task, handle) = timeout_task.abortable();
-        self._timeout_handle = Some(handle);
+    /// Create a popup that stays visible until it is replaced by another
+    /// popup or hidden explicitly.
+    pub fn persistent(message: impl Into<String>) -> Self {
+        Self {
+            timeout: None,
+            ..Self::new(message, Severity::Info)
+        }
+    }
 
-        (self, task)
+    fn is_expired(&self, now: Instant) -> bool {
+        match (self.shown_at, self.timeout) {
+            (Some(shown_at), Some(timeout)) => now.duration_since(shown_at) >= timeout,
+            _ => false,
+        }
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        container(text(&self.message).size(36).color(color!(0x000000)))
-            .style(|_theme: &Theme| container::background(color!(0xffffff)).border(rounded(10.)))
+        let (background, text_color) = self.severity.colors();
+
+        container(text(&self.message).size(36).color(text_color))
+            .style(move |_theme: &Theme| container::background(background).border(rounded(10.)))
             .padding([15, 30])
             .into()
     }
 }
+
+/// The currently shown popup and the popups that are waiting to be shown.
+///
+/// Popups are shown one after another, so that e.g. an error message is not
+/// immediately replaced by a subsequent success message.
+#[derive(Debug, Default)]
+pub struct Popups {
+    current: Option<Popup>,
+    queue: VecDeque<Popup>,
+}
+
+impl Popups {
+    /// The currently shown popup, if any.
+    pub fn current(&self) -> Option<&Popup> {
+        self.current.as_ref()
+    }
+
+    /// Show the popup, or queue it if another popup is currently shown.
+    pub fn push(&mut self, popup: Popup) {
+        debug!("Showing popup: {}", popup.message);
+
+        let replace_current = self
+            .current
+            .as_ref()
+            .is_none_or(|current| current.timeout.is_none());
+
+        if replace_current {
+            self.show(popup);
+        } else {
+            self.queue.push_back(popup);
+        }
+    }
+
+    /// Hide the current popup if its timeout was reached.
+    pub fn tick(&mut self, now: Instant) {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|popup| popup.is_expired(now))
+        {
+            self.next();
+        }
+    }
+
+    /// Hide all popups that are not errors, because the user continued to
+    /// interact with the application.
+    ///
+    /// Error popups are kept until their timeout is reached.
+    pub fn dismiss(&mut self) {
+        self.queue.retain(|popup| popup.severity == Severity::Error);
+
+        let is_error = |popup: &Popup| popup.severity == Severity::Error;
+        if self.current.as_ref().is_some_and(|popup| !is_error(popup)) {
+            self.next();
+        }
+    }
+
+    /// Hide the current popup and show the next queued popup, if any.
+    fn next(&mut self) {
+        match self.queue.pop_front() {
+            Some(popup) => self.show(popup),
+            None => {
+                if self.current.take().is_some() {
+                    debug!("Hiding popup");
+                }
+            }
+        }
+    }
+
+    fn show(&mut self, mut popup: Popup) {
+        popup.shown_at = Some(Instant::now());
+        self.current = Some(popup);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current(popups: &Popups) -> Option<&str> {
+        popups.current().map(|popup| popup.message.as_str())
+    }
+
+    #[test]
+    fn test_popup_queue() {
+        let mut popups = Popups::default();
+        popups.push(Popup::new("Artikel nicht gefunden", Severity::Error));
+        popups.push(Popup::new("Danke für deinen Kauf", Severity::Success));
+        assert_eq!(current(&popups), Some("Artikel nicht gefunden"));
+
+        let later = Instant::now() + Duration::from_secs(10);
+        popups.tick(later);
+        assert_eq!(current(&popups), Some("Danke für deinen Kauf"));
+
+        popups.tick(later + Duration::from_secs(10));
+        assert_eq!(current(&popups), None);
+    }
+
+    #[test]
+    fn test_dismiss_popups() {
+        let mut popups = Popups::default();
+        popups.push(Popup::new("Artikel nicht gefunden", Severity::Error));
+        popups.push(Popup::new("Tageslimits aufgehoben", Severity::Success));
+        popups.push(Popup::new("Benutzer nicht gefunden", Severity::Error));
+
+        // Errors are kept when the user interacts with the application
+        popups.dismiss();
+        assert_eq!(current(&popups), Some("Artikel nicht gefunden"));
+
+        popups.tick(Instant::now() + Duration::from_secs(10));
+        assert_eq!(current(&popups), Some("Benutzer nicht gefunden"));
+
+        popups.push(Popup::new("Danke für deinen Kauf", Severity::Success));
+        popups.tick(Instant::now() + Duration::from_secs(10));
+        assert_eq!(current(&popups), Some("Danke für deinen Kauf"));
+
+        popups.dismiss();
+        assert_eq!(current(&popups), None);
+    }
+
+    #[test]
+    fn test_persistent_popup() {
+        let mut popups = Popups::default();
+        popups.push(Popup::persistent("Prüfe Zugangsdaten…"));
+        popups.tick(Instant::now() + Duration::from_secs(60));
+        assert_eq!(current(&popups), Some("Prüfe Zugangsdaten…"));
+
+        popups.push(Popup::new(
+            "Authentifizierung fehlgeschlagen",
+            Severity::Error,
+        ));
+        assert_eq!(current(&popups), Some("Authentifizierung fehlgeschlagen"));
+    }
+}
@@ -0,0 +1,78 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The prefix of login QR codes.
+pub const PREFIX: &str = "CFL-";
+
+/// The number of bytes of the HMAC that are included in the code.
+const SIGNATURE_LEN: usize = 16;
+
+fn mac(secret: &[u8], member_id: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(member_id.as_bytes());
+    mac
+}
+
+/// Create the login code of the member with the given ID, for members who
+/// don't carry their RFID chip.
+///
+/// The code contains the member ID and a truncated HMAC-SHA256 of the member
+/// ID with the secret of the installation, e.g. `CFL-1234-<32 hex digits>`.
+/// Members show it as QR code on their phone (e.g. on a companion web page
+/// or a wallet pass) and scan it at the fridge. Only `-` is used as
+/// separator, so that barcode scanners can type it without modifier keys.
+pub fn code(secret: &[u8], member_id: &str) -> String {
+    let signature = mac(secret, member_id).finalize().into_bytes();
+    let signature = signature[..SIGNATURE_LEN]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    format!("{PREFIX}{member_id}-{signature}")
+}
+
+/// Check the signature of a scanned login code and return the member ID,
+/// or `None` if the code is invalid.
+pub fn verify(secret: &[u8], input: &str) -> Option<String> {
+    let (member_id, signature) = input.strip_prefix(PREFIX)?.rsplit_once('-')?;
+    if member_id.is_empty() || signature.len() != SIGNATURE_LEN * 2 {
+        return None;
+    }
+
+    let signature = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(signature.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    mac(secret, member_id)
+        .verify_truncated_left(&signature)
+        .ok()
+        .map(|_| member_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let code = code(b"secret", "1234");
+        assert!(code.starts_with("CFL-1234-"));
+        assert_eq!(code.len(), "CFL-1234-".len() + 32);
+        assert_eq!(verify(b"secret", &code).as_deref(), Some("1234"));
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        let code = code(b"secret", "1234");
+        assert_eq!(verify(b"other secret", &code), None);
+        assert_eq!(verify(b"secret", &code.replace("1234", "1235")), None);
+        assert_eq!(verify(b"secret", &code[..code.len() - 2]), None);
+        assert_eq!(verify(b"secret", "CFL-1234"), None);
+        assert_eq!(verify(b"secret", "1234"), None);
+        assert_eq!(
+            verify(b"secret", "CFL--00000000000000000000000000000000"),
+            None
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::running::Sale;
+use iced::widget::qr_code;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tracing::warn;
+
+/// A digital receipt of a purchase, which is shown to the member as a QR
+/// code encoding the receipt as JSON.
+#[derive(Debug, Serialize)]
+pub struct Receipt<'a> {
+    /// The member ID of the buyer (aka. "Mitgliedsnummer").
+    member_id: &'a str,
+    /// The time of the purchase.
+    created_at: jiff::Timestamp,
+    /// Whether the purchase was booked as a refund.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    refund: bool,
+    /// The purchased articles.
+    items: Vec<ReceiptItem<'a>>,
+    /// The total price of the purchase.
+    total: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+struct ReceiptItem<'a> {
+    article_id: &'a str,
+    designation: &'a str,
+    amount: i32,
+    unit_price: Decimal,
+    total: Decimal,
+}
+
+impl<'a> Receipt<'a> {
+    pub fn new(member_id: &'a str, sales: &'a [Sale], refund: bool) -> Self {
+        let sign = if refund { -1 } else { 1 };
+
+        let items = sales
+            .iter()
+            .map(|sale| ReceiptItem {
+                article_id: &sale.article.id,
+                designation: &sale.article.designation,
+                amount: sign * sale.amount as i32,
+                unit_price: sale.unit_price,
+                total: Decimal::from(sign) * sale.total(),
+            })
+            .collect::<Vec<_>>();
+
+        let total = items.iter().map(|item| item.total).sum();
+
+        Self {
+            member_id,
+            created_at: jiff::Timestamp::now(),
+            refund,
+            items,
+            total,
+        }
+    }
+
+    /// Encode the receipt as a QR code.
+    ///
+    /// Returns `None` if the receipt is too large to fit into a QR code.
+    pub fn to_qr_code(&self) -> Option<qr_code::Data> {
+        let json = serde_json::to_string(self)
+            .inspect_err(|err| warn!("Failed to serialize receipt: {err}"))
+            .ok()?;
+
+        qr_code::Data::new(json)
+            .inspect_err(|err| warn!("Failed to encode receipt as QR code: {err}"))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Article;
+
+    #[test]
+    fn test_receipt_json() {
+        let sales = vec![Sale {
+            amount: 2,
+            article: Article {
+                id: "1234".to_string(),
+                designation: "Wasser".to_string(),
+                prices: vec![],
+                category: None,
+                account: None,
+            },
+            unit_price: Decimal::new(150, 2),
+            open_price: false,
+            discount: false,
+            voucher: None,
+        }];
+
+        let receipt = Receipt::new("11011", &sales, false);
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["member_id"], "11011");
+        assert_eq!(json["items"][0]["amount"], 2);
+        assert_eq!(json["total"], "3.00");
+        assert!(json.get("refund").is_none());
+
+        let receipt = Receipt::new("11011", &sales, true);
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["items"][0]["amount"], -2);
+        assert_eq!(json["total"], "-3.00");
+        assert_eq!(json["refund"], true);
+    }
+}
@@ -0,0 +1,546 @@
+use crate::state::Message;
+use iced::keyboard::key::Named;
+use iced::keyboard::Key;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+/// A single step of a scripted input sequence that is replayed against the
+/// application.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Type the characters of a barcode or keycode and submit them with
+    /// Enter, like the scanner does (`scan <code>`).
+    #[cfg(test)]
+    Scan(String),
+    /// Type the characters without submitting them (`type <text>`). The
+    /// text is taken verbatim, including spaces.
+    Type(String),
+    /// Press a named key (`key Enter`, `key Tab`, `key Escape` or
+    /// `key Backspace`).
+    Key(Named),
+    /// Let the given number of seconds of the interaction timeout pass
+    /// (`wait <seconds>`).
+    Wait(u32),
+    /// Press the pay button (`pay`).
+    Pay,
+    /// Press the cancel button (`cancel`).
+    Cancel,
+}
+
+impl Step {
+    /// The messages that the application receives for this step.
+    #[cfg(test)]
+    pub fn messages(&self) -> Vec<Message> {
+        let key_press = |key| Message::KeyPress(key, iced::keyboard::Modifiers::empty());
+        let characters = |text: &str| {
+            text.chars()
+                .map(|c| key_press(Key::Character(c.to_string().into())))
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            Step::Scan(code) => {
+                let mut messages = characters(code);
+                messages.push(key_press(Key::Named(Named::Enter)));
+                messages
+            }
+            Step::Type(text) => characters(text),
+            Step::Key(named) => vec![key_press(Key::Named(*named))],
+            Step::Wait(seconds) => (0..*seconds).map(|_| Message::DecrementTimeout).collect(),
+            Step::Pay => vec![Message::Pay],
+            Step::Cancel => vec![Message::Cancel],
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(test)]
+            Step::Scan(code) => write!(f, "scan {code}"),
+            Step::Type(text) => write!(f, "type {text}"),
+            Step::Key(named) => write!(f, "key {named:?}"),
+            Step::Wait(seconds) => write!(f, "wait {seconds}"),
+            Step::Pay => f.write_str("pay"),
+            Step::Cancel => f.write_str("cancel"),
+        }
+    }
+}
+
+/// Parse a replay script into its steps.
+///
+/// A script contains one step per line, and empty lines and lines starting
+/// with `#` are ignored. Recordings of the `--record-input` option use the
+/// same format:
+///
+/// ```text
+/// # Erika buys two Club-Mate
+/// scan 0000000001
+/// scan 4029764001807
+/// scan 4029764001807
+/// pay
+/// ```
+#[cfg(test)]
+pub fn parse(script: &str) -> anyhow::Result<Vec<Step>> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_start()))
+        .filter(|(_, line)| !line.trim_end().is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            parse_step(line).map_err(|err| anyhow::anyhow!("Line {number}: {err}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn parse_step(line: &str) -> anyhow::Result<Step> {
+    let (command, verbatim) = line.split_once(' ').unwrap_or((line.trim_end(), ""));
+    let argument = verbatim.trim();
+
+    Ok(match command {
+        "scan" if !argument.is_empty() => Step::Scan(argument.to_string()),
+        "type" if !verbatim.is_empty() => Step::Type(verbatim.to_string()),
+        "key" => Step::Key(match argument {
+            "Enter" => Named::Enter,
+            "Tab" => Named::Tab,
+            "Escape" => Named::Escape,
+            "Backspace" => Named::Backspace,
+            _ => anyhow::bail!("Unknown key: {argument}"),
+        }),
+        "wait" => Step::Wait(argument.parse()?),
+        "pay" => Step::Pay,
+        "cancel" => Step::Cancel,
+        _ => anyhow::bail!("Invalid step: {line}"),
+    })
+}
+
+/// Records the keyboard input and button presses of the running
+/// application as a replay script, to reproduce scanner issues from the
+/// field on a developer machine.
+///
+/// The scanned input is anonymized with [`anonymize`] before it is written.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    /// The characters that were typed since the last submit.
+    input: String,
+    /// The number of seconds of the interaction timeout that passed since
+    /// the last recorded step.
+    wait: u32,
+    /// Whether the next pay or cancel message was triggered by the
+    /// interaction timeout instead of a button press.
+    timed_out: bool,
+    /// The recorded steps that have not been written yet.
+    steps: Vec<Step>,
+    /// Mutex to ensure that the steps are appended in the recorded order.
+    write_mutex: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            input: String::new(),
+            wait: 0,
+            timed_out: false,
+            steps: Vec::new(),
+            write_mutex: Default::default(),
+        }
+    }
+
+    /// Record the given message, if it is caused by the user or the
+    /// interaction timeout.
+    pub fn record(&mut self, message: &Message) {
+        match message {
+            Message::KeyPress(Key::Character(c), modifiers) => {
+                let Some(mut c) = c.chars().next() else {
+                    return;
+                };
+                if c == '\r' || c == '\n' {
+                    self.push_key(Named::Enter);
+                    return;
+                }
+                if modifiers.shift() {
+                    c = c.to_ascii_uppercase();
+                }
+                self.input.push(c);
+            }
+            Message::KeyPress(Key::Named(named), _) => {
+                if matches!(
+                    named,
+                    Named::Enter | Named::Tab | Named::Escape | Named::Backspace
+                ) {
+                    self.push_key(*named);
+                }
+            }
+            Message::DecrementTimeout => self.wait += 1,
+            Message::Pay | Message::Cancel | Message::CloseReceipt if self.timed_out => {
+                self.timed_out = false;
+            }
+            Message::Pay => self.push(Step::Pay),
+            Message::Cancel => self.push(Step::Cancel),
+            _ => {}
+        }
+    }
+
+    /// Remember that the interaction timeout expired, so that the message
+    /// that it triggers is replayed by the `wait` step instead.
+    pub fn timeout_expired(&mut self) {
+        self.flush_wait();
+        self.timed_out = true;
+    }
+
+    fn push_key(&mut self, named: Named) {
+        if !self.input.is_empty() {
+            let input = anonymize(&mem::take(&mut self.input));
+            self.push(Step::Type(input));
+        }
+        self.push(Step::Key(named));
+    }
+
+    fn push(&mut self, step: Step) {
+        self.flush_wait();
+        self.steps.push(step);
+    }
+
+    fn flush_wait(&mut self) {
+        if self.wait > 0 {
+            let wait = mem::take(&mut self.wait);
+            self.steps.push(Step::Wait(wait));
+        }
+    }
+
+    /// Append the steps that were recorded so far to the recording file,
+    /// or return `None` if there is nothing to write.
+    pub fn flush(&mut self) -> Option<impl Future<Output = ()> + 'static> {
+        if self.steps.is_empty() {
+            return None;
+        }
+
+        let lines = mem::take(&mut self.steps)
+            .iter()
+            .map(|step| format!("{step}\n"))
+            .collect::<String>();
+
+        let path = self.path.clone();
+        let write_mutex = self.write_mutex.clone();
+        Some(async move {
+            let _guard = write_mutex.lock().await;
+            let result = async {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await?;
+                file.write_all(lines.as_bytes()).await
+            };
+
+            if let Err(err) = result.await {
+                warn!(
+                    "Failed to write input recording to {}: {err}",
+                    path.display()
+                );
+            }
+        })
+    }
+}
+
+/// Replace the letters and digits of the scanned input with `X`, `x` and
+/// `0`, unless it is an article barcode (EAN-8, UPC-A or EAN-13).
+///
+/// This hides keycodes, PINs and login codes, but keeps the length of the
+/// input and any prefixes or suffixes that the scanner added. In demo mode,
+/// the anonymized keycodes still log in one of the fake members.
+pub fn anonymize(input: &str) -> String {
+    if is_article_barcode(input) {
+        return input.to_string();
+    }
+
+    input
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => '0',
+            'a'..='z' => 'x',
+            'A'..='Z' => 'X',
+            c if c.is_alphanumeric() => 'x',
+            c => c,
+        })
+        .collect()
+}
+
+/// Check if the input is a GTIN with 8, 12 or 13 digits and a valid check
+/// digit.
+fn is_article_barcode(input: &str) -> bool {
+    if !matches!(input.len(), 8 | 12 | 13) || !input.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    // The digits are weighted 3 and 1 alternately, starting from the right
+    let sum = input
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * if i % 2 == 1 { 3 } else { 1 })
+        .sum::<u32>();
+
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use crate::state::{ClubFridge, Options, State};
+    use clap::Parser;
+    use iced::futures::StreamExt;
+    use iced::keyboard::Modifiers;
+    use iced::Task;
+    use iced_runtime::{task, Action};
+    use sqlx::SqlitePool;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    /// The time after which a task that did not finish fails the test.
+    const TASK_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Runs the application in demo mode and feeds it the messages of
+    /// replay scripts, including the messages produced by its tasks.
+    struct Harness {
+        cf: ClubFridge,
+    }
+
+    impl Harness {
+        async fn new(args: &[&str]) -> Self {
+            let default_args = ["clubfridge-neo", "--demo", "--scan-debounce", "0"];
+            let args = default_args.iter().chain(args);
+            let options = Options::try_parse_from(args).unwrap();
+
+            let (cf, task) = ClubFridge::new(options);
+            let mut harness = Self { cf };
+            harness.run_task(task).await;
+            assert!(matches!(harness.cf.state, State::Running(_)));
+
+            harness
+        }
+
+        fn pool(&self) -> SqlitePool {
+            match &self.cf.state {
+                State::Running(cf) => cf.pool.clone(),
+                _ => panic!("application is not running"),
+            }
+        }
+
+        async fn replay(&mut self, script: &str) {
+            for step in parse(script).unwrap() {
+                for message in step.messages() {
+                    let task = self.cf.update(message);
+                    self.run_task(task).await;
+                }
+            }
+        }
+
+        /// Run the task and all tasks that are started by the messages it
+        /// produces, until there is nothing left to do.
+        async fn run_task(&mut self, task: Task<Message>) {
+            let mut tasks = VecDeque::from([task]);
+            while let Some(task) = tasks.pop_front() {
+                let Some(mut stream) = task::into_stream(task) else {
+                    continue;
+                };
+
+                loop {
+                    let next = tokio::time::timeout(TASK_TIMEOUT, stream.next()).await;
+                    let Some(action) = next.expect("task should finish") else {
+                        break;
+                    };
+
+                    match action {
+                        // Checking for updates would need network access
+                        Action::Output(Message::SelfUpdate) => {}
+                        Action::Output(message) => tasks.push_back(self.cf.update(message)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    async fn sales(pool: &SqlitePool) -> Vec<(String, String, i32)> {
+        let sales = database::Sale::load_all(pool.clone()).await.unwrap();
+        let mut sales = sales
+            .into_iter()
+            .map(|sale| (sale.member_id, sale.article_id, sale.amount))
+            .collect::<Vec<_>>();
+        sales.sort();
+        sales
+    }
+
+    #[test]
+    fn test_parse() {
+        let script = "# comment\n\nscan 0001\ntype 12\nkey Enter\nwait 60\n  pay  \ncancel\n";
+        assert_eq!(
+            parse(script).unwrap(),
+            vec![
+                Step::Scan("0001".to_string()),
+                Step::Type("12".to_string()),
+                Step::Key(Named::Enter),
+                Step::Wait(60),
+                Step::Pay,
+                Step::Cancel,
+            ]
+        );
+
+        let err = parse("scan 1\nkey F1\n").unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: Unknown key: F1");
+        assert!(parse("scan\n").is_err());
+        assert!(parse("wait soon\n").is_err());
+        assert!(parse("dance\n").is_err());
+    }
+
+    #[test]
+    fn test_messages() {
+        let messages = Step::Scan("12".to_string()).messages();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(
+            &messages[2],
+            Message::KeyPress(Key::Named(Named::Enter), _)
+        ));
+
+        assert_eq!(Step::Wait(3).messages().len(), 3);
+    }
+
+    #[test]
+    fn test_display() {
+        let script = "scan 0001\ntype  1 2\nkey Tab\nwait 5\npay\ncancel\n";
+        let steps = parse(script).unwrap();
+        assert_eq!(steps[1], Step::Type(" 1 2".to_string()));
+
+        let lines = steps
+            .iter()
+            .map(|step| format!("{step}\n"))
+            .collect::<String>();
+        assert_eq!(lines, script);
+    }
+
+    #[test]
+    fn test_anonymize() {
+        assert_eq!(anonymize("4029764001807"), "4029764001807");
+        assert_eq!(anonymize("40822938"), "40822938");
+        assert_eq!(anonymize("4029764001808"), "0000000000000");
+        assert_eq!(anonymize("]E0abC12345"), "]X0xxX00000");
+        assert_eq!(anonymize("CFL-42-ab"), "XXX-00-xx");
+        assert_eq!(anonymize(""), "");
+    }
+
+    #[test]
+    fn test_recorder() {
+        let key = |key| Message::KeyPress(key, Modifiers::empty());
+        let typed = |c: &str| key(Key::Character(c.into()));
+
+        let mut recorder = Recorder::new(PathBuf::from("input.txt"));
+        for message in [
+            typed("1"),
+            typed("2"),
+            key(Key::Named(Named::Enter)),
+            typed("4"),
+            typed("0"),
+            typed("8"),
+            typed("2"),
+            typed("2"),
+            typed("9"),
+            typed("3"),
+            typed("8"),
+            typed("\r"),
+            Message::DecrementTimeout,
+            Message::DecrementTimeout,
+            Message::Cancel,
+            Message::DecrementTimeout,
+        ] {
+            recorder.record(&message);
+        }
+        recorder.timeout_expired();
+        recorder.record(&Message::Pay);
+
+        assert_eq!(
+            recorder.steps,
+            vec![
+                Step::Type("00".to_string()),
+                Step::Key(Named::Enter),
+                Step::Type("40822938".to_string()),
+                Step::Key(Named::Enter),
+                Step::Wait(2),
+                Step::Cancel,
+                Step::Wait(1),
+            ]
+        );
+        assert!(recorder.flush().is_some());
+        assert!(recorder.flush().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_checkout() {
+        let mut harness = Harness::new(&[]).await;
+        harness
+            .replay(
+                "scan 0000000001\n\
+                 scan 4029764001807\n\
+                 scan 4029764001807\n\
+                 scan 40822938\n\
+                 pay\n",
+            )
+            .await;
+
+        let sales = sales(&harness.pool()).await;
+        assert_eq!(
+            sales,
+            vec![
+                ("90001".to_string(), "4029764001807".to_string(), 2),
+                ("90001".to_string(), "40822938".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_typed_input() {
+        let mut harness = Harness::new(&[]).await;
+        harness
+            .replay("type 0000000002\nkey Enter\ntype 5449000000996\nkey Enter\npay\n")
+            .await;
+
+        let sales = sales(&harness.pool()).await;
+        assert_eq!(
+            sales,
+            vec![("90002".to_string(), "5449000000996".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_timeout() {
+        let mut harness = Harness::new(&[]).await;
+        harness
+            .replay("scan 0000000001\nscan 40822938\nwait 60\n")
+            .await;
+
+        let sales = sales(&harness.pool()).await;
+        assert_eq!(
+            sales,
+            vec![("90001".to_string(), "40822938".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_cancel() {
+        let mut harness = Harness::new(&[]).await;
+        harness
+            .replay("scan 0000000001\nscan 40822938\ncancel\n")
+            .await;
+
+        assert!(sales(&harness.pool()).await.is_empty());
+    }
+}
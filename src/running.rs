@@ -1,62 +1,413 @@
+use crate::admin::{
+    Admin, Confirmation, PendingSaleEdit, PendingSaleUpdate, PendingSales, Restocking, Stocktaking,
+};
+use crate::alert;
+use crate::announcement::Announcement;
+use crate::calendar;
 use crate::database;
-use crate::state::{GlobalState, Message};
+use crate::datev;
+use crate::discount::{self, DiscountRule};
+use crate::disk;
+use crate::door;
+use crate::events::{Event, EventStream};
+use crate::import;
+use crate::logging;
+use crate::network;
+use crate::qr_login;
+use crate::receipt::Receipt;
+use crate::replay::Recorder;
+use crate::scanner::{self, KeyAction, SubmitKey};
+use crate::screenshot;
+use crate::sepa;
+use crate::state::{GlobalState, Message, Options};
+use crate::statement;
+use crate::sumup::{self, SumUp};
+use crate::sync::{self, is_rate_limited, ArticleCategory, RateLimited};
+use crate::system;
+use crate::temperature;
+use crate::texts;
+use crate::totp;
+use crate::transfer::TransferTarget;
+use crate::verify::Verifier;
 use iced::keyboard::key::Named;
 use iced::keyboard::Key;
-use iced::{Subscription, Task};
+use iced::widget::operation::{self, RelativeOffset};
+use iced::widget::qr_code;
+use iced::{window, Subscription, Task};
 use rust_decimal::Decimal;
+use secrecy::SecretString;
 use sqlx::types::Text;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::mem;
 use std::ops::Sub;
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 use ulid::Ulid;
 
-/// The interval at which the app should load articles and users from
-/// the Vereinsflieger API.
-const SYNC_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// The interval at which the app should reload the club calendar.
+const CALENDAR_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
-/// The interval at which the app should upload new sales to
-/// the Vereinsflieger API.
-const SALES_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// The interval at which the app should check whether Vereinsflieger is
+/// reachable.
+const CONNECTIVITY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval at which the app should look up articles that expire soon.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The interval at which the temperature sensor should be read.
+const TEMPERATURE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval at which the free disk space should be checked.
+const DISK_SPACE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The interval at which the door sensor should be read.
+const DOOR_SENSOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The interval at which the alarm sound is repeated while the door is open.
+const DOOR_ALARM_SOUND_INTERVAL: jiff::SignedDuration = jiff::SignedDuration::from_secs(30);
+
+/// The ID of the scrollable cart on the member screen.
+pub const CART_ID: &str = "cart";
+
+/// The time for which a cart row is highlighted after its amount changed.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+
+/// The time between closing the admin screen and taking the screenshot, so
+/// that the member screen is rendered.
+const SCREENSHOT_DELAY: Duration = Duration::from_millis(500);
+
+/// The number of audit log entries that are shown on the admin screen.
+const AUDIT_LOG_LIMIT: u32 = 200;
+
+/// The number of price changes that are shown on the admin screen.
+const PRICE_CHANGES_LIMIT: u32 = 100;
 
 /// The time after which the sale is automatically processed.
 const INTERACTION_TIMEOUT: jiff::SignedDuration = jiff::SignedDuration::from_secs(60);
 
+/// The time for which the receipt QR code is shown after a purchase.
+const RECEIPT_TIMEOUT: jiff::SignedDuration = jiff::SignedDuration::from_secs(30);
+
+/// The time for which the sync is paused after the first rate-limited
+/// Vereinsflieger request. This is doubled for each subsequent rate limit.
+const INITIAL_RATE_LIMIT_BACKOFF: jiff::SignedDuration = jiff::SignedDuration::from_mins(15);
+
+/// The maximum time for which the sync is paused because of rate limiting.
+const MAX_RATE_LIMIT_BACKOFF: jiff::SignedDuration = jiff::SignedDuration::from_hours(6);
+
 pub struct RunningClubFridge {
     pub pool: SqlitePool,
-    pub vereinsflieger: Option<vereinsflieger::Client>,
+    /// The Vereinsflieger client of the club whose articles are synchronized.
+    pub article_client: Option<vereinsflieger::Client>,
+    /// The Vereinsflieger client of the club whose members are synchronized
+    /// and to which sales are uploaded.
+    pub sales_client: Option<vereinsflieger::Client>,
+    /// Checks uploaded sales against Vereinsflieger, if enabled.
+    pub upload_verifier: Option<Verifier>,
     /// Mutex to ensure that only one upload task runs at a time.
     pub upload_mutex: Arc<tokio::sync::Mutex<()>>,
+    /// Mutex that is held while sales are written to the local database.
+    pub insert_mutex: Arc<tokio::sync::Mutex<()>>,
+    /// The articles, which are cached for scan lookups until the next
+    /// article sync.
+    pub article_cache: database::ArticleCache,
+    /// The time until which no requests are sent to Vereinsflieger, because
+    /// it responded with a rate limit error.
+    pub rate_limited_until: Option<jiff::Timestamp>,
+    /// The time for which the sync is paused on the next rate limit error.
+    pub rate_limit_backoff: jiff::SignedDuration,
+    /// Whether Vereinsflieger was reachable at the last connectivity check.
+    ///
+    /// The sync is paused while Vereinsflieger is unreachable and resumed
+    /// once the connection is back.
+    pub online: bool,
 
     pub user: Option<database::Member>,
     pub input: String,
     pub sales: Vec<Sale>,
     pub interaction_timeout: Option<jiff::SignedDuration>,
+    /// The last scanned input and the time it was scanned, used to ignore
+    /// accidental double reads of the scanner.
+    pub last_scan: Option<(String, Instant)>,
+    /// The admin screen, if it is currently open.
+    pub admin: Option<Admin>,
+    /// The sales of the logged-in member from earlier today, used to
+    /// enforce daily purchase limits.
+    pub todays_sales: Vec<database::Sale>,
+    /// The article of the cart row whose amount was just increased, and the
+    /// time at which it was highlighted.
+    pub highlighted_row: Option<(String, Instant)>,
+    /// The article IDs and amounts of the previous purchase of the logged-in
+    /// member, which can be added to the cart again.
+    pub last_purchase: Vec<(String, u16)>,
+    /// The prepaid balance of the logged-in member, once it is loaded.
+    pub balance: Option<Decimal>,
+    /// Whether an admin lifted the daily purchase limits for the
+    /// logged-in member.
+    pub limits_overridden: bool,
+    /// The price entered on the numpad for the open-price article, if the
+    /// numpad is currently open.
+    pub open_price_input: Option<String>,
+    /// Whether an admin started a refund for the logged-in member, which
+    /// books the cart with negative amounts.
+    pub refund: bool,
+    /// Whether the payment confirmation dialog is currently shown.
+    pub confirming_payment: bool,
+    /// The receipt of the purchase that is currently being saved.
+    pub pending_receipt: Option<qr_code::Data>,
+    /// The receipt QR code of the last purchase, if it is currently shown.
+    pub receipt: Option<qr_code::Data>,
+    /// The SumUp client that is used to charge guests by card, if configured.
+    pub sumup: Option<SumUp>,
+    /// Whether a card payment is currently waiting for the card terminal.
+    pub card_payment_pending: bool,
+    /// The bank account or PayPal.me name that guests can transfer money to,
+    /// if configured.
+    pub transfer_target: Option<TransferTarget>,
+    /// The QR code for the transfer of the guest's total, if it is
+    /// currently shown.
+    pub transfer_qr: Option<qr_code::Data>,
+    /// The announcements that are shown in rotation on the idle screen.
+    pub announcements: Vec<Announcement>,
+    /// The time after which the next announcement is shown.
+    pub announcement_interval: Duration,
+    /// The index of the announcement that is currently shown.
+    pub announcement_index: usize,
+    /// The upcoming events from the club calendar.
+    pub calendar_events: Vec<calendar::Event>,
+    /// Whether a calendar feed is configured and should be loaded.
+    pub calendar_enabled: bool,
+    /// Whether best-before dates are tracked and expiring articles should
+    /// be looked up regularly.
+    pub expiry_tracking: bool,
+    /// The batches in the fridge that expire soon.
+    pub expiring_batches: Vec<database::ExpiringBatch>,
+    /// The number of synchronized price changes that no admin has seen yet.
+    pub unseen_price_changes: u32,
+    /// Whether a temperature sensor is configured and should be read.
+    pub temperature_enabled: bool,
+    /// The last successfully read fridge temperature in °C.
+    pub temperature: Option<f64>,
+    /// Whether the fridge is too warm and an alert was already shown.
+    pub temperature_alert: bool,
+    /// The base32-encoded TOTP secret for opening the admin screen, if one
+    /// was provisioned with the `admin-totp` command.
+    pub admin_totp_secret: Option<String>,
+    /// The time step of the last accepted TOTP code, so that a code can not
+    /// be used twice.
+    pub admin_totp_step: Option<i64>,
+    /// The admin action that waits for a fresh admin code, if any.
+    pub confirmation: Option<Confirmation>,
+    /// Whether the admin action that is currently running was confirmed with
+    /// a fresh admin code.
+    action_confirmed: bool,
+    /// Whether the member wants to round up their total to the next Euro as
+    /// a donation.
+    pub round_up: bool,
+    /// Whether the admin allowed booking purchases to a cost center.
+    pub cost_centers_enabled: bool,
+    /// Whether the member is choosing the cost center for their purchase.
+    pub choosing_cost_center: bool,
+    /// The time for which members can park their cart, if enabled.
+    pub park_window: Option<jiff::SignedDuration>,
+    /// The volume discounts, which are recalculated whenever the cart
+    /// changes.
+    pub discounts: Vec<DiscountRule>,
+    /// The article to which the volume discounts are booked.
+    pub discount_article: Option<String>,
+    /// The time after which member IDs are removed from old records, if
+    /// configured.
+    pub member_data_retention: Option<jiff::SignedDuration>,
+    /// The interval at which the articles and members are loaded from
+    /// Vereinsflieger, and old member data is anonymized.
+    pub sync_interval: Duration,
+    /// The interval at which new sales are uploaded to Vereinsflieger.
+    pub upload_interval: Duration,
+    /// Whether the database is stored in a file and the free disk space
+    /// should be checked.
+    pub disk_check_enabled: bool,
+    /// The free disk space in bytes, if it is below the configured minimum.
+    pub low_disk_space: Option<u64>,
+    /// The number of pending sales that repeatedly failed to upload or are
+    /// too old.
+    pub stuck_sales: u32,
+    /// The transaction IDs of successful card payments whose sales could not
+    /// be saved, which have to be booked manually from the card payment log.
+    pub unsaved_card_payments: Vec<String>,
+    /// Whether a door sensor is configured and should be read.
+    pub door_enabled: bool,
+    /// The time at which the fridge door was opened, if it is open.
+    pub door_opened_at: Option<jiff::Timestamp>,
+    /// Whether the door was left open for too long and the alarm is active.
+    pub door_alarm: bool,
+    /// The time at which the alarm sound was last played.
+    pub door_alarm_sound_at: Option<jiff::Timestamp>,
+    /// The audit log entries that were recorded while handling the current
+    /// message and still have to be saved.
+    pub audit_entries: Vec<database::AuditEntry>,
+    /// The stream to which cart and sale events are sent.
+    pub events: EventStream,
+    /// The recorder of the keyboard input, if enabled.
+    pub recorder: Option<Recorder>,
 }
 
 impl RunningClubFridge {
     pub fn new(
         pool: SqlitePool,
-        vereinsflieger: Option<vereinsflieger::Client>,
+        credentials: Vec<database::Credentials>,
+        options: &Options,
+        events: EventStream,
     ) -> (Self, Task<Message>) {
-        let mut tasks = vec![];
-        if vereinsflieger.is_some() {
+        let upload_verifier = options.upload_verifier(&credentials);
+
+        let clients = credentials
+            .into_iter()
+            .map(|credentials| {
+                let club_id = credentials.club_id;
+                (club_id, vereinsflieger::Client::new(credentials.into()))
+            })
+            .collect::<Vec<_>>();
+
+        let article_client = select_client(&clients, options.article_club);
+        let sales_client = select_client(&clients, options.sales_club);
+
+        let sumup = match (
+            &options.sumup_api_key,
+            &options.sumup_merchant_code,
+            &options.sumup_reader_id,
+        ) {
+            (Some(api_key), Some(merchant_code), Some(reader_id)) => Some(SumUp::new(
+                SecretString::from(api_key.clone()),
+                merchant_code.clone(),
+                reader_id.clone(),
+            )),
+            _ => None,
+        };
+
+        let transfer_target = match (&options.transfer_iban, &options.transfer_name) {
+            (Some(iban), Some(name)) => Some(TransferTarget::Bank {
+                name: name.clone(),
+                iban: iban.clone(),
+            }),
+            _ => options
+                .paypal_me
+                .clone()
+                .map(|name| TransferTarget::PayPal { name }),
+        };
+
+        let mut tasks = vec![Task::done(Message::RestoreSession)];
+
+        let totp_pool = pool.clone();
+        tasks.push(Task::future(async move {
+            let result = database::Settings::admin_totp_secret(&totp_pool).await;
+            Message::AdminTotpSecretLoaded(result.map_err(Arc::new))
+        }));
+
+        let calendar_enabled = options.calendar_url.is_some() && !options.offline;
+        if calendar_enabled {
+            tasks.push(Task::done(Message::LoadCalendar));
+        }
+        let temperature_enabled = options.temperature_sensor.is_some();
+        if temperature_enabled {
+            tasks.push(Task::done(Message::ReadTemperature));
+        }
+        let member_data_retention = options
+            .member_data_retention_days
+            .map(|days| jiff::SignedDuration::from_hours(days * 24));
+        if member_data_retention.is_some() {
+            tasks.push(Task::done(Message::AnonymizeMemberData));
+        }
+        // The temporary database of the demo mode has no file
+        let disk_check_enabled = !options.demo;
+        if disk_check_enabled {
+            tasks.push(Task::done(Message::CheckDiskSpace));
+        }
+        let door_enabled = options.door_sensor.is_some();
+        let expiry_tracking = options.expiry_warning_days.is_some();
+        if expiry_tracking {
+            tasks.push(Task::done(Message::LoadExpiringBatches));
+        }
+        if article_client.is_some() || sales_client.is_some() {
+            tasks.push(Task::done(Message::CountPriceChanges));
             tasks.push(Task::done(Message::LoadFromVF));
             tasks.push(Task::done(Message::UploadSalesToVF));
+            tasks.push(Task::done(Message::CheckStuckSales));
         } else {
             info!("Running in offline mode, skipping Vereinsflieger sync");
         }
 
         let cf = Self {
             pool,
-            vereinsflieger,
+            article_client,
+            sales_client,
+            upload_verifier,
             upload_mutex: Default::default(),
+            insert_mutex: Default::default(),
+            article_cache: Default::default(),
+            rate_limited_until: None,
+            rate_limit_backoff: INITIAL_RATE_LIMIT_BACKOFF,
+            online: true,
             user: None,
             input: String::new(),
             sales: Vec::new(),
             interaction_timeout: None,
+            last_scan: None,
+            admin: None,
+            todays_sales: Vec::new(),
+            last_purchase: Vec::new(),
+            highlighted_row: None,
+            balance: None,
+            limits_overridden: false,
+            open_price_input: None,
+            refund: false,
+            confirming_payment: false,
+            pending_receipt: None,
+            receipt: None,
+            sumup,
+            card_payment_pending: false,
+            transfer_target,
+            transfer_qr: None,
+            announcements: options.announcements.clone(),
+            announcement_interval: Duration::from_secs(options.announcement_interval.max(1)),
+            announcement_index: 0,
+            calendar_events: Vec::new(),
+            calendar_enabled,
+            expiry_tracking,
+            expiring_batches: Vec::new(),
+            unseen_price_changes: 0,
+            temperature_enabled,
+            temperature: None,
+            temperature_alert: false,
+            admin_totp_secret: None,
+            admin_totp_step: None,
+            confirmation: None,
+            action_confirmed: false,
+            round_up: false,
+            cost_centers_enabled: false,
+            choosing_cost_center: false,
+            park_window: options
+                .park_cart_minutes
+                .map(jiff::SignedDuration::from_mins),
+            discounts: options.discounts.clone(),
+            discount_article: options.discount_article.clone(),
+            member_data_retention,
+            sync_interval: Duration::from_secs(options.sync_interval * 60),
+            upload_interval: Duration::from_secs(options.upload_interval * 60),
+            disk_check_enabled,
+            low_disk_space: None,
+            stuck_sales: 0,
+            unsaved_card_payments: Vec::new(),
+            door_enabled,
+            door_opened_at: None,
+            door_alarm: false,
+            door_alarm_sound_at: None,
+            audit_entries: Vec::new(),
+            events,
+            recorder: options.record_input.clone().map(Recorder::new),
         };
 
         (cf, Task::batch(tasks))
@@ -71,9 +422,16 @@ impl RunningClubFridge {
             }
         })];
 
-        if self.vereinsflieger.is_some() {
-            subscriptions.push(iced::time::every(SYNC_INTERVAL).map(|_| Message::LoadFromVF));
-            subscriptions.push(iced::time::every(SALES_INTERVAL).map(|_| Message::UploadSalesToVF));
+        if self.article_client.is_some() || self.sales_client.is_some() {
+            subscriptions
+                .push(iced::time::every(CONNECTIVITY_INTERVAL).map(|_| Message::CheckConnectivity));
+        }
+        if self.online && (self.article_client.is_some() || self.sales_client.is_some()) {
+            subscriptions.push(iced::time::every(self.sync_interval).map(|_| Message::LoadFromVF));
+        }
+        if self.online && self.sales_client.is_some() {
+            subscriptions
+                .push(iced::time::every(self.upload_interval).map(|_| Message::UploadSalesToVF));
         }
 
         if self.interaction_timeout.is_some() {
@@ -81,355 +439,3578 @@ impl RunningClubFridge {
                 .push(iced::time::every(Duration::from_secs(1)).map(|_| Message::DecrementTimeout));
         }
 
+        if self.calendar_enabled {
+            subscriptions.push(iced::time::every(CALENDAR_INTERVAL).map(|_| Message::LoadCalendar));
+        }
+
+        if self.temperature_enabled {
+            subscriptions
+                .push(iced::time::every(TEMPERATURE_INTERVAL).map(|_| Message::ReadTemperature));
+        }
+
+        if self.member_data_retention.is_some() {
+            subscriptions
+                .push(iced::time::every(self.sync_interval).map(|_| Message::AnonymizeMemberData));
+        }
+
+        if self.disk_check_enabled {
+            subscriptions
+                .push(iced::time::every(DISK_SPACE_INTERVAL).map(|_| Message::CheckDiskSpace));
+        }
+
+        if self.door_enabled {
+            subscriptions
+                .push(iced::time::every(DOOR_SENSOR_INTERVAL).map(|_| Message::ReadDoorSensor));
+        }
+
+        if self.expiry_tracking {
+            subscriptions.push(
+                iced::time::every(EXPIRY_CHECK_INTERVAL).map(|_| Message::LoadExpiringBatches),
+            );
+        }
+
+        if self.user.is_none() && self.announcements.len() > 1 {
+            subscriptions.push(
+                iced::time::every(self.announcement_interval).map(|_| Message::NextAnnouncement),
+            );
+        }
+
         Subscription::batch(subscriptions)
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Sale {
-    pub amount: u16,
-    pub article: database::Article,
+/// Load the saved session together with the logged-in member.
+///
+/// Sessions of members that no longer exist are discarded.
+async fn load_session(
+    pool: SqlitePool,
+) -> sqlx::Result<Option<(database::Member, database::Session)>> {
+    let Some(session) = database::Session::load(&pool).await? else {
+        return Ok(None);
+    };
+
+    let member_id = &session.member_id;
+    let Some(member) = database::Member::find_by_id(pool.clone(), member_id).await? else {
+        warn!(%member_id, "Discarding saved session of unknown member");
+        database::Session::clear(&pool).await?;
+        return Ok(None);
+    };
+
+    Ok(Some((member, session)))
 }
 
-impl Sale {
-    pub fn total(&self) -> Decimal {
-        Decimal::from(self.amount) * self.article.current_price().unwrap_or_default()
+/// Export the monthly statements for the month of the given date, together
+/// with the SEPA direct debits and DATEV bookings if they are configured.
+async fn export_month(
+    pool: &SqlitePool,
+    dir: &std::path::Path,
+    month: jiff::civil::Date,
+    creditor: Option<sepa::Creditor>,
+    datev: Option<datev::Config>,
+) -> anyhow::Result<usize> {
+    let count = statement::export_month(pool, dir, month).await?;
+    if let Some(creditor) = creditor {
+        sepa::export_month(pool, dir, month, &creditor).await?;
+    }
+    if let Some(datev) = datev {
+        datev::export_month(pool, dir, month, &datev).await?;
     }
+
+    Ok(count)
 }
 
-impl RunningClubFridge {
-    pub fn update(&mut self, message: Message, global_state: &mut GlobalState) -> Task<Message> {
-        match message {
-            Message::LoadFromVF => {
-                let Some(vereinsflieger) = &self.vereinsflieger else {
-                    return Task::none();
-                };
+/// Resolve a scanned bundle barcode into the article ID and the number of
+/// units, or return the barcode itself with a single unit. The number of
+/// units is multiplied by the typed quantity.
+fn resolve_bundle(options: &Options, input: &str, quantity: u16) -> (String, u16) {
+    let bundle = options
+        .bundles
+        .iter()
+        .find(|bundle| bundle.barcode == input);
 
-                let vf_clone = vereinsflieger.clone();
-                let pool_clone = self.pool.clone();
-                let load_articles_task = Task::future(async move {
-                    info!("Loading articles from Vereinsflieger API…");
-                    let articles = vf_clone.list_articles().await?;
-                    info!(
-                        "Received {} articles from Vereinsflieger API",
-                        articles.len()
-                    );
+    match bundle {
+        Some(bundle) => (
+            bundle.article_id.clone(),
+            bundle.amount.saturating_mul(quantity),
+        ),
+        None => (input.to_string(), quantity),
+    }
+}
 
-                    let articles = articles
-                        .into_iter()
-                        .filter_map(|article| {
-                            database::Article::try_from(article)
-                                .inspect_err(|err| warn!("Found invalid article: {err}"))
-                                .ok()
-                        })
-                        .collect::<Vec<_>>();
+/// The largest quantity that can be typed before scanning an article.
+const MAX_QUANTITY: u16 = 99;
 
-                    info!("Saving {} articles to database…", articles.len());
-                    database::Article::save_all(pool_clone, articles).await?;
+/// Split a typed quantity prefix like `6*` off the scanned input, so that
+/// multiple units of an article can be added with a single scan.
+///
+/// The input is returned unchanged with a quantity of one if it has no
+/// valid quantity prefix.
+fn split_quantity(input: &str) -> (u16, &str) {
+    let Some((quantity, rest)) = input.split_once('*') else {
+        return (1, input);
+    };
 
-                    Ok::<_, anyhow::Error>(())
-                })
-                .then(|result| {
-                    match result {
-                        Ok(_) => info!("Articles successfully saved to database"),
-                        Err(err) => error!("Failed to load articles: {err}"),
-                    }
+    if quantity.is_empty() || !quantity.bytes().all(|b| b.is_ascii_digit()) {
+        return (1, input);
+    }
 
-                    Task::none()
-                });
+    match quantity.parse() {
+        Ok(quantity @ 1..=MAX_QUANTITY) => (quantity, rest),
+        _ => (1, input),
+    }
+}
 
-                let vf_clone = vereinsflieger.clone();
-                let pool_clone = self.pool.clone();
-                let load_members_task = Task::future(async move {
-                    info!("Loading users from Vereinsflieger API…");
-                    let users = vf_clone.list_users().await?;
-                    info!("Received {} users from Vereinsflieger API", users.len());
+/// Select the client of the club with the given ID, or the client of the
+/// first club if no ID is configured.
+pub fn select_client(
+    clients: &[(u32, vereinsflieger::Client)],
+    club_id: Option<u32>,
+) -> Option<vereinsflieger::Client> {
+    let Some(club_id) = club_id else {
+        return clients.first().map(|(_, client)| client.clone());
+    };
 
-                    let users = users
-                        .into_iter()
-                        .flat_map(|user| {
-                            user.keymanagement
-                                .into_iter()
-                                .filter_map(database::Member::parse_keycode)
-                                .map(move |keycode| database::Member {
-                                    keycode,
-                                    id: user.member_id.clone(),
-                                    firstname: user.first_name.clone(),
-                                    lastname: user.last_name.clone(),
-                                    nickname: user.nickname.clone(),
-                                })
-                        })
-                        .collect::<Vec<_>>();
+    let client = clients.iter().find(|(id, _)| *id == club_id);
+    if client.is_none() && !clients.is_empty() {
+        error!("No credentials found for club {club_id}");
+    }
+
+    client.map(|(_, client)| client.clone())
+}
 
-                    info!("Saving {} users with keycodes to database…", users.len());
-                    database::Member::save_all(pool_clone, users).await?;
+/// Send an alert email about sales that could not be uploaded, if an
+/// address is configured.
+fn send_upload_alert(count: u32, global_state: &GlobalState) -> Task<Message> {
+    let Some(to) = global_state.options.upload_alert_email.clone() else {
+        return Task::none();
+    };
 
-                    Ok::<_, anyhow::Error>(())
-                })
-                .then(|result| {
-                    match result {
-                        Ok(_) => info!("Users successfully saved to database"),
-                        Err(err) => error!("Failed to load users: {err}"),
-                    }
+    let subject = "clubfridge-neo: Upload-Fehler".to_string();
+    let body = format!(
+        "{count} Verkäufe konnten wiederholt nicht zu Vereinsflieger hochgeladen werden.\n\n\
+         Bitte die Logdateien prüfen oder `clubfridge-neo pending` ausführen."
+    );
+    Task::future(async move {
+        if let Err(err) = alert::send_email(to, subject, body).await {
+            error!("Failed to send upload alert email: {err:#}");
+        }
+    })
+    .discard()
+}
 
-                    Task::none()
-                });
+/// Append the entries to the audit log in the background.
+fn write_audit_log(pool: SqlitePool, entries: Vec<database::AuditEntry>) -> Task<Message> {
+    Task::future(async move {
+        if let Err(err) = database::AuditEntry::insert_all(&pool, entries).await {
+            error!("Failed to write audit log: {err}");
+        }
+    })
+    .discard()
+}
 
-                return Task::batch([load_articles_task, load_members_task]);
+impl RunningClubFridge {
+    /// Load the articles from the Vereinsflieger API and save them to
+    /// the local database.
+    fn load_articles(
+        &self,
+        vereinsflieger: vereinsflieger::Client,
+        global_state: &GlobalState,
+    ) -> Task<Message> {
+        let health = global_state.health.clone();
+        let pool = self.pool.clone();
+        let categories = global_state.options.article_categories.clone();
+        let article_cache = self.article_cache.clone();
+        let sync = sync::sync_articles(vereinsflieger, pool.clone(), categories);
+        Task::future(sync).then(move |result| {
+            article_cache.invalidate();
+
+            let (details, price_changes) = match result {
+                Ok(0) => {
+                    info!("Articles successfully saved to database");
+                    health.article_sync_finished();
+                    (String::new(), false)
+                }
+                Ok(count) => {
+                    info!("Articles successfully saved to database, {count} prices changed");
+                    health.article_sync_finished();
+                    (format!("{count} Preise geändert"), true)
+                }
+                Err(err) if is_rate_limited(&err) => return Task::done(Message::RateLimited),
+                Err(err) => {
+                    error!("Failed to load articles: {err}");
+                    (format!("Fehler: {err}"), false)
+                }
+            };
+
+            let entry = database::AuditEntry::new("sync_articles", None, details);
+            let audit = write_audit_log(pool.clone(), vec![entry]);
+            match price_changes {
+                true => Task::batch([audit, Task::done(Message::CountPriceChanges)]),
+                false => audit,
             }
-            Message::UploadSalesToVF => {
-                let Some(vereinsflieger) = &self.vereinsflieger else {
-                    return Task::none();
-                };
+        })
+    }
 
-                let vereinsflieger = vereinsflieger.clone();
-                let pool = self.pool.clone();
-                let upload_mutex = self.upload_mutex.clone();
+    /// Load the members from the Vereinsflieger API and save them to
+    /// the local database.
+    fn load_members(
+        &self,
+        vereinsflieger: vereinsflieger::Client,
+        global_state: &GlobalState,
+    ) -> Task<Message> {
+        let health = global_state.health.clone();
+        let pool = self.pool.clone();
+        let purge_removed = global_state.options.purge_removed_members;
+        let store_emails = global_state.options.store_member_emails;
+        let future = sync::sync_members(vereinsflieger, pool.clone(), purge_removed, store_emails);
+        Task::future(future).then(move |result| {
+            let details = match result {
+                Ok(_) => {
+                    info!("Users successfully saved to database");
+                    health.member_sync_finished();
+                    String::new()
+                }
+                Err(err) if is_rate_limited(&err) => return Task::done(Message::RateLimited),
+                Err(err) => {
+                    error!("Failed to load users: {err}");
+                    format!("Fehler: {err}")
+                }
+            };
 
-                return Task::future(async move {
-                    let _guard = upload_mutex.lock().await;
+            let entry = database::AuditEntry::new("sync_members", None, details);
+            write_audit_log(pool.clone(), vec![entry])
+        })
+    }
 
-                    info!("Loading sales from database…");
-                    let sales = database::Sale::load_all(pool.clone()).await?;
-                    if sales.is_empty() {
-                        info!("No sales to upload");
-                        return Ok(());
-                    }
-
-                    info!("Uploading {} sales to Vereinsflieger API…", sales.len());
-                    for (i, sale) in sales.into_iter().enumerate() {
-                        let sale_id = *sale.id;
-                        debug!(%sale_id, "Uploading sale #{}…", i + 1);
-
-                        async fn save_sale(
-                            vereinsflieger: &vereinsflieger::Client,
-                            sale: database::Sale,
-                        ) -> Result<(), anyhow::Error> {
-                            let sale = vereinsflieger::NewSale {
-                                booking_date: &sale.date.to_string(),
-                                article_id: &sale.article_id,
-                                amount: sale.amount as f64,
-                                member_id: Some(sale.member_id.parse()?),
-                                callsign: None,
-                                sales_tax: None,
-                                total_price: None,
-                                counter: None,
-                                comment: None,
-                                cost_type: None,
-                                caid2: None,
-                                spid: None,
-                            };
-
-                            Ok(vereinsflieger.add_sale(&sale).await?)
-                        }
+    /// Whether requests to Vereinsflieger are currently paused because of
+    /// rate limiting.
+    pub fn is_rate_limited(&self) -> bool {
+        self.rate_limited_until
+            .is_some_and(|until| jiff::Timestamp::now() < until)
+    }
 
-                        if let Err(error) = save_sale(&vereinsflieger, sale).await {
-                            warn!(%sale_id, "Failed to upload sale: {error}");
-                        } else {
-                            debug!(%sale_id, "Deleting sale from database…");
-                            match database::Sale::delete_by_id(&pool, sale_id).await {
-                                Ok(()) => debug!(%sale_id, "Sale successfully deleted"),
-                                Err(err) => warn!(%sale_id, "Failed to delete sale: {err}"),
-                            }
-                        }
-                    }
+    /// Whether the admin screen can be opened, which requires an admin PIN or
+    /// a TOTP secret.
+    pub fn admin_enabled(&self, options: &Options) -> bool {
+        options.admin_pin.is_some() || self.admin_totp_secret.is_some()
+    }
 
-                    Ok::<_, anyhow::Error>(())
-                })
-                .then(|result| {
-                    match result {
-                        Ok(_) => info!("Sales successfully uploaded"),
-                        Err(err) => error!("Failed to upload sales: {err}"),
-                    }
+    /// Whether daily purchase limits are configured.
+    pub fn limits_enabled(options: &Options) -> bool {
+        options.daily_spending_limit.is_some() || !options.daily_article_limits.is_empty()
+    }
 
-                    Task::none()
-                });
-            }
-            Message::KeyPress(Key::Character(c), modifiers) => {
-                let mut c = c.chars().next().unwrap();
-                if modifiers.shift() {
-                    c = c.to_ascii_uppercase();
-                }
+    /// Whether guests can buy articles, which requires a way for them to
+    /// pay without a member account.
+    pub fn guests_enabled(&self) -> bool {
+        self.sumup.is_some() || self.transfer_target.is_some()
+    }
 
-                debug!("Key pressed: {c:?}");
-                self.input.push(c);
-                global_state.hide_popup();
-            }
-            Message::KeyPress(Key::Named(Named::Enter), _) => {
-                debug!("Key pressed: Enter");
-                let input = mem::take(&mut self.input);
-                let pool = self.pool.clone();
+    /// Take the articles out of the current cart and convert them into
+    /// sales for the logged-in member.
+    fn take_cart(&mut self, payment: Payment) -> Vec<database::Sale> {
+        let mut member_id = self
+            .user
+            .as_ref()
+            .map(|user| &user.id)
+            .cloned()
+            .unwrap_or_default();
 
-                global_state.hide_popup();
+        let now = jiff::Zoned::now();
+        let sign = if self.refund { -1 } else { 1 };
 
-                return if self.user.is_some() {
-                    Task::future(async move {
-                        let result = database::Article::find_by_barcode(pool, &input).await;
-                        let result = result.map_err(Arc::new);
-                        Message::FindArticleResult { input, result }
-                    })
-                } else {
-                    Task::future(async move {
-                        let result = database::Member::find_by_keycode(pool, &input).await;
-                        let result = result.map_err(Arc::new);
-                        Message::FindMemberResult { input, result }
-                    })
-                };
+        let (payment_reference, self_paid, cost_type) = match payment {
+            Payment::Account | Payment::Cash => (None, false, None),
+            Payment::Card(transaction_id) => (Some(transaction_id), false, None),
+            Payment::SelfPaid => (None, true, None),
+            Payment::CostCenter(cost_center) => {
+                member_id = cost_center.member_id;
+                (None, false, cost_center.cost_type)
             }
-            #[cfg(debug_assertions)]
-            Message::KeyPress(Key::Named(Named::Control), _) => {
-                use rust_decimal_macros::dec;
+        };
 
-                let task = if self.user.is_some() {
-                    let ulid = Ulid::new();
+        mem::take(&mut self.sales)
+            .into_iter()
+            .map(|item| database::Sale {
+                id: Text(Ulid::new()),
+                created_at: Text(now.clone()),
+                member_id: member_id.clone(),
+                article_id: item.article.id,
+                amount: sign * item.amount as i32,
+                unit_price: Some(Text(item.unit_price)),
+                open_price: item.open_price,
+                payment_reference: payment_reference.clone(),
+                self_paid,
+                cost_type: cost_type.clone(),
+                upload_started_at: None,
+            })
+            .collect()
+    }
 
-                    let timestamp = ulid.timestamp_ms();
+    /// Take the current cart for saving it with the given payment method,
+    /// together with the vouchers and prepaid balance changes of the cart.
+    fn checkout(&mut self, payment: Payment, options: &Options) -> database::Checkout {
+        let cash = matches!(payment, Payment::Cash);
+        let vouchers = self.cart_vouchers();
 
-                    let designations = [
-                        "Testartikel 1",
-                        "Testartikel 2 asd nflkjdbnslf kjalsdk fj lkdjsnlfkjnaldsknf lknlksdanfl kndslkf nlkaflkn a",
-                        "Test",
-                    ];
-                    let n = timestamp % designations.len() as u64;
+        // Purchases on account are paid from the prepaid balance first, which
+        // is booked to the first credit article with a negative price
+        let balance_article = match payment {
+            Payment::Account => options.credit_articles.first().cloned(),
+            _ => None,
+        };
 
-                    let ulid = ulid.to_string();
-                    Task::done(Message::FindArticleResult {
-                        input: ulid.clone(),
-                        result: Ok(Some(database::Article {
-                            id: designations[n as usize].to_string(),
-                            designation: designations[n as usize].to_string(),
-                            prices: vec![{
-                                database::Price {
-                                    valid_from: jiff::civil::Date::constant(2000, 1, 1),
-                                    valid_to: jiff::civil::Date::constant(2999, 12, 31),
-                                    unit_price: Decimal::from(timestamp % 1000) / dec!(100),
-                                }
-                            }],
-                        })),
-                    })
-                } else {
-                    Task::done(Message::FindMemberResult {
-                        input: "1234567890".to_string(),
-                        result: Ok(Some(database::Member {
-                            keycode: "1234567890".to_string(),
-                            id: "11011".to_string(),
-                            firstname: "Tobias".to_string(),
-                            lastname: "Bieniek".to_string(),
-                            nickname: "Turbo".to_string(),
-                        })),
-                    })
-                };
+        let sales = self.take_cart(payment);
 
-                global_state.hide_popup();
+        // Credit articles top up the prepaid balance of the member
+        let credit = sales
+            .iter()
+            .filter(|sale| options.credit_articles.contains(&sale.article_id))
+            .filter_map(|sale| sale.total())
+            .sum::<Decimal>();
 
-                return task;
-            }
-            Message::FindArticleResult { input, result } => match result {
-                Ok(Some(article)) => {
-                    info!("Adding article to sale: {article:?}");
-                    if self.user.is_some() && article.current_price().is_some() {
-                        let sales = &mut self.sales;
+        database::Checkout {
+            sales,
+            cash,
+            vouchers,
+            credit,
+            balance_article,
+        }
+    }
 
-                        let existing_sale =
-                            sales.iter_mut().find(|item| item.article.id == article.id);
-                        match existing_sale {
-                            Some(item) => item.amount += 1,
-                            None => sales.push(Sale { amount: 1, article }),
-                        }
+    /// The quantity that was typed for the next scanned article, if any.
+    pub fn pending_quantity(&self) -> Option<u16> {
+        match split_quantity(&self.input) {
+            (quantity, "") if self.user.is_some() && self.input.ends_with('*') => Some(quantity),
+            _ => None,
+        }
+    }
 
-                        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
-                    }
-                }
-                Ok(None) => {
-                    warn!("No article found for barcode: {input}");
-                    return global_state.show_popup(format!("Artikel nicht gefunden ({input})"));
-                }
-                Err(err) => {
-                    error!("Failed to find article: {err}");
-                }
-            },
-            Message::FindMemberResult { input, result } => match result {
-                Ok(Some(member)) => {
-                    info!("Setting user: {member:?}");
-                    self.user = Some(member);
-                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
-                }
-                Ok(None) => {
-                    warn!("No user found for keycode: {input}");
-                    return global_state.show_popup(format!("Benutzer nicht gefunden ({input})"));
-                }
-                Err(err) => {
-                    error!("Failed to find user: {err}");
-                }
+    /// The donation that rounds the total of the cart up to the next Euro.
+    pub fn round_up_amount(&self) -> Decimal {
+        let total = self.sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+        round_up_difference(total)
+    }
+
+    /// Add the round-up donation to the cart, if the member chose it.
+    fn add_round_up(&mut self, global_state: &GlobalState) {
+        let Some(article_id) = &global_state.options.round_up_article else {
+            return;
+        };
+
+        let amount = self.round_up_amount();
+        if !self.round_up || self.refund || amount.is_zero() {
+            return;
+        }
+
+        info!("Adding round-up donation to sale: {amount}€");
+        self.sales.push(Sale {
+            amount: 1,
+            article: database::Article {
+                id: article_id.clone(),
+                designation: global_state.options.round_up_label.clone(),
+                prices: vec![],
+                category: None,
+                account: None,
             },
-            Message::DecrementTimeout => {
-                if let Some(timeout) = &mut self.interaction_timeout {
-                    *timeout = timeout.sub(jiff::SignedDuration::from_secs(1));
-                    if timeout.is_zero() {
-                        info!("Interaction timeout reached");
-                        self.interaction_timeout = None;
-                        return Task::done(if self.sales.is_empty() {
-                            Message::Cancel
-                        } else {
-                            Message::Pay
-                        });
-                    }
-                }
-            }
-            Message::Pay => {
-                info!("Processing sale");
-                let pool = self.pool.clone();
-                let date = jiff::Zoned::now().date();
+            unit_price: amount,
+            open_price: true,
+            discount: false,
+            voucher: None,
+        });
+    }
 
-                let sales = mem::take(&mut self.sales)
-                    .into_iter()
-                    .map(|item| database::Sale {
-                        id: Text(Ulid::new()),
-                        date: Text(date),
-                        member_id: self
-                            .user
-                            .as_ref()
-                            .map(|user| &user.id)
-                            .cloned()
-                            .unwrap_or_default(),
-                        article_id: item.article.id,
-                        amount: item.amount as u32,
-                    })
-                    .collect();
+    /// The barcodes of the vouchers that are redeemed with the current cart.
+    fn cart_vouchers(&self) -> Vec<String> {
+        self.sales
+            .iter()
+            .filter_map(|sale| sale.voucher.clone())
+            .collect()
+    }
 
-                self.interaction_timeout = None;
+    /// The pending sale that is currently edited on the admin screen.
+    fn pending_sale_edit(&mut self) -> Option<&mut PendingSaleEdit> {
+        let admin = self.admin.as_mut()?;
+        admin.pending_sales.as_mut()?.editing.as_mut()
+    }
 
-                return Task::future(database::Sale::insert_all(pool, sales)).then(|result| {
-                    match result {
-                        Ok(()) => Task::batch([
-                            Task::done(Message::SalesSaved),
-                            Task::done(Message::UploadSalesToVF),
-                        ]),
-                        Err(err) => {
-                            error!("Failed to save sales: {err}");
-                            Task::done(Message::SavingSalesFailed)
-                        }
-                    }
-                });
-            }
-            Message::SalesSaved => {
-                info!("Sales saved");
-                self.user = None;
-                self.sales.clear();
-                return global_state.show_popup("Danke für deinen Kauf");
-            }
-            Message::SavingSalesFailed => {
-                error!("Failed to save sales");
-            }
-            Message::Cancel => {
-                info!("Cancelling sale");
-                self.user = None;
-                self.sales.clear();
-                self.interaction_timeout = None;
-            }
-            _ => {}
+    /// Record an action in the audit log once the current message is handled.
+    fn audit(&mut self, action: &str, member_id: Option<&str>, details: impl Into<String>) {
+        let entry = database::AuditEntry::new(action, member_id, details);
+        self.audit_entries.push(entry);
+    }
+
+    /// Show an error popup with a short error ID for an unexpected error,
+    /// and record the error with this ID in the audit log.
+    fn internal_error(
+        &mut self,
+        global_state: &mut GlobalState,
+        message: &str,
+        err: impl Display,
+    ) -> String {
+        let error_id = global_state.show_internal_error(message);
+        self.audit("error", None, format!("{error_id}: {err:#}"));
+        error_id
+    }
+
+    /// Log in the given member and load their sales from earlier today
+    /// and their prepaid balance.
+    fn login(&mut self, member: database::Member) -> Task<Message> {
+        let pool = self.pool.clone();
+        let member_id = member.id.clone();
+        self.audit("login", Some(&member_id), "");
+
+        self.receipt = None;
+        self.user = Some(member);
+        self.todays_sales.clear();
+        self.last_purchase.clear();
+        self.balance = None;
+        self.limits_overridden = false;
+        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+        // Guests have no earlier sales and their cart is not restored
+        if self.user.as_ref().is_some_and(|user| user.is_guest()) {
+            return Task::none();
         }
 
-        Task::none()
+        let balance_task = {
+            let pool = pool.clone();
+            let member_id = member_id.clone();
+            Task::future(async move {
+                let result = database::Balance::load(&pool, &member_id).await;
+                let result = result.map_err(Arc::new);
+                Message::BalanceLoaded { member_id, result }
+            })
+        };
+
+        let parked_task = match self.park_window {
+            Some(window) => {
+                let pool = pool.clone();
+                let member_id = member_id.clone();
+                Task::future(async move {
+                    let parked_after = jiff::Timestamp::now() - window;
+                    let result = database::Session::take_parked(&pool, &member_id, parked_after);
+                    let result = result.await.map_err(Arc::new);
+                    Message::ParkedCartLoaded { member_id, result }
+                })
+            }
+            None => Task::none(),
+        };
+
+        let last_purchase_task = {
+            let pool = pool.clone();
+            let member_id = member_id.clone();
+            Task::future(async move {
+                let result = database::Sale::load_last_purchase(&pool, &member_id).await;
+                let result = result.map_err(Arc::new);
+                Message::LastPurchaseLoaded { member_id, result }
+            })
+        };
+
+        let load_task = Task::future(async move {
+            let today = jiff::Zoned::now().date();
+            let result = database::Sale::load_for_member_on(pool, &member_id, today).await;
+            let result = result.map_err(Arc::new);
+            Message::TodaysSalesLoaded { member_id, result }
+        });
+
+        Task::batch([
+            load_task,
+            last_purchase_task,
+            balance_task,
+            parked_task,
+            self.save_session(),
+        ])
+    }
+
+    /// Add the free birthday article to the cart, if it is the birthday of
+    /// the logged-in member and they did not get it today already.
+    ///
+    /// This needs to be called after the sales of today have been loaded.
+    fn add_birthday_article(&self, global_state: &GlobalState) -> Task<Message> {
+        let Some(article_id) = &global_state.options.birthday_article else {
+            return Task::none();
+        };
+        let Some(user) = &self.user else {
+            return Task::none();
+        };
+
+        let today = jiff::Zoned::now().date();
+        if self.refund || !user.has_birthday_on(today) {
+            return Task::none();
+        }
+
+        let is_free = |article: &str, total: Option<Decimal>| {
+            article == article_id && total.is_some_and(|total| total.is_zero())
+        };
+        let received_today = self
+            .todays_sales
+            .iter()
+            .any(|sale| is_free(&sale.article_id, sale.total()));
+        let in_cart = self
+            .sales
+            .iter()
+            .any(|sale| is_free(&sale.article.id, Some(sale.total())));
+        if received_today || in_cart {
+            return Task::none();
+        }
+
+        let pool = self.pool.clone();
+        let article_cache = self.article_cache.clone();
+        let member_id = user.id.clone();
+        let article_id = article_id.clone();
+        Task::future(async move {
+            let result = article_cache.find_by_barcode(pool, &article_id).await;
+            let result = result.map_err(Arc::new);
+            Message::BirthdayArticleLoaded { member_id, result }
+        })
+    }
+
+    /// Recalculate the volume discounts of the cart. This must be called
+    /// after every change of the cart, which [`Self::save_session()`]
+    /// takes care of.
+    fn update_discounts(&mut self) {
+        if let Some(article_id) = &self.discount_article {
+            discount::apply(&mut self.sales, &self.discounts, article_id);
+        }
+    }
+
+    /// Save the logged-in member and the current cart to the database, or
+    /// delete the saved session if nobody is logged in.
+    ///
+    /// Since this runs after every change of the cart, it also recalculates
+    /// the volume discounts first. The carts of guests are not saved,
+    /// because they have not paid yet.
+    fn save_session(&mut self) -> Task<Message> {
+        self.update_discounts();
+        self.send_cart_event();
+
+        if self.user.as_ref().is_some_and(|user| user.is_guest()) {
+            return Task::none();
+        }
+
+        let pool = self.pool.clone();
+        let session = self.user.as_ref().map(|user| database::Session {
+            member_id: user.id.clone(),
+            refund: self.refund,
+            cart: self.sales.clone(),
+        });
+
+        Task::future(async move {
+            let result = match session {
+                Some(session) => session.save(&pool).await,
+                None => database::Session::clear(&pool).await,
+            };
+
+            if let Err(err) = result {
+                warn!("Failed to save session: {err}");
+            }
+        })
+        .discard()
+    }
+
+    /// Send the current cart to the event stream.
+    fn send_cart_event(&self) {
+        let logged_in = self.user.is_some();
+        let event = Event::cart(logged_in, self.refund, &self.sales);
+        self.events.send(event);
+    }
+
+    /// Highlight the cart row of the article whose amount was just increased
+    /// and scroll it into view, so that the member notices that the scan
+    /// worked although no row was added.
+    fn highlight_row(&mut self, article_id: String) -> Task<Message> {
+        let index = self
+            .sales
+            .iter()
+            .position(|sale| !sale.open_price && sale.article.id == article_id);
+        let Some(index) = index else {
+            return Task::none();
+        };
+
+        self.highlighted_row = Some((article_id, Instant::now()));
+
+        // The cart is anchored to the bottom, so the offset counts from the
+        // last row
+        let last = self.sales.len() - 1;
+        let y = (last - index) as f32 / last.max(1) as f32;
+        let scroll = operation::snap_to(CART_ID, RelativeOffset { x: 0., y });
+
+        let clear =
+            Task::future(tokio::time::sleep(HIGHLIGHT_DURATION)).map(|_| Message::ClearHighlight);
+        Task::batch([scroll, clear])
+    }
+
+    /// Log out the current member and clear the cart.
+    fn logout(&mut self) {
+        self.user = None;
+        self.sales.clear();
+        self.highlighted_row = None;
+        self.todays_sales.clear();
+        self.last_purchase.clear();
+        self.balance = None;
+        self.limits_overridden = false;
+        self.interaction_timeout = None;
+        self.confirmation = None;
+        self.open_price_input = None;
+        self.refund = false;
+        self.confirming_payment = false;
+        self.choosing_cost_center = false;
+        self.round_up = false;
+        self.transfer_qr = None;
+        self.send_cart_event();
+    }
+
+    /// Check if adding one more unit of the given article to the cart would
+    /// exceed the configured daily purchase limits.
+    ///
+    /// Returns the message that should be shown to the member if a limit
+    /// is exceeded.
+    fn check_daily_limits(
+        &self,
+        article: &database::Article,
+        amount: u16,
+        price: Decimal,
+        global_state: &GlobalState,
+    ) -> Option<String> {
+        let is_guest = self.user.as_ref().is_some_and(|user| user.is_guest());
+        if self.limits_overridden || self.refund || is_guest {
+            return None;
+        }
+
+        let options = &global_state.options;
+
+        let article_limit = options
+            .daily_article_limits
+            .iter()
+            .find(|limit| limit.article_id == article.id);
+
+        if let Some(limit) = article_limit {
+            let earlier_amount = self
+                .todays_sales
+                .iter()
+                .filter(|sale| sale.article_id == article.id)
+                .map(|sale| sale.amount as i64)
+                .sum::<i64>();
+
+            let cart_amount = self
+                .sales
+                .iter()
+                .filter(|sale| sale.article.id == article.id)
+                .map(|sale| sale.amount as i64)
+                .sum::<i64>();
+
+            if earlier_amount + cart_amount + amount as i64 > limit.max_amount as i64 {
+                return Some(format!(
+                    "Tageslimit für {} erreicht ({}x)",
+                    article.designation, limit.max_amount
+                ));
+            }
+        }
+
+        if let Some(spending_limit) = options.daily_spending_limit {
+            let earlier_total = self
+                .todays_sales
+                .iter()
+                .filter_map(|sale| sale.total())
+                .sum::<Decimal>();
+
+            let cart_total = self.sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+
+            if earlier_total + cart_total + Decimal::from(amount) * price > spending_limit {
+                return Some(format!("Tageslimit von {spending_limit:.2}€ erreicht"));
+            }
+        }
+
+        None
+    }
+
+    /// The action that is bound to the pressed key, while no admin screen
+    /// is open.
+    fn key_action(&self, key: &str, global_state: &GlobalState) -> Option<KeyAction> {
+        let bindings = global_state.options.key_bindings.as_ref()?;
+        self.admin.is_none().then(|| bindings.action(key))?
+    }
+
+    /// Check whether the input is the admin PIN.
+    ///
+    /// The static PIN is replaced by time-based codes once a TOTP secret was
+    /// provisioned. Each code is only accepted once.
+    fn check_admin_pin(&mut self, input: &str, options: &Options) -> bool {
+        match &self.admin_totp_secret {
+            Some(secret) => {
+                let step = totp::verify(secret, input, jiff::Timestamp::now());
+                let unused =
+                    step.filter(|step| self.admin_totp_step.is_none_or(|last| *step > last));
+                if unused.is_some() {
+                    self.admin_totp_step = unused;
+                }
+                unused.is_some()
+            }
+            None => options
+                .admin_pin
+                .as_ref()
+                .is_some_and(|admin_pin| *admin_pin == input),
+        }
+    }
+
+    /// Open the admin screen after the admin code was entered.
+    fn open_admin(&mut self, global_state: &mut GlobalState) -> Task<Message> {
+        info!("Opening admin screen");
+        self.audit("admin_open", None, "");
+        self.admin = Some(Admin {
+            statements_enabled: global_state.options.statement_dir.is_some(),
+            member_import_enabled: global_state.options.member_csv.is_some(),
+            cost_centers_enabled: (!global_state.options.cost_centers.is_empty())
+                .then_some(self.cost_centers_enabled),
+            ..Default::default()
+        });
+        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+        self.load_cash_balance(global_state)
+    }
+
+    /// Whether an admin action may run. Unless the admin just confirmed it
+    /// with a fresh admin code, the code is requested first and the `action`
+    /// runs again afterwards.
+    fn confirm_admin_action(&mut self, action: Message) -> bool {
+        if self.action_confirmed {
+            return true;
+        }
+
+        self.confirmation = Some(Confirmation {
+            action,
+            code: String::new(),
+        });
+        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+        false
+    }
+
+    /// Process the scanned input, either as a member keycode or as an
+    /// article barcode, depending on whether a member is logged in.
+    fn submit_input(&mut self, global_state: &mut GlobalState) -> Task<Message> {
+        let options = &global_state.options;
+        let mut input = mem::take(&mut self.input);
+        if let Some(key_map) = &options.scanner_remap {
+            input = key_map.apply(&input);
+        }
+
+        // The quantity is typed before the scanner sends its prefix
+        let (quantity, input) = split_quantity(&input);
+        let input =
+            scanner::strip_affixes(input, &options.scanner_prefixes, &options.scanner_suffixes)
+                .to_string();
+
+        let pool = self.pool.clone();
+        let article_cache = self.article_cache.clone();
+
+        let debounce = Duration::from_millis(options.scan_debounce);
+        if self.is_repeated_scan(&input, debounce) {
+            debug!("Ignoring repeated scan: {}", logging::keycode(&input));
+            return Task::none();
+        }
+
+        let member_card_id = options
+            .member_card_prefix
+            .as_deref()
+            .and_then(|prefix| input.strip_prefix(prefix))
+            .map(ToString::to_string);
+
+        let qr_login_secret = options.qr_login_secret.as_deref();
+        let qr_login = input.starts_with(qr_login::PREFIX) && qr_login_secret.is_some();
+        let qr_login_id =
+            qr_login_secret.and_then(|secret| qr_login::verify(secret.as_bytes(), &input));
+        let member_card_id = member_card_id.or(qr_login_id);
+
+        global_state.popups.dismiss();
+
+        if self
+            .admin
+            .as_ref()
+            .is_some_and(|admin| admin.stocktaking.is_some())
+        {
+            let (barcode, amount) = resolve_bundle(options, &input, quantity);
+            return Task::future(async move {
+                let result = article_cache.find_by_barcode(pool, &barcode).await;
+                let result = result.map_err(Arc::new);
+                Message::StocktakingArticleResult {
+                    input,
+                    amount,
+                    result,
+                }
+            });
+        }
+
+        if let Some(restocking) = self
+            .admin
+            .as_ref()
+            .and_then(|admin| admin.restocking.as_ref())
+        {
+            if restocking.member.is_none() {
+                return Task::future(async move {
+                    let result = database::Member::find_by_keycode(pool, &input).await;
+                    let result = result.map_err(Arc::new);
+                    Message::RestockMemberResult { input, result }
+                });
+            }
+
+            let (barcode, amount) = resolve_bundle(options, &input, quantity);
+            return Task::future(async move {
+                let result = article_cache.find_by_barcode(pool, &barcode).await;
+                let result = result.map_err(Arc::new);
+                Message::RestockArticleResult {
+                    input,
+                    amount,
+                    result,
+                }
+            });
+        }
+
+        if self.user.is_some() {
+            let (barcode, amount) = resolve_bundle(options, &input, quantity);
+
+            let vouchers_enabled = options.voucher_article.is_some();
+            Task::future(async move {
+                if vouchers_enabled {
+                    match database::Voucher::find_by_barcode(pool.clone(), &input).await {
+                        Ok(Some(voucher)) => return Message::VoucherFound(voucher),
+                        Ok(None) => {}
+                        Err(err) => error!("Failed to find voucher: {err}"),
+                    }
+                }
+
+                let result = article_cache.find_by_barcode(pool, &barcode).await;
+                let result = result.map_err(Arc::new);
+                Message::FindArticleResult {
+                    input,
+                    amount,
+                    result,
+                }
+            })
+        } else if qr_login && member_card_id.is_none() {
+            warn!("Invalid login QR code: {}", logging::keycode(&input));
+            self.audit("login_qr_invalid", None, "");
+            global_state.show_error("Ungültiger Login-Code");
+            Task::none()
+        } else if let Some(member_id) = member_card_id {
+            Task::future(async move {
+                let result = database::Member::find_by_id(pool, &member_id).await;
+                let result = result.map_err(Arc::new);
+                Message::FindMemberResult { input, result }
+            })
+        } else {
+            Task::future(async move {
+                let result = database::Member::find_by_keycode(pool, &input).await;
+                let result = result.map_err(Arc::new);
+                Message::FindMemberResult { input, result }
+            })
+        }
+    }
+
+    /// Save the current cart as sales and log out the member.
+    fn pay(&mut self, payment: Payment, global_state: &mut GlobalState) -> Task<Message> {
+        self.confirming_payment = false;
+        self.transfer_qr = None;
+
+        let member_id = self.user.as_ref().map(|user| user.id.clone());
+        info!(member_id = member_id.as_deref(), "Processing sale");
+        self.add_round_up(global_state);
+        self.update_discounts();
+
+        let total = self.sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+        let details = format!("{} Positionen, {total:.2}€, {payment:?}", self.sales.len());
+        let action = if self.refund { "refund" } else { "pay" };
+        self.audit(action, member_id.as_deref(), details);
+        let event = Event::sale(self.refund, payment.name(), &self.sales);
+        self.events.send(event);
+
+        if global_state.options.receipt_qr && !self.sales.is_empty() {
+            let member_id = member_id.as_deref().unwrap_or_default();
+            let receipt = Receipt::new(member_id, &self.sales, self.refund);
+            self.pending_receipt = receipt.to_qr_code();
+        }
+
+        let pool = self.pool.clone();
+        let insert_mutex = self.insert_mutex.clone();
+        let card_transaction = match &payment {
+            Payment::Card(transaction_id) => Some(transaction_id.clone()),
+            _ => None,
+        };
+        let checkout = self.checkout(payment, &global_state.options);
+
+        self.interaction_timeout = None;
+
+        let transaction_id = card_transaction.clone();
+        Task::future(async move {
+            // The card was already charged, so the payment must not get lost
+            // if the sales can't be saved
+            if let Some(transaction_id) = &transaction_id {
+                if let Err(err) = sumup::log_payment(transaction_id, &checkout.sales).await {
+                    error!(%transaction_id, "Failed to log card payment: {err}");
+                }
+            }
+
+            let _guard = insert_mutex.lock().await;
+            let debit = checkout.save(&pool).await?;
+            if !debit.is_zero() {
+                info!("Paid {debit}€ from the prepaid balance");
+            }
+
+            if let Err(err) = database::Session::clear(&pool).await {
+                warn!("Failed to clear session: {err}");
+            }
+
+            Ok::<_, sqlx::Error>(())
+        })
+        .then(move |result| match result {
+            Ok(()) => Task::batch([
+                Task::done(Message::SalesSaved),
+                Task::done(Message::UploadSalesToVF),
+            ]),
+            Err(err) => {
+                error!("Failed to save sales: {err}");
+                match card_transaction.clone() {
+                    Some(transaction_id) => {
+                        Task::done(Message::CardPaymentNotSaved(transaction_id))
+                    }
+                    None => Task::done(Message::SavingSalesFailed),
+                }
+            }
+        })
+    }
+
+    /// Add the entered number of units of the scanned article to its stock.
+    fn save_restock(&mut self, best_before: Option<jiff::civil::Date>) -> Task<Message> {
+        let Some(restocking) = self
+            .admin
+            .as_mut()
+            .and_then(|admin| admin.restocking.as_mut())
+        else {
+            return Task::none();
+        };
+        let (Some(member), Some(amount)) = (
+            &restocking.member,
+            Restocking::parse_quantity(&restocking.quantity),
+        ) else {
+            return Task::none();
+        };
+        let Some(article) = restocking.article.take() else {
+            return Task::none();
+        };
+        restocking.quantity.clear();
+        restocking.best_before = None;
+        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+        let member_id = member.id.clone();
+        info!(%member_id, ?best_before, "Restocking {amount}x article: {article:?}");
+
+        let pool = self.pool.clone();
+        Task::future(async move {
+            let result =
+                database::Stock::restock(&pool, &member_id, &article.id, amount, best_before).await;
+            Message::RestockSaved {
+                designation: article.designation,
+                amount,
+                result: result.map_err(Arc::new),
+            }
+        })
+    }
+
+    /// Start or continue the door alarm if the door is open for too long.
+    fn door_open(&mut self, global_state: &GlobalState) {
+        let now = jiff::Timestamp::now();
+
+        // Restocking and stocktaking take a while, so the timeout only starts
+        // once the admin screen is closed again
+        if self.admin.is_some() && !self.door_alarm {
+            self.door_opened_at = Some(now);
+            return;
+        }
+
+        let opened_at = *self.door_opened_at.get_or_insert(now);
+        let timeout =
+            jiff::SignedDuration::from_secs(global_state.options.door_alarm_timeout as i64);
+        if now.duration_since(opened_at) < timeout {
+            return;
+        }
+
+        if !self.door_alarm {
+            warn!("Fridge door is open since {opened_at}");
+            self.door_alarm = true;
+        }
+
+        if let Some(sound) = &global_state.options.door_alarm_sound {
+            let repeat = self
+                .door_alarm_sound_at
+                .is_none_or(|played_at| now.duration_since(played_at) >= DOOR_ALARM_SOUND_INTERVAL);
+            if repeat {
+                door::play_alarm(sound);
+                self.door_alarm_sound_at = Some(now);
+            }
+        }
+    }
+
+    /// Show a warning about stuck sales, and send an alert when they are
+    /// first detected.
+    fn stuck_sales_checked(&mut self, count: u32, global_state: &GlobalState) -> Task<Message> {
+        let previous = mem::replace(&mut self.stuck_sales, count);
+        if count == 0 || previous > 0 {
+            return Task::none();
+        }
+
+        warn!("{count} sales repeatedly failed to upload to Vereinsflieger");
+        send_upload_alert(count, global_state)
+    }
+
+    /// Reset the door alarm after the door was closed, and log the alarm.
+    fn door_closed(&mut self) -> Task<Message> {
+        let opened_at = self.door_opened_at.take();
+        let alarm = mem::take(&mut self.door_alarm);
+        self.door_alarm_sound_at = None;
+
+        let Some(opened_at) = opened_at.filter(|_| alarm) else {
+            return Task::none();
+        };
+
+        let closed_at = jiff::Timestamp::now();
+        info!(
+            "Fridge door was closed after {:#}",
+            closed_at.duration_since(opened_at)
+        );
+
+        let pool = self.pool.clone();
+        Task::future(async move {
+            if let Err(err) = database::DoorAlarm::insert(&pool, opened_at, closed_at).await {
+                error!("Failed to log door alarm: {err}");
+            }
+        })
+        .discard()
+    }
+
+    /// Load the batches in the fridge that expire within the configured
+    /// number of days.
+    fn load_expiring_batches(&self, global_state: &GlobalState) -> Task<Message> {
+        let Some(days) = global_state.options.expiry_warning_days else {
+            return Task::none();
+        };
+
+        let pool = self.pool.clone();
+        Task::future(async move {
+            let until = jiff::Zoned::now().date() + jiff::Span::new().days(days);
+            let result = database::Stock::expiring_until(&pool, until).await;
+            Message::ExpiringBatchesLoaded(result.map_err(Arc::new))
+        })
+    }
+
+    /// Load the expected contents of the cash box for the admin screen.
+    fn load_cash_balance(&self, global_state: &GlobalState) -> Task<Message> {
+        if !global_state.options.cash_payment {
+            return Task::none();
+        }
+
+        let pool = self.pool.clone();
+        Task::future(async move {
+            let result = database::CashEntry::balance(&pool).await;
+            Message::CashBalanceLoaded(result.map_err(Arc::new))
+        })
+    }
+
+    /// Check if the same input was already scanned within the `debounce`
+    /// duration, and remember the input for the next check.
+    fn is_repeated_scan(&mut self, input: &str, debounce: Duration) -> bool {
+        let now = Instant::now();
+        let repeated = self
+            .last_scan
+            .as_ref()
+            .is_some_and(|(last_input, last_time)| {
+                last_input == input && now.duration_since(*last_time) < debounce
+            });
+
+        self.last_scan = Some((input.to_string(), now));
+        repeated
+    }
+
+    /// Save the current cart as the session and wait for any running database
+    /// writes and uploads to finish, so that no sales are lost when the
+    /// application exits.
+    ///
+    /// The cart is not checked out, since the member has not confirmed the
+    /// purchase yet. Instead, it is restored on the next start.
+    pub fn shutdown(&mut self) -> Task<Message> {
+        let pool = self.pool.clone();
+        let insert_mutex = self.insert_mutex.clone();
+        let upload_mutex = self.upload_mutex.clone();
+
+        let sales = mem::take(&mut self.sales);
+        let session = match self.user.take() {
+            Some(user) if user.is_guest() => {
+                if !sales.is_empty() {
+                    warn!("Discarding unpaid cart of guest: {sales:?}");
+                }
+                None
+            }
+            Some(user) if !sales.is_empty() => Some(database::Session {
+                member_id: user.id,
+                refund: self.refund,
+                cart: sales,
+            }),
+            _ => None,
+        };
+
+        self.interaction_timeout = None;
+
+        Task::future(async move {
+            let _insert_guard = insert_mutex.lock().await;
+            let result = match session {
+                Some(session) => {
+                    info!("Saving current cart before shutting down…");
+                    session.save(&pool).await
+                }
+                None => database::Session::clear(&pool).await,
+            };
+
+            if let Err(err) = result {
+                error!("Failed to save session: {err}");
+            }
+
+            info!("Waiting for running uploads to finish…");
+            let _upload_guard = upload_mutex.lock().await;
+        })
+        .discard()
+    }
+}
+
+/// An article that may only be sold to members of a minimum age.
+///
+/// This is parsed from `<article ID>=<minimum age>`, e.g. `1234=18`.
+#[derive(Debug, Clone)]
+pub struct AgeRestriction {
+    pub article_id: String,
+    pub minimum_age: i16,
+}
+
+impl AgeRestriction {
+    /// The error message if the article may not be sold to the member on the
+    /// given date, or `None` if it may be sold.
+    ///
+    /// Members with an unknown birthday are refused, unless
+    /// `allow_unknown_age` is set.
+    fn check(
+        &self,
+        article: &database::Article,
+        member: &database::Member,
+        date: jiff::civil::Date,
+        allow_unknown_age: bool,
+    ) -> Option<String> {
+        let minimum_age = self.minimum_age;
+        let designation = &article.designation;
+        match member.is_of_age(minimum_age, date) {
+            Some(true) => None,
+            None if allow_unknown_age => None,
+            Some(false) => Some(format!("{designation} ist erst ab {minimum_age} Jahren erhältlich")),
+            None => Some(format!(
+                "{designation} ist erst ab {minimum_age} Jahren erhältlich, dein Geburtsdatum ist unbekannt"
+            )),
+        }
+    }
+}
+
+impl FromStr for AgeRestriction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((article_id, minimum_age)) = s.rsplit_once('=') else {
+            anyhow::bail!("Expected `<article ID>=<minimum age>`");
+        };
+
+        Ok(Self {
+            article_id: article_id.to_string(),
+            minimum_age: minimum_age.parse()?,
+        })
+    }
+}
+
+/// Sort the cart by the categories of the articles, in the order in which
+/// the categories are configured, followed by the other categories in
+/// alphabetical order. Articles without a category and the discount and
+/// voucher lines are kept at the end, and the order within a category is
+/// preserved.
+fn group_by_category(sales: &mut [Sale], categories: &[ArticleCategory]) {
+    sales.sort_by_cached_key(|sale| {
+        let is_adjustment = sale.discount || sale.voucher.is_some();
+        let category = sale.article.category.clone();
+        let index = category
+            .as_ref()
+            .and_then(|name| categories.iter().position(|c| c.name == *name))
+            .unwrap_or(categories.len());
+        (is_adjustment, category.is_none(), index, category)
+    });
+}
+
+/// The maximum amount of an article that a member may buy per day.
+///
+/// This is parsed from `<article ID>=<amount>`, e.g. `1234=2`.
+#[derive(Debug, Clone)]
+pub struct DailyArticleLimit {
+    pub article_id: String,
+    pub max_amount: u32,
+}
+
+impl FromStr for DailyArticleLimit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((article_id, max_amount)) = s.rsplit_once('=') else {
+            anyhow::bail!("Expected `<article ID>=<amount>`");
+        };
+
+        Ok(Self {
+            article_id: article_id.to_string(),
+            max_amount: max_amount.parse()?,
+        })
+    }
+}
+
+/// A bundle barcode (e.g. of a crate or multi-pack) that adds multiple units
+/// of a single article to the cart.
+///
+/// This is parsed from `<barcode>=<article ID>*<amount>`,
+/// e.g. `4008501011009=1234*12`.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub barcode: String,
+    pub article_id: String,
+    pub amount: u16,
+}
+
+impl FromStr for Bundle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((barcode, right)) = s.split_once('=') else {
+            anyhow::bail!("Expected `<barcode>=<article ID>*<amount>`");
+        };
+        let Some((article_id, amount)) = right.rsplit_once('*') else {
+            anyhow::bail!("Expected `<barcode>=<article ID>*<amount>`");
+        };
+
+        let amount = amount.parse()?;
+        anyhow::ensure!(amount > 0, "Bundle amount must be positive");
+
+        Ok(Self {
+            barcode: barcode.to_string(),
+            article_id: article_id.to_string(),
+            amount,
+        })
+    }
+}
+
+/// A price of an article for a specific member group.
+///
+/// This is parsed from `<article ID>:<member group>=<price>`, optionally
+/// followed by the date range in which the price is valid, e.g.
+/// `1234:Jugend=1.00` or `1234:Jugend=1.00@2025-01-01..2025-12-31`. Either
+/// end of the date range may be omitted.
+#[derive(Debug, Clone)]
+pub struct GroupPrice {
+    pub article_id: String,
+    pub member_group: String,
+    pub unit_price: Decimal,
+    pub valid_from: jiff::civil::Date,
+    pub valid_to: jiff::civil::Date,
+}
+
+impl GroupPrice {
+    fn to_price(&self) -> database::Price {
+        database::Price {
+            valid_from: self.valid_from,
+            valid_to: self.valid_to,
+            unit_price: self.unit_price,
+            member_group: Some(self.member_group.clone()),
+            sales_tax: None,
+        }
+    }
+}
+
+impl FromStr for GroupPrice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((left, right)) = s.rsplit_once('=') else {
+            anyhow::bail!("Expected `<article ID>:<member group>=<price>`");
+        };
+        let Some((article_id, member_group)) = left.split_once(':') else {
+            anyhow::bail!("Expected `<article ID>:<member group>=<price>`");
+        };
+
+        let (unit_price, valid_from, valid_to) = match right.split_once('@') {
+            Some((unit_price, dates)) => {
+                let Some((valid_from, valid_to)) = dates.split_once("..") else {
+                    anyhow::bail!("Expected `<valid from>..<valid to>`");
+                };
+                let parse_date = |date: &str, default| match date {
+                    "" => Ok(default),
+                    date => date.parse(),
+                };
+                let valid_from = parse_date(valid_from, jiff::civil::Date::MIN)?;
+                let valid_to = parse_date(valid_to, jiff::civil::Date::MAX)?;
+                (unit_price, valid_from, valid_to)
+            }
+            None => (right, jiff::civil::Date::MIN, jiff::civil::Date::MAX),
+        };
+        anyhow::ensure!(valid_from <= valid_to, "Invalid date range");
+
+        Ok(Self {
+            article_id: article_id.to_string(),
+            member_group: member_group.to_string(),
+            unit_price: unit_price.parse()?,
+            valid_from,
+            valid_to,
+        })
+    }
+}
+
+/// The difference between the given total and the next full Euro, or zero
+/// if the total is not positive.
+fn round_up_difference(total: Decimal) -> Decimal {
+    match total > Decimal::ZERO {
+        true => total.ceil() - total,
+        false => Decimal::ZERO,
+    }
+}
+
+/// A club account or event cost center (e.g. "Flugplatzfest") to which
+/// members can book a purchase instead of their own account.
+///
+/// This is parsed from `<name>=<member ID>` or
+/// `<name>=<member ID>:<cost type>`, e.g. `Flugplatzfest=9001:4711`.
+#[derive(Debug, Clone)]
+pub struct CostCenter {
+    pub name: String,
+    /// The Vereinsflieger member ID of the club account to which the sales
+    /// are booked.
+    pub member_id: String,
+    /// The Vereinsflieger cost type ("Kostenart") of the sales.
+    pub cost_type: Option<String>,
+}
+
+impl FromStr for CostCenter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, right)) = s.split_once('=') else {
+            anyhow::bail!("Expected `<name>=<member ID>[:<cost type>]`");
+        };
+        let (member_id, cost_type) = match right.split_once(':') {
+            Some((member_id, cost_type)) => (member_id, Some(cost_type.to_string())),
+            None => (right, None),
+        };
+        anyhow::ensure!(
+            member_id.parse::<u32>().is_ok(),
+            "Invalid member ID: {member_id}"
+        );
+
+        Ok(Self {
+            name: name.to_string(),
+            member_id: member_id.to_string(),
+            cost_type,
+        })
+    }
+}
+
+/// The designation that is shown for the open-price article in the cart.
+pub const OPEN_PRICE_DESIGNATION: &str = "Sonstiges";
+
+/// The designation that is shown for redeemed vouchers in the cart.
+const VOUCHER_DESIGNATION: &str = "Gutschein";
+
+/// The maximum price that can be entered for the open-price article.
+const MAX_OPEN_PRICE: Decimal = rust_decimal_macros::dec!(100);
+
+/// Parse a (partially) entered price for the open-price article, using a
+/// comma as decimal separator.
+///
+/// Returns `None` if the input is not a valid price with at most two
+/// decimal places, or exceeds the maximum price. An empty input is
+/// parsed as zero.
+pub fn parse_open_price(input: &str) -> Option<Decimal> {
+    let (euros, cents) = input.split_once(',').unwrap_or((input, ""));
+    if cents.len() > 2
+        || !euros
+            .chars()
+            .chain(cents.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let euros = if euros.is_empty() { "0" } else { euros };
+    let price: Decimal = format!("{euros}.{cents:0<2}").parse().ok()?;
+    (price <= MAX_OPEN_PRICE).then_some(price)
+}
+
+/// How the current cart is paid.
+#[derive(Debug, Clone)]
+enum Payment {
+    /// Booked to the account of the logged-in member.
+    Account,
+    /// Paid by card, with the transaction ID of the payment.
+    Card(String),
+    /// Paid by the guest themselves via bank transfer or PayPal.
+    SelfPaid,
+    /// Paid in cash, which is recorded in the local cash ledger.
+    Cash,
+    /// Booked to a club account or event cost center instead of the
+    /// logged-in member.
+    CostCenter(CostCenter),
+}
+
+impl Payment {
+    /// The name of the payment method in the event stream.
+    fn name(&self) -> &'static str {
+        match self {
+            Payment::Account => "account",
+            Payment::Card(_) => "card",
+            Payment::SelfPaid => "self_paid",
+            Payment::Cash => "cash",
+            Payment::CostCenter(_) => "cost_center",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Sale {
+    pub amount: u16,
+    pub article: database::Article,
+    /// The unit price for the logged-in member at the time of the scan.
+    pub unit_price: Decimal,
+    /// Whether the unit price was entered manually by the member.
+    pub open_price: bool,
+    /// Whether this is a discount line that was added by a volume
+    /// discount rule.
+    #[serde(default)]
+    pub discount: bool,
+    /// The barcode of the voucher, if this line is a redeemed voucher.
+    #[serde(default)]
+    pub voucher: Option<String>,
+}
+
+impl Sale {
+    pub fn total(&self) -> Decimal {
+        Decimal::from(self.amount) * self.unit_price
+    }
+}
+
+#[cfg(test)]
+impl Sale {
+    /// Create a cart line for tests, with the article ID as designation and
+    /// the unit price in cents.
+    pub fn test(article_id: &str, amount: u16, cents: i64) -> Self {
+        Self {
+            amount,
+            article: database::Article {
+                id: article_id.to_string(),
+                designation: article_id.to_string(),
+                prices: vec![],
+                category: None,
+                account: None,
+            },
+            unit_price: Decimal::new(cents, 2),
+            open_price: false,
+            discount: false,
+            voucher: None,
+        }
+    }
+}
+
+impl RunningClubFridge {
+    pub fn update(&mut self, message: Message, global_state: &mut GlobalState) -> Task<Message> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&message);
+        }
+
+        let mut tasks = vec![self.handle_message(message, global_state)];
+
+        let entries = mem::take(&mut self.audit_entries);
+        if !entries.is_empty() {
+            tasks.push(write_audit_log(self.pool.clone(), entries));
+        }
+
+        if let Some(write) = self.recorder.as_mut().and_then(Recorder::flush) {
+            tasks.push(Task::future(write).discard());
+        }
+
+        Task::batch(tasks)
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Message,
+        global_state: &mut GlobalState,
+    ) -> Task<Message> {
+        match message {
+            Message::RateLimited => {
+                let backoff = self.rate_limit_backoff;
+                let until = jiff::Timestamp::now() + backoff;
+                warn!("Vereinsflieger rate limit reached, pausing sync until {until}");
+                self.audit("sync_rate_limited", None, format!("bis {until}"));
+
+                self.rate_limited_until = Some(until);
+                self.rate_limit_backoff = (backoff * 2).min(MAX_RATE_LIMIT_BACKOFF);
+                global_state.health.set_rate_limited_until(Some(until));
+            }
+            Message::SalesUploaded => {
+                global_state.health.sales_upload_finished();
+                self.rate_limited_until = None;
+                self.rate_limit_backoff = INITIAL_RATE_LIMIT_BACKOFF;
+                global_state.health.set_rate_limited_until(None);
+                return Task::done(Message::CheckStuckSales);
+            }
+            Message::CheckConnectivity => {
+                let address = network::connectivity_address();
+                return Task::future(async move {
+                    Message::ConnectivityChecked(network::is_reachable(&address).await)
+                });
+            }
+            Message::ConnectivityChecked(online) => {
+                if online == mem::replace(&mut self.online, online) {
+                    return Task::none();
+                }
+
+                if !online {
+                    warn!("Vereinsflieger is unreachable, pausing sync");
+                    self.audit("sync_offline", None, "");
+                    return Task::none();
+                }
+
+                info!("Vereinsflieger is reachable again, resuming sync");
+                self.audit("sync_online", None, "");
+                return Task::batch([
+                    Task::done(Message::LoadFromVF),
+                    Task::done(Message::UploadSalesToVF),
+                ]);
+            }
+            Message::LoadFromVF | Message::UploadSalesToVF if !self.online => {
+                debug!("Skipping Vereinsflieger sync because it is unreachable");
+            }
+            Message::LoadFromVF | Message::UploadSalesToVF
+                if global_state
+                    .options
+                    .sync_hours
+                    .is_some_and(|window| !window.contains(jiff::Zoned::now().time())) =>
+            {
+                debug!("Skipping Vereinsflieger sync outside of the sync hours");
+            }
+            Message::LoadFromVF | Message::UploadSalesToVF if self.is_rate_limited() => {
+                debug!("Skipping Vereinsflieger sync because of rate limiting");
+            }
+            Message::LoadFromVF => {
+                let mut tasks = Vec::new();
+
+                if let Some(vereinsflieger) = &self.article_client {
+                    tasks.push(self.load_articles(vereinsflieger.clone(), global_state));
+                }
+                if let Some(vereinsflieger) = &self.sales_client {
+                    tasks.push(self.load_members(vereinsflieger.clone(), global_state));
+                }
+
+                return Task::batch(tasks);
+            }
+            Message::UploadSalesToVF => {
+                let Some(vereinsflieger) = &self.sales_client else {
+                    return Task::none();
+                };
+
+                let vereinsflieger = vereinsflieger.clone();
+                let verifier = self.upload_verifier.clone();
+                let pool = self.pool.clone();
+                let upload_mutex = self.upload_mutex.clone();
+
+                // Successful uploads are already recorded in the sales table
+                let audit_pool = self.pool.clone();
+                return Task::future(async move {
+                    let _guard = upload_mutex.lock().await;
+                    sync::upload_sales(vereinsflieger, pool, verifier.as_ref()).await
+                })
+                .then(move |result| match result {
+                    Ok(_) => {
+                        info!("Sales successfully uploaded");
+                        Task::done(Message::SalesUploaded)
+                    }
+                    Err(err) if err.is::<RateLimited>() => Task::done(Message::RateLimited),
+                    Err(err) => {
+                        error!("Failed to upload sales: {err}");
+                        let details = format!("Fehler: {err}");
+                        let entry = database::AuditEntry::new("sync_sales", None, details);
+                        write_audit_log(audit_pool.clone(), vec![entry])
+                            .chain(Task::done(Message::CheckStuckSales))
+                    }
+                });
+            }
+            Message::KeyPress(Key::Named(Named::Escape), _) if self.confirmation.is_some() => {
+                return Task::done(Message::CancelConfirmation);
+            }
+            Message::KeyPress(Key::Named(Named::Escape), _) if self.admin.is_some() => {
+                return Task::done(Message::CloseAdmin);
+            }
+            // Articles are scanned on the admin screen during a stocktaking or
+            // restocking, but not while the numpad is shown
+            Message::KeyPress(..)
+                if self
+                    .admin
+                    .as_ref()
+                    .is_some_and(|admin| !admin.is_scanning()) => {}
+            Message::KeyPress(..) if self.confirmation.is_some() => {}
+            Message::KeyPress(..) if self.open_price_input.is_some() => {}
+            Message::KeyPress(..) if self.confirming_payment => {}
+            Message::KeyPress(..) if self.choosing_cost_center => {}
+            Message::KeyPress(..) if self.card_payment_pending => {}
+            Message::KeyPress(..) if self.transfer_qr.is_some() => {}
+            Message::KeyPress(Key::Character(c), modifiers) => {
+                if let Some(action) = self.key_action(&c, global_state) {
+                    debug!("Key pressed: {action:?}");
+                    match action {
+                        KeyAction::Pay if self.user.is_some() => return Task::done(Message::Pay),
+                        KeyAction::Cancel if self.user.is_some() => {
+                            return Task::done(Message::Cancel)
+                        }
+                        KeyAction::Pay | KeyAction::Cancel => self.input.clear(),
+                        KeyAction::Admin => {
+                            let input = mem::take(&mut self.input);
+                            if self.user.is_none()
+                                && self.check_admin_pin(&input, &global_state.options)
+                            {
+                                return self.open_admin(global_state);
+                            }
+                            global_state.show_error("Ungültige PIN");
+                        }
+                    }
+                    return Task::none();
+                }
+
+                let mut c = c.chars().next().unwrap();
+                if c == '\r' || c == '\n' {
+                    debug!("Key pressed: {:?}", logging::key(c));
+                    if global_state.options.submit_keys.contains(&SubmitKey::Enter) {
+                        return self.submit_input(global_state);
+                    }
+                    return Task::none();
+                }
+
+                if modifiers.shift() {
+                    c = c.to_ascii_uppercase();
+                }
+
+                debug!("Key pressed: {:?}", logging::key(c));
+                self.input.push(c);
+                global_state.hide_popup();
+            }
+            Message::KeyPress(Key::Named(Named::Enter), _)
+                if global_state.options.submit_keys.contains(&SubmitKey::Enter) =>
+            {
+                debug!("Key pressed: Enter");
+                return self.submit_input(global_state);
+            }
+            Message::KeyPress(Key::Named(Named::Tab), _)
+                if global_state.options.submit_keys.contains(&SubmitKey::Tab) =>
+            {
+                debug!("Key pressed: Tab");
+                return self.submit_input(global_state);
+            }
+            #[cfg(debug_assertions)]
+            Message::KeyPress(Key::Named(Named::Control), _) => {
+                use rust_decimal_macros::dec;
+
+                let task = if self.user.is_some() {
+                    let ulid = Ulid::new();
+
+                    let timestamp = ulid.timestamp_ms();
+
+                    let designations = [
+                        "Testartikel 1",
+                        "Testartikel 2 asd nflkjdbnslf kjalsdk fj lkdjsnlfkjnaldsknf lknlksdanfl kndslkf nlkaflkn a",
+                        "Test",
+                    ];
+                    let n = timestamp % designations.len() as u64;
+
+                    let ulid = ulid.to_string();
+                    Task::done(Message::FindArticleResult {
+                        input: ulid.clone(),
+                        amount: 1,
+                        result: Ok(Some(database::Article {
+                            id: designations[n as usize].to_string(),
+                            designation: designations[n as usize].to_string(),
+                            prices: vec![{
+                                database::Price {
+                                    valid_from: jiff::civil::Date::constant(2000, 1, 1),
+                                    valid_to: jiff::civil::Date::constant(2999, 12, 31),
+                                    unit_price: Decimal::from(timestamp % 1000) / dec!(100),
+                                    member_group: None,
+                                    sales_tax: None,
+                                }
+                            }],
+                            category: None,
+                            account: None,
+                        })),
+                    })
+                } else {
+                    Task::done(Message::FindMemberResult {
+                        input: "1234567890".to_string(),
+                        result: Ok(Some(database::Member {
+                            keycode: "1234567890".to_string(),
+                            id: "11011".to_string(),
+                            firstname: "Tobias".to_string(),
+                            lastname: "Bieniek".to_string(),
+                            nickname: "Turbo".to_string(),
+                            birthday: None,
+                            member_group: String::new(),
+                            email: None,
+                            blocked: false,
+                        })),
+                    })
+                };
+
+                global_state.hide_popup();
+
+                return task;
+            }
+            Message::FindArticleResult {
+                input,
+                amount,
+                result,
+            } => match result {
+                Ok(Some(mut article)) => {
+                    let group_prices = global_state
+                        .options
+                        .group_prices
+                        .iter()
+                        .filter(|group_price| group_price.article_id == article.id)
+                        .map(GroupPrice::to_price);
+                    article.prices.extend(group_prices);
+
+                    let member_group = self
+                        .user
+                        .as_ref()
+                        .map(|user| user.member_group.as_str())
+                        .unwrap_or_default();
+                    let today = jiff::Zoned::now().date();
+                    let unit_price = article.price_for_member_group(&today, member_group);
+
+                    let restriction = global_state
+                        .options
+                        .age_restrictions
+                        .iter()
+                        .find(|restriction| restriction.article_id == article.id);
+
+                    if let (Some(restriction), Some(user)) = (restriction, &self.user) {
+                        let allow_unknown_age = global_state.options.allow_unknown_age;
+                        let error = restriction.check(&article, user, today, allow_unknown_age);
+                        if let Some(message) = error.filter(|_| !self.refund) {
+                            warn!(
+                                member_id = %user.id,
+                                "Refusing age-restricted article: {article:?}"
+                            );
+                            global_state.show_error(message);
+                            return Task::none();
+                        }
+                    }
+
+                    let is_credit = global_state.options.credit_articles.contains(&article.id);
+                    if is_credit && self.user.as_ref().is_some_and(|user| user.is_guest()) {
+                        warn!("Refusing credit article for guest: {article:?}");
+                        global_state.show_error("Guthaben nur für Mitglieder");
+                        return Task::none();
+                    }
+
+                    if let Some(unit_price) = unit_price {
+                        let message =
+                            self.check_daily_limits(&article, amount, unit_price, global_state);
+                        if let Some(message) = message {
+                            warn!("Refusing article because of daily limits: {article:?}");
+                            global_state.show_error(message);
+                            return Task::none();
+                        }
+                    }
+
+                    info!("Adding {amount}x article to sale: {article:?}");
+                    if let (Some(user), Some(unit_price)) = (&self.user, unit_price) {
+                        let member_id = user.id.clone();
+                        let details = format!("{amount}x {} ({})", article.id, article.designation);
+                        self.audit("scan", Some(&member_id), details);
+
+                        let sales = &mut self.sales;
+                        let article_id = article.id.clone();
+
+                        let existing_sale = sales
+                            .iter_mut()
+                            .find(|item| !item.open_price && item.article.id == article.id);
+                        let changed_row = match existing_sale {
+                            Some(item) => {
+                                item.amount += amount;
+                                true
+                            }
+                            None => {
+                                sales.push(Sale {
+                                    amount,
+                                    article,
+                                    unit_price,
+                                    open_price: false,
+                                    discount: false,
+                                    voucher: None,
+                                });
+                                false
+                            }
+                        };
+
+                        // New rows might be sorted into the middle of the
+                        // cart, so they are highlighted as well
+                        let options = &global_state.options;
+                        let grouped = options.group_cart_by_category;
+                        if grouped {
+                            group_by_category(sales, &options.article_categories);
+                        }
+
+                        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                        let save = self.save_session();
+                        if changed_row || grouped {
+                            let highlight = self.highlight_row(article_id);
+                            return Task::batch([save, highlight]);
+                        }
+                        return save;
+                    }
+                }
+                Ok(None) if global_state.options.demo => {
+                    return Task::done(Message::FindArticleResult {
+                        result: Ok(Some(crate::demo::article_for_barcode(&input))),
+                        input,
+                        amount,
+                    });
+                }
+                Ok(None) => {
+                    warn!("No article found for barcode: {input}");
+                    let message = texts::with_input(&global_state.texts.article_not_found, &input);
+                    global_state.show_error(message);
+                    return Task::none();
+                }
+                Err(err) => {
+                    error!("Failed to find article: {err}");
+                }
+            },
+            Message::FindMemberResult { input, result } => match result {
+                Ok(Some(member)) if member.blocked => {
+                    warn!(
+                        member_id = %member.id,
+                        "Blocked user tried to log in: {:?}",
+                        logging::member(&member)
+                    );
+                    global_state.show_error(global_state.texts.member_blocked.clone());
+                    return Task::none();
+                }
+                Ok(Some(member)) => {
+                    info!(member_id = %member.id, "Setting user: {:?}", logging::member(&member));
+                    if member.has_birthday_on(jiff::Zoned::now().date()) {
+                        global_state.show_success(global_state.texts.birthday.clone());
+                    }
+                    return self.login(member);
+                }
+                Ok(None) if global_state.options.demo => {
+                    return self.login(crate::demo::member_for_keycode(&input));
+                }
+                Ok(None) => {
+                    warn!("No user found for keycode: {}", logging::keycode(&input));
+                    let message = texts::with_input(&global_state.texts.member_not_found, &input);
+                    global_state.show_error(message);
+                    return Task::none();
+                }
+                Err(err) => {
+                    error!("Failed to find user: {err}");
+                }
+            },
+            Message::LoadCalendar => {
+                let Some(url) = global_state.options.calendar_url.clone() else {
+                    return Task::none();
+                };
+
+                let count = global_state.options.calendar_events;
+                return Task::future(async move {
+                    let result = calendar::load_upcoming(&url, count).await;
+                    Message::CalendarLoaded(result.map_err(Arc::new))
+                });
+            }
+            Message::CalendarLoaded(result) => match result {
+                Ok(events) => {
+                    debug!("Loaded {} upcoming calendar events", events.len());
+                    self.calendar_events = events;
+                }
+                Err(err) => warn!("Failed to load calendar: {err}"),
+            },
+            Message::NextAnnouncement => {
+                let next = self.announcement_index + 1;
+                self.announcement_index = next.checked_rem(self.announcements.len()).unwrap_or(0);
+            }
+            Message::DecrementTimeout => {
+                if let Some(timeout) = &mut self.interaction_timeout {
+                    *timeout = timeout.sub(jiff::SignedDuration::from_secs(1));
+                    if timeout.is_zero() {
+                        info!("Interaction timeout reached");
+                        self.interaction_timeout = None;
+                        self.confirmation = None;
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.timeout_expired();
+                        }
+                        let is_guest = self.user.as_ref().is_some_and(|user| user.is_guest());
+                        return Task::done(if self.receipt.is_some() {
+                            Message::CloseReceipt
+                        } else if self.sales.is_empty() || is_guest {
+                            Message::Cancel
+                        } else {
+                            Message::Pay
+                        });
+                    }
+                }
+            }
+            Message::Pay
+                if global_state.options.confirm_payment
+                    && !self.confirming_payment
+                    && !self.sales.is_empty() =>
+            {
+                info!("Asking for payment confirmation");
+                self.confirming_payment = true;
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::Pay if self.card_payment_pending => {}
+            Message::Pay if self.user.as_ref().is_some_and(|user| user.is_guest()) => {
+                self.confirming_payment = false;
+
+                let total = self.sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+                if total <= Decimal::ZERO {
+                    info!("Guest left without buying anything");
+                    self.logout();
+                    return Task::none();
+                }
+
+                let Some(sumup) = self.sumup.clone() else {
+                    if let Some(target) = &self.transfer_target {
+                        info!("Showing transfer QR code of {total:.2}€ for guest");
+                        self.transfer_qr = target.to_qr_code(total, "Clubfridge");
+                        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                    }
+                    return Task::none();
+                };
+
+                info!("Starting card payment of {total:.2}€ for guest");
+                self.card_payment_pending = true;
+                self.interaction_timeout = None;
+
+                return Task::future(async move {
+                    let result = sumup.charge(total, "Clubfridge").await;
+                    Message::CardPaymentResult(result.map_err(Arc::new))
+                });
+            }
+            Message::Pay => return self.pay(Payment::Account, global_state),
+            Message::CardPaymentResult(result) => {
+                self.card_payment_pending = false;
+
+                match result {
+                    Ok(transaction_id) => {
+                        info!(%transaction_id, "Card payment successful");
+                        return self.pay(Payment::Card(transaction_id), global_state);
+                    }
+                    Err(err) => {
+                        warn!("Card payment failed: {err}");
+                        global_state.show_error("Kartenzahlung fehlgeschlagen");
+                        self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                    }
+                }
+            }
+            Message::CardPaymentNotSaved(transaction_id) => {
+                error!(%transaction_id, "Sales of a successful card payment were not saved");
+                let details = format!("{transaction_id}: Sales not saved");
+                self.audit("card_payment_not_saved", None, details);
+                self.unsaved_card_payments.push(transaction_id);
+                return Task::done(Message::SavingSalesFailed);
+            }
+            Message::ConfirmTransfer if self.transfer_qr.is_some() => {
+                info!("Guest confirmed transfer");
+                return self.pay(Payment::SelfPaid, global_state);
+            }
+            Message::PayCash if self.user.is_some() && !self.refund => {
+                info!("Paying in cash");
+                return self.pay(Payment::Cash, global_state);
+            }
+            Message::CashBalanceLoaded(result) => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(balance) => admin.cash_balance = Some(balance),
+                    Err(err) => error!("Failed to load cash balance: {err}"),
+                }
+            }
+            Message::EmptyCashBox if self.admin.is_some() => {
+                if !self.confirm_admin_action(Message::EmptyCashBox) {
+                    return Task::none();
+                }
+
+                info!("Admin emptied the cash box");
+                let balance = self.admin.as_ref().and_then(|admin| admin.cash_balance);
+                let details = balance.map(|balance| format!("{balance:.2}€"));
+                self.audit("cash_box_empty", None, details.unwrap_or_default());
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let pool = self.pool.clone();
+                return Task::future(
+                    async move { database::CashEntry::empty_cash_box(&pool).await },
+                )
+                .then(|result| match result {
+                    Ok(()) => Task::done(Message::CashBalanceLoaded(Ok(Decimal::ZERO))),
+                    Err(err) => {
+                        error!("Failed to empty cash box: {err}");
+                        Task::none()
+                    }
+                });
+            }
+            Message::ExportStatements => {
+                let Some(dir) = global_state.options.statement_dir.clone() else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let today = jiff::Zoned::now().date();
+                let Ok(last_month) = today.first_of_month().yesterday() else {
+                    return Task::none();
+                };
+
+                let options = &global_state.options;
+                let creditor = match (
+                    &options.sepa_creditor_name,
+                    &options.sepa_creditor_iban,
+                    &options.sepa_creditor_id,
+                    options.sepa_mandate_date,
+                ) {
+                    (Some(name), Some(iban), Some(id), Some(mandate_date)) => {
+                        Some(sepa::Creditor {
+                            name: name.clone(),
+                            iban: iban.clone(),
+                            id: id.clone(),
+                            mandate_date,
+                        })
+                    }
+                    _ => None,
+                };
+
+                let datev = match (
+                    &options.datev_contra_account,
+                    options.datev_consultant,
+                    options.datev_client,
+                ) {
+                    (Some(contra_account), Some(consultant), Some(client)) => Some(datev::Config {
+                        accounts: options.datev_accounts.clone(),
+                        contra_account: contra_account.clone(),
+                        consultant,
+                        client,
+                    }),
+                    _ => None,
+                };
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = export_month(&pool, &dir, last_month, creditor, datev).await;
+                    Message::StatementsExported(result.map_err(Arc::new))
+                });
+            }
+            Message::StatementsExported(result) => match result {
+                Ok(count) => {
+                    self.audit("statements_export", None, format!("{count} Abrechnungen"));
+                    global_state.show_success(format!("{count} Abrechnungen exportiert"));
+                }
+                Err(err) => {
+                    let error_id = self.internal_error(global_state, "Export fehlgeschlagen", &err);
+                    error!(%error_id, "Failed to export monthly statements: {err}");
+                }
+            },
+            Message::ImportMembers => {
+                let Some(path) = global_state.options.member_csv.clone() else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                info!("Importing members from {}…", path.display());
+                let pool = self.pool.clone();
+                let store_emails = global_state.options.store_member_emails;
+                return Task::future(async move {
+                    let result = async {
+                        let members = import::read_members(&path, store_emails).await?;
+                        let count = members.len();
+                        database::Member::save_all(pool, members).await?;
+                        Ok::<_, anyhow::Error>(count)
+                    };
+
+                    Message::MembersImported(result.await.map_err(Arc::new))
+                });
+            }
+            Message::MembersImported(result) => match result {
+                Ok(count) => {
+                    self.audit("members_import", None, format!("{count} Mitglieder"));
+                    global_state.show_success(format!("{count} Mitglieder importiert"));
+                }
+                Err(err) => {
+                    let error_id = self.internal_error(global_state, "Import fehlgeschlagen", &err);
+                    error!(%error_id, "Failed to import members: {err:#}");
+                }
+            },
+            Message::CancelTransfer => {
+                self.transfer_qr = None;
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::CancelPayment => {
+                self.confirming_payment = false;
+                self.choosing_cost_center = false;
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::AdminTotpSecretLoaded(result) => match result {
+                Ok(secret) => {
+                    if secret.is_some() {
+                        info!("Admin screen is protected with TOTP codes");
+                    }
+                    self.admin_totp_secret = secret;
+                }
+                Err(err) => error!("Failed to load admin TOTP secret: {err}"),
+            },
+            Message::ToggleRoundUp => {
+                let is_member = self.user.as_ref().is_some_and(|user| !user.is_guest());
+                if is_member && global_state.options.round_up_article.is_some() {
+                    self.round_up = !self.round_up;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ToggleCostCenters => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                self.cost_centers_enabled = !self.cost_centers_enabled;
+                admin.cost_centers_enabled = Some(self.cost_centers_enabled);
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let action = match self.cost_centers_enabled {
+                    true => "cost_centers_enable",
+                    false => "cost_centers_disable",
+                };
+                info!("Admin changed cost centers: {action}");
+                self.audit(action, None, "");
+            }
+            Message::ChooseCostCenter => {
+                let is_member = self.user.as_ref().is_some_and(|user| !user.is_guest());
+                if self.cost_centers_enabled && is_member && !self.sales.is_empty() {
+                    self.choosing_cost_center = true;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::PayToCostCenter(index) => {
+                self.choosing_cost_center = false;
+                let Some(cost_center) = global_state.options.cost_centers.get(index).cloned()
+                else {
+                    return Task::none();
+                };
+                if !self.cost_centers_enabled {
+                    return Task::none();
+                }
+
+                let member_id = self.user.as_ref().map(|user| user.id.clone());
+                let details = format!("Kostenstelle {}", cost_center.name);
+                self.audit("cost_center", member_id.as_deref(), details);
+                return self.pay(Payment::CostCenter(cost_center), global_state);
+            }
+            Message::SalesSaved => {
+                info!("Sales saved");
+                global_state.health.sale_saved();
+                let message = match self.refund {
+                    true => global_state.texts.refund_saved.clone(),
+                    false => global_state.texts.thank_you.clone(),
+                };
+                self.logout();
+                global_state.show_success(message);
+
+                self.receipt = self.pending_receipt.take();
+                if self.receipt.is_some() {
+                    self.interaction_timeout = Some(RECEIPT_TIMEOUT);
+                }
+
+                return self.load_expiring_batches(global_state);
+            }
+            Message::CloseReceipt => {
+                self.receipt = None;
+                self.interaction_timeout = None;
+            }
+            Message::SavingSalesFailed => {
+                let message = global_state.texts.save_failed.clone();
+                let error_id = global_state.show_internal_error(&message);
+                error!(%error_id, "Failed to save sales");
+                self.audit("error", None, format!("{error_id}: Failed to save sales"));
+                self.pending_receipt = None;
+            }
+            Message::Cancel => {
+                info!("Cancelling sale");
+                if let Some(user) = &self.user {
+                    let member_id = user.id.clone();
+                    let details = format!("{} Positionen verworfen", self.sales.len());
+                    self.audit("cancel", Some(&member_id), details);
+                }
+                self.logout();
+                self.admin = None;
+                return self.save_session();
+            }
+            Message::SetMemberSearch(query) => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                admin.search_query = query.clone();
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                if query.trim().len() < 2 {
+                    admin.search_results.clear();
+                    return Task::none();
+                }
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = database::Member::search_by_name(pool, query.trim()).await;
+                    let result = result.map_err(Arc::new);
+                    Message::MemberSearchResult { query, result }
+                });
+            }
+            Message::MemberSearchResult { query, result } => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                // Ignore results of outdated queries
+                if admin.search_query != query {
+                    return Task::none();
+                }
+
+                match result {
+                    Ok(members) => admin.search_results = members,
+                    Err(err) => error!("Failed to search members: {err}"),
+                }
+            }
+            Message::AdminLogin(member) => {
+                info!(
+                    member_id = %member.id,
+                    "Admin logged in user: {:?}",
+                    logging::member(&member)
+                );
+                self.audit("admin_login", Some(&member.id), "");
+                self.admin = None;
+                return self.login(member);
+            }
+            Message::AdminRefund(member) => {
+                info!(
+                    member_id = %member.id,
+                    "Admin started refund for user: {:?}",
+                    logging::member(&member)
+                );
+                self.audit("admin_refund", Some(&member.id), "");
+                self.admin = None;
+                self.refund = true;
+                return self.login(member);
+            }
+            Message::RestoreSession => {
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = load_session(pool).await.map_err(Arc::new);
+                    Message::SessionRestored(result)
+                });
+            }
+            Message::SessionRestored(result) => match result {
+                Ok(Some((member, session))) if self.user.is_none() => {
+                    info!(member_id = %member.id, "Restoring unfinished purchase: {session:?}");
+                    self.sales = session.cart;
+                    self.refund = session.refund;
+                    global_state.show_popup(global_state.texts.session_restored.clone());
+                    return self.login(member);
+                }
+                Ok(_) => {}
+                Err(err) => error!("Failed to restore session: {err}"),
+            },
+            Message::SetMemberBlocked { member_id, blocked } => {
+                let Some(admin) = &self.admin else {
+                    return Task::none();
+                };
+
+                info!(%member_id, "Setting member blocked status to {blocked}");
+                let pool = self.pool.clone();
+                let query = admin.search_query.clone();
+                let action = if blocked {
+                    "member_block"
+                } else {
+                    "member_unblock"
+                };
+                self.audit(action, Some(&member_id), "");
+                return Task::future(async move {
+                    database::Member::set_blocked(pool, &member_id, blocked).await
+                })
+                .then(move |result| match result {
+                    // Refresh the search results to show the new status
+                    Ok(()) => Task::done(Message::SetMemberSearch(query.clone())),
+                    Err(err) => {
+                        error!("Failed to update blocklist: {err}");
+                        Task::none()
+                    }
+                });
+            }
+            Message::EditNickname(member) => {
+                if let Some(admin) = &mut self.admin {
+                    admin.nickname_edit = Some((member.id, member.nickname));
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SetNickname(nickname) => {
+                if let Some((_, edited)) =
+                    self.admin.as_mut().and_then(|a| a.nickname_edit.as_mut())
+                {
+                    *edited = nickname;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::CancelNicknameEdit => {
+                if let Some(admin) = &mut self.admin {
+                    admin.nickname_edit = None;
+                }
+            }
+            Message::SaveNickname => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+                let Some((member_id, nickname)) = admin.nickname_edit.take() else {
+                    return Task::none();
+                };
+
+                info!(%member_id, "Setting local nickname to {nickname:?}");
+                let pool = self.pool.clone();
+                let query = admin.search_query.clone();
+                self.audit("member_nickname", Some(&member_id), nickname.trim());
+                return Task::future(async move {
+                    database::Member::set_nickname(pool, &member_id, &nickname).await
+                })
+                .then(move |result| match result {
+                    // Refresh the search results to show the new nickname
+                    Ok(()) => Task::done(Message::SetMemberSearch(query.clone())),
+                    Err(err) => {
+                        error!("Failed to save nickname: {err}");
+                        Task::none()
+                    }
+                });
+            }
+            Message::ParkCart => {
+                let Some(user) = &self.user else {
+                    return Task::none();
+                };
+                if self.park_window.is_none() || user.is_guest() || self.sales.is_empty() {
+                    return Task::none();
+                }
+
+                let session = database::Session {
+                    member_id: user.id.clone(),
+                    refund: self.refund,
+                    cart: mem::take(&mut self.sales),
+                };
+
+                let member_id = session.member_id.clone();
+                info!(%member_id, "Parking cart: {:?}", session.cart);
+                let details = format!("{} Positionen", session.cart.len());
+                self.audit("cart_park", Some(&member_id), details);
+                self.logout();
+                self.admin = None;
+                global_state.show_popup(global_state.texts.cart_parked.clone());
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    if let Err(err) = session.park(&pool).await {
+                        error!(%member_id, "Failed to park cart: {err}");
+                    }
+                })
+                .discard();
+            }
+            Message::ParkedCartLoaded { member_id, result } => {
+                let session = match result {
+                    Ok(Some(session)) => session,
+                    Ok(None) => return Task::none(),
+                    Err(err) => {
+                        error!(%member_id, "Failed to load parked cart: {err}");
+                        return Task::none();
+                    }
+                };
+
+                // The parked cart was already removed from the database, so
+                // it is lost if the member is not logged in anymore
+                if self.user.as_ref().is_none_or(|user| user.id != member_id) {
+                    warn!(%member_id, "Discarding parked cart: {session:?}");
+                    return Task::none();
+                }
+                if !self.sales.is_empty() && self.refund != session.refund {
+                    warn!(%member_id, "Discarding parked cart of other type: {session:?}");
+                    return Task::none();
+                }
+
+                info!(%member_id, "Resuming parked cart: {session:?}");
+                let details = format!("{} Positionen", session.cart.len());
+                self.audit("cart_resume", Some(&member_id), details);
+                self.refund = session.refund;
+                self.sales.splice(0..0, session.cart);
+                global_state.show_popup(global_state.texts.cart_resumed.clone());
+                return self.save_session();
+            }
+            Message::BalanceLoaded { member_id, result } => {
+                // Ignore results for members that are not logged in anymore
+                if self.user.as_ref().is_none_or(|user| user.id != member_id) {
+                    return Task::none();
+                }
+
+                match result {
+                    Ok(balance) => self.balance = Some(balance),
+                    Err(err) => error!(%member_id, "Failed to load prepaid balance: {err}"),
+                }
+            }
+            Message::LastPurchaseLoaded { member_id, result } => {
+                // Ignore results for members that are not logged in anymore
+                if self.user.as_ref().is_none_or(|user| user.id != member_id) {
+                    return Task::none();
+                }
+
+                let sales = match result {
+                    Ok(sales) => sales,
+                    Err(err) => {
+                        error!(%member_id, "Failed to load last purchase: {err}");
+                        return Task::none();
+                    }
+                };
+
+                // Discounts, vouchers, open prices and the free birthday
+                // article are not repeated
+                let birthday_article = global_state.options.birthday_article.as_ref();
+                self.last_purchase = sales
+                    .into_iter()
+                    .filter(|sale| !sale.open_price)
+                    .filter(|sale| {
+                        sale.unit_price
+                            .is_none_or(|price| !price.is_sign_negative())
+                    })
+                    .filter(|sale| birthday_article != Some(&sale.article_id))
+                    .filter_map(|sale| Some((sale.article_id, u16::try_from(sale.amount).ok()?)))
+                    .collect();
+            }
+            Message::RepeatLastPurchase if self.user.is_some() && self.sales.is_empty() => {
+                let items = mem::take(&mut self.last_purchase);
+                info!("Repeating last purchase: {items:?}");
+
+                let pool = self.pool.clone();
+                let article_cache = self.article_cache.clone();
+                return Task::future(async move {
+                    let mut messages = Vec::with_capacity(items.len());
+                    for (article_id, amount) in items {
+                        let result = article_cache.find_by_barcode(pool.clone(), &article_id);
+                        let result = result.await.map_err(Arc::new);
+                        messages.push(Message::FindArticleResult {
+                            input: article_id,
+                            amount,
+                            result,
+                        });
+                    }
+                    messages
+                })
+                // The articles are added in the order of the previous purchase
+                .then(|messages| {
+                    let tasks = messages.into_iter().map(Task::done);
+                    tasks.fold(Task::none(), Task::chain)
+                });
+            }
+            Message::RepeatLastPurchase => {}
+            Message::ClearHighlight => {
+                // A later change of the cart restarts the highlight
+                let expired = self
+                    .highlighted_row
+                    .as_ref()
+                    .is_some_and(|(_, at)| at.elapsed() >= HIGHLIGHT_DURATION);
+                if expired {
+                    self.highlighted_row = None;
+                }
+            }
+            Message::TodaysSalesLoaded { member_id, result } => {
+                // Ignore results for members that are not logged in anymore
+                if self.user.as_ref().is_none_or(|user| user.id != member_id) {
+                    return Task::none();
+                }
+
+                match result {
+                    Ok(sales) => self.todays_sales = sales,
+                    Err(err) => {
+                        error!(%member_id, "Failed to load sales of today: {err}");
+                        return Task::none();
+                    }
+                }
+
+                return self.add_birthday_article(global_state);
+            }
+            Message::BirthdayArticleLoaded { member_id, result } => {
+                if self.user.as_ref().is_none_or(|user| user.id != member_id) {
+                    return Task::none();
+                }
+
+                match result {
+                    Ok(Some(article)) => {
+                        info!(%member_id, "Adding free birthday article: {article:?}");
+                        // The price is sent explicitly so that the article
+                        // is booked for free in Vereinsflieger
+                        self.sales.push(Sale {
+                            amount: 1,
+                            article,
+                            unit_price: Decimal::ZERO,
+                            open_price: true,
+                            discount: false,
+                            voucher: None,
+                        });
+                        return self.save_session();
+                    }
+                    Ok(None) => warn!("Birthday article not found"),
+                    Err(err) => error!("Failed to find birthday article: {err}"),
+                }
+            }
+            Message::StartStocktaking => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                info!("Admin started stocktaking");
+                admin.stocktaking = Some(Stocktaking::default());
+                self.input.clear();
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::StocktakingArticleResult {
+                input,
+                amount,
+                result,
+            } => {
+                let Some(stocktaking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.stocktaking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                match result {
+                    Ok(Some(article)) => {
+                        debug!("Counting {amount}x article: {article:?}");
+                        stocktaking.count(article, amount as i64);
+                    }
+                    Ok(None) => {
+                        warn!("No article found for barcode: {input}");
+                        let message =
+                            texts::with_input(&global_state.texts.article_not_found, &input);
+                        global_state.show_error(message);
+                    }
+                    Err(err) => error!("Failed to find article: {err}"),
+                }
+            }
+            Message::FinishStocktaking => {
+                let Some(stocktaking) = self
+                    .admin
+                    .as_ref()
+                    .and_then(|admin| admin.stocktaking.as_ref())
+                else {
+                    return Task::none();
+                };
+
+                info!("Admin finished stocktaking");
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let counted = stocktaking
+                    .counted
+                    .iter()
+                    .map(|(article, counted)| (article.id.clone(), *counted))
+                    .collect::<HashMap<_, _>>();
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let differences = database::Stock::save_stocktaking(&pool, &counted).await?;
+                    let designations = database::Article::load_designations(&pool).await?;
+
+                    let report = differences
+                        .into_iter()
+                        .map(|difference| {
+                            let designation = designations
+                                .get(&difference.article_id)
+                                .cloned()
+                                .unwrap_or_else(|| difference.article_id.clone());
+                            (designation, difference)
+                        })
+                        .collect();
+
+                    Ok::<_, sqlx::Error>(report)
+                })
+                .map(|result| Message::StocktakingSaved(result.map_err(Arc::new)));
+            }
+            Message::StocktakingSaved(result) => {
+                let Some(stocktaking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.stocktaking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(report) => {
+                        for (designation, difference) in &report {
+                            info!(
+                                "Stocktaking of {designation}: expected {}, counted {}",
+                                difference.expected, difference.counted
+                            );
+                        }
+                        let details = format!("{} Artikel abweichend", report.len());
+                        stocktaking.report = Some(report);
+                        self.audit("stocktaking", None, details);
+                        return self.load_expiring_batches(global_state);
+                    }
+                    Err(err) => {
+                        let error_id = self.internal_error(
+                            global_state,
+                            "Inventur konnte nicht gespeichert werden",
+                            &err,
+                        );
+                        error!(%error_id, "Failed to save stocktaking: {err}");
+                    }
+                }
+            }
+            Message::TakeScreenshot if self.admin.is_some() => {
+                info!("Taking screenshot of the member screen");
+                self.audit("screenshot", None, "");
+
+                // The admin screen is closed first, so that the screenshot
+                // shows what members see
+                let screenshot = Task::future(tokio::time::sleep(SCREENSHOT_DELAY))
+                    .then(|_| window::latest().and_then(window::screenshot))
+                    .map(Message::ScreenshotTaken);
+                return Task::done(Message::CloseAdmin).chain(screenshot);
+            }
+            Message::ScreenshotTaken(screenshot) => {
+                let upload_url = global_state.options.support_upload_url.clone();
+                return Task::future(async move {
+                    let result = screenshot::save(screenshot, upload_url).await;
+                    Message::ScreenshotSaved(result.map_err(Arc::new))
+                });
+            }
+            Message::ScreenshotSaved(result) => match result {
+                Ok(path) => {
+                    let message = match global_state.options.support_upload_url {
+                        Some(_) => "Bildschirmfoto gespeichert und hochgeladen".to_string(),
+                        None => format!("Bildschirmfoto gespeichert: {}", path.display()),
+                    };
+                    global_state.show_success(message);
+                }
+                Err(err) => {
+                    let error_id =
+                        self.internal_error(global_state, "Bildschirmfoto fehlgeschlagen", &err);
+                    error!(%error_id, "Failed to save screenshot: {err:#}");
+                }
+            },
+            Message::ShowSystemInfo if self.admin.is_some() => {
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let database = global_state.options.database().get_filename().to_path_buf();
+                let health = global_state.health.clone();
+                let started_at = global_state.started_at;
+                let update_check = global_state.last_update_check.clone();
+                let online = self.online;
+                return Task::future(async move {
+                    let info =
+                        system::collect(database, &health, started_at, update_check, online).await;
+                    Message::SystemInfoLoaded(Box::new(info))
+                });
+            }
+            Message::SystemInfoLoaded(info) => {
+                if let Some(admin) = &mut self.admin {
+                    admin.system_info = Some(*info);
+                }
+            }
+            Message::CloseSystemInfo => {
+                if let Some(admin) = &mut self.admin {
+                    admin.system_info = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ShowAuditLog if self.admin.is_some() => {
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = database::AuditEntry::load_recent(&pool, AUDIT_LOG_LIMIT).await;
+                    Message::AuditLogLoaded(result.map_err(Arc::new))
+                });
+            }
+            Message::AuditLogLoaded(result) => match (result, &mut self.admin) {
+                (Ok(entries), Some(admin)) => admin.audit_log = Some(entries),
+                (Ok(_), None) => {}
+                (Err(err), _) => {
+                    let error_id = self.internal_error(
+                        global_state,
+                        "Protokoll konnte nicht geladen werden",
+                        &err,
+                    );
+                    error!(%error_id, "Failed to load audit log: {err}");
+                }
+            },
+            Message::CloseAuditLog => {
+                if let Some(admin) = &mut self.admin {
+                    admin.audit_log = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::CountPriceChanges => {
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = database::PriceChange::count_unseen(&pool).await;
+                    Message::PriceChangesCounted(result.map_err(Arc::new))
+                });
+            }
+            Message::PriceChangesCounted(result) => match result {
+                Ok(count) => self.unseen_price_changes = count,
+                Err(err) => error!("Failed to count price changes: {err}"),
+            },
+            Message::ShowPriceChanges if self.admin.is_some() => {
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result =
+                        database::PriceChange::load_recent(&pool, PRICE_CHANGES_LIMIT).await;
+                    Message::PriceChangesLoaded(result.map_err(Arc::new))
+                });
+            }
+            Message::PriceChangesLoaded(result) => match (result, &mut self.admin) {
+                (Ok(changes), Some(admin)) => {
+                    admin.price_changes = Some(changes);
+                    self.unseen_price_changes = 0;
+                }
+                (Ok(_), None) => {}
+                (Err(err), _) => {
+                    let error_id = self.internal_error(
+                        global_state,
+                        "Preisänderungen konnten nicht geladen werden",
+                        &err,
+                    );
+                    error!(%error_id, "Failed to load price changes: {err}");
+                }
+            },
+            Message::ClosePriceChanges => {
+                if let Some(admin) = &mut self.admin {
+                    admin.price_changes = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ShowPendingSales if self.admin.is_some() => {
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = async {
+                        let designations = database::Article::load_designations(&pool).await?;
+                        let sales = database::Sale::load_all(pool).await?;
+                        let sales = sales.into_iter().map(|sale| {
+                            let designation = designations.get(&sale.article_id);
+                            let designation = designation.unwrap_or(&sale.article_id).clone();
+                            (sale, designation)
+                        });
+                        Ok(sales.collect())
+                    };
+                    Message::PendingSalesLoaded(result.await.map_err(Arc::new))
+                });
+            }
+            Message::PendingSalesLoaded(result) => match (result, &mut self.admin) {
+                (Ok(sales), Some(admin)) => {
+                    admin.pending_sales = Some(PendingSales {
+                        sales,
+                        editing: None,
+                    })
+                }
+                (Ok(_), None) => {}
+                (Err(err), _) => {
+                    let error_id = self.internal_error(
+                        global_state,
+                        "Offene Verkäufe konnten nicht geladen werden",
+                        &err,
+                    );
+                    error!(%error_id, "Failed to load pending sales: {err}");
+                }
+            },
+            Message::EditPendingSale(id) => {
+                let Some(pending) = self.admin.as_mut().and_then(|a| a.pending_sales.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                let mut sales = pending.sales.iter().map(|(sale, _)| sale);
+                let sale = sales.find(|sale| *sale.id == id && PendingSales::is_editable(sale));
+                if let Some(sale) = sale {
+                    pending.editing = Some(PendingSaleEdit {
+                        id,
+                        amount: sale.amount,
+                        member_id: sale.member_id.clone(),
+                    });
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SetPendingSaleAmount(amount) => {
+                if let Some(edit) = self.pending_sale_edit() {
+                    // The sign of the original amount is kept
+                    if amount != 0 && amount.signum() == edit.amount.signum() {
+                        edit.amount = amount;
+                    }
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SetPendingSaleMember(member_id) => {
+                if let Some(edit) = self.pending_sale_edit() {
+                    edit.member_id = member_id.trim().to_string();
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SavePendingSale if self.admin.is_some() => {
+                if self.pending_sale_edit().is_none()
+                    || !self.confirm_admin_action(Message::SavePendingSale)
+                {
+                    return Task::none();
+                }
+                let Some(edit) = self.pending_sale_edit() else {
+                    return Task::none();
+                };
+
+                let id = edit.id;
+                let amount = edit.amount;
+                let member_id = edit.member_id.clone();
+                let pool = self.pool.clone();
+                let upload_mutex = self.upload_mutex.clone();
+                return Task::future(async move {
+                    // The upload works on the sales that were loaded before,
+                    // so the sale must not change while it runs
+                    let _guard = upload_mutex.lock().await;
+
+                    let result = async {
+                        let member = database::Member::find_by_id(pool.clone(), &member_id).await?;
+                        if member.is_none() {
+                            return Ok(PendingSaleUpdate::UnknownMember);
+                        }
+
+                        let updated =
+                            database::Sale::update_pending(&pool, id, &member_id, amount).await?;
+                        Ok(match updated {
+                            true => PendingSaleUpdate::Saved,
+                            false => PendingSaleUpdate::Uploading,
+                        })
+                    };
+                    Message::PendingSaleSaved(result.await.map_err(Arc::new))
+                });
+            }
+            Message::PendingSaleSaved(result) => {
+                let Some(pending) = self.admin.as_mut().and_then(|a| a.pending_sales.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                match result {
+                    Ok(PendingSaleUpdate::Saved) => {
+                        let details = pending.describe_edit().unwrap_or_default();
+                        let member_id = pending.editing.take().map(|edit| edit.member_id);
+                        info!("Admin edited pending sale: {details}");
+                        self.audit("sale_edit", member_id.as_deref(), details);
+                        global_state.show_success("Verkauf geändert");
+                        return Task::done(Message::ShowPendingSales);
+                    }
+                    Ok(PendingSaleUpdate::UnknownMember) => {
+                        global_state.show_error("Mitglied nicht gefunden");
+                    }
+                    Ok(PendingSaleUpdate::Uploading) => {
+                        pending.editing = None;
+                        global_state.show_error("Verkauf wird bereits hochgeladen");
+                        return Task::done(Message::ShowPendingSales);
+                    }
+                    Err(err) => {
+                        let error_id = self.internal_error(
+                            global_state,
+                            "Verkauf konnte nicht gespeichert werden",
+                            &err,
+                        );
+                        error!(%error_id, "Failed to save pending sale: {err}");
+                    }
+                }
+            }
+            Message::ResolveInterruptedSale(id, resend) if self.admin.is_some() => {
+                let action = Message::ResolveInterruptedSale(id, resend);
+                if !self.confirm_admin_action(action) {
+                    return Task::none();
+                }
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let pool = self.pool.clone();
+                let upload_mutex = self.upload_mutex.clone();
+                return Task::future(async move {
+                    let _guard = upload_mutex.lock().await;
+                    let result = database::Sale::resolve_interrupted(&pool, id, resend).await;
+                    Message::InterruptedSaleResolved(id, resend, result.map_err(Arc::new))
+                });
+            }
+            Message::InterruptedSaleResolved(id, resend, result) => match result {
+                Ok(true) => {
+                    let details = match resend {
+                        true => format!("{id}: erneut senden"),
+                        false => format!("{id}: verworfen"),
+                    };
+                    info!("Admin resolved interrupted sale: {details}");
+                    self.audit("sale_interrupted", None, details);
+                    global_state.show_success("Verkauf aktualisiert");
+                    return Task::done(Message::ShowPendingSales);
+                }
+                Ok(false) => {
+                    global_state.show_error("Verkauf wurde bereits bearbeitet");
+                    return Task::done(Message::ShowPendingSales);
+                }
+                Err(err) => {
+                    let error_id = self.internal_error(
+                        global_state,
+                        "Verkauf konnte nicht aktualisiert werden",
+                        &err,
+                    );
+                    error!(%error_id, "Failed to resolve interrupted sale: {err}");
+                }
+            },
+            Message::SetConfirmationCode(code) => {
+                if let Some(confirmation) = &mut self.confirmation {
+                    confirmation.code = code;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SubmitConfirmationCode => {
+                let Some(confirmation) = self.confirmation.take() else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                if !self.check_admin_pin(&confirmation.code, &global_state.options) {
+                    warn!("Invalid admin code for confirming an action");
+                    self.audit("admin_confirm_failed", None, "");
+                    global_state.show_error("Ungültiger Code");
+                    self.confirmation = Some(Confirmation {
+                        code: String::new(),
+                        ..confirmation
+                    });
+                    return Task::none();
+                }
+
+                // The confirmation only applies to this action, so that it
+                // can not be used by any later action
+                self.action_confirmed = true;
+                let task = self.update(confirmation.action, global_state);
+                self.action_confirmed = false;
+                return task;
+            }
+            Message::CancelConfirmation => {
+                self.confirmation = None;
+                self.interaction_timeout =
+                    (self.admin.is_some() || self.user.is_some()).then_some(INTERACTION_TIMEOUT);
+            }
+            Message::LiftLimits
+                if self.user.as_ref().is_some_and(|user| !user.is_guest())
+                    && !self.limits_overridden
+                    && self.admin_enabled(&global_state.options) =>
+            {
+                if !self.confirm_admin_action(Message::LiftLimits) {
+                    return Task::none();
+                }
+
+                info!("Admin lifted daily purchase limits");
+                let member_id = self.user.as_ref().map(|user| user.id.clone());
+                self.audit("admin_lift_limits", member_id.as_deref(), "");
+                self.limits_overridden = true;
+                global_state.show_success("Tageslimits aufgehoben");
+            }
+            Message::OpenAdmin
+                if self.user.is_none()
+                    && self.admin.is_none()
+                    && self.admin_enabled(&global_state.options) =>
+            {
+                if self.confirm_admin_action(Message::OpenAdmin) {
+                    return self.open_admin(global_state);
+                }
+            }
+            Message::CancelPendingSaleEdit => {
+                if let Some(pending) = self.admin.as_mut().and_then(|a| a.pending_sales.as_mut()) {
+                    pending.editing = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ClosePendingSales => {
+                if let Some(admin) = &mut self.admin {
+                    admin.pending_sales = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::CloseStocktaking => {
+                if let Some(admin) = &mut self.admin {
+                    admin.stocktaking = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ReadTemperature => {
+                let Some(path) = global_state.options.temperature_sensor.clone() else {
+                    return Task::none();
+                };
+
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    let result = temperature::read(&path).await;
+                    if let Ok(temperature) = result {
+                        if let Err(err) = database::Temperature::insert(&pool, temperature).await {
+                            warn!("Failed to log temperature: {err}");
+                        }
+                    }
+
+                    Message::TemperatureRead(result.map_err(Arc::new))
+                });
+            }
+            Message::TemperatureRead(result) => match result {
+                Ok(temperature) => {
+                    debug!("Fridge temperature: {temperature:.1}°C");
+                    self.temperature = Some(temperature);
+                    global_state.health.set_temperature(Some(temperature));
+
+                    // Only alert once until the temperature is fine again
+                    let too_warm = temperature > global_state.options.max_temperature;
+                    if too_warm && !self.temperature_alert {
+                        warn!("Fridge is too warm: {temperature:.1}°C");
+                        let message = format!("Kühlschrank zu warm: {temperature:.1}°C");
+                        global_state.show_error(message);
+                    }
+                    self.temperature_alert = too_warm;
+                }
+                Err(err) => {
+                    warn!("Failed to read temperature: {err:#}");
+                    self.temperature = None;
+                    global_state.health.set_temperature(None);
+                }
+            },
+            Message::AnonymizeMemberData => {
+                let Some(retention) = self.member_data_retention else {
+                    return Task::none();
+                };
+
+                let before = jiff::Timestamp::now() - retention;
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    match database::anonymize_member_data(&pool, before).await {
+                        Ok(0) => {}
+                        Ok(count) => info!("Removed member IDs from {count} old records"),
+                        Err(err) => error!("Failed to remove old member IDs: {err}"),
+                    }
+                })
+                .discard();
+            }
+            Message::CheckDiskSpace => {
+                let database = global_state.options.database().get_filename().to_path_buf();
+                let min_free_space = global_state.options.min_free_disk_space * 1024 * 1024;
+                return Task::future(async move {
+                    let result = async {
+                        let free_space = disk::free_space(&database).await?;
+                        if free_space >= min_free_space {
+                            return Ok(free_space);
+                        }
+
+                        // Clean up before SQLite writes start failing
+                        disk::cleanup(Some(database.clone())).await;
+                        disk::free_space(&database).await
+                    };
+
+                    Message::DiskSpaceChecked(result.await.map_err(Arc::new))
+                });
+            }
+            Message::DiskSpaceChecked(result) => match result {
+                Ok(free_space) => {
+                    let min_free_space = global_state.options.min_free_disk_space * 1024 * 1024;
+                    if free_space < min_free_space {
+                        warn!("Low disk space: {} MB free", free_space / 1024 / 1024);
+                        self.low_disk_space = Some(free_space);
+                    } else {
+                        self.low_disk_space = None;
+                    }
+                }
+                Err(err) => warn!("Failed to check free disk space: {err:#}"),
+            },
+            Message::CheckStuckSales => {
+                let pool = self.pool.clone();
+                let options = &global_state.options;
+                let max_failures = options.upload_alert_failures;
+                let max_age = jiff::SignedDuration::from_hours(options.upload_alert_days * 24);
+                return Task::future(async move {
+                    let result = database::Sale::count_stuck(&pool, max_failures, max_age).await;
+                    Message::StuckSalesChecked(result.map_err(Arc::new))
+                });
+            }
+            Message::StuckSalesChecked(result) => match result {
+                Ok(count) => return self.stuck_sales_checked(count, global_state),
+                Err(err) => error!("Failed to check for stuck sales: {err}"),
+            },
+            Message::ReadDoorSensor => {
+                let Some(path) = global_state.options.door_sensor.clone() else {
+                    return Task::none();
+                };
+
+                let active_low = global_state.options.door_sensor_active_low;
+                return Task::future(async move {
+                    let result = door::is_open(&path, active_low).await;
+                    Message::DoorSensorRead(result.map_err(Arc::new))
+                });
+            }
+            Message::DoorSensorRead(result) => match result {
+                Ok(true) => self.door_open(global_state),
+                Ok(false) => return self.door_closed(),
+                Err(err) => warn!("Failed to read door sensor: {err:#}"),
+            },
+            Message::LoadExpiringBatches => return self.load_expiring_batches(global_state),
+            Message::ExpiringBatchesLoaded(result) => match result {
+                Ok(batches) => self.expiring_batches = batches,
+                Err(err) => error!("Failed to load expiring batches: {err}"),
+            },
+            Message::StartRestocking => {
+                let Some(admin) = &mut self.admin else {
+                    return Task::none();
+                };
+
+                info!("Admin started restocking");
+                admin.restocking = Some(Restocking::default());
+                self.input.clear();
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::RestockMemberResult { input, result } => {
+                let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                match result {
+                    Ok(Some(member)) => {
+                        info!(member_id = %member.id, "Member started restocking");
+                        restocking.member = Some(member);
+                    }
+                    Ok(None) => {
+                        warn!("No user found for keycode: {}", logging::keycode(&input));
+                        let message =
+                            texts::with_input(&global_state.texts.member_not_found, &input);
+                        global_state.show_error(message);
+                    }
+                    Err(err) => error!("Failed to find user: {err}"),
+                }
+            }
+            Message::RestockArticleResult {
+                input,
+                amount,
+                result,
+            } => {
+                let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                match result {
+                    Ok(Some(article)) => {
+                        restocking.article = Some(article);
+                        restocking.quantity = amount.to_string();
+                    }
+                    Ok(None) => {
+                        warn!("No article found for barcode: {input}");
+                        let message =
+                            texts::with_input(&global_state.texts.article_not_found, &input);
+                        global_state.show_error(message);
+                    }
+                    Err(err) => error!("Failed to find article: {err}"),
+                }
+            }
+            Message::SetRestockQuantity(quantity) => {
+                let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                if quantity.is_empty() || Restocking::parse_quantity(&quantity).is_some() {
+                    restocking.quantity = quantity;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::CancelRestockArticle => {
+                if let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                {
+                    restocking.article = None;
+                    restocking.quantity.clear();
+                    restocking.best_before = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::ConfirmRestock => {
+                let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                let expiry_tracking = global_state.options.expiry_warning_days.is_some();
+                match restocking.best_before.clone() {
+                    // Ask for the best-before date after the number of units
+                    None if expiry_tracking => {
+                        if Restocking::parse_quantity(&restocking.quantity).is_some() {
+                            restocking.best_before = Some(String::new());
+                            self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                        }
+                    }
+                    None => return self.save_restock(None),
+                    Some(input) => {
+                        if let Some(best_before) = Restocking::parse_best_before(&input) {
+                            return self.save_restock(Some(best_before));
+                        }
+                    }
+                }
+            }
+            Message::SetRestockBestBefore(input) => {
+                let Some(restocking) = self
+                    .admin
+                    .as_mut()
+                    .and_then(|admin| admin.restocking.as_mut())
+                else {
+                    return Task::none();
+                };
+
+                let is_valid = input.len() <= 6 && input.chars().all(|c| c.is_ascii_digit());
+                if restocking.best_before.is_some() && is_valid {
+                    restocking.best_before = Some(input);
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SkipRestockBestBefore => return self.save_restock(None),
+            Message::RestockSaved {
+                designation,
+                amount,
+                result,
+            } => match result {
+                Ok(stock) => {
+                    info!("Stock of {designation} is now {stock}");
+                    let member_id = self
+                        .admin
+                        .as_ref()
+                        .and_then(|admin| admin.restocking.as_ref())
+                        .and_then(|restocking| restocking.member.as_ref())
+                        .map(|member| member.id.clone());
+                    let details = format!("{amount}x {designation}");
+                    self.audit("restock", member_id.as_deref(), details);
+
+                    if let Some(restocking) = self
+                        .admin
+                        .as_mut()
+                        .and_then(|admin| admin.restocking.as_mut())
+                    {
+                        restocking.restocked.push((designation, amount));
+                    }
+                    return self.load_expiring_batches(global_state);
+                }
+                Err(err) => {
+                    let error_id = self.internal_error(
+                        global_state,
+                        "Auffüllen konnte nicht gespeichert werden",
+                        &err,
+                    );
+                    error!(%error_id, "Failed to save restocking: {err}");
+                }
+            },
+            Message::CloseRestocking => {
+                if let Some(admin) = &mut self.admin {
+                    admin.restocking = None;
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::VoucherFound(voucher) => {
+                let Some(article_id) = global_state.options.voucher_article.clone() else {
+                    return Task::none();
+                };
+
+                let is_guest = self.user.as_ref().is_some_and(|user| user.is_guest());
+                if self.refund || is_guest {
+                    global_state.show_error("Gutscheine nur für Einkäufe von Mitgliedern");
+                    return Task::none();
+                }
+
+                let barcode = voucher.barcode.clone();
+                if !voucher.is_valid_on(jiff::Zoned::now().date()) {
+                    warn!("Refusing invalid or redeemed voucher: {voucher:?}");
+                    global_state.show_error("Gutschein ungültig oder bereits eingelöst");
+                    return Task::none();
+                }
+                if self.cart_vouchers().contains(&barcode) {
+                    global_state.show_error("Gutschein bereits eingescannt");
+                    return Task::none();
+                }
+
+                // The voucher can not reduce the total below zero, so any
+                // remaining value is forfeited
+                let total = self.sales.iter().map(|sale| sale.total()).sum::<Decimal>();
+                let value = (*voucher.value).min(total);
+                if value <= Decimal::ZERO {
+                    global_state.show_error("Bitte zuerst Produkte scannen");
+                    return Task::none();
+                }
+
+                info!("Redeeming voucher {barcode} for {value}€");
+                self.sales.push(Sale {
+                    amount: 1,
+                    article: database::Article {
+                        id: article_id,
+                        designation: VOUCHER_DESIGNATION.to_string(),
+                        prices: vec![],
+                        category: None,
+                        account: None,
+                    },
+                    unit_price: -value,
+                    open_price: true,
+                    discount: false,
+                    voucher: Some(barcode),
+                });
+
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                return self.save_session();
+            }
+            Message::OpenPriceEntry => {
+                if self.user.is_some() {
+                    self.open_price_input = Some(String::new());
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::SetOpenPrice(input) => {
+                if self.open_price_input.is_some() && parse_open_price(&input).is_some() {
+                    self.open_price_input = Some(input);
+                    self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+                }
+            }
+            Message::CancelOpenPrice => {
+                self.open_price_input = None;
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+            }
+            Message::ConfirmOpenPrice => {
+                let Some(input) = self.open_price_input.take() else {
+                    return Task::none();
+                };
+                self.interaction_timeout = Some(INTERACTION_TIMEOUT);
+
+                let Some(article_id) = global_state.options.open_price_article.clone() else {
+                    return Task::none();
+                };
+                let Some(unit_price) =
+                    parse_open_price(&input).filter(|price| *price > Decimal::ZERO)
+                else {
+                    return Task::none();
+                };
+
+                let article = database::Article {
+                    id: article_id,
+                    designation: OPEN_PRICE_DESIGNATION.to_string(),
+                    prices: vec![],
+                    category: None,
+                    account: None,
+                };
+
+                if let Some(message) =
+                    self.check_daily_limits(&article, 1, unit_price, global_state)
+                {
+                    warn!("Refusing open-price article because of daily limits");
+                    global_state.show_error(message);
+                    return Task::none();
+                }
+
+                info!("Adding open-price article to sale: {unit_price}€");
+                self.sales.push(Sale {
+                    amount: 1,
+                    article,
+                    unit_price,
+                    open_price: true,
+                    discount: false,
+                    voucher: None,
+                });
+
+                return self.save_session();
+            }
+            Message::StartGuestSale => {
+                if self.user.is_none() && self.guests_enabled() {
+                    info!("Starting guest sale");
+                    return self.login(database::Member::guest());
+                }
+            }
+            Message::CloseAdmin => {
+                info!("Closing admin screen");
+                self.admin = None;
+                self.confirmation = None;
+                self.interaction_timeout = None;
+            }
+            _ => {}
+        }
+
+        Task::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_quantity() {
+        assert_eq!(split_quantity("4001234"), (1, "4001234"));
+        assert_eq!(split_quantity("6*4001234"), (6, "4001234"));
+        assert_eq!(split_quantity("12*4001234"), (12, "4001234"));
+        assert_eq!(split_quantity("6*"), (6, ""));
+        assert_eq!(split_quantity("0*4001234"), (1, "0*4001234"));
+        assert_eq!(split_quantity("100*4001234"), (1, "100*4001234"));
+        assert_eq!(split_quantity("*4001234"), (1, "*4001234"));
+        assert_eq!(split_quantity("a*4001234"), (1, "a*4001234"));
+    }
+
+    #[test]
+    fn test_parse_open_price() {
+        let check = |input, expected: Option<i64>| {
+            let expected = expected.map(|cents| Decimal::new(cents, 2));
+            assert_eq!(parse_open_price(input), expected);
+        };
+
+        check("", Some(0));
+        check("3", Some(300));
+        check("3,", Some(300));
+        check("3,5", Some(350));
+        check(",99", Some(99));
+        check("100", Some(10000));
+        check("100,01", None);
+        check("3,501", None);
+        check("3.50", None);
+        check("-3", None);
+    }
+
+    #[test]
+    fn test_round_up_difference() {
+        assert_eq!(
+            round_up_difference(Decimal::new(360, 2)),
+            Decimal::new(40, 2)
+        );
+        assert_eq!(round_up_difference(Decimal::new(400, 2)), Decimal::ZERO);
+        assert_eq!(round_up_difference(Decimal::new(1, 2)), Decimal::new(99, 2));
+        assert_eq!(round_up_difference(Decimal::ZERO), Decimal::ZERO);
+        assert_eq!(round_up_difference(Decimal::new(-150, 2)), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_group_price() {
+        let price: GroupPrice = "1234:Jugend=1.00".parse().unwrap();
+        assert_eq!(price.article_id, "1234");
+        assert_eq!(price.member_group, "Jugend");
+        assert_eq!(price.unit_price, Decimal::new(100, 2));
+        assert_eq!(price.valid_from, jiff::civil::Date::MIN);
+        assert_eq!(price.valid_to, jiff::civil::Date::MAX);
+
+        let price: GroupPrice = "1234:Jugend=1.00@2025-01-01..2025-12-31".parse().unwrap();
+        assert_eq!(price.unit_price, Decimal::new(100, 2));
+        assert_eq!(price.valid_from, jiff::civil::date(2025, 1, 1));
+        assert_eq!(price.valid_to, jiff::civil::date(2025, 12, 31));
+
+        let price: GroupPrice = "1234:Jugend=1.00@2025-06-01..".parse().unwrap();
+        assert_eq!(price.valid_from, jiff::civil::date(2025, 6, 1));
+        assert_eq!(price.valid_to, jiff::civil::Date::MAX);
+
+        assert!("1234:Jugend=1.00@2025-06-01".parse::<GroupPrice>().is_err());
+        assert!("1234:Jugend=1.00@2025-12-31..2025-01-01"
+            .parse::<GroupPrice>()
+            .is_err());
+        assert!("1234=1.00".parse::<GroupPrice>().is_err());
+    }
+
+    #[test]
+    fn test_age_restriction() {
+        let restriction: AgeRestriction = "1234=18".parse().unwrap();
+        let article = database::Article::with_fixed_price(
+            "1234".to_string(),
+            "Bier".to_string(),
+            Decimal::new(150, 2),
+        );
+        let date = jiff::civil::date(2025, 3, 1);
+
+        let member = |birthday: Option<jiff::civil::Date>| database::Member {
+            birthday: birthday.map(Text),
+            ..database::Member::guest()
+        };
+        let check = |member: &database::Member, allow_unknown_age| {
+            restriction.check(&article, member, date, allow_unknown_age)
+        };
+
+        let adult = member(Some(jiff::civil::date(2007, 3, 1)));
+        assert_eq!(check(&adult, false), None);
+
+        let minor = member(Some(jiff::civil::date(2007, 3, 2)));
+        let message = "Bier ist erst ab 18 Jahren erhältlich";
+        assert_eq!(check(&minor, false).as_deref(), Some(message));
+        assert_eq!(check(&minor, true).as_deref(), Some(message));
+
+        // Members without a known birthday are refused, unless allowed
+        let unknown = member(None);
+        assert!(check(&unknown, false).is_some());
+        assert_eq!(check(&unknown, true), None);
+    }
+
+    #[test]
+    fn test_group_by_category() {
+        let sale = |id, category: Option<&str>, discount| {
+            let mut sale = Sale::test(id, 1, 100);
+            sale.article.category = category.map(ToString::to_string);
+            sale.discount = discount;
+            sale
+        };
+
+        let categories = ["Getränke=1,2", "Snacks=3"].map(|c| c.parse().unwrap());
+
+        let mut sales = vec![
+            sale("3", Some("Snacks"), false),
+            sale("4", None, false),
+            sale("2", Some("Getränke"), false),
+            sale("rabatt", None, true),
+            sale("1", Some("Getränke"), false),
+            sale("6", Some("Zubehör"), false),
+            sale("5", Some("Bekleidung"), false),
+        ];
+        group_by_category(&mut sales, &categories);
+
+        let ids = sales.iter().map(|sale| sale.article.id.as_str());
+        let expected = vec!["2", "1", "3", "5", "6", "4", "rabatt"];
+        assert_eq!(ids.collect::<Vec<_>>(), expected);
     }
 }
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Keys that can be configured to submit the scanned input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SubmitKey {
+    Enter,
+    Tab,
+}
+
+/// An action that can be bound to a key of a dedicated hardware keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Book the cart, like the "Pay" button.
+    Pay,
+    /// Discard the cart and log out, like the "Cancel" button.
+    Cancel,
+    /// Submit the typed input as the admin PIN to open the admin screen.
+    Admin,
+}
+
+impl FromStr for KeyAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pay" => Ok(Self::Pay),
+            "cancel" => Ok(Self::Cancel),
+            "admin" => Ok(Self::Admin),
+            _ => Err(anyhow::anyhow!("Unknown key action: {s:?}")),
+        }
+    }
+}
+
+/// Keys that trigger an action instead of being added to the scanned input,
+/// parsed from a comma-separated list of `key=action` pairs (e.g.
+/// `+=pay,-=cancel,*=admin`).
+///
+/// Characters that appear in scanned barcodes or keycodes must not be bound,
+/// since scanners send them as regular key presses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyBindings(HashMap<char, KeyAction>);
+
+impl KeyBindings {
+    /// The action that is bound to the pressed key, if there is one.
+    pub fn action(&self, key: &str) -> Option<KeyAction> {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.0.get(&c).copied(),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for KeyBindings {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bindings = s
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut chars = pair.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(key), Some('=')) => Ok((key, chars.as_str().parse()?)),
+                    _ => Err(anyhow::anyhow!("Invalid key binding: {pair:?}")),
+                }
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(bindings))
+    }
+}
+
+/// A character translation table that is applied to the scanned input.
+///
+/// Barcode scanners act like keyboards and send key codes, which are
+/// translated into characters using the keyboard layout of the system. If the
+/// layout of the scanner does not match the layout of the system, the scanned
+/// input contains the wrong characters (e.g. `z` instead of `y`).
+///
+/// The table can be parsed either from a preset name (`de` for a scanner
+/// with US layout on a system with German layout), or from a comma-separated
+/// list of `from=to` pairs (e.g. `z=y,y=z`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyMap(HashMap<char, char>);
+
+/// Translation of characters produced by the German keyboard layout to the
+/// characters of a US keyboard layout on the same physical keys.
+const PRESET_DE: &[(char, char)] = &[
+    ('z', 'y'),
+    ('y', 'z'),
+    ('Z', 'Y'),
+    ('Y', 'Z'),
+    ('ß', '-'),
+    ('-', '/'),
+    ('_', '?'),
+    ('"', '@'),
+    ('§', '#'),
+    ('&', '^'),
+    ('/', '&'),
+    ('(', '*'),
+    (')', '('),
+    ('=', ')'),
+];
+
+impl KeyMap {
+    /// Translate all characters of the input using this table.
+    pub fn apply(&self, input: &str) -> String {
+        input
+            .chars()
+            .map(|c| self.0.get(&c).copied().unwrap_or(c))
+            .collect()
+    }
+}
+
+impl FromStr for KeyMap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "de" {
+            return Ok(Self(PRESET_DE.iter().copied().collect()));
+        }
+
+        let map = s
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.chars().collect::<Vec<_>>()[..] {
+                [from, '=', to] => Ok((from, to)),
+                _ => Err(anyhow::anyhow!("Invalid key mapping: {pair:?}")),
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(map))
+    }
+}
+
+/// Remove the first matching prefix and suffix from the scanned input.
+///
+/// Many barcode scanners add e.g. an AIM symbology identifier like `]E0` in
+/// front of the actual code, or other characters after it.
+pub fn strip_affixes<'a>(input: &'a str, prefixes: &[String], suffixes: &[String]) -> &'a str {
+    let input = prefixes
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix.as_str()))
+        .unwrap_or(input);
+
+    suffixes
+        .iter()
+        .find_map(|suffix| input.strip_suffix(suffix.as_str()))
+        .unwrap_or(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_affixes() {
+        let prefixes = vec!["]E0".to_string(), "]C1".to_string()];
+        let suffixes = vec!["\t".to_string()];
+
+        let check = |input, expected| {
+            assert_eq!(strip_affixes(input, &prefixes, &suffixes), expected);
+        };
+
+        check("4006381333931", "4006381333931");
+        check("]E04006381333931", "4006381333931");
+        check("]C1ABC\t", "ABC");
+        check("ABC]E0", "ABC]E0");
+        check("", "");
+    }
+
+    #[test]
+    fn test_key_map() {
+        let key_map: KeyMap = "de".parse().unwrap();
+        assert_eq!(key_map.apply("Zuckerfrei-1"), "Yuckerfrei/1");
+        assert_eq!(key_map.apply("0005635570"), "0005635570");
+
+        let key_map: KeyMap = "z=y,y=z,==-".parse().unwrap();
+        assert_eq!(key_map.apply("xyz="), "xzy-");
+
+        assert!("z=y,foo".parse::<KeyMap>().is_err());
+        assert_eq!("".parse::<KeyMap>().unwrap(), KeyMap::default());
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        let bindings: KeyBindings = "+=pay,-=cancel,*=admin".parse().unwrap();
+        assert_eq!(bindings.action("+"), Some(KeyAction::Pay));
+        assert_eq!(bindings.action("-"), Some(KeyAction::Cancel));
+        assert_eq!(bindings.action("*"), Some(KeyAction::Admin));
+        assert_eq!(bindings.action("1"), None);
+        assert_eq!(bindings.action("+-"), None);
+
+        let bindings: KeyBindings = "==pay".parse().unwrap();
+        assert_eq!(bindings.action("="), Some(KeyAction::Pay));
+
+        assert!("+=pay,-=foo".parse::<KeyBindings>().is_err());
+        assert!("+pay".parse::<KeyBindings>().is_err());
+        assert_eq!("".parse::<KeyBindings>().unwrap(), KeyBindings::default());
+    }
+}
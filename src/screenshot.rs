@@ -0,0 +1,114 @@
+use crate::paths;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use iced::window::Screenshot;
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::info;
+
+/// The signature at the start of every PNG file.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Save the screenshot as a PNG file in the screenshot directory, and
+/// upload it to the support endpoint if one is configured.
+///
+/// Returns the path of the saved file.
+pub async fn save(screenshot: Screenshot, upload_url: Option<String>) -> anyhow::Result<PathBuf> {
+    let png = tokio::task::spawn_blocking(move || {
+        let size = screenshot.size;
+        encode_png(size.width, size.height, &screenshot.rgba)
+    })
+    .await??;
+
+    let dir = paths::screenshot_dir();
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let now = jiff::Zoned::now();
+    let path = dir.join(format!("screenshot-{}.png", now.strftime("%Y%m%d-%H%M%S")));
+    tokio::fs::write(&path, &png).await?;
+    info!("Saved screenshot to {}", path.display());
+
+    if let Some(url) = upload_url {
+        info!("Uploading screenshot to {url}…");
+        reqwest::Client::new()
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "image/png")
+            .body(png)
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+
+    Ok(path)
+}
+
+/// Encode RGBA pixels as a PNG image.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let row_len = width as usize * 4;
+    anyhow::ensure!(
+        rgba.len() == row_len * height as usize,
+        "Expected {width}x{height} RGBA pixels, got {} bytes",
+        rgba.len()
+    );
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, default compression, filtering and no
+    // interlacing
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Every row starts with its filter type, which is always "none" here
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+    for row in rgba.chunks(row_len.max(1)).take(height as usize) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let idat = encoder.finish()?;
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &idat);
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+/// Append a PNG chunk with its length and checksum.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    png.extend_from_slice(&hasher.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_encode_png() {
+        let rgba = [255, 0, 0, 255, 0, 255, 0, 128];
+        let png = encode_png(2, 1, &rgba).unwrap();
+
+        assert!(png.starts_with(PNG_SIGNATURE));
+        assert_eq!(&png[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        // The well-known checksum of the `IEND` chunk
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut pixels = Vec::new();
+        let mut decoder = ZlibDecoder::new(&png[41..41 + idat_len]);
+        decoder.read_to_end(&mut pixels).unwrap();
+        assert_eq!(pixels, [0, 255, 0, 0, 255, 0, 255, 0, 128]);
+
+        assert!(encode_png(2, 2, &rgba).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::database;
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// The time between the export and the requested collection date, which
+/// leaves enough time to upload the file to the bank.
+const COLLECTION_DELAY: jiff::SignedDuration = jiff::SignedDuration::from_hours(7 * 24);
+
+/// The club that collects the direct debits.
+#[derive(Debug, Clone)]
+pub struct Creditor {
+    pub name: String,
+    pub iban: String,
+    /// The SEPA creditor identifier (aka. "Gläubiger-ID").
+    pub id: String,
+    /// The date on which the mandates of the members were signed.
+    pub mandate_date: jiff::civil::Date,
+}
+
+/// A direct debit of the aggregated purchases of a member.
+#[derive(Debug, Clone)]
+pub struct Debit {
+    /// The member ID, which is used as mandate reference.
+    pub member_id: String,
+    pub name: String,
+    pub iban: String,
+    pub amount: Decimal,
+}
+
+/// Export a SEPA direct debit file for the member purchases in the month of
+/// the given date into `<dir>/<YYYY-MM>/sepa.xml`.
+///
+/// Purchases that were paid by card, transfer or in cash are not included.
+/// Returns the number of debits in the file.
+pub async fn export_month(
+    pool: &SqlitePool,
+    dir: &Path,
+    month: jiff::civil::Date,
+    creditor: &Creditor,
+) -> anyhow::Result<usize> {
+    let start = month.first_of_month();
+    let end = month.last_of_month();
+
+    info!("Exporting SEPA direct debits for {start} to {end}…");
+    let sales = database::Sale::load_between(pool, start, end).await?;
+    let bank_accounts = database::BankAccount::load_all(pool).await?;
+
+    let mut totals = BTreeMap::<String, Decimal>::new();
+    for sale in sales {
+        if sale.payment_reference.is_some() || sale.self_paid {
+            continue;
+        }
+
+        let total = sale.total().unwrap_or_default();
+        *totals.entry(sale.member_id).or_default() += total;
+    }
+
+    let mut debits = Vec::with_capacity(totals.len());
+    for (member_id, amount) in totals {
+        if amount <= Decimal::ZERO {
+            continue;
+        }
+
+        let Some(iban) = bank_accounts.get(&member_id) else {
+            warn!(%member_id, "Skipping direct debit of {amount}€ without IBAN");
+            continue;
+        };
+
+        let member = database::Member::find_by_id(pool.clone(), &member_id).await?;
+        let name = member
+            .map(|member| format!("{} {}", member.firstname, member.lastname))
+            .unwrap_or_default();
+
+        debits.push(Debit {
+            member_id,
+            name,
+            iban: iban.clone(),
+            amount,
+        });
+    }
+
+    let month = start.strftime("%Y-%m").to_string();
+    let message_id = format!("clubfridge-{month}");
+    let collection_date = jiff::Zoned::now().date().checked_add(COLLECTION_DELAY)?;
+    let remittance = format!("Getränke {month}");
+    let xml = pain008(creditor, &message_id, collection_date, &remittance, &debits);
+
+    let dir = dir.join(&month);
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join("sepa.xml"), xml).await?;
+
+    info!(
+        "Exported {} SEPA direct debits to {}",
+        debits.len(),
+        dir.display()
+    );
+    Ok(debits.len())
+}
+
+/// Build a SEPA core direct debit file in the `pain.008.001.02` format.
+pub fn pain008(
+    creditor: &Creditor,
+    message_id: &str,
+    collection_date: jiff::civil::Date,
+    remittance: &str,
+    debits: &[Debit],
+) -> String {
+    let count = debits.len();
+    let sum = debits.iter().map(|debit| debit.amount).sum::<Decimal>();
+    let created_at = jiff::Zoned::now().strftime("%Y-%m-%dT%H:%M:%S");
+
+    let name = escape(&creditor.name);
+    let iban = creditor.iban.replace(' ', "");
+    let message_id = escape(message_id);
+
+    let mut xml = String::new();
+    let _ = write!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pain.008.001.02">
+  <CstmrDrctDbtInitn>
+    <GrpHdr>
+      <MsgId>{message_id}</MsgId>
+      <CreDtTm>{created_at}</CreDtTm>
+      <NbOfTxs>{count}</NbOfTxs>
+      <CtrlSum>{sum:.2}</CtrlSum>
+      <InitgPty><Nm>{name}</Nm></InitgPty>
+    </GrpHdr>
+    <PmtInf>
+      <PmtInfId>{message_id}</PmtInfId>
+      <PmtMtd>DD</PmtMtd>
+      <NbOfTxs>{count}</NbOfTxs>
+      <CtrlSum>{sum:.2}</CtrlSum>
+      <PmtTpInf>
+        <SvcLvl><Cd>SEPA</Cd></SvcLvl>
+        <LclInstrm><Cd>CORE</Cd></LclInstrm>
+        <SeqTp>RCUR</SeqTp>
+      </PmtTpInf>
+      <ReqdColltnDt>{collection_date}</ReqdColltnDt>
+      <Cdtr><Nm>{name}</Nm></Cdtr>
+      <CdtrAcct><Id><IBAN>{iban}</IBAN></Id></CdtrAcct>
+      <CdtrAgt><FinInstnId><Othr><Id>NOTPROVIDED</Id></Othr></FinInstnId></CdtrAgt>
+      <ChrgBr>SLEV</ChrgBr>
+      <CdtrSchmeId>
+        <Id><PrvtId><Othr>
+          <Id>{creditor_id}</Id>
+          <SchmeNm><Prtry>SEPA</Prtry></SchmeNm>
+        </Othr></PrvtId></Id>
+      </CdtrSchmeId>
+"#,
+        creditor_id = escape(&creditor.id),
+    );
+
+    for debit in debits {
+        let _ = write!(
+            xml,
+            r#"      <DrctDbtTxInf>
+        <PmtId><EndToEndId>{message_id}-{member_id}</EndToEndId></PmtId>
+        <InstdAmt Ccy="EUR">{amount:.2}</InstdAmt>
+        <DrctDbtTx>
+          <MndtRltdInf>
+            <MndtId>{member_id}</MndtId>
+            <DtOfSgntr>{mandate_date}</DtOfSgntr>
+          </MndtRltdInf>
+        </DrctDbtTx>
+        <DbtrAgt><FinInstnId><Othr><Id>NOTPROVIDED</Id></Othr></FinInstnId></DbtrAgt>
+        <Dbtr><Nm>{name}</Nm></Dbtr>
+        <DbtrAcct><Id><IBAN>{iban}</IBAN></Id></DbtrAcct>
+        <RmtInf><Ustrd>{remittance}</Ustrd></RmtInf>
+      </DrctDbtTxInf>
+"#,
+            member_id = escape(&debit.member_id),
+            amount = debit.amount,
+            mandate_date = creditor.mandate_date,
+            name = escape(&debit.name),
+            iban = debit.iban.replace(' ', ""),
+            remittance = escape(remittance),
+        );
+    }
+
+    xml.push_str("    </PmtInf>\n  </CstmrDrctDbtInitn>\n</Document>\n");
+    xml
+}
+
+/// Escape the special characters of XML text content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pain008() {
+        let creditor = Creditor {
+            name: "Flugsport & Co. e.V.".to_string(),
+            iban: "DE02 1203 0000 0000 2020 51".to_string(),
+            id: "DE98ZZZ09999999999".to_string(),
+            mandate_date: jiff::civil::date(2020, 1, 1),
+        };
+
+        let debits = vec![
+            Debit {
+                member_id: "11011".to_string(),
+                name: "Tobias Bieniek".to_string(),
+                iban: "DE89370400440532013000".to_string(),
+                amount: Decimal::new(1250, 2),
+            },
+            Debit {
+                member_id: "11012".to_string(),
+                name: "Max Mustermann".to_string(),
+                iban: "DE89370400440532013001".to_string(),
+                amount: Decimal::new(3, 0),
+            },
+        ];
+
+        let date = jiff::civil::date(2025, 4, 8);
+        let xml = pain008(&creditor, "clubfridge-2025-03", date, "Getränke", &debits);
+
+        assert!(xml.contains("<NbOfTxs>2</NbOfTxs>"));
+        assert!(xml.contains("<CtrlSum>15.50</CtrlSum>"));
+        assert!(xml.contains("<Nm>Flugsport &amp; Co. e.V.</Nm>"));
+        assert!(xml.contains("<IBAN>DE02120300000000202051</IBAN>"));
+        assert!(xml.contains("<ReqdColltnDt>2025-04-08</ReqdColltnDt>"));
+        assert!(xml.contains(r#"<InstdAmt Ccy="EUR">3.00</InstdAmt>"#));
+        assert!(xml.contains("<MndtId>11011</MndtId>"));
+        assert!(xml.contains("<DtOfSgntr>2020-01-01</DtOfSgntr>"));
+        assert!(xml.ends_with("</Document>\n"));
+    }
+}
@@ -7,23 +7,88 @@ use iced::{color, Center, Element, Fill, Right, Shrink, Subscription, Task};
 use sqlx::SqlitePool;
 use tracing::{error, info, warn};
 
+/// The reason why the credentials could not be checked or saved on the
+/// setup screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupFailure {
+    /// Vereinsflieger could not be reached, e.g. because the internet
+    /// connection is not set up yet.
+    Unreachable,
+    InvalidAppKey,
+    WrongPassword,
+    WrongClubId,
+    /// Vereinsflieger rejected the credentials for another reason.
+    Unknown,
+    /// The credentials could not be saved to the database.
+    Database,
+}
+
+impl SetupFailure {
+    /// Determine the reason of a failed authentication from the error
+    /// message.
+    ///
+    /// The `vereinsflieger` client does not expose the HTTP status code or
+    /// the error response, so this checks the error messages instead.
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        let contains_any = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+
+        if contains_any(&[
+            "error sending request",
+            "dns error",
+            "connection refused",
+            "timed out",
+            "unreachable",
+        ]) {
+            SetupFailure::Unreachable
+        } else if contains_any(&["appkey"]) {
+            SetupFailure::InvalidAppKey
+        } else if contains_any(&["cid"]) {
+            SetupFailure::WrongClubId
+        } else if contains_any(&["password", "passwort", "username", "benutzer", "401", "403"]) {
+            SetupFailure::WrongPassword
+        } else {
+            SetupFailure::Unknown
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            SetupFailure::Unreachable => {
+                "Vereinsflieger nicht erreichbar, bitte Internetverbindung prüfen"
+            }
+            SetupFailure::InvalidAppKey => "Ungültiger Appkey",
+            SetupFailure::WrongPassword => "Benutzername oder Passwort falsch",
+            SetupFailure::WrongClubId => "Falsche CID",
+            SetupFailure::Unknown => "Authentifizierung fehlgeschlagen",
+            SetupFailure::Database => "Zugangsdaten konnten nicht gespeichert werden",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Setup {
     pool: SqlitePool,
+    /// Whether credentials for another club are added from the admin screen,
+    /// which allows going back without saving.
+    cancelable: bool,
     club_id: String,
     app_key: String,
     username: String,
     password: String,
+    auth_secret: String,
 }
 
 impl Setup {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlitePool, cancelable: bool) -> Self {
         Self {
             pool,
+            cancelable,
             club_id: String::new(),
             app_key: String::new(),
             username: String::new(),
             password: String::new(),
+            auth_secret: String::new(),
         }
     }
 
@@ -48,6 +113,7 @@ impl Setup {
             Message::SetAppKey(app_key) => self.app_key = app_key,
             Message::SetUsername(username) => self.username = username,
             Message::SetPassword(password) => self.password = password,
+            Message::SetAuthSecret(auth_secret) => self.auth_secret = auth_secret,
             Message::SubmitSetup => {
                 info!("Checking credentials…");
 
@@ -59,15 +125,21 @@ impl Setup {
                 let app_key = self.app_key.clone();
                 let username = self.username.clone();
                 let password = self.password.clone().into();
+                let auth_secret = Some(self.auth_secret.trim())
+                    .filter(|auth_secret| !auth_secret.is_empty())
+                    .map(ToString::to_string);
 
                 let credentials = database::Credentials {
                     club_id,
                     app_key,
                     username,
                     password,
+                    auth_secret,
                 };
 
-                global_state.popup = Some(Popup::new("Prüfe Zugangsdaten…".to_string()));
+                global_state
+                    .popups
+                    .push(Popup::persistent("Prüfe Zugangsdaten…"));
 
                 let pool = self.pool.clone();
                 return Task::future(async move {
@@ -79,21 +151,56 @@ impl Setup {
 
                             if let Err(err) = credentials.insert(pool.clone()).await {
                                 error!("Failed to save credentials to the database: {err}");
-                                Message::AuthenticationFailed
-                            } else {
-                                Message::StartupComplete(pool, Some(vereinsflieger))
+                                return Message::AuthenticationFailed(SetupFailure::Database);
+                            }
+
+                            match database::Credentials::find_all(pool.clone()).await {
+                                Ok(credentials) => Message::StartupComplete(pool, credentials),
+                                Err(err) => {
+                                    error!("Failed to load credentials from the database: {err}");
+                                    Message::AuthenticationFailed(SetupFailure::Database)
+                                }
                             }
                         }
                         Err(err) => {
-                            warn!("Failed to authenticate: {err}");
-                            Message::AuthenticationFailed
+                            let err = format!("{err:#}");
+                            let failure = SetupFailure::classify(&err);
+                            warn!(?failure, "Failed to authenticate: {err}");
+                            Message::AuthenticationFailed(failure)
+                        }
+                    }
+                });
+            }
+            Message::CancelSetup if self.cancelable => {
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    match database::Credentials::find_all(pool.clone()).await {
+                        Ok(credentials) => Message::StartupComplete(pool, credentials),
+                        Err(err) => {
+                            error!("Failed to load credentials from the database: {err}");
+                            Message::CredentialLookupFailed
+                        }
+                    }
+                });
+            }
+            Message::SetupOffline if !self.cancelable => {
+                info!("Setting up without Vereinsflieger…");
+                let pool = self.pool.clone();
+                return Task::future(async move {
+                    match database::Settings::set_offline(&pool, true).await {
+                        Ok(()) => Message::EnableOfflineMode(pool),
+                        Err(err) => {
+                            error!("Failed to save offline mode: {err}");
+                            Message::SetupOfflineFailed
                         }
                     }
                 });
             }
-            Message::AuthenticationFailed => {
-                let message = "Authentifizierung fehlgeschlagen".to_string();
-                return global_state.show_popup(message);
+            Message::AuthenticationFailed(failure) => {
+                global_state.show_error(failure.message());
+            }
+            Message::SetupOfflineFailed => {
+                global_state.show_error("Offline-Modus konnte nicht gespeichert werden");
             }
             _ => {}
         }
@@ -135,6 +242,13 @@ impl Setup {
                 Message::SetPassword,
                 submit_fn.clone()
             ),
+            input_field(
+                "2FA-Schlüssel (optional)",
+                &self.auth_secret,
+                true,
+                Message::SetAuthSecret,
+                submit_fn.clone()
+            ),
         ]
         .spacing(20)
         .width(Fixed(400.));
@@ -148,8 +262,29 @@ impl Setup {
         .padding([10, 20])
         .style(button::primary);
 
+        let cancel_button = self.cancelable.then(|| {
+            button(text("Abbrechen").size(24).color(color!(0xffffff)))
+                .on_press(Message::CancelSetup)
+                .padding([10, 20])
+                .style(button::danger)
+        });
+
+        // Clubs that don't use Vereinsflieger can manage articles and
+        // members locally instead
+        let offline_button = (!self.cancelable).then(|| {
+            button(text("Offline einrichten").size(24).color(color!(0xffffff)))
+                .on_press(Message::SetupOffline)
+                .padding([10, 20])
+                .style(button::secondary)
+        });
+
+        let buttons = iced::widget::row![submit_button]
+            .extend(cancel_button.map(Into::into))
+            .extend(offline_button.map(Into::into))
+            .spacing(20);
+
         container(
-            iced::widget::column![title, inputs, submit_button]
+            iced::widget::column![title, inputs, buttons]
                 .spacing(30)
                 .align_x(Center),
         )
@@ -160,7 +295,7 @@ impl Setup {
     }
 }
 
-fn input_field<'a>(
+pub fn input_field<'a>(
     label: &'a str,
     value: &'a str,
     secure: bool,
@@ -186,3 +321,31 @@ fn input_field<'a>(
     .spacing(10)
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_setup_failure() {
+        let check = |error: &str, expected: SetupFailure| {
+            assert_eq!(SetupFailure::classify(error), expected, "{error}");
+        };
+
+        check(
+            "error sending request for url (https://www.vereinsflieger.de/): dns error",
+            SetupFailure::Unreachable,
+        );
+        check("Invalid appkey", SetupFailure::InvalidAppKey);
+        check("Unknown CID", SetupFailure::WrongClubId);
+        check(
+            "Benutzername oder Passwort falsch",
+            SetupFailure::WrongPassword,
+        );
+        check(
+            "HTTP status client error (403 Forbidden) for url (https://www.vereinsflieger.de/)",
+            SetupFailure::WrongPassword,
+        );
+        check("Something went wrong", SetupFailure::Unknown);
+    }
+}
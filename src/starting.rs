@@ -1,14 +1,24 @@
+use crate::backup;
 use crate::database;
-use crate::state::{GlobalState, Message};
-use iced::futures::FutureExt;
+use crate::state::{GlobalState, Message, Options};
 use iced::{Subscription, Task};
+use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::SqlitePool;
-use tracing::{error, info};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// The maximum delay between two attempts to connect to the database.
+const MAX_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 #[derive(Debug)]
 pub struct StartingClubFridge {
     pub pool: Option<SqlitePool>,
     pub migrations_finished: bool,
+    /// Whether the database is corrupted and could not be repaired.
+    pub database_corrupted: bool,
+    /// The number of failed attempts to connect to the database, e.g.
+    /// because a network filesystem is not mounted yet.
+    pub failed_connection_attempts: u32,
 }
 
 impl StartingClubFridge {
@@ -16,6 +26,8 @@ impl StartingClubFridge {
         Self {
             pool: None,
             migrations_finished: false,
+            database_corrupted: false,
+            failed_connection_attempts: 0,
         }
     }
 
@@ -28,8 +40,27 @@ impl StartingClubFridge {
             Message::DatabaseConnected(pool) => {
                 info!("Connected to database");
                 self.pool = Some(pool.clone());
+                global_state.health.set_pool(pool.clone());
+
+                // The temporary database of the demo mode has no file
+                let path = Some(global_state.options.database().get_filename().to_path_buf())
+                    .filter(|_| !global_state.options.demo);
 
                 return Task::future(async move {
+                    // Restoring a backup is left to an admin, since
+                    // it can lose data that is only in the corrupted file
+                    if let Err(err) = check_integrity(&pool).await {
+                        error!("Database is corrupted: {err}");
+                        error!("Run `clubfridge-neo restore-backup` to restore the latest backup");
+                        return Message::DatabaseCorrupted;
+                    }
+
+                    if let Some(path) = &path {
+                        if let Err(err) = backup::create_before_migrations(&pool, path).await {
+                            warn!("Failed to back up database: {err}");
+                        }
+                    }
+
                     info!("Running database migrations…");
                     match sqlx::migrate!().run(&pool).await {
                         Ok(()) => Message::DatabaseMigrated,
@@ -41,7 +72,13 @@ impl StartingClubFridge {
                 });
             }
             Message::DatabaseConnectionFailed => {
-                error!("Failed to connect to database");
+                self.failed_connection_attempts += 1;
+                let delay = connect_retry_delay(self.failed_connection_attempts);
+                warn!(
+                    "Failed to connect to database ({} attempts), retrying in {delay:?}",
+                    self.failed_connection_attempts
+                );
+                return connect(&global_state.options, delay);
             }
             Message::DatabaseMigrated => {
                 info!("Database migrations finished");
@@ -50,36 +87,49 @@ impl StartingClubFridge {
                 if let Some(pool) = &self.pool {
                     let pool = pool.clone();
 
+                    if global_state.options.demo {
+                        return Task::future(async move {
+                            if let Err(err) = crate::demo::seed(&pool).await {
+                                error!("Failed to seed database with demo data: {err}");
+                            }
+                            Message::StartupComplete(pool, Vec::new())
+                        });
+                    }
+
                     if global_state.options.offline {
-                        return Task::done(Message::StartupComplete(pool, None));
+                        return start_offline(pool);
                     }
 
-                    let future =
-                        database::Credentials::find_first(pool.clone()).map(
-                            |result| match result {
-                                Ok(Some(credentials)) => Message::CredentialsFound(credentials),
-                                Ok(None) => {
-                                    info!(
-                                        "No credentials found in database, going to setup screen"
-                                    );
-                                    Message::GotoSetup(pool)
-                                }
-                                _ => Message::CredentialLookupFailed,
-                            },
-                        );
-
-                    return Task::future(future);
+                    return Task::future(async move {
+                        match database::Settings::offline(&pool).await {
+                            Ok(true) => return Message::EnableOfflineMode(pool),
+                            Ok(false) => {}
+                            Err(err) => warn!("Failed to load offline mode setting: {err}"),
+                        }
+
+                        match database::Credentials::find_all(pool.clone()).await {
+                            Ok(credentials) if credentials.is_empty() => {
+                                info!("No credentials found in database, going to setup screen");
+                                Message::GotoSetup(pool)
+                            }
+                            Ok(credentials) => Message::CredentialsFound(credentials),
+                            _ => Message::CredentialLookupFailed,
+                        }
+                    });
                 }
             }
             Message::DatabaseMigrationFailed => {
                 error!("Failed to run database migrations");
             }
+            Message::DatabaseCorrupted => {
+                self.database_corrupted = true;
+            }
             Message::CredentialsFound(credentials) => {
-                info!("Found credentials in database: {credentials:?}");
+                let club_ids = credentials.iter().map(|c| c.club_id).collect::<Vec<_>>();
+                info!("Found credentials in database for clubs {club_ids:?}");
 
                 if let Some(pool) = self.pool.take() {
-                    let vereinsflieger = vereinsflieger::Client::new(credentials.into());
-                    return Task::done(Message::StartupComplete(pool, Some(vereinsflieger)));
+                    return Task::done(Message::StartupComplete(pool, credentials));
                 }
             }
             Message::CredentialLookupFailed => {
@@ -91,3 +141,88 @@ impl StartingClubFridge {
         Task::none()
     }
 }
+
+/// Connect to the configured database after the given delay.
+pub fn connect(options: &Options, delay: Duration) -> Task<Message> {
+    let connect_options = options.database();
+    // The in-memory database of the demo mode is lost when the last
+    // connection is closed
+    let min_connections = u32::from(options.demo);
+
+    Task::future(async move {
+        tokio::time::sleep(delay).await;
+
+        info!("Connecting to database…");
+        let pool_options = SqlitePoolOptions::default().min_connections(min_connections);
+        match pool_options.connect_with(connect_options).await {
+            Ok(pool) => Message::DatabaseConnected(pool),
+            Err(err) => {
+                error!("Failed to connect to database: {err}");
+                Message::DatabaseConnectionFailed
+            }
+        }
+    })
+}
+
+/// The delay before the next attempt to connect to the database, which
+/// doubles with every failed attempt.
+fn connect_retry_delay(failed_attempts: u32) -> Duration {
+    let delay = Duration::from_secs(1 << failed_attempts.min(6));
+    delay.min(MAX_CONNECT_RETRY_DELAY)
+}
+
+/// Start without Vereinsflieger, going to the offline setup screen first if
+/// there are no articles yet.
+pub fn start_offline(pool: SqlitePool) -> Task<Message> {
+    Task::future(async move {
+        match database::Article::count(&pool).await {
+            Ok(0) => {
+                info!("No articles found in database, going to offline setup");
+                Message::GotoOfflineSetup(pool)
+            }
+            Ok(_) => Message::StartupComplete(pool, Vec::new()),
+            Err(err) => {
+                error!("Failed to count articles: {err}");
+                Message::StartupComplete(pool, Vec::new())
+            }
+        }
+    })
+}
+
+/// Check the integrity of the database and try to repair it if it is
+/// corrupted (e.g. because of a flaky SD card).
+async fn check_integrity(pool: &SqlitePool) -> anyhow::Result<()> {
+    info!("Checking database integrity…");
+    let problems = database::integrity_check(pool).await?;
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    for problem in &problems {
+        warn!("Database integrity problem: {problem}");
+    }
+
+    let problems = database::repair(pool).await?;
+    anyhow::ensure!(
+        problems.is_empty(),
+        "{} problems remain after repair",
+        problems.len()
+    );
+
+    info!("Database successfully repaired");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_retry_delay() {
+        assert_eq!(connect_retry_delay(1), Duration::from_secs(2));
+        assert_eq!(connect_retry_delay(2), Duration::from_secs(4));
+        assert_eq!(connect_retry_delay(5), Duration::from_secs(32));
+        assert_eq!(connect_retry_delay(6), MAX_CONNECT_RETRY_DELAY);
+        assert_eq!(connect_retry_delay(100), MAX_CONNECT_RETRY_DELAY);
+    }
+}
@@ -1,76 +1,830 @@
+use crate::admin::PendingSaleUpdate;
+use crate::announcement::Announcement;
+use crate::calendar;
+use crate::cli::Command;
+use crate::crash::{self, CrashReport};
+use crate::currency::CurrencyFormat;
 use crate::database;
-use crate::popup::Popup;
-use crate::running::RunningClubFridge;
-use crate::setup::Setup;
-use crate::starting::StartingClubFridge;
+use crate::datev::DatevAccount;
+use crate::discount::DiscountRule;
+use crate::events::EventStream;
+use crate::fonts;
+use crate::health::HealthStatus;
+use crate::logging::LogFormat;
+use crate::mqtt::MqttConfig;
+use crate::offline_setup::OfflineSetup;
+use crate::paths;
+use crate::popup::{Popup, Popups, Severity};
+use crate::running::{
+    AgeRestriction, Bundle, CostCenter, DailyArticleLimit, GroupPrice, RunningClubFridge,
+};
+use crate::scanner::{KeyBindings, KeyMap, SubmitKey};
+use crate::setup::{Setup, SetupFailure};
+use crate::starting::{self, StartingClubFridge};
+use crate::sync::{ArticleCategory, SyncWindow};
+use crate::system::{SystemInfo, UpdateCheck};
+use crate::texts::Texts;
+use crate::theme;
+use crate::ui::CustomerDisplay;
+use crate::update::{self, ReleaseNotes, UpdateBackend, UpdateConfig, UpdateRepo, UpdateStatus};
+use crate::verify::Verifier;
 use iced::keyboard::{Key, Modifiers};
 use iced::{application, window, Subscription, Task};
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use rust_decimal::Decimal;
+use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
+use ulid::Ulid;
 
 /// The interval at which the app should check for updates of itself.
 const SELF_UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
-#[derive(Debug, Default, clap::Parser)]
+/// The interval at which the app should compare the local clock against the
+/// configured time server.
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The interval at which the app should check if the scheduled daily restart
+/// is due.
+const RESTART_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval at which the app should check if the night colors should be
+/// switched on or off.
+const NIGHT_MODE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval at which the timeout of the current popup is checked.
+const POPUP_TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Default, Clone, clap::Parser)]
 pub struct Options {
+    /// Run a command without the GUI instead of starting the application
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Run in fullscreen
     #[arg(long)]
     fullscreen: bool,
 
-    /// Run in fullscreen
-    #[arg(long, default_value = "clubfridge.db?mode=rwc")]
-    database: SqliteConnectOptions,
+    /// An announcement text or the path of a PNG/JPEG image that is shown
+    /// on the idle screen (`\n` starts a new line), may be used multiple times
+    #[arg(long = "announcement", value_name = "TEXT_OR_IMAGE")]
+    pub announcements: Vec<Announcement>,
+
+    /// The number of seconds after which the next announcement is shown
+    #[arg(long, default_value_t = 15, value_name = "SECONDS")]
+    pub announcement_interval: u64,
+
+    /// The URL of an iCal feed whose upcoming events are shown on the idle
+    /// screen
+    #[arg(long)]
+    pub calendar_url: Option<String>,
+
+    /// The number of upcoming calendar events shown on the idle screen
+    #[arg(long, default_value_t = 3)]
+    pub calendar_events: usize,
+
+    /// The ID of an article that members get for free on their birthday (once
+    /// per day)
+    #[arg(long, value_name = "ARTICLE_ID")]
+    pub birthday_article: Option<String>,
+
+    /// The ID of the article to which redeemed vouchers are booked with a
+    /// negative price, which enables scanning vouchers
+    #[arg(long, value_name = "ARTICLE_ID")]
+    pub voucher_article: Option<String>,
+
+    /// Ask for the best-before date when restocking articles, and warn about
+    /// articles in the fridge that expire within this number of days
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(i64).range(0..=365))]
+    pub expiry_warning_days: Option<i64>,
+
+    /// The file of a temperature sensor in the fridge, either the `w1_slave`
+    /// file of a DS18B20 1-wire sensor or a file with the temperature in
+    /// millidegrees (e.g. `/sys/class/hwmon/hwmon0/temp1_input`)
+    #[arg(long, value_name = "PATH")]
+    pub temperature_sensor: Option<PathBuf>,
+
+    /// The temperature in °C above which an alert is shown
+    #[arg(long, default_value_t = 8.0)]
+    pub max_temperature: f64,
+
+    /// The GPIO value file of a door contact on the fridge
+    /// (e.g. `/sys/class/gpio/gpio17/value`)
+    #[arg(long, value_name = "PATH")]
+    pub door_sensor: Option<PathBuf>,
+
+    /// The door contact reads `0` instead of `1` while the door is open
+    #[arg(long)]
+    pub door_sensor_active_low: bool,
+
+    /// The number of seconds after which an alarm is raised if the fridge
+    /// door is still open
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub door_alarm_timeout: u64,
+
+    /// A sound file that is played with `aplay` while the door alarm is active
+    #[arg(long, value_name = "PATH")]
+    pub door_alarm_sound: Option<PathBuf>,
+
+    /// The free disk space in MB below which old log files and backups are
+    /// deleted and a warning is shown
+    #[arg(long, value_name = "MB", default_value_t = 100)]
+    pub min_free_disk_space: u64,
+
+    /// The number of failed upload attempts after which a sale is reported
+    /// as stuck
+    #[arg(long, value_name = "COUNT", default_value_t = 5)]
+    pub upload_alert_failures: u32,
+
+    /// The number of days after which a sale that was not uploaded yet is
+    /// reported as stuck
+    #[arg(long, value_name = "DAYS", default_value_t = 3)]
+    #[arg(value_parser = clap::value_parser!(i64).range(1..=365))]
+    pub upload_alert_days: i64,
+
+    /// An email address that is notified with `sendmail` when sales are stuck
+    #[arg(long, value_name = "ADDRESS")]
+    pub upload_alert_email: Option<String>,
+
+    /// A JSON file with customized texts for members (e.g. `texts.json`)
+    #[arg(long, value_name = "PATH")]
+    pub texts: Option<PathBuf>,
+
+    /// Show the cart and total for customers in an additional region of the
+    /// window, e.g. on a second screen that extends the desktop
+    #[arg(long, value_enum)]
+    pub customer_display: Option<CustomerDisplay>,
+
+    /// The SQLite database URL (default: `clubfridge.db` in the data
+    /// directory, e.g. `~/.local/share/clubfridge-neo`)
+    #[arg(long)]
+    pub database: Option<SqliteConnectOptions>,
 
     /// Run in offline mode (no network requests)
     #[arg(long)]
     pub offline: bool,
 
+    /// Run in demo mode with fake members and articles in a temporary
+    /// database, accepting any scanned keycode or barcode (implies `--offline`)
+    #[arg(long)]
+    pub demo: bool,
+
+    /// The club ID (CID) whose articles are synchronized, if credentials for
+    /// multiple clubs are stored (defaults to the first club)
+    #[arg(long, value_name = "CID")]
+    pub article_club: Option<u32>,
+
+    /// The club ID (CID) whose members are synchronized and to which sales
+    /// are uploaded, if credentials for multiple clubs are stored (defaults
+    /// to the first club)
+    #[arg(long, value_name = "CID")]
+    pub sales_club: Option<u32>,
+
+    /// The number of minutes between loading the articles and members from
+    /// Vereinsflieger
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        default_value_t = 360,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub sync_interval: u64,
+
+    /// The number of minutes between uploads of new sales to Vereinsflieger
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub upload_interval: u64,
+
+    /// Only send requests to Vereinsflieger within this daily time window
+    /// (e.g. `06:00-22:00`), to save requests of the daily limit
+    #[arg(long, value_name = "HH:MM-HH:MM")]
+    pub sync_hours: Option<SyncWindow>,
+
+    /// Check that uploaded sales were actually booked in Vereinsflieger
+    /// before marking them as uploaded. Needs additional API requests.
+    #[arg(long)]
+    pub verify_uploads: bool,
+
     /// When an application update is available, show an "Update" button that
     /// quits the application. Should only be used when the application is
     /// automatically restarted by a supervisor.
     #[arg(long)]
     pub update_button: bool,
+
+    /// Where to look for application updates, e.g. for clubs that mirror
+    /// the releases internally or have GitHub blocked
+    #[arg(long, value_enum, default_value_t)]
+    pub update_backend: UpdateBackend,
+
+    /// The GitHub or Gitea repository whose releases are used for
+    /// application updates, e.g. for clubs running their own fork
+    #[arg(
+        long,
+        value_name = "OWNER/REPO",
+        default_value = "Turbo87/clubfridge-neo"
+    )]
+    pub update_repo: UpdateRepo,
+
+    /// The name of the binary in the release assets of the update repository
+    #[arg(long, value_name = "NAME", default_value = "clubfridge-neo")]
+    pub update_bin_name: String,
+
+    /// The base URL of the Gitea server for the `gitea` update backend
+    #[arg(long, value_name = "URL", required_if_eq("update_backend", "gitea"))]
+    pub update_host: Option<String>,
+
+    /// The S3 bucket with the release archives for the `s3` update backend
+    #[arg(long, value_name = "BUCKET", required_if_eq("update_backend", "s3"))]
+    pub update_s3_bucket: Option<String>,
+
+    /// The region of the S3 bucket for the `s3` update backend
+    #[arg(long, value_name = "REGION", default_value = "eu-central-1")]
+    pub update_s3_region: String,
+
+    /// The key prefix of the release archives in the S3 bucket
+    #[arg(long, value_name = "PREFIX")]
+    pub update_s3_prefix: Option<String>,
+
+    /// Install this version (e.g. `0.11.0`) instead of the latest version,
+    /// to stay on a known-good version. Newer versions are still reported
+    /// on the system screen.
+    #[arg(long, value_name = "VERSION")]
+    pub pin_version: Option<String>,
+
+    /// Do not install this version (e.g. a problematic release), but still
+    /// install newer versions. Can be specified multiple times.
+    #[arg(long = "skip-version", value_name = "VERSION")]
+    pub skip_versions: Vec<String>,
+
+    /// The URL of the JSON release index for the `index` update backend
+    #[arg(long, value_name = "URL", required_if_eq("update_backend", "index"))]
+    pub update_index_url: Option<String>,
+
+    /// Ask for confirmation with the item count and total before booking
+    /// the cart
+    #[arg(long)]
+    pub confirm_payment: bool,
+
+    /// Show the countdown until the sale is automatically processed on the
+    /// button that is triggered, once fewer than this number of seconds are
+    /// remaining (0 disables the countdown)
+    #[arg(long, value_name = "SECONDS", default_value_t = 15)]
+    pub countdown_threshold: i64,
+
+    /// Show the countdown on both the "Cancel" and the "Pay" button for the
+    /// whole time until the sale is automatically processed
+    #[arg(long)]
+    pub countdown_always: bool,
+
+    /// Allow members to park their cart and resume it on their next login
+    /// within this number of minutes
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(i64).range(1..=1440))]
+    pub park_cart_minutes: Option<i64>,
+
+    /// Show a QR code with a digital receipt after each purchase
+    #[arg(long)]
+    pub receipt_qr: bool,
+
+    /// The directory into which the admin can export monthly statements
+    /// of all members as CSV files
+    #[arg(long, value_name = "DIR")]
+    pub statement_dir: Option<PathBuf>,
+
+    /// The number of days after which member IDs are removed from uploaded
+    /// sales, cash ledger entries, restocks and the audit log (should cover
+    /// the period of the monthly statements)
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(i64).range(1..=3650))]
+    pub member_data_retention_days: Option<i64>,
+
+    /// Delete the blocklist entries and manual keycodes of members that are
+    /// not in Vereinsflieger anymore after each sync
+    #[arg(long)]
+    pub purge_removed_members: bool,
+
+    /// A CSV file with the columns `keycode;member ID;name;nickname;email`,
+    /// from which the admin can replace the members in offline installations
+    #[arg(long, value_name = "PATH")]
+    pub member_csv: Option<PathBuf>,
+
+    /// Store the email addresses of imported and synced members, which should only be
+    /// enabled if the members agreed to be contacted
+    #[arg(long)]
+    pub store_member_emails: bool,
+
+    /// The name of the club, which collects the purchases of members via
+    /// SEPA direct debit as part of the monthly export
+    #[arg(long, requires_all = ["sepa_creditor_iban", "sepa_creditor_id", "sepa_mandate_date"])]
+    pub sepa_creditor_name: Option<String>,
+
+    /// The IBAN of the club for SEPA direct debits
+    #[arg(long)]
+    pub sepa_creditor_iban: Option<String>,
+
+    /// The SEPA creditor identifier (aka. "Gläubiger-ID") of the club
+    #[arg(long)]
+    pub sepa_creditor_id: Option<String>,
+
+    /// The date on which the members signed their direct debit mandates,
+    /// which use the member ID as mandate reference
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub sepa_mandate_date: Option<jiff::civil::Date>,
+
+    /// The booking account and tax rate of an article for the DATEV export
+    /// (e.g. `1234=8400:19`, or `*=8400:19` for all other articles), which
+    /// overrides the account and sales tax from Vereinsflieger, may be used
+    /// multiple times
+    #[arg(long = "datev-account", value_name = "ARTICLE_ID=ACCOUNT:TAX_RATE")]
+    pub datev_accounts: Vec<DatevAccount>,
+
+    /// The account against which all sales are booked in the DATEV export,
+    /// which is written as part of the monthly export if configured
+    #[arg(long, requires_all = ["datev_consultant", "datev_client"])]
+    pub datev_contra_account: Option<String>,
+
+    /// The DATEV consultant number (aka. "Beraternummer")
+    #[arg(long)]
+    pub datev_consultant: Option<u32>,
+
+    /// The DATEV client number (aka. "Mandantennummer")
+    #[arg(long)]
+    pub datev_client: Option<u32>,
+
+    /// Allow paying in cash, which records the sale in a local cash ledger
+    /// instead of booking it to the member account
+    #[arg(long)]
+    pub cash_payment: bool,
+
+    /// The format of the log output
+    #[arg(long, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// A font file that is loaded in addition to the system fonts and the
+    /// fonts in the `fonts` directory of the data directory (e.g. for emoji),
+    /// may be used multiple times
+    #[arg(long = "font", value_name = "PATH")]
+    pub fonts: Vec<PathBuf>,
+
+    /// The family name of the font that is used for all texts
+    /// (e.g. `Noto Sans`)
+    #[arg(long, value_name = "NAME")]
+    pub default_font: Option<String>,
+
+    /// The format of prices on the screen
+    #[arg(long, value_enum, default_value_t)]
+    pub currency_format: CurrencyFormat,
+
+    /// Write sanitized Vereinsflieger API requests and responses to separate
+    /// `vf-debug` log files
+    #[arg(long)]
+    pub vf_debug: bool,
+
+    /// Replace member names and keycodes with hashes in the log output,
+    /// keeping only the member IDs readable
+    #[arg(long)]
+    pub pseudonymize_logs: bool,
+
+    /// Send all HTTP and HTTPS requests through this proxy (e.g.
+    /// `http://proxy.example.com:3128`). Without this option, the proxy is
+    /// read from the `HTTPS_PROXY` and `HTTP_PROXY` environment variables.
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Serve `/healthz` and `/metrics` HTTP endpoints on this address
+    /// (e.g. `0.0.0.0:8080`)
+    #[arg(long)]
+    pub health_address: Option<SocketAddr>,
+
+    /// Stream cart and sale events as JSON over a WebSocket at `/events` on
+    /// this address (e.g. `0.0.0.0:8082`)
+    #[arg(long)]
+    pub events_address: Option<SocketAddr>,
+
+    /// Append the keyboard input and button presses to this file as a
+    /// replay script, with keycodes and PINs anonymized
+    #[arg(long, value_name = "FILE")]
+    pub record_input: Option<PathBuf>,
+
+    /// Upload screenshots that are taken on the admin screen to this URL
+    /// via HTTP POST, so that remote helpers can see them
+    #[arg(long, value_name = "URL")]
+    pub support_upload_url: Option<String>,
+
+    /// Publish sensors like the pending sales and the fridge temperature to
+    /// this MQTT broker (e.g. `homeassistant.local:1883`), with Home
+    /// Assistant discovery
+    #[arg(long, value_name = "HOST:PORT")]
+    pub mqtt_address: Option<String>,
+
+    /// The username for the MQTT broker
+    #[arg(long)]
+    pub mqtt_username: Option<String>,
+
+    /// The password for the MQTT broker, preferably passed via the
+    /// environment to keep it out of the process list
+    #[arg(
+        long,
+        env = "MQTT_PASSWORD",
+        hide_env_values = true,
+        requires = "mqtt_username"
+    )]
+    pub mqtt_password: Option<String>,
+
+    /// The prefix of the Home Assistant MQTT discovery topics
+    #[arg(long, default_value = "homeassistant")]
+    pub mqtt_discovery_prefix: String,
+
+    /// The ID of this fridge in Home Assistant and the MQTT topics
+    #[arg(long, default_value = "clubfridge")]
+    pub mqtt_node_id: String,
+
+    /// Quit the application every day at this local time (e.g. `04:00`).
+    /// Should only be used when the application is automatically restarted
+    /// by a supervisor.
+    #[arg(long, value_name = "HH:MM")]
+    pub restart_daily_at: Option<jiff::civil::Time>,
+
+    /// Dim the colors of the user interface from this local time on (e.g.
+    /// `22:00`) until `--night-end`
+    #[arg(long, value_name = "HH:MM", requires = "night_end")]
+    pub night_start: Option<jiff::civil::Time>,
+
+    /// The local time at which the dimmed night colors end (e.g. `06:00`)
+    #[arg(long, value_name = "HH:MM", requires = "night_start")]
+    pub night_end: Option<jiff::civil::Time>,
+
+    /// Dim the colors based on an ambient light sensor file with the
+    /// illuminance in lux instead (e.g. `/sys/bus/iio/devices/iio:device0/in_illuminance_input`)
+    #[arg(long, value_name = "PATH")]
+    pub light_sensor: Option<PathBuf>,
+
+    /// Dim the colors while the ambient light sensor reads less than this
+    /// illuminance in lux
+    #[arg(long, value_name = "LUX", default_value_t = 10.)]
+    pub night_illuminance: f64,
+
+    /// The NTP server used to detect a wrong system clock
+    #[arg(long, default_value = "pool.ntp.org:123")]
+    pub time_server: String,
+
+    /// Show a warning if the system clock deviates from the time server by
+    /// more than this number of seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    pub max_clock_skew: u64,
+
+    /// Ignore identical scans within this number of milliseconds, which are
+    /// usually caused by the scanner reading the same barcode twice
+    #[arg(long, value_name = "MILLISECONDS", default_value_t = 300)]
+    pub scan_debounce: u64,
+
+    /// Strip this prefix from scanned input (e.g. `]E0`), may be
+    /// used multiple times
+    #[arg(long = "scanner-prefix", value_name = "PREFIX")]
+    pub scanner_prefixes: Vec<String>,
+
+    /// Strip this suffix from scanned input, may be used multiple times
+    #[arg(long = "scanner-suffix", value_name = "SUFFIX")]
+    pub scanner_suffixes: Vec<String>,
+
+    /// Translate characters of the scanned input to fix keyboard layout
+    /// mismatches, either `de` or a list of pairs like `z=y,y=z`
+    #[arg(long, value_name = "MAPPING")]
+    pub scanner_remap: Option<KeyMap>,
+
+    /// Scanned input starting with this prefix is treated as a printed
+    /// member card, with the member ID following the prefix
+    #[arg(long, value_name = "PREFIX")]
+    pub member_card_prefix: Option<String>,
+
+    /// The secret with which the login QR codes of members are signed, see
+    /// the `qr-login-code` command
+    #[arg(long, value_name = "SECRET")]
+    pub qr_login_secret: Option<String>,
+
+    /// Entering this PIN after pressing the admin button or key opens the
+    /// admin screen (ignored once a TOTP secret was created with the
+    /// `admin-totp` command)
+    #[arg(long)]
+    pub admin_pin: Option<String>,
+
+    /// The SumUp API key used to charge guests by card, preferably passed
+    /// via the environment to keep it out of the process list
+    #[arg(
+        long,
+        env = "SUMUP_API_KEY",
+        hide_env_values = true,
+        requires_all = ["sumup_merchant_code", "sumup_reader_id"]
+    )]
+    pub sumup_api_key: Option<String>,
+
+    /// The SumUp merchant code of the club
+    #[arg(long)]
+    pub sumup_merchant_code: Option<String>,
+
+    /// The ID of the paired SumUp card terminal
+    #[arg(long)]
+    pub sumup_reader_id: Option<String>,
+
+    /// The IBAN that guests can transfer money to via EPC QR code
+    #[arg(long, requires = "transfer_name")]
+    pub transfer_iban: Option<String>,
+
+    /// The account holder of the IBAN that guests can transfer money to
+    #[arg(long)]
+    pub transfer_name: Option<String>,
+
+    /// The PayPal.me name that guests can send money to, if no IBAN
+    /// is configured
+    #[arg(long, value_name = "NAME")]
+    pub paypal_me: Option<String>,
+
+    /// Only sell the article with the given ID to members of at least the
+    /// given age (e.g. `1234=18`), may be used multiple times
+    #[arg(long = "age-restriction", value_name = "ARTICLE_ID=AGE")]
+    pub age_restrictions: Vec<AgeRestriction>,
+
+    /// Sell age-restricted articles to members and guests whose birthday is
+    /// unknown, instead of refusing them
+    #[arg(long)]
+    pub allow_unknown_age: bool,
+
+    /// The article ID that is booked when a member enters a free-form
+    /// amount for items without barcode (shown as "Sonstiges")
+    #[arg(long, value_name = "ARTICLE_ID")]
+    pub open_price_article: Option<String>,
+
+    /// The article ID that is booked when a member rounds up their total to
+    /// the next Euro as a donation (e.g. for the youth fund)
+    #[arg(long, value_name = "ARTICLE_ID")]
+    pub round_up_article: Option<String>,
+
+    /// The label of the round-up option at checkout
+    #[arg(
+        long,
+        value_name = "TEXT",
+        default_value = "Aufrunden für die Jugendkasse"
+    )]
+    pub round_up_label: String,
+
+    /// An article ID of a credit article (e.g. "Guthaben 10€"), which tops
+    /// up the prepaid balance of the member, may be used multiple times.
+    /// Purchases on account are paid from the balance first, which is booked
+    /// to the first credit article with a negative price
+    #[arg(long = "credit-article", value_name = "ARTICLE_ID")]
+    pub credit_articles: Vec<String>,
+
+    /// A bundle barcode that adds multiple units of an article to the cart
+    /// (e.g. `4008501011009=1234*12` for a crate of 12 bottles), may be used
+    /// multiple times
+    #[arg(long = "bundle", value_name = "BARCODE=ARTICLE_ID*AMOUNT")]
+    pub bundles: Vec<Bundle>,
+
+    /// A price of an article for members of a specific member group
+    /// (e.g. `1234:Jugend=1.00`), optionally only valid within a date range
+    /// (e.g. `1234:Jugend=1.00@2025-01-01..2025-12-31`), may be used
+    /// multiple times
+    #[arg(long = "group-price", value_name = "ARTICLE_ID:GROUP=PRICE[@FROM..TO]")]
+    pub group_prices: Vec<GroupPrice>,
+
+    /// A club account or event cost center to which members can book their
+    /// purchase once an admin allowed it (e.g. `Flugplatzfest=9001:4711`),
+    /// may be used multiple times
+    #[arg(long = "cost-center", value_name = "NAME=MEMBER_ID[:COST_TYPE]")]
+    pub cost_centers: Vec<CostCenter>,
+
+    /// A volume discount for an article, either every n-th unit for free
+    /// (e.g. `1234*10=free`) or a lower unit price from a minimum amount
+    /// (e.g. `1234*12=0.90`), may be used multiple times
+    #[arg(
+        long = "discount",
+        value_name = "ARTICLE_ID*AMOUNT=PRICE",
+        requires = "discount_article"
+    )]
+    pub discounts: Vec<DiscountRule>,
+
+    /// The ID of the article to which the volume discounts are booked with
+    /// a negative price
+    #[arg(long, value_name = "ARTICLE_ID")]
+    pub discount_article: Option<String>,
+
+    /// The maximum amount of money a member may spend per day
+    #[arg(long, value_name = "EURO")]
+    pub daily_spending_limit: Option<Decimal>,
+
+    /// The maximum amount of an article a member may buy per day
+    /// (e.g. `1234=2`), may be used multiple times
+    #[arg(long = "daily-article-limit", value_name = "ARTICLE_ID=AMOUNT")]
+    pub daily_article_limits: Vec<DailyArticleLimit>,
+
+    /// The category of a group of articles (e.g. `Getränke=1001,1002`),
+    /// which overrides the category from Vereinsflieger on synchronization,
+    /// may be used multiple times
+    #[arg(long = "article-category", value_name = "CATEGORY=ARTICLE_IDS")]
+    pub article_categories: Vec<ArticleCategory>,
+
+    /// Group the articles in the cart by their category, in the order of
+    /// the `--article-category` options followed by the other categories in
+    /// alphabetical order
+    #[arg(long)]
+    pub group_cart_by_category: bool,
+
+    /// The keys that submit the scanned input
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "enter")]
+    pub submit_keys: Vec<SubmitKey>,
+
+    /// Keys of a hardware keypad that trigger an action instead of being
+    /// scanned, as a list of pairs like `+=pay,-=cancel,*=admin`
+    #[arg(long, value_name = "BINDINGS")]
+    pub key_bindings: Option<KeyBindings>,
+}
+
+impl Options {
+    /// The verifier of uploaded sales for the credentials of the sales club,
+    /// if `--verify-uploads` is set.
+    pub fn upload_verifier(&self, credentials: &[database::Credentials]) -> Option<Verifier> {
+        if !self.verify_uploads {
+            return None;
+        }
+
+        let credentials = match self.sales_club {
+            Some(club_id) => credentials.iter().find(|c| c.club_id == club_id),
+            None => credentials.first(),
+        };
+
+        Some(Verifier::new(credentials?.clone()))
+    }
+
+    /// Where and how to look for application updates.
+    pub fn update_config(&self) -> UpdateConfig {
+        UpdateConfig {
+            backend: self.update_backend,
+            repo: self.update_repo.clone(),
+            bin_name: self.update_bin_name.clone(),
+            host: self.update_host.clone(),
+            s3_bucket: self.update_s3_bucket.clone(),
+            s3_region: self.update_s3_region.clone(),
+            s3_prefix: self.update_s3_prefix.clone(),
+            index_url: self.update_index_url.clone(),
+            pin_version: self.pin_version.as_deref().map(strip_version_prefix),
+            skip_versions: self
+                .skip_versions
+                .iter()
+                .map(|v| strip_version_prefix(v))
+                .collect(),
+        }
+    }
+
+    /// The connect options of the configured database, or of the default
+    /// database in the data directory.
+    pub fn database(&self) -> SqliteConnectOptions {
+        self.database
+            .clone()
+            .unwrap_or_else(paths::default_database)
+    }
+
+    /// Whether the colors are dimmed at night, either based on the time of
+    /// day or on an ambient light sensor.
+    pub fn night_mode_enabled(&self) -> bool {
+        self.light_sensor.is_some() || (self.night_start.is_some() && self.night_end.is_some())
+    }
+
+    /// The connection details of the MQTT broker, if one is configured.
+    pub fn mqtt_config(&self) -> Option<MqttConfig> {
+        Some(MqttConfig {
+            address: self.mqtt_address.clone()?,
+            username: self.mqtt_username.clone(),
+            password: self.mqtt_password.clone(),
+            discovery_prefix: self.mqtt_discovery_prefix.clone(),
+            node_id: self.mqtt_node_id.clone(),
+        })
+    }
 }
 
 pub struct GlobalState {
     pub options: Options,
 
+    /// The texts that are shown to members.
+    pub texts: Texts,
+
     /// The updated app version, if the app has been updated.
     pub self_updated: Option<String>,
 
-    pub popup: Option<Popup>,
+    pub popups: Popups,
+
+    /// The shared status reported by the `/healthz` endpoint and the
+    /// MQTT sensors.
+    pub health: HealthStatus,
+
+    /// The stream of cart and sale events for WebSocket clients.
+    pub events: EventStream,
+
+    /// The time at which the application should quit, if a daily restart
+    /// is configured.
+    pub restart_at: Option<jiff::Zoned>,
+
+    /// The detected deviation of the system clock, if it exceeds the
+    /// configured threshold.
+    pub clock_skew: Option<jiff::SignedDuration>,
+
+    /// Whether the dimmed night colors are currently shown.
+    pub night_mode: bool,
+
+    /// The time at which the application was started.
+    pub started_at: jiff::Timestamp,
+
+    /// The result of the last check for app updates.
+    pub last_update_check: Option<UpdateCheck>,
+
+    /// The release notes that are shown after the app has been updated.
+    pub release_notes: Option<ReleaseNotes>,
+
+    /// The panic of the previous run, if the app was restarted after it.
+    pub crash_report: Option<CrashReport>,
 }
 
 impl GlobalState {
     fn self_update(&self) -> Task<Message> {
         let self_updated = self.self_updated.clone();
+        let config = self.options.update_config();
         Task::future(async move {
-            let result = self_update(self_updated).await;
+            let result = update::run(config, self_updated).await;
             let result = result.map_err(Arc::new);
             Message::SelfUpdateResult(result)
         })
     }
 
-    /// Show a popup message to the user with the default timeout.
-    pub fn show_popup(&mut self, message: impl Into<String>) -> Task<Message> {
-        let message = message.into();
+    fn check_clock(&self) -> Task<Message> {
+        let server = self.options.time_server.clone();
+        Task::future(async move {
+            debug!("Checking system clock against {server}…");
+            let result = crate::clock::query_skew(&server).await;
+            let result = result.map_err(Arc::new);
+            Message::ClockCheckResult(result)
+        })
+    }
+
+    /// Check whether the night colors should be shown, based on the ambient
+    /// light sensor or on the time of day.
+    fn check_night_mode(&mut self) -> Task<Message> {
+        if let Some(path) = self.options.light_sensor.clone() {
+            return Task::future(async move {
+                let result = theme::read_light_sensor(&path).await;
+                Message::LightSensorRead(result.map_err(Arc::new))
+            });
+        }
 
-        debug!("Showing popup: {message}");
-        let (popup, task) = Popup::new(message).with_timeout();
+        if let (Some(start), Some(end)) = (self.options.night_start, self.options.night_end) {
+            let now = jiff::Zoned::now().time();
+            self.set_night_mode(theme::is_night(now, start, end));
+        }
 
-        self.popup = Some(popup);
-        task
+        Task::none()
     }
 
-    /// Hide the currently shown popup, if any.
-    pub fn hide_popup(&mut self) {
-        if self.popup.take().is_some() {
-            debug!("Hiding popup");
+    fn set_night_mode(&mut self, night_mode: bool) {
+        if night_mode != self.night_mode {
+            info!(night_mode, "Switching night colors");
+            self.night_mode = night_mode;
         }
     }
+
+    /// Show an informational popup message to the user.
+    pub fn show_popup(&mut self, message: impl Into<String>) {
+        self.popups.push(Popup::new(message, Severity::Info));
+    }
+
+    /// Show a popup message to the user that confirms a successful action.
+    pub fn show_success(&mut self, message: impl Into<String>) {
+        self.popups.push(Popup::new(message, Severity::Success));
+    }
+
+    /// Show an error popup message to the user, which is shown longer and
+    /// is not hidden by further user input.
+    pub fn show_error(&mut self, message: impl Into<String>) {
+        self.popups.push(Popup::new(message, Severity::Error));
+    }
+
+    /// Show an error popup for an unexpected error, including a short error
+    /// ID that the caller should log together with the error, so that an
+    /// error reported by a member can be found in the log.
+    pub fn show_internal_error(&mut self, message: &str) -> String {
+        let error_id = error_id();
+        self.show_error(format!("{message} (Fehler {error_id})"));
+        error_id
+    }
+
+    /// Hide the currently shown popup because of user input, unless it is
+    /// an error.
+    pub fn hide_popup(&mut self) {
+        self.popups.dismiss();
+    }
 }
 
 pub struct ClubFridge {
@@ -89,55 +843,146 @@ pub enum State {
     /// database.
     Setup(Setup),
 
+    /// The application is in the offline setup screen, where the user can
+    /// create the first articles. This state is only shown in offline mode
+    /// if there are no articles in the database.
+    OfflineSetup(OfflineSetup),
+
     /// The application is running and the user can interact with it.
-    Running(RunningClubFridge),
+    Running(Box<RunningClubFridge>),
 }
 
 impl ClubFridge {
-    pub fn run() -> iced::Result {
-        let options = <Options as clap::Parser>::parse();
+    pub fn run(options: Options) -> iced::Result {
+        let fullscreen = options.fullscreen;
+
+        let mut size = iced::Size::new(800., 480.);
+        if let Some(customer_display) = options.customer_display {
+            size = customer_display.window_size(size);
+        }
+
+        let fonts = fonts::load(&options.fonts);
+        // The font name needs to live for the whole runtime of the app
+        let default_font = options
+            .default_font
+            .clone()
+            .map(|name| iced::Font::with_name(Box::leak(name.into_boxed_str())));
 
-        application(Self::new_from_clap, Self::update, Self::view)
+        let mut app = application(move || Self::new(options.clone()), Self::update, Self::view)
             .theme(Self::theme)
             .subscription(Self::subscription)
             .resizable(true)
             .window(window::Settings {
-                size: (800., 480.).into(),
-                fullscreen: options.fullscreen,
+                size,
+                fullscreen,
                 ..Default::default()
-            })
-            .run()
-    }
+            });
+
+        for font in fonts {
+            app = app.font(font);
+        }
+        if let Some(font) = default_font {
+            app = app.default_font(font);
+        }
 
-    fn new_from_clap() -> (Self, Task<Message>) {
-        let options = <Options as clap::Parser>::parse();
-        Self::new(options)
+        app.run()
     }
 
-    pub fn new(options: Options) -> (Self, Task<Message>) {
-        let connect_options = options.database.clone();
-        let connect_task = Task::future(async move {
-            info!("Connecting to database…");
-            let pool_options = SqlitePoolOptions::default();
-            match pool_options.connect_with(connect_options).await {
-                Ok(pool) => Message::DatabaseConnected(pool),
-                Err(err) => {
-                    error!("Failed to connect to database: {err}");
-                    Message::DatabaseConnectionFailed
-                }
-            }
-        });
+    pub fn new(mut options: Options) -> (Self, Task<Message>) {
+        if options.demo {
+            info!("Running in demo mode");
+            options.offline = true;
+            options.database = Some(
+                SqliteConnectOptions::from_str(":memory:")
+                    .expect("in-memory database options should be valid"),
+            );
+        }
+
+        let connect_task = starting::connect(&options, Duration::ZERO);
 
         let popup_message = format!("clubfridge-neo v{} gestartet", env!("CARGO_PKG_VERSION"));
-        let (popup, popup_task) = Popup::new(popup_message).with_timeout();
-        let popup = Some(popup);
+        let mut popups = Popups::default();
+        popups.push(Popup::new(popup_message, Severity::Info));
+
+        let started_at = jiff::Timestamp::now();
+        let health = HealthStatus::default();
+        health.set_started_at(started_at);
+        let events = EventStream::default();
+
+        let mut startup_tasks = vec![connect_task, Task::done(Message::SelfUpdate)];
+        if !options.offline {
+            startup_tasks.push(Task::done(Message::CheckClock));
+        }
+        if options.night_mode_enabled() {
+            startup_tasks.push(Task::done(Message::CheckNightMode));
+        }
+        if !options.offline {
+            let config = options.update_config();
+            startup_tasks.push(Task::future(async move {
+                let result = update::pending_release_notes(config).await;
+                Message::ReleaseNotesLoaded(result.map_err(Arc::new))
+            }));
+        }
+        if let Some(address) = options.health_address {
+            let health = health.clone();
+            startup_tasks.push(
+                Task::future(async move {
+                    if let Err(err) = crate::health::serve(address, health).await {
+                        error!("Health endpoint failed: {err}");
+                    }
+                })
+                .discard(),
+            );
+        }
+
+        if let Some(address) = options.events_address {
+            let events = events.clone();
+            startup_tasks.push(
+                Task::future(async move {
+                    if let Err(err) = crate::events::serve(address, events).await {
+                        error!("Event stream failed: {err}");
+                    }
+                })
+                .discard(),
+            );
+        }
 
-        let startup_task = Task::batch([connect_task, popup_task, Task::done(Message::SelfUpdate)]);
+        if let Some(config) = options.mqtt_config() {
+            let health = health.clone();
+            startup_tasks.push(Task::future(crate::mqtt::run(config, health)).discard());
+        }
+
+        let restart_at = options.restart_daily_at.and_then(|time| {
+            next_restart(&jiff::Zoned::now(), time)
+                .inspect(|restart_at| info!("Scheduled restart at {restart_at}"))
+                .inspect_err(|err| error!("Failed to schedule daily restart: {err}"))
+                .ok()
+        });
+
+        let texts = options
+            .texts
+            .as_deref()
+            .and_then(|path| {
+                Texts::load(path)
+                    .inspect_err(|err| error!("Failed to load texts, using defaults: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default();
 
         let global_state = GlobalState {
             options,
+            texts,
             self_updated: None,
-            popup,
+            popups,
+            health,
+            events,
+            restart_at,
+            clock_skew: None,
+            night_mode: false,
+            started_at,
+            last_update_check: None,
+            release_notes: None,
+            crash_report: crash::take_report(),
         };
 
         let cf = Self {
@@ -145,31 +990,73 @@ impl ClubFridge {
             state: State::Starting(StartingClubFridge::new()),
         };
 
-        (cf, startup_task)
+        (cf, Task::batch(startup_tasks))
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
         let subscription = match &self.state {
             State::Starting(cf) => cf.subscription(),
             State::Setup(cf) => cf.subscription(),
+            State::OfflineSetup(cf) => cf.subscription(),
             State::Running(cf) => cf.subscription(),
         };
 
-        Subscription::batch([
+        let mut subscriptions = vec![
             subscription,
             iced::time::every(SELF_UPDATE_INTERVAL).map(|_| Message::SelfUpdate),
-        ])
+        ];
+
+        if !self.global_state.options.offline {
+            subscriptions
+                .push(iced::time::every(CLOCK_CHECK_INTERVAL).map(|_| Message::CheckClock));
+        }
+
+        if self.global_state.options.night_mode_enabled() {
+            subscriptions.push(
+                iced::time::every(NIGHT_MODE_CHECK_INTERVAL).map(|_| Message::CheckNightMode),
+            );
+        }
+
+        if self.global_state.restart_at.is_some() {
+            subscriptions
+                .push(iced::time::every(RESTART_CHECK_INTERVAL).map(|_| Message::CheckRestart));
+        }
+
+        if self.global_state.popups.current().is_some() {
+            subscriptions.push(iced::time::every(POPUP_TICK_INTERVAL).map(Message::PopupTick));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::GotoSetup(pool) => {
-                self.state = State::Setup(Setup::new(pool));
+                self.state = State::Setup(Setup::new(pool, false));
+            }
+
+            Message::GotoOfflineSetup(pool) => {
+                self.state = State::OfflineSetup(OfflineSetup::new(pool));
+            }
+
+            Message::EnableOfflineMode(pool) => {
+                info!("Offline mode was enabled on the setup screen");
+                self.global_state.options.offline = true;
+                return starting::start_offline(pool);
             }
 
-            Message::StartupComplete(pool, vereinsflieger) => {
-                let (cf, task) = RunningClubFridge::new(pool, vereinsflieger);
-                self.state = State::Running(cf);
+            Message::AddClub => {
+                if let State::Running(cf) = &self.state {
+                    info!("Opening setup screen to add another club");
+                    self.state = State::Setup(Setup::new(cf.pool.clone(), true));
+                }
+            }
+
+            Message::StartupComplete(pool, credentials) => {
+                let options = &self.global_state.options;
+                let events = self.global_state.events.clone();
+                let (cf, task) = RunningClubFridge::new(pool, credentials, options, events);
+                self.state = State::Running(Box::new(cf));
                 return task;
             }
 
@@ -177,32 +1064,128 @@ impl ClubFridge {
                 return self.global_state.self_update();
             }
 
-            Message::SelfUpdateResult(result) => match result {
-                Ok(self_update::Status::Updated(version)) => {
-                    info!("App has been updated to version {version}");
-                    self.global_state.self_updated = Some(version);
-                }
-                Ok(self_update::Status::UpToDate(_)) => {
-                    info!("App is already up-to-date");
+            Message::SelfUpdateResult(result) => {
+                let mut task = Task::none();
+                let result = match result {
+                    Ok(UpdateStatus::Updated(version)) => {
+                        info!("App has been updated to version {version}");
+                        let result = format!("Aktualisiert auf v{version}");
+                        self.global_state.self_updated = Some(version.clone());
+                        task = Task::future(async move {
+                            if let Err(err) = update::remember_update(&version).await {
+                                warn!("Failed to remember update for the release notes: {err}");
+                            }
+                        })
+                        .discard();
+                        result
+                    }
+                    Ok(UpdateStatus::UpToDate) => {
+                        info!("App is already up-to-date");
+                        "Aktuell".to_string()
+                    }
+                    Ok(UpdateStatus::Available(version)) => {
+                        info!("Version {version} is available, but another version is pinned");
+                        format!("v{version} verfügbar (Version festgelegt)")
+                    }
+                    Err(err) => {
+                        warn!("Failed to check for updates: {err}");
+                        format!("Fehler: {err}")
+                    }
+                };
+
+                self.global_state.last_update_check = Some(UpdateCheck {
+                    checked_at: jiff::Timestamp::now(),
+                    result,
+                });
+
+                return task;
+            }
+
+            Message::ReleaseNotesLoaded(result) => match result {
+                Ok(release_notes) => self.global_state.release_notes = release_notes,
+                Err(err) => warn!("Failed to load release notes: {err:#}"),
+            },
+
+            Message::CloseReleaseNotes => {
+                self.global_state.release_notes = None;
+            }
+
+            Message::CloseCrashReport => {
+                self.global_state.crash_report = None;
+            }
+
+            Message::CheckClock => {
+                return self.global_state.check_clock();
+            }
+
+            Message::ClockCheckResult(result) => match result {
+                Ok(skew) => {
+                    let max_skew = self.global_state.options.max_clock_skew as i64;
+                    if skew.as_secs().abs() > max_skew {
+                        warn!("System clock deviates from time server by {skew:?}");
+                        self.global_state.clock_skew = Some(skew);
+                    } else {
+                        debug!("System clock deviates from time server by {skew:?}");
+                        self.global_state.clock_skew = None;
+                    }
                 }
                 Err(err) => {
-                    warn!("Failed to check for updates: {err}");
+                    warn!("Failed to check system clock: {err}");
                 }
             },
 
-            Message::PopupTimeoutReached => {
-                self.global_state.hide_popup();
+            Message::CheckNightMode => {
+                return self.global_state.check_night_mode();
+            }
+
+            Message::LightSensorRead(result) => match result {
+                Ok(illuminance) => {
+                    let threshold = self.global_state.options.night_illuminance;
+                    let night = self.global_state.night_mode;
+                    self.global_state
+                        .set_night_mode(theme::is_dark(illuminance, threshold, night));
+                }
+                Err(err) => warn!("Failed to read light sensor: {err:#}"),
+            },
+
+            Message::PopupTick(now) => {
+                self.global_state.popups.tick(now);
+            }
+
+            Message::CheckRestart => {
+                let Some(restart_at) = &self.global_state.restart_at else {
+                    return Task::none();
+                };
+
+                if jiff::Zoned::now() < *restart_at {
+                    return Task::none();
+                }
+
+                if let State::Running(cf) = &self.state {
+                    if cf.user.is_some() {
+                        debug!("Postponing scheduled restart while a sale is in progress");
+                        return Task::none();
+                    }
+                }
+
+                info!("Scheduled restart time reached");
+                return Task::done(Message::Shutdown);
             }
 
             Message::Shutdown => {
                 info!("Shutting down…");
-                return window::latest().and_then(window::close);
+                let close_task = window::latest().and_then(window::close);
+                return match &mut self.state {
+                    State::Running(cf) => cf.shutdown().chain(close_task),
+                    _ => close_task,
+                };
             }
 
             message => {
                 return match &mut self.state {
                     State::Starting(cf) => cf.update(message, &mut self.global_state),
                     State::Setup(cf) => cf.update(message, &mut self.global_state),
+                    State::OfflineSetup(cf) => cf.update(message, &mut self.global_state),
                     State::Running(cf) => cf.update(message, &mut self.global_state),
                 }
             }
@@ -212,25 +1195,28 @@ impl ClubFridge {
     }
 }
 
-async fn self_update(self_updated: Option<String>) -> anyhow::Result<self_update::Status> {
-    let status = tokio::task::spawn_blocking(move || {
-        info!("Checking for updates…");
-
-        let current_version = self_updated.as_deref().unwrap_or(env!("CARGO_PKG_VERSION"));
-
-        self_update::backends::github::Update::configure()
-            .repo_owner("Turbo87")
-            .repo_name("clubfridge-neo")
-            .bin_name("clubfridge-neo")
-            .current_version(current_version)
-            .show_output(false)
-            .no_confirm(true)
-            .build()?
-            .update()
-    })
-    .await??;
-
-    Ok(status)
+/// A random, short ID of an error, e.g. `7F3A`.
+fn error_id() -> String {
+    format!("{:04X}", Ulid::new().random() as u16)
+}
+
+/// Strip the `v` prefix of a version, so that e.g. both `v0.11.0` and
+/// `0.11.0` can be passed to `--pin-version`.
+fn strip_version_prefix(version: &str) -> String {
+    version.trim_start_matches('v').to_string()
+}
+
+/// Calculate the next point in time after `now` at which the local clock
+/// shows the given `time`.
+fn next_restart(now: &jiff::Zoned, time: jiff::civil::Time) -> Result<jiff::Zoned, jiff::Error> {
+    let time_zone = now.time_zone().clone();
+
+    let today = now.date().to_datetime(time).to_zoned(time_zone.clone())?;
+    if today > *now {
+        return Ok(today);
+    }
+
+    now.date().tomorrow()?.to_datetime(time).to_zoned(time_zone)
 }
 
 #[derive(Debug, Clone)]
@@ -243,12 +1229,20 @@ pub enum Message {
     DatabaseMigrated,
     /// The database migrations failed.
     DatabaseMigrationFailed,
+    /// The database is corrupted and could not be repaired.
+    DatabaseCorrupted,
     /// Credentials were found in the database.
-    CredentialsFound(database::Credentials),
+    CredentialsFound(Vec<database::Credentials>),
     /// The user should be taken to the setup screen to enter their credentials.
     GotoSetup(SqlitePool),
     /// The database lookup for credentials failed.
     CredentialLookupFailed,
+    /// The user should be taken to the offline setup screen to create the
+    /// first articles.
+    GotoOfflineSetup(SqlitePool),
+    /// The installation runs without Vereinsflieger, because it was set up
+    /// offline on the setup screen.
+    EnableOfflineMode(SqlitePool),
 
     /// The user entered a club ID.
     SetClubId(String),
@@ -258,24 +1252,62 @@ pub enum Message {
     SetUsername(String),
     /// The user entered a password.
     SetPassword(String),
+    /// The user entered a two-factor authentication secret.
+    SetAuthSecret(String),
     /// The user submitted the setup form.
     SubmitSetup,
-    /// Authentication with Vereinsflieger failed.
-    AuthenticationFailed,
+    /// The user cancelled adding the credentials of another club.
+    CancelSetup,
+    /// The user wants to set up the installation without Vereinsflieger.
+    SetupOffline,
+    /// The offline mode could not be saved to the database.
+    SetupOfflineFailed,
+    /// Checking or saving the credentials failed for the given reason.
+    AuthenticationFailed(SetupFailure),
+
+    /// The user entered an article ID in the offline setup.
+    SetOfflineArticleId(String),
+    /// The user entered an article designation in the offline setup.
+    SetOfflineDesignation(String),
+    /// The user entered an article price in the offline setup.
+    SetOfflinePrice(String),
+    /// The user entered the path of a CSV file in the offline setup.
+    SetOfflineCsvPath(String),
+    /// The user submitted the article form of the offline setup.
+    AddOfflineArticle,
+    /// The user wants to import the articles from the CSV file.
+    ImportOfflineArticles,
+    /// The articles of the offline setup were saved to the database.
+    OfflineArticlesSaved(Result<Vec<database::Article>, Arc<anyhow::Error>>),
+    /// The user finished the offline setup.
+    FinishOfflineSetup,
 
     /// Authentication with Vereinsflieger was successful, the application is
     /// transitioning to the running state.
-    StartupComplete(SqlitePool, Option<vereinsflieger::Client>),
+    ///
+    /// The list of credentials is empty in offline mode.
+    StartupComplete(SqlitePool, Vec<database::Credentials>),
 
     /// The application should check for updates.
     SelfUpdate,
     /// The self-update check completed.
-    SelfUpdateResult(Result<self_update::Status, Arc<anyhow::Error>>),
+    SelfUpdateResult(Result<UpdateStatus, Arc<anyhow::Error>>),
+    /// Loading the release notes of the version that the app was updated to
+    /// finished.
+    ReleaseNotesLoaded(Result<Option<ReleaseNotes>, Arc<anyhow::Error>>),
+    /// The release notes after an update were closed.
+    CloseReleaseNotes,
+    /// The error screen after a restart because of a panic was closed.
+    CloseCrashReport,
     /// The application should load the latest lists of members and articles
     /// from the Vereinsflieger API.
     LoadFromVF,
     /// The application should upload all sales to Vereinsflieger.
     UploadSalesToVF,
+    /// All pending sales were uploaded to Vereinsflieger.
+    SalesUploaded,
+    /// Vereinsflieger rejected a request because too many requests were sent.
+    RateLimited,
     /// The application received a key press event.
     KeyPress(Key, Modifiers),
     /// A "find member by keycode" query finished.
@@ -286,21 +1318,301 @@ pub enum Message {
     /// A "find article by barcode" query finished.
     FindArticleResult {
         input: String,
+        /// The number of units to add, which is larger than one for bundles.
+        amount: u16,
+        result: Result<Option<database::Article>, Arc<sqlx::Error>>,
+    },
+    /// The admin started a stocktaking ("Inventur").
+    StartStocktaking,
+    /// A "find article by barcode" query for the stocktaking finished.
+    StocktakingArticleResult {
+        input: String,
+        /// The number of units to count, which is larger than one for
+        /// bundles.
+        amount: u16,
+        result: Result<Option<database::Article>, Arc<sqlx::Error>>,
+    },
+    /// The admin finished counting the articles.
+    FinishStocktaking,
+    /// The stocktaking was saved, with the shrinkage report.
+    StocktakingSaved(Result<Vec<(String, database::StockDifference)>, Arc<sqlx::Error>>),
+    /// The admin cancelled the stocktaking or closed its report.
+    CloseStocktaking,
+    /// The temperature sensor should be read.
+    ReadTemperature,
+    /// The temperature sensor was read, with the temperature in °C.
+    TemperatureRead(Result<f64, Arc<anyhow::Error>>),
+    /// Member IDs older than the retention period should be removed.
+    AnonymizeMemberData,
+    /// The free disk space should be checked.
+    CheckDiskSpace,
+    /// The free disk space was checked, with the free space in bytes.
+    DiskSpaceChecked(Result<u64, Arc<anyhow::Error>>),
+    /// The pending sales should be checked for sales that repeatedly failed
+    /// to upload.
+    CheckStuckSales,
+    /// The pending sales were checked, with the number of stuck sales.
+    StuckSalesChecked(Result<u32, Arc<sqlx::Error>>),
+    /// It should be checked whether Vereinsflieger is reachable.
+    CheckConnectivity,
+    /// The connectivity was checked, with whether Vereinsflieger is
+    /// reachable.
+    ConnectivityChecked(bool),
+    /// The door sensor should be read.
+    ReadDoorSensor,
+    /// The door sensor was read, with whether the door is open.
+    DoorSensorRead(Result<bool, Arc<anyhow::Error>>),
+    /// The batches in the fridge that expire soon should be looked up.
+    LoadExpiringBatches,
+    /// The lookup of the batches that expire soon finished.
+    ExpiringBatchesLoaded(Result<Vec<database::ExpiringBatch>, Arc<sqlx::Error>>),
+    /// The admin started restocking the fridge.
+    StartRestocking,
+    /// A "find member by keycode" query for the restocking finished.
+    RestockMemberResult {
+        input: String,
+        result: Result<Option<database::Member>, Arc<sqlx::Error>>,
+    },
+    /// A "find article by barcode" query for the restocking finished.
+    RestockArticleResult {
+        input: String,
+        /// The number of units that is suggested, which is larger than one
+        /// for bundles.
+        amount: u16,
+        result: Result<Option<database::Article>, Arc<sqlx::Error>>,
+    },
+    /// The number of restocked units on the numpad changed.
+    SetRestockQuantity(String),
+    /// The restocked number of units or the best-before date of the
+    /// scanned article was confirmed.
+    ConfirmRestock,
+    /// The best-before date on the numpad changed.
+    SetRestockBestBefore(String),
+    /// The scanned article was restocked without a best-before date.
+    SkipRestockBestBefore,
+    /// The restocking of the scanned article was cancelled.
+    CancelRestockArticle,
+    /// The restocked units were added to the stock.
+    RestockSaved {
+        designation: String,
+        amount: i64,
+        result: Result<i64, Arc<sqlx::Error>>,
+    },
+    /// The restocking is finished.
+    CloseRestocking,
+    /// A scanned barcode belongs to a voucher.
+    VoucherFound(database::Voucher),
+    /// The member wants to enter a free-form price for the open-price
+    /// article.
+    OpenPriceEntry,
+    /// The member changed the price on the numpad.
+    SetOpenPrice(String),
+    /// The member confirmed the entered price.
+    ConfirmOpenPrice,
+    /// The member closed the numpad without adding the article.
+    CancelOpenPrice,
+    /// The admin entered a member search query.
+    SetMemberSearch(String),
+    /// A "search members by name" query finished.
+    MemberSearchResult {
+        query: String,
+        result: Result<Vec<database::Member>, Arc<sqlx::Error>>,
+    },
+    /// The sales of the logged-in member from earlier today were loaded.
+    TodaysSalesLoaded {
+        member_id: String,
+        result: Result<Vec<database::Sale>, Arc<sqlx::Error>>,
+    },
+    /// The most recent purchase of the logged-in member was loaded.
+    LastPurchaseLoaded {
+        member_id: String,
+        result: Result<Vec<database::Sale>, Arc<sqlx::Error>>,
+    },
+    /// The member wants to add the articles of their previous purchase to
+    /// the cart again.
+    RepeatLastPurchase,
+    /// The highlight of the cart row whose amount changed should end.
+    ClearHighlight,
+    /// The free birthday article for the logged-in member was loaded.
+    BirthdayArticleLoaded {
+        member_id: String,
         result: Result<Option<database::Article>, Arc<sqlx::Error>>,
     },
-    /// The user pressed the "Pay" button.
+    /// The cart that the logged-in member parked earlier was loaded.
+    ParkedCartLoaded {
+        member_id: String,
+        result: Result<Option<database::Session>, Arc<sqlx::Error>>,
+    },
+    /// The member wants to park their cart and resume it later.
+    ParkCart,
+    /// The TOTP secret for opening the admin screen was loaded.
+    AdminTotpSecretLoaded(Result<Option<String>, Arc<sqlx::Error>>),
+    /// The member toggled rounding up their total to the next Euro.
+    ToggleRoundUp,
+    /// The admin allowed or disallowed booking purchases to cost centers.
+    ToggleCostCenters,
+    /// The member wants to book their purchase to a cost center.
+    ChooseCostCenter,
+    /// The member chose the cost center with the given index for their
+    /// purchase.
+    PayToCostCenter(usize),
+    /// The prepaid balance of the logged-in member was loaded.
+    BalanceLoaded {
+        member_id: String,
+        result: Result<Decimal, Arc<sqlx::Error>>,
+    },
+    /// The admin added a member to the blocklist or removed them from it.
+    SetMemberBlocked { member_id: String, blocked: bool },
+    /// The admin started editing the local nickname of a member.
+    EditNickname(database::Member),
+    /// The admin changed the edited nickname.
+    SetNickname(String),
+    /// The admin saved the edited nickname.
+    SaveNickname,
+    /// The admin discarded the edited nickname.
+    CancelNicknameEdit,
+    /// The admin manually logged in a member.
+    AdminLogin(database::Member),
+    /// The saved session of an unfinished purchase should be restored.
+    RestoreSession,
+    /// Loading the saved session finished.
+    SessionRestored(Result<Option<(database::Member, database::Session)>, Arc<sqlx::Error>>),
+    /// The admin started a refund for a member, so that scanned articles
+    /// are booked with negative amounts.
+    AdminRefund(database::Member),
+    /// The admin screen should be closed.
+    CloseAdmin,
+    /// The setup screen should be opened to add the credentials of
+    /// another club.
+    AddClub,
+    /// The user pressed the "Pay" button, or confirmed the payment.
     Pay,
+    /// The user went back from the payment confirmation to the cart.
+    CancelPayment,
+    /// The receipt QR code should be hidden.
+    CloseReceipt,
+    /// A guest wants to buy articles and pay by card.
+    StartGuestSale,
+    /// The card payment of a guest finished, returning the transaction ID.
+    CardPaymentResult(Result<String, Arc<anyhow::Error>>),
+    /// The guest confirmed that they transferred the money.
+    ConfirmTransfer,
+    /// The member or guest paid the cart in cash.
+    PayCash,
+    /// Loading the expected contents of the cash box finished.
+    CashBalanceLoaded(Result<Decimal, Arc<sqlx::Error>>),
+    /// The admin emptied the cash box.
+    EmptyCashBox,
+    /// The admin requested the monthly statements of the previous month.
+    ExportStatements,
+    /// Exporting the monthly statements finished, returning their number.
+    StatementsExported(Result<usize, Arc<anyhow::Error>>),
+    /// The admin requested a screenshot of the member screen for remote
+    /// support.
+    TakeScreenshot,
+    /// The screenshot of the window was captured.
+    ScreenshotTaken(window::Screenshot),
+    /// Saving (and uploading) the screenshot finished, returning the path
+    /// of the saved file.
+    ScreenshotSaved(Result<PathBuf, Arc<anyhow::Error>>),
+    /// The admin wants to see the system diagnostics.
+    ShowSystemInfo,
+    /// Collecting the system diagnostics finished.
+    SystemInfoLoaded(Box<SystemInfo>),
+    /// The admin closed the system diagnostics.
+    CloseSystemInfo,
+    /// The admin wants to see the most recent audit log entries.
+    ShowAuditLog,
+    /// Loading the audit log entries finished.
+    AuditLogLoaded(Result<Vec<database::AuditEntry>, Arc<sqlx::Error>>),
+    /// The admin closed the audit log.
+    CloseAuditLog,
+    /// The price changes that no admin has seen yet should be counted.
+    CountPriceChanges,
+    /// Counting the unseen price changes finished.
+    PriceChangesCounted(Result<u32, Arc<sqlx::Error>>),
+    /// The admin wants to see the most recent price changes.
+    ShowPriceChanges,
+    /// Loading the price changes finished, which marks them as seen.
+    PriceChangesLoaded(Result<Vec<database::PriceChange>, Arc<sqlx::Error>>),
+    /// The admin closed the price changes.
+    ClosePriceChanges,
+    /// The admin wants to see the sales that have not been uploaded yet.
+    ShowPendingSales,
+    /// Loading the pending sales and their article designations finished.
+    PendingSalesLoaded(Result<Vec<(database::Sale, String)>, Arc<sqlx::Error>>),
+    /// The admin started editing a pending sale.
+    EditPendingSale(Ulid),
+    /// The admin changed the amount of the edited pending sale.
+    SetPendingSaleAmount(i32),
+    /// The admin changed the member ID of the edited pending sale.
+    SetPendingSaleMember(String),
+    /// The admin saved the edited pending sale.
+    SavePendingSale,
+    /// Saving the edited pending sale finished.
+    PendingSaleSaved(Result<PendingSaleUpdate, Arc<sqlx::Error>>),
+    /// The admin discarded the changes of the edited pending sale.
+    CancelPendingSaleEdit,
+    /// The admin decided about a sale with an interrupted upload: `true`
+    /// uploads it again, `false` marks it as uploaded because it was found
+    /// in Vereinsflieger.
+    ResolveInterruptedSale(Ulid, bool),
+    /// Saving the decision about the interrupted sale finished, with
+    /// `false` if the sale was not waiting for a decision anymore.
+    InterruptedSaleResolved(Ulid, bool, Result<bool, Arc<sqlx::Error>>),
+    /// The sales of the successful card payment with the given transaction ID
+    /// could not be saved.
+    CardPaymentNotSaved(String),
+    /// The admin changed the code that confirms an admin action.
+    SetConfirmationCode(String),
+    /// The admin submitted the code that confirms an admin action.
+    SubmitConfirmationCode,
+    /// The admin cancelled the admin action instead of confirming it.
+    CancelConfirmation,
+    /// The admin button was pressed, which opens the admin screen once the
+    /// admin code was entered.
+    OpenAdmin,
+    /// An admin wants to lift the daily purchase limits for the logged-in
+    /// member, which happens once the admin code was entered.
+    LiftLimits,
+    /// The admin closed the pending sales.
+    ClosePendingSales,
+    /// The admin requested to import the members from the CSV file.
+    ImportMembers,
+    /// Importing the members finished, returning their number.
+    MembersImported(Result<usize, Arc<anyhow::Error>>),
+    /// The guest went back from the transfer QR code to the cart.
+    CancelTransfer,
     /// The user pressed the "Cancel" button.
     Cancel,
     /// Decrement the automatic sale timeout until it reaches zero.
     DecrementTimeout,
-    /// The popup timeout was reached, the popup should be closed.
-    PopupTimeoutReached,
+    /// Show the next announcement on the idle screen.
+    NextAnnouncement,
+    /// Load the upcoming events from the club calendar.
+    LoadCalendar,
+    /// The upcoming events from the club calendar were loaded.
+    CalendarLoaded(Result<Vec<calendar::Event>, Arc<anyhow::Error>>),
+    /// The timeout of the current popup should be checked.
+    PopupTick(Instant),
     /// Sales were successfully saved to the local database.
     SalesSaved,
     /// Saving sales to the local database failed.
     SavingSalesFailed,
 
+    /// The application should compare the system clock against the
+    /// time server.
+    CheckClock,
+    /// The system clock check completed, returning the deviation of the
+    /// system clock.
+    ClockCheckResult(Result<jiff::SignedDuration, Arc<anyhow::Error>>),
+    /// The application should check if the scheduled daily restart is due.
+    CheckRestart,
+    /// The application should check if the night colors should be shown.
+    CheckNightMode,
+    /// Reading the ambient light sensor finished, returning the
+    /// illuminance in lux.
+    LightSensorRead(Result<f64, Arc<anyhow::Error>>),
     /// The application should shut down.
     Shutdown,
 }
@@ -314,4 +1626,28 @@ mod tests {
         let (cf, _) = ClubFridge::new(Default::default());
         assert!(matches!(cf.state, State::Starting(_)));
     }
+
+    #[test]
+    fn test_error_id() {
+        let error_id = error_id();
+        assert_eq!(error_id.len(), 4);
+        assert!(error_id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_next_restart() {
+        let time = jiff::civil::time(4, 0, 0, 0);
+
+        let now: jiff::Zoned = "2025-03-01T21:00:00+00:00[UTC]".parse().unwrap();
+        let restart_at = next_restart(&now, time).unwrap();
+        assert_eq!(restart_at.to_string(), "2025-03-02T04:00:00+00:00[UTC]");
+
+        let now: jiff::Zoned = "2025-03-01T03:59:00+00:00[UTC]".parse().unwrap();
+        let restart_at = next_restart(&now, time).unwrap();
+        assert_eq!(restart_at.to_string(), "2025-03-01T04:00:00+00:00[UTC]");
+
+        let now: jiff::Zoned = "2025-03-01T04:00:00+00:00[UTC]".parse().unwrap();
+        let restart_at = next_restart(&now, time).unwrap();
+        assert_eq!(restart_at.to_string(), "2025-03-02T04:00:00+00:00[UTC]");
+    }
 }
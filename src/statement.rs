@@ -0,0 +1,121 @@
+use crate::database;
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+use tracing::info;
+
+/// Export monthly statements of all members for the month of the given
+/// date as CSV files into `<dir>/<YYYY-MM>/<member ID>.csv`.
+///
+/// Returns the number of exported statements.
+pub async fn export_month(
+    pool: &SqlitePool,
+    dir: &Path,
+    month: jiff::civil::Date,
+) -> anyhow::Result<usize> {
+    let start = month.first_of_month();
+    let end = month.last_of_month();
+
+    info!("Exporting monthly statements for {start} to {end}…");
+    let sales = database::Sale::load_between(pool, start, end).await?;
+    let designations = database::Article::load_designations(pool).await?;
+
+    let dir = dir.join(start.strftime("%Y-%m").to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let mut count = 0;
+    for sales in sales.chunk_by(|a, b| a.member_id == b.member_id) {
+        // Guests do not get a statement
+        let member_id = &sales[0].member_id;
+        if member_id.is_empty() {
+            continue;
+        }
+
+        let path = dir.join(format!("{member_id}.csv"));
+        tokio::fs::write(&path, to_csv(sales, &designations)).await?;
+        count += 1;
+    }
+
+    info!("Exported {count} monthly statements to {}", dir.display());
+    Ok(count)
+}
+
+/// Format the sales of a member as CSV statement, using semicolons as
+/// separators so that the file can be opened with a German spreadsheet
+/// application.
+fn to_csv(sales: &[database::Sale], designations: &HashMap<String, String>) -> String {
+    let mut csv = "Datum;Artikelnummer;Bezeichnung;Menge;Einzelpreis;Gesamt\n".to_string();
+
+    for sale in sales {
+        let designation = designations
+            .get(&sale.article_id)
+            .map(String::as_str)
+            .unwrap_or_default();
+
+        let unit_price = sale.unit_price.map(|price| format_price(*price));
+        let total = sale.total().map(format_price);
+
+        let _ = writeln!(
+            csv,
+            "{};{};{};{};{};{}",
+            sale.booking_date(),
+            escape(&sale.article_id),
+            escape(designation),
+            sale.amount,
+            unit_price.unwrap_or_default(),
+            total.unwrap_or_default(),
+        );
+    }
+
+    let total = sales
+        .iter()
+        .filter_map(|sale| sale.total())
+        .sum::<Decimal>();
+    let _ = writeln!(csv, ";;Summe;;;{}", format_price(total));
+
+    csv
+}
+
+/// Format a price with two decimal places and a comma as decimal separator.
+fn format_price(price: Decimal) -> String {
+    format!("{price:.2}").replace('.', ",")
+}
+
+/// Quote a CSV field if it contains separators, quotes or line breaks.
+pub fn escape(field: &str) -> String {
+    if field.contains([';', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_csv() {
+        let sale = |article_id| {
+            database::Sale::test(article_id)
+                .with_created_at("2025-03-01T12:00:00+01:00[Europe/Berlin]")
+                .with_member("11011")
+        };
+
+        let sales = vec![sale("1").with_amount(2).with_unit_price(150), sale("2")];
+        let designations = HashMap::from([("1".to_string(), "Wasser; still".to_string())]);
+
+        let csv = to_csv(&sales, &designations);
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                "Datum;Artikelnummer;Bezeichnung;Menge;Einzelpreis;Gesamt",
+                "2025-03-01;1;\"Wasser; still\";2;1,50;3,00",
+                "2025-03-01;2;;1;;",
+                ";;Summe;;;3,00",
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::database;
+use crate::paths;
+use anyhow::Context;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+const API_URL: &str = "https://api.sumup.com";
+
+/// The interval at which the status of a pending card payment is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The time after which a pending card payment is cancelled.
+const PAYMENT_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+/// A client for the SumUp Cloud API, which is used to charge guests on a
+/// SumUp card terminal.
+#[derive(Debug, Clone)]
+pub struct SumUp {
+    client: reqwest::Client,
+    api_key: SecretString,
+    merchant_code: String,
+    reader_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutResponse {
+    data: CheckoutData,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutData {
+    client_transaction_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    status: String,
+    transaction_code: Option<String>,
+}
+
+impl SumUp {
+    pub fn new(api_key: SecretString, merchant_code: String, reader_id: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+            merchant_code,
+            reader_id,
+        }
+    }
+
+    /// Charge the given amount (in Euro) on the card terminal and wait for
+    /// the payment to complete.
+    ///
+    /// Returns the SumUp transaction code of the successful payment.
+    pub async fn charge(&self, amount: Decimal, description: &str) -> anyhow::Result<String> {
+        let cents = (amount * Decimal::ONE_HUNDRED)
+            .round()
+            .to_i64()
+            .context("Invalid amount")?;
+
+        let url = format!(
+            "{API_URL}/v0.1/merchants/{}/readers/{}/checkout",
+            self.merchant_code, self.reader_id
+        );
+        let body = json!({
+            "total_amount": { "value": cents, "currency": "EUR", "minor_unit": 2 },
+            "description": description,
+        });
+
+        info!(
+            "Starting card payment of {amount:.2}€ on SumUp reader {}",
+            self.reader_id
+        );
+        let response: CheckoutResponse = self
+            .client
+            .post(url)
+            .bearer_auth(self.api_key.expose_secret())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let client_transaction_id = response.data.client_transaction_id;
+        debug!(%client_transaction_id, "Waiting for card payment to complete…");
+
+        let result = tokio::time::timeout(PAYMENT_TIMEOUT, async {
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                match self.transaction(&client_transaction_id).await {
+                    Ok(Some(transaction)) if transaction.status == "PENDING" => {}
+                    Ok(Some(transaction)) => break transaction,
+                    Ok(None) => {}
+                    Err(err) => warn!("Failed to check card payment status: {err}"),
+                }
+            }
+        })
+        .await;
+
+        let transaction = match result {
+            Ok(transaction) => transaction,
+            Err(_) => {
+                if let Err(err) = self.terminate().await {
+                    warn!("Failed to cancel card payment: {err}");
+                }
+                anyhow::bail!("Card payment timed out");
+            }
+        };
+
+        anyhow::ensure!(
+            transaction.status == "SUCCESSFUL",
+            "Card payment failed with status {}",
+            transaction.status
+        );
+
+        Ok(transaction
+            .transaction_code
+            .unwrap_or(client_transaction_id))
+    }
+
+    /// Look up the transaction that was created for a checkout, which
+    /// returns `None` until the card is presented on the terminal.
+    async fn transaction(
+        &self,
+        client_transaction_id: &str,
+    ) -> anyhow::Result<Option<Transaction>> {
+        let url = format!(
+            "{API_URL}/v2.1/merchants/{}/transactions",
+            self.merchant_code
+        );
+        let response = self
+            .client
+            .get(url)
+            .query(&[("client_transaction_id", client_transaction_id)])
+            .bearer_auth(self.api_key.expose_secret())
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Cancel the checkout that is currently shown on the terminal.
+    async fn terminate(&self) -> anyhow::Result<()> {
+        let url = format!(
+            "{API_URL}/v0.1/merchants/{}/readers/{}/terminate",
+            self.merchant_code, self.reader_id
+        );
+        self.client
+            .post(url)
+            .bearer_auth(self.api_key.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Append a successful card payment with its sales to the card payment log,
+/// with the timestamp, transaction ID, total and items separated by tabs.
+pub async fn log_payment(transaction_id: &str, sales: &[database::Sale]) -> anyhow::Result<()> {
+    let total = sales
+        .iter()
+        .filter_map(database::Sale::total)
+        .sum::<Decimal>();
+    let items = sales
+        .iter()
+        .map(|sale| format!("{}x {}", sale.amount, sale.article_id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let line = format!(
+        "{}\t{transaction_id}\t{total:.2}\t{items}\n",
+        jiff::Timestamp::now()
+    );
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths::card_payment_log())
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    file.sync_data().await?;
+
+    Ok(())
+}
@@ -0,0 +1,571 @@
+use crate::database;
+use crate::logging::VF_DEBUG_TARGET;
+use crate::verify::{self, Verifier};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::types::Text;
+use sqlx::SqlitePool;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{debug, info, warn};
+
+/// The time for which uploaded sales are kept in the local database.
+const SALES_HISTORY_RETENTION: jiff::SignedDuration = jiff::SignedDuration::from_hours(365 * 24);
+
+/// The daily time window in which requests are sent to Vereinsflieger,
+/// parsed from `HH:MM-HH:MM` (e.g. `06:00-22:00`). The window may span
+/// midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncWindow {
+    pub start: jiff::civil::Time,
+    pub end: jiff::civil::Time,
+}
+
+impl SyncWindow {
+    /// Check whether the local time is within the window.
+    pub fn contains(&self, time: jiff::civil::Time) -> bool {
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => time >= self.start || time < self.end,
+        }
+    }
+}
+
+impl std::str::FromStr for SyncWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.split_once('-') else {
+            anyhow::bail!("Expected a time window like `06:00-22:00`");
+        };
+
+        let start = start.trim().parse()?;
+        let end = end.trim().parse()?;
+        anyhow::ensure!(start != end, "The time window must not be empty");
+
+        Ok(Self { start, end })
+    }
+}
+
+/// The error that is returned when Vereinsflieger rejected a request
+/// because too many requests were sent.
+#[derive(Debug)]
+pub struct RateLimited;
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Vereinsflieger rate limit reached")
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Check if a Vereinsflieger request failed because of rate limiting.
+///
+/// This covers the [`vereinsflieger::Error::TooManyRequests`] error and any
+/// request that failed with a `429 Too Many Requests` status.
+pub fn is_rate_limited(error: &anyhow::Error) -> bool {
+    fn is_too_many_requests(error: &reqwest::Error) -> bool {
+        error.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    error.chain().any(|error| {
+        if let Some(error) = error.downcast_ref::<vereinsflieger::Error>() {
+            return match error {
+                vereinsflieger::Error::TooManyRequests => true,
+                vereinsflieger::Error::RequestFailed(error) => is_too_many_requests(error),
+                _ => false,
+            };
+        }
+
+        error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(is_too_many_requests)
+    })
+}
+
+/// A category of articles (e.g. drinks or snacks), which overrides the
+/// category from Vereinsflieger when the articles are synchronized.
+///
+/// This is parsed from `<category>=<article ID>,<article ID>,…`, e.g.
+/// `Getränke=1001,1002`.
+#[derive(Debug, Clone)]
+pub struct ArticleCategory {
+    pub name: String,
+    pub article_ids: Vec<String>,
+}
+
+impl std::str::FromStr for ArticleCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, article_ids)) = s.split_once('=') else {
+            anyhow::bail!("Expected `<category>=<article ID>,<article ID>,…`");
+        };
+
+        let article_ids = article_ids
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        anyhow::ensure!(!article_ids.is_empty(), "Missing article IDs");
+
+        Ok(Self {
+            name: name.trim().to_string(),
+            article_ids,
+        })
+    }
+}
+
+/// The name of the configured category of the given article, if there is
+/// one.
+fn category_of(categories: &[ArticleCategory], article_id: &str) -> Option<String> {
+    categories
+        .iter()
+        .find(|category| category.article_ids.iter().any(|id| id == article_id))
+        .map(|category| category.name.clone())
+}
+
+/// Load the articles from the Vereinsflieger API and save them to
+/// the local database, with their categories overridden by the configured
+/// categories.
+///
+/// Changes of the current prices are recorded, and their number is returned.
+pub async fn sync_articles(
+    vereinsflieger: vereinsflieger::Client,
+    pool: SqlitePool,
+    categories: Vec<ArticleCategory>,
+) -> anyhow::Result<usize> {
+    info!("Loading articles from Vereinsflieger API…");
+    let articles = vereinsflieger.list_articles().await?;
+    info!(
+        "Received {} articles from Vereinsflieger API",
+        articles.len()
+    );
+
+    for article in &articles {
+        debug!(target: VF_DEBUG_TARGET, response = ?article, "list_articles");
+    }
+
+    let articles = articles
+        .into_iter()
+        .filter_map(|article| {
+            database::Article::try_from(article)
+                .inspect_err(|err| warn!("Found invalid article: {err}"))
+                .ok()
+        })
+        .map(|article| database::Article {
+            category: category_of(&categories, &article.id).or(article.category),
+            ..article
+        })
+        .collect::<Vec<_>>();
+
+    let previous = database::Article::load_all(&pool).await?;
+    let today = jiff::Zoned::now().date();
+    let price_changes = database::PriceChange::diff(&previous, &articles, today);
+
+    info!("Saving {} articles to database…", articles.len());
+    database::Article::save_all(pool.clone(), articles).await?;
+
+    if !price_changes.is_empty() {
+        info!("Saving {} price changes to database…", price_changes.len());
+        database::PriceChange::insert_all(&pool, &price_changes).await?;
+    }
+
+    Ok(price_changes.len())
+}
+
+/// The email address of a Vereinsflieger user, if there is one and it should
+/// be stored.
+fn member_email(email: &str, store_emails: bool) -> Option<String> {
+    let email = email.trim();
+    (store_emails && !email.is_empty()).then(|| email.to_string())
+}
+
+/// Load the members and their bank accounts from the Vereinsflieger API and
+/// save them to the local database.
+///
+/// If `purge_removed` is set, the local data of members that are not in the
+/// list anymore is deleted afterwards. The email addresses are only stored
+/// if `store_emails` is set.
+pub async fn sync_members(
+    vereinsflieger: vereinsflieger::Client,
+    pool: SqlitePool,
+    purge_removed: bool,
+    store_emails: bool,
+) -> anyhow::Result<()> {
+    info!("Loading users from Vereinsflieger API…");
+    let users = vereinsflieger.list_users().await?;
+    info!("Received {} users from Vereinsflieger API", users.len());
+
+    // Names and birthdays are not written to the debug log
+    for user in &users {
+        let keys = user.keymanagement.iter().map(|key| &key.name);
+        debug!(
+            target: VF_DEBUG_TARGET,
+            member_id = %user.member_id,
+            member_status = %user.member_status,
+            keys = ?keys.collect::<Vec<_>>(),
+            has_birthday = !user.birthday.is_empty(),
+            "list_users"
+        );
+    }
+
+    let users = users
+        .into_iter()
+        .flat_map(|user| {
+            let mut keycodes = user
+                .keymanagement
+                .into_iter()
+                .filter_map(database::Member::parse_keycode)
+                .collect::<Vec<_>>();
+
+            // Members without keycodes can still log in
+            // with their member card.
+            if keycodes.is_empty() {
+                keycodes.push(String::new());
+            }
+
+            let birthday = database::Member::parse_birthday(&user.birthday);
+            let email = member_email(&user.email, store_emails);
+
+            keycodes.into_iter().map(move |keycode| database::Member {
+                keycode,
+                id: user.member_id.clone(),
+                firstname: user.first_name.clone(),
+                lastname: user.last_name.clone(),
+                nickname: user.nickname.clone(),
+                birthday: birthday.map(Text),
+                member_group: user.member_status.clone(),
+                email: email.clone(),
+                blocked: false,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    info!("Saving {} users to database…", users.len());
+    database::Member::save_all(pool.clone(), users).await?;
+
+    if purge_removed {
+        let count = database::Member::purge_removed(&pool).await?;
+        if count > 0 {
+            info!("Deleted {count} entries of members that were removed");
+        }
+    }
+
+    Ok(())
+}
+
+/// Upload all pending sales to the Vereinsflieger API and delete uploaded
+/// sales that are older than the retention period.
+///
+/// The upload stops early with a [`RateLimited`] error if Vereinsflieger
+/// rejects a request because of rate limiting.
+///
+/// With a `verifier`, uploaded sales are only marked as uploaded once they
+/// were found in Vereinsflieger. Sales with an interrupted upload are
+/// checked the same way, and uploaded again if they are missing. Without
+/// a `verifier` they are skipped until an admin resends or discards them
+/// in the pending sales.
+pub async fn upload_sales(
+    vereinsflieger: vereinsflieger::Client,
+    pool: SqlitePool,
+    verifier: Option<&Verifier>,
+) -> anyhow::Result<()> {
+    info!("Loading sales from database…");
+    let sales = database::Sale::load_all(pool.clone()).await?;
+    if sales.is_empty() {
+        info!("No sales to upload");
+        return Ok(());
+    }
+
+    let (sales, interrupted): (Vec<_>, Vec<_>) = sales
+        .into_iter()
+        .partition(|sale| sale.upload_started_at.is_none());
+
+    match verifier {
+        Some(verifier) => verify_uploads(verifier, &pool, interrupted).await?,
+        None => {
+            for sale in interrupted {
+                let sale_id = *sale.id;
+                let member_id = &sale.member_id;
+                let comment = sale.comment();
+                warn!(
+                    %sale_id, %member_id, %comment,
+                    "Skipping sale with interrupted upload, waiting for an admin decision"
+                );
+            }
+        }
+    }
+
+    // The synced prices are needed to detect group prices and discounts
+    let articles = database::Article::load_all(&pool)
+        .await?
+        .into_iter()
+        .map(|article| (article.id.clone(), article))
+        .collect::<HashMap<_, _>>();
+
+    let mut uploaded = Vec::new();
+
+    info!("Uploading {} sales to Vereinsflieger API…", sales.len());
+    for (i, sale) in sales.into_iter().enumerate() {
+        let sale_id = *sale.id;
+        let member_id = sale.member_id.clone();
+        debug!(%sale_id, %member_id, "Uploading sale #{}…", i + 1);
+
+        async fn save_sale(
+            vereinsflieger: &vereinsflieger::Client,
+            sale: database::Sale,
+            standard_price: Option<Decimal>,
+        ) -> Result<(), anyhow::Error> {
+            let booking_date = sale.booking_date().to_string();
+            let comment = sale.comment();
+            let sale = new_sale(&sale, &booking_date, &comment, standard_price)?;
+
+            debug!(target: VF_DEBUG_TARGET, request = ?sale, "add_sale");
+            let result = vereinsflieger.add_sale(&sale).await;
+            debug!(target: VF_DEBUG_TARGET, response = ?result, "add_sale");
+
+            Ok(result?)
+        }
+
+        // Remember that the upload has started, so that the
+        // sale is not uploaded again if we crash before it
+        // is marked as uploaded in the database.
+        if let Err(err) = database::Sale::mark_upload_started(&pool, sale_id).await {
+            warn!(%sale_id, "Failed to mark sale as uploading: {err}");
+            continue;
+        }
+
+        let standard_price = articles
+            .get(&sale.article_id)
+            .and_then(|article| article.price_for_date(&sale.booking_date()));
+
+        if let Err(error) = save_sale(&vereinsflieger, sale.clone(), standard_price).await {
+            warn!(%sale_id, %member_id, "Failed to upload sale: {error}");
+            if let Err(err) = database::Sale::mark_upload_failed(&pool, sale_id).await {
+                warn!(%sale_id, "Failed to reset sale upload state: {err}");
+            }
+
+            // Stop hammering the API, the remaining sales
+            // are uploaded after the backoff.
+            if is_rate_limited(&error) {
+                return Err(RateLimited.into());
+            }
+        } else if verifier.is_some() {
+            // The sale stays marked as uploading until it is verified
+            uploaded.push(sale);
+        } else {
+            debug!(%sale_id, "Marking sale as uploaded…");
+            match database::Sale::mark_uploaded(&pool, sale_id).await {
+                Ok(()) => debug!(%sale_id, "Sale successfully marked as uploaded"),
+                Err(err) => {
+                    warn!(%sale_id, "Failed to mark sale as uploaded: {err}")
+                }
+            }
+        }
+    }
+
+    if let Some(verifier) = verifier {
+        verify_uploads(verifier, &pool, uploaded).await?;
+    }
+
+    let retention_start = jiff::Timestamp::now().checked_sub(SALES_HISTORY_RETENTION)?;
+    match database::Sale::delete_uploaded_before(&pool, retention_start).await {
+        Ok(0) => {}
+        Ok(count) => info!("Deleted {count} old sales from the history"),
+        Err(err) => warn!("Failed to delete old sales: {err}"),
+    }
+
+    Ok(())
+}
+
+/// Build the Vereinsflieger request for a sale.
+///
+/// The total price is only sent if it differs from the standard price of
+/// the article on the booking date, e.g. for open prices, group prices and
+/// discounts, since Vereinsflieger books the standard price otherwise.
+fn new_sale<'a>(
+    sale: &'a database::Sale,
+    booking_date: &'a str,
+    comment: &'a str,
+    standard_price: Option<Decimal>,
+) -> anyhow::Result<vereinsflieger::NewSale<'a>> {
+    let unit_price = sale.unit_price.as_deref().copied();
+    let total_price = match sale.open_price || unit_price != standard_price {
+        true => sale.total().and_then(|total| total.to_f64()),
+        false => None,
+    };
+
+    Ok(vereinsflieger::NewSale {
+        booking_date,
+        article_id: &sale.article_id,
+        amount: sale.amount as f64,
+        // Sales of guests are not booked to a member
+        member_id: match sale.member_id.as_str() {
+            "" => None,
+            member_id => Some(member_id.parse()?),
+        },
+        callsign: None,
+        sales_tax: None,
+        total_price,
+        counter: None,
+        comment: Some(comment),
+        cost_type: sale.cost_type.as_deref(),
+        caid2: None,
+        spid: None,
+    })
+}
+
+/// Check which of the `sales` were booked in Vereinsflieger. Booked sales
+/// are marked as uploaded, missing sales are reset so that they are uploaded
+/// again.
+///
+/// If Vereinsflieger can't be queried, the sales are left as they are and
+/// checked again on the next upload.
+async fn verify_uploads(
+    verifier: &Verifier,
+    pool: &SqlitePool,
+    sales: Vec<database::Sale>,
+) -> anyhow::Result<()> {
+    if sales.is_empty() {
+        return Ok(());
+    }
+
+    info!("Verifying {} uploaded sales…", sales.len());
+    let result = async {
+        let session = verifier.sign_in().await?;
+
+        // Only one request per booking date is needed
+        let mut booked = BTreeMap::new();
+        for sale in &sales {
+            let date = sale.booking_date();
+            if let Entry::Vacant(entry) = booked.entry(date) {
+                entry.insert(session.booked_comments(date).await?);
+            }
+        }
+
+        anyhow::Ok(booked)
+    };
+
+    let booked = match result.await {
+        Ok(booked) => booked,
+        Err(error) if is_rate_limited(&error) => return Err(RateLimited.into()),
+        Err(error) => {
+            warn!("Failed to verify uploaded sales: {error}");
+            return Ok(());
+        }
+    };
+
+    for sale in sales {
+        let sale_id = *sale.id;
+        let comments = &booked[&sale.booking_date()];
+        if verify::is_booked(comments, &sale) {
+            debug!(%sale_id, "Sale found in Vereinsflieger, marking as uploaded…");
+            if let Err(err) = database::Sale::mark_uploaded(pool, sale_id).await {
+                warn!(%sale_id, "Failed to mark sale as uploaded: {err}");
+            }
+        } else {
+            let member_id = sale.member_id;
+            warn!(%sale_id, %member_id, "Sale not found in Vereinsflieger, uploading again");
+            if let Err(err) = database::Sale::mark_upload_failed(pool, sale_id).await {
+                warn!(%sale_id, "Failed to reset sale upload state: {err}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jiff::civil::time;
+
+    #[test]
+    fn test_sync_window() {
+        let window: SyncWindow = "06:00-22:00".parse().unwrap();
+        assert!(window.contains(time(6, 0, 0, 0)));
+        assert!(window.contains(time(12, 30, 0, 0)));
+        assert!(!window.contains(time(22, 0, 0, 0)));
+        assert!(!window.contains(time(3, 0, 0, 0)));
+
+        let window: SyncWindow = "22:00-02:00".parse().unwrap();
+        assert!(window.contains(time(23, 0, 0, 0)));
+        assert!(window.contains(time(1, 0, 0, 0)));
+        assert!(!window.contains(time(12, 0, 0, 0)));
+
+        assert!("06:00".parse::<SyncWindow>().is_err());
+        assert!("06:00-25:00".parse::<SyncWindow>().is_err());
+        assert!("06:00-06:00".parse::<SyncWindow>().is_err());
+    }
+
+    #[test]
+    fn test_is_rate_limited() {
+        let error = anyhow::Error::from(vereinsflieger::Error::TooManyRequests);
+        assert!(is_rate_limited(&error));
+        assert!(is_rate_limited(&error.context("Failed to load articles")));
+
+        let error = anyhow::Error::from(vereinsflieger::Error::Unauthorized);
+        assert!(!is_rate_limited(&error));
+
+        let error = anyhow::anyhow!("Article 4290 not found");
+        assert!(!is_rate_limited(&error));
+    }
+
+    #[test]
+    fn test_member_email() {
+        let email = member_email(" max@example.com ", true);
+        assert_eq!(email.as_deref(), Some("max@example.com"));
+
+        assert_eq!(member_email("max@example.com", false), None);
+        assert_eq!(member_email("", true), None);
+    }
+
+    #[test]
+    fn test_new_sale() {
+        let sale = database::Sale::test("1234")
+            .with_member("11011")
+            .with_amount(2);
+        let standard_price = Some(Decimal::new(150, 2));
+
+        // Group price
+        let group_sale = sale.clone().with_unit_price(120);
+        let request = new_sale(&group_sale, "2025-03-01", "", standard_price).unwrap();
+        assert_eq!(request.article_id, "1234");
+        assert_eq!(request.amount, 2.);
+        assert_eq!(request.member_id, Some(11011));
+        assert_eq!(request.total_price, Some(2.4));
+
+        // Standard price
+        let standard_sale = sale.clone().with_unit_price(150);
+        let request = new_sale(&standard_sale, "2025-03-01", "", standard_price).unwrap();
+        assert_eq!(request.total_price, None);
+
+        // Open price that matches the standard price
+        let mut open_sale = sale.with_unit_price(150);
+        open_sale.open_price = true;
+        let request = new_sale(&open_sale, "2025-03-01", "", standard_price).unwrap();
+        assert_eq!(request.total_price, Some(3.));
+    }
+
+    #[test]
+    fn test_article_category() {
+        let drinks: ArticleCategory = "Getränke=1001, 1002".parse().unwrap();
+        assert_eq!(drinks.name, "Getränke");
+        assert_eq!(drinks.article_ids, vec!["1001", "1002"]);
+
+        let snacks: ArticleCategory = "Snacks=2001".parse().unwrap();
+        let categories = [drinks, snacks];
+        assert_eq!(
+            category_of(&categories, "1002").as_deref(),
+            Some("Getränke")
+        );
+        assert_eq!(category_of(&categories, "2001").as_deref(), Some("Snacks"));
+        assert_eq!(category_of(&categories, "3001"), None);
+
+        assert!("Getränke".parse::<ArticleCategory>().is_err());
+        assert!("Getränke=".parse::<ArticleCategory>().is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::database;
+use crate::disk;
+use crate::health::{HealthReport, HealthStatus};
+use std::path::PathBuf;
+
+/// The result of the last check for app updates.
+#[derive(Debug, Clone)]
+pub struct UpdateCheck {
+    pub checked_at: jiff::Timestamp,
+    /// A short description of the result, shown on the system screen.
+    pub result: String,
+}
+
+/// The diagnostics that are shown on the "System" screen of the admin
+/// screen. Values that could not be determined are `None`.
+#[derive(Debug, Clone)]
+pub struct SystemInfo {
+    /// The time at which the application was started.
+    pub started_at: jiff::Timestamp,
+    /// The time since the system was booted.
+    pub system_uptime: Option<jiff::SignedDuration>,
+    /// The size of the database file in bytes.
+    pub database_size: Option<u64>,
+    /// The free space on the partition of the database in bytes.
+    pub free_disk_space: Option<u64>,
+    /// The memory used by the application in bytes.
+    pub memory_usage: Option<u64>,
+    /// The memory that is still available on the system in bytes.
+    pub memory_available: Option<u64>,
+    /// The result of the last check for app updates.
+    pub update_check: Option<UpdateCheck>,
+    /// Whether Vereinsflieger was reachable at the last connectivity check.
+    pub online: bool,
+    /// The version, sync times and pending sales.
+    pub health: HealthReport,
+    /// The logins and sales of the last days.
+    pub usage: Option<database::Usage>,
+}
+
+/// Collect the diagnostics for the database at `database`.
+pub async fn collect(
+    database: PathBuf,
+    health: &HealthStatus,
+    started_at: jiff::Timestamp,
+    update_check: Option<UpdateCheck>,
+    online: bool,
+) -> SystemInfo {
+    let database_size = tokio::fs::metadata(&database)
+        .await
+        .ok()
+        .map(|metadata| metadata.len());
+
+    let free_disk_space = disk::free_space(&database).await.ok();
+
+    let status = tokio::fs::read_to_string("/proc/self/status").await;
+    let memory_usage = status.ok().and_then(|status| parse_kb(&status, "VmRSS"));
+
+    let meminfo = tokio::fs::read_to_string("/proc/meminfo").await;
+    let memory_available = meminfo
+        .ok()
+        .and_then(|meminfo| parse_kb(&meminfo, "MemAvailable"));
+
+    let uptime = tokio::fs::read_to_string("/proc/uptime").await;
+    let system_uptime = uptime.ok().and_then(|uptime| parse_uptime(&uptime));
+
+    SystemInfo {
+        started_at,
+        system_uptime,
+        database_size,
+        free_disk_space,
+        memory_usage,
+        memory_available,
+        update_check,
+        online,
+        health: health.report().await,
+        usage: health.usage().await,
+    }
+}
+
+/// Parse a value in kB with the given key from `/proc/self/status` or
+/// `/proc/meminfo`, returning it in bytes.
+fn parse_kb(content: &str, key: &str) -> Option<u64> {
+    let line = content.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name == key).then_some(value)
+    })?;
+
+    let value = line.trim().strip_suffix("kB")?;
+    Some(value.trim().parse::<u64>().ok()? * 1024)
+}
+
+/// Parse the system uptime from `/proc/uptime`.
+fn parse_uptime(content: &str) -> Option<jiff::SignedDuration> {
+    let seconds = content.split_whitespace().next()?;
+    let seconds = seconds.parse::<f64>().ok()?;
+    jiff::SignedDuration::try_from_secs_f64(seconds).ok()
+}
+
+/// Format a number of bytes for the system screen (e.g. `1,5 GB`).
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]).replace('.', ","),
+    }
+}
+
+/// Format a duration for the system screen (e.g. `3 Tage, 04:05 h`).
+pub fn format_duration(duration: jiff::SignedDuration) -> String {
+    let minutes = duration.as_secs().max(0) / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    match days {
+        0 => format!("{hours:02}:{minutes:02} h"),
+        1 => format!("1 Tag, {hours:02}:{minutes:02} h"),
+        _ => format!("{days} Tage, {hours:02}:{minutes:02} h"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kb() {
+        let status = "Name:\tclubfridge-neo\nVmPeak:\t  123456 kB\nVmRSS:\t   45678 kB\n";
+        assert_eq!(parse_kb(status, "VmRSS"), Some(45678 * 1024));
+        assert_eq!(parse_kb(status, "VmSwap"), None);
+        assert_eq!(parse_kb(status, "Name"), None);
+
+        let meminfo = "MemTotal:        3884376 kB\nMemAvailable:    2715640 kB\n";
+        assert_eq!(parse_kb(meminfo, "MemAvailable"), Some(2715640 * 1024));
+    }
+
+    #[test]
+    fn test_parse_uptime() {
+        let uptime = parse_uptime("350735.47 234388.90\n").unwrap();
+        assert_eq!(uptime.as_secs(), 350735);
+        assert_eq!(parse_uptime(""), None);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2,0 KB");
+        assert_eq!(format_bytes(45678 * 1024), "44,6 MB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1,5 GB");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let format = |secs| format_duration(jiff::SignedDuration::from_secs(secs));
+        assert_eq!(format(59), "00:00 h");
+        assert_eq!(format(3 * 3600 + 5 * 60), "03:05 h");
+        assert_eq!(format(86400 + 60), "1 Tag, 00:01 h");
+        assert_eq!(format(3 * 86400 + 4 * 3600 + 5 * 60), "3 Tage, 04:05 h");
+    }
+}
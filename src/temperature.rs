@@ -0,0 +1,65 @@
+use anyhow::Context;
+use std::path::Path;
+
+/// The reading of a DS18B20 sensor after a power-on reset, which does not
+/// reflect the actual temperature.
+const POWER_ON_RESET_READING: i64 = 85_000;
+
+/// Read the current temperature in °C from a sensor file.
+///
+/// This supports the `w1_slave` file of DS18B20 1-wire sensors (e.g.
+/// `/sys/bus/w1/devices/28-…/w1_slave`) and files that only contain the
+/// temperature in millidegrees, like the `temperature` file of 1-wire
+/// sensors or the `temp1_input` file of USB sensors with a hwmon driver.
+pub async fn read(path: &Path) -> anyhow::Result<f64> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read temperature sensor {}", path.display()))?;
+
+    parse(&content)
+}
+
+fn parse(content: &str) -> anyhow::Result<f64> {
+    // The `w1_slave` file contains the result of the CRC check at the end
+    // of the first line, and the temperature as `t=…` in the second line
+    let millidegrees = match content.rsplit_once("t=") {
+        Some((raw, millidegrees)) => {
+            let crc_valid = raw.lines().next().is_some_and(|line| line.ends_with("YES"));
+            anyhow::ensure!(crc_valid, "CRC check of temperature reading failed");
+            millidegrees
+        }
+        None => content,
+    };
+
+    let millidegrees: i64 = millidegrees
+        .trim()
+        .parse()
+        .context("Invalid temperature reading")?;
+    anyhow::ensure!(
+        millidegrees != POWER_ON_RESET_READING,
+        "Temperature sensor was reset"
+    );
+
+    Ok(millidegrees as f64 / 1000.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_temperature() {
+        let w1_slave = "50 00 4b 46 7f ff 0c 10 1c : crc=1c YES\n\
+            50 00 4b 46 7f ff 0c 10 1c t=5000\n";
+        assert_eq!(parse(w1_slave).unwrap(), 5.0);
+
+        let w1_slave = "50 00 4b 46 7f ff 0c 10 1c : crc=1d NO\n\
+            50 00 4b 46 7f ff 0c 10 1c t=5000\n";
+        assert!(parse(w1_slave).is_err());
+
+        assert_eq!(parse("4312\n").unwrap(), 4.312);
+        assert_eq!(parse("-1500").unwrap(), -1.5);
+        assert!(parse("85000\n").is_err());
+        assert!(parse("").is_err());
+    }
+}
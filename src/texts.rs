@@ -0,0 +1,102 @@
+use serde::Deserialize;
+use std::path::Path;
+
+/// The texts that are shown to members, which can be customized by the club
+/// with a JSON file (e.g. `{"thank_you": "Prost!"}`).
+///
+/// Texts that are not included in the file keep their default value.
+/// Placeholders like `{name}` are replaced when the text is shown.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Texts {
+    /// The prompt on the idle screen.
+    pub login_prompt: String,
+    /// The prompt after a member logged in, with a `{name}` placeholder.
+    pub scan_prompt: String,
+    /// The prompt after an admin started a refund, with a `{name}`
+    /// placeholder.
+    pub refund_prompt: String,
+    /// The greeting on the customer display while nobody is logged in.
+    pub customer_welcome: String,
+    /// The popup after a successful purchase.
+    pub thank_you: String,
+    /// The popup after a successful refund.
+    pub refund_saved: String,
+    /// The popup when a member logs in on their birthday.
+    pub birthday: String,
+    /// The popup when a blocked member tries to log in.
+    pub member_blocked: String,
+    /// The popup for unknown keycodes, with an `{input}` placeholder.
+    pub member_not_found: String,
+    /// The popup for unknown barcodes, with an `{input}` placeholder.
+    pub article_not_found: String,
+    /// The popup when the purchase could not be saved.
+    pub save_failed: String,
+    /// The popup when an interrupted purchase was restored after a restart.
+    pub session_restored: String,
+    /// The popup after a member parked their cart.
+    pub cart_parked: String,
+    /// The popup when a parked cart was resumed after the login.
+    pub cart_resumed: String,
+}
+
+impl Default for Texts {
+    fn default() -> Self {
+        Self {
+            login_prompt: "Bitte RFID Chip".to_string(),
+            scan_prompt: "{name} – Produkte scannen bitte".to_string(),
+            refund_prompt: "Erstattung für {name} – Produkte scannen bitte".to_string(),
+            customer_welcome: "Willkommen!".to_string(),
+            thank_you: "Danke für deinen Kauf".to_string(),
+            refund_saved: "Erstattung gespeichert".to_string(),
+            birthday: "Alles Gute zum Geburtstag!".to_string(),
+            member_blocked: "Bitte beim Vorstand melden".to_string(),
+            member_not_found: "Benutzer nicht gefunden ({input})".to_string(),
+            article_not_found: "Artikel nicht gefunden ({input})".to_string(),
+            save_failed: "Einkauf konnte nicht gespeichert werden".to_string(),
+            session_restored: "Unterbrochener Einkauf wiederhergestellt".to_string(),
+            cart_parked: "Einkauf geparkt, bis zum nächsten Login".to_string(),
+            cart_resumed: "Geparkter Einkauf wiederhergestellt".to_string(),
+        }
+    }
+}
+
+impl Texts {
+    /// Load the customized texts from a JSON file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Replace the `{name}` placeholder in a text.
+pub fn with_name(text: &str, name: &str) -> String {
+    text.replace("{name}", name)
+}
+
+/// Replace the `{input}` placeholder in a text.
+pub fn with_input(text: &str, input: &str) -> String {
+    text.replace("{input}", input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_texts() {
+        let texts: Texts = serde_json::from_str(r#"{"thank_you": "Prost!"}"#).unwrap();
+        assert_eq!(texts.thank_you, "Prost!");
+        assert_eq!(texts.login_prompt, "Bitte RFID Chip");
+        assert_eq!(
+            with_name(&texts.scan_prompt, "Turbo"),
+            "Turbo – Produkte scannen bitte"
+        );
+        assert_eq!(
+            with_input(&texts.article_not_found, "1234"),
+            "Artikel nicht gefunden (1234)"
+        );
+
+        assert!(serde_json::from_str::<Texts>(r#"{"thankyou": "Prost!"}"#).is_err());
+    }
+}
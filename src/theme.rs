@@ -0,0 +1,90 @@
+use anyhow::Context;
+use iced::color;
+use iced::theme::Palette;
+use jiff::civil::Time;
+use std::path::Path;
+
+/// The colors of the user interface, which are dimmed at night to avoid
+/// blinding people in a dark clubhouse.
+pub fn palette(night: bool) -> Palette {
+    match night {
+        false => Palette {
+            background: color!(0x000000),
+            text: color!(0xffffff),
+            primary: color!(0x2E54C8),
+            success: color!(0x4BD130),
+            danger: color!(0xD5A30F),
+            warning: color!(0xD5A30F),
+        },
+        true => Palette {
+            background: color!(0x000000),
+            text: color!(0x9a9a9a),
+            primary: color!(0x1B3274),
+            success: color!(0x2B7A1C),
+            danger: color!(0x7D5F09),
+            warning: color!(0x7D5F09),
+        },
+    }
+}
+
+/// Check whether the local time is between the start and the end of the
+/// night, which usually spans midnight.
+pub fn is_night(time: Time, start: Time, end: Time) -> bool {
+    match start <= end {
+        true => start <= time && time < end,
+        false => time >= start || time < end,
+    }
+}
+
+/// Check whether it is night based on the reading of an ambient light
+/// sensor.
+///
+/// It only becomes day again once the illuminance is twice the threshold,
+/// so that the colors do not flicker while the reading hovers around it.
+pub fn is_dark(illuminance: f64, threshold: f64, night: bool) -> bool {
+    match night {
+        true => illuminance < threshold * 2.,
+        false => illuminance < threshold,
+    }
+}
+
+/// Read the illuminance in lux from an ambient light sensor file, like the
+/// `in_illuminance_input` file of sensors with an IIO driver.
+pub async fn read_light_sensor(path: &Path) -> anyhow::Result<f64> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read light sensor {}", path.display()))?;
+
+    content
+        .trim()
+        .parse()
+        .context("Invalid light sensor reading")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jiff::civil::time;
+
+    #[test]
+    fn test_is_night() {
+        let (start, end) = (time(22, 0, 0, 0), time(6, 0, 0, 0));
+        assert!(is_night(time(23, 30, 0, 0), start, end));
+        assert!(is_night(time(2, 0, 0, 0), start, end));
+        assert!(is_night(time(22, 0, 0, 0), start, end));
+        assert!(!is_night(time(6, 0, 0, 0), start, end));
+        assert!(!is_night(time(12, 0, 0, 0), start, end));
+
+        let (start, end) = (time(0, 30, 0, 0), time(5, 0, 0, 0));
+        assert!(is_night(time(1, 0, 0, 0), start, end));
+        assert!(!is_night(time(23, 0, 0, 0), start, end));
+    }
+
+    #[test]
+    fn test_is_dark() {
+        assert!(is_dark(5., 10., false));
+        assert!(!is_dark(15., 10., false));
+        assert!(is_dark(15., 10., true));
+        assert!(!is_dark(25., 10., true));
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::io::Read;
+
+/// The duration of a TOTP time step in seconds.
+const STEP_SECS: i64 = 30;
+
+/// The number of bytes of newly generated secrets.
+const SECRET_LEN: usize = 20;
+
+/// The alphabet of the base32 encoding of secrets (RFC 4648).
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generate a new random secret, encoded as base32 for authenticator apps.
+pub fn generate_secret() -> anyhow::Result<String> {
+    let mut secret = [0; SECRET_LEN];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(&mut secret))
+        .context("Failed to read random bytes")?;
+
+    Ok(base32_encode(&secret))
+}
+
+/// The `otpauth://` URI of the given secret, which can be scanned as QR code
+/// with an authenticator app.
+pub fn uri(secret: &str, label: &str) -> String {
+    format!("otpauth://totp/clubfridge-neo:{label}?secret={secret}&issuer=clubfridge-neo")
+}
+
+/// The 6-digit code of the given secret for the given time step.
+fn code(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    value % 1_000_000
+}
+
+/// Check a 6-digit code against the base32-encoded secret, allowing one time
+/// step of clock drift in both directions.
+///
+/// Returns the matched time step, so that the caller can reject codes that
+/// were already used.
+pub fn verify(secret: &str, input: &str, now: jiff::Timestamp) -> Option<i64> {
+    if input.len() != 6 || !input.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let secret = base32_decode(secret)?;
+    let input = input.parse::<u32>().ok()?;
+    let step = now.as_second() / STEP_SECS;
+
+    (step - 1..=step + 1).find(|step| code(&secret, *step) == input)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for chunk in bytes.chunks(5) {
+        let mut buffer = [0; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer
+            .iter()
+            .fold(0u64, |bits, byte| bits << 8 | *byte as u64);
+
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            let index = (bits >> (35 - i * 5)) & 0x1f;
+            output.push(BASE32_ALPHABET[index as usize] as char);
+        }
+    }
+    output
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+
+    for char in input.bytes().filter(|byte| !matches!(byte, b' ' | b'=')) {
+        let char = char.to_ascii_uppercase();
+        let value = BASE32_ALPHABET.iter().position(|c| *c == char)? as u32;
+        bits = (bits << 5) | value;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            output.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32_encode(b"Hello!\xde\xad\xbe\xef"), "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            base32_decode("JBSWY3DPEHPK3PXP").unwrap(),
+            b"Hello!\xde\xad\xbe\xef"
+        );
+        assert_eq!(base32_decode("jbsw y3dp").unwrap(), b"Hello");
+        assert_eq!(base32_decode("JBSWY3DP!"), None);
+    }
+
+    #[test]
+    fn test_code() {
+        // Test vectors from RFC 6238, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!(code(secret, 59 / STEP_SECS), 287082);
+        assert_eq!(code(secret, 1111111109 / STEP_SECS), 81804);
+        assert_eq!(code(secret, 1234567890 / STEP_SECS), 5924);
+    }
+
+    #[test]
+    fn test_verify() {
+        let secret = base32_encode(b"12345678901234567890");
+        let now = jiff::Timestamp::from_second(1111111109).unwrap();
+        let step = 1111111109 / STEP_SECS;
+        assert_eq!(verify(&secret, "081804", now), Some(step));
+
+        let later = jiff::Timestamp::from_second(1111111109 + 30).unwrap();
+        assert_eq!(verify(&secret, "081804", later), Some(step));
+
+        let much_later = jiff::Timestamp::from_second(1111111109 + 90).unwrap();
+        assert_eq!(verify(&secret, "081804", much_later), None);
+
+        assert_eq!(verify(&secret, "81804", now), None);
+        assert_eq!(verify(&secret, "000000", now), None);
+    }
+}
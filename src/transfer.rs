@@ -0,0 +1,87 @@
+use iced::widget::qr_code;
+use rust_decimal::Decimal;
+use tracing::warn;
+
+/// The bank account or PayPal.me name that guests can transfer money to.
+#[derive(Debug, Clone)]
+pub enum TransferTarget {
+    /// A SEPA bank account, shown as EPC QR code (aka. "GiroCode").
+    Bank { name: String, iban: String },
+    /// A PayPal.me name, shown as QR code with a PayPal.me link.
+    PayPal { name: String },
+}
+
+impl TransferTarget {
+    /// The text that is encoded in the QR code for a transfer of the
+    /// given amount.
+    pub fn payload(&self, amount: Decimal, remittance: &str) -> String {
+        match self {
+            TransferTarget::Bank { name, iban } => epc_payload(name, iban, amount, remittance),
+            TransferTarget::PayPal { name } => format!("https://paypal.me/{name}/{amount:.2}EUR"),
+        }
+    }
+
+    /// Encode a transfer of the given amount as a QR code.
+    pub fn to_qr_code(&self, amount: Decimal, remittance: &str) -> Option<qr_code::Data> {
+        qr_code::Data::new(self.payload(amount, remittance))
+            .inspect_err(|err| warn!("Failed to encode transfer as QR code: {err}"))
+            .ok()
+    }
+}
+
+/// Build the payload of an EPC QR code (version 002, UTF-8) for a SEPA
+/// credit transfer, as specified by the European Payments Council.
+fn epc_payload(name: &str, iban: &str, amount: Decimal, remittance: &str) -> String {
+    let iban = iban.replace(' ', "");
+    [
+        "BCD",
+        "002",
+        "1",
+        "SCT",
+        "",
+        name,
+        &iban,
+        &format!("EUR{amount:.2}"),
+        "",
+        "",
+        remittance,
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_payload() {
+        let target = TransferTarget::Bank {
+            name: "Flugsportverein e.V.".to_string(),
+            iban: "DE02 1203 0000 0000 2020 51".to_string(),
+        };
+        let payload = target.payload(Decimal::new(350, 2), "Clubfridge");
+        let lines = payload.split('\n').collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "BCD",
+                "002",
+                "1",
+                "SCT",
+                "",
+                "Flugsportverein e.V.",
+                "DE02120300000000202051",
+                "EUR3.50",
+                "",
+                "",
+                "Clubfridge"
+            ]
+        );
+
+        let target = TransferTarget::PayPal {
+            name: "fsv".to_string(),
+        };
+        let payload = target.payload(Decimal::new(12, 0), "Clubfridge");
+        assert_eq!(payload, "https://paypal.me/fsv/12.00EUR");
+    }
+}
@@ -1,47 +1,89 @@
-use crate::running::{RunningClubFridge, Sale};
+use crate::admin;
+use crate::announcement::Announcement;
+use crate::calendar;
+use crate::crash::CrashReport;
+use crate::currency;
+use crate::paths;
+use crate::running::{
+    parse_open_price, CostCenter, RunningClubFridge, Sale, CART_ID, OPEN_PRICE_DESIGNATION,
+};
 use crate::starting::StartingClubFridge;
-use crate::state::{ClubFridge, GlobalState, Message, State};
+use crate::state::{ClubFridge, GlobalState, Message, Options, State};
+use crate::texts::{self, Texts};
+use crate::theme;
+use crate::update::ReleaseNotes;
 use iced::widget::text::Wrapping;
-use iced::widget::{button, column, container, row, scrollable, stack, text, Row};
+use iced::widget::{button, column, container, image, qr_code, row, scrollable, stack, text, Row};
 use iced::Length::Fixed;
-use iced::{color, Center, Element, Fill, Length, Right, Shrink, Theme};
+use iced::{color, Center, ContentFit, Element, Fill, Length, Right, Shrink, Theme};
 use rust_decimal::Decimal;
 use std::sync::Arc;
+use std::time::Instant;
 
 impl ClubFridge {
     pub fn theme(&self) -> Theme {
         Theme::Custom(Arc::new(iced::theme::Custom::new(
             "clubfridge".to_string(),
-            iced::theme::Palette {
-                background: color!(0x000000),
-                text: color!(0xffffff),
-                primary: color!(0x2E54C8),
-                success: color!(0x4BD130),
-                danger: color!(0xD5A30F),
-                warning: color!(0xD5A30F),
-            },
+            theme::palette(self.global_state.night_mode),
         )))
     }
 
     pub fn view(&self) -> Element<'_, Message> {
-        let content = match &self.state {
-            State::Starting(cf) => cf.view(),
-            State::Setup(cf) => cf.view(),
-            State::Running(cf) => cf.view(&self.global_state),
+        let global_state = &self.global_state;
+        let content = match (&global_state.crash_report, &global_state.release_notes) {
+            (Some(crash_report), _) => crash_view(crash_report),
+            (None, Some(release_notes)) => release_notes_view(release_notes),
+            (None, None) => match &self.state {
+                State::Starting(cf) => cf.view(),
+                State::Setup(cf) => cf.view(),
+                State::OfflineSetup(cf) => cf.view(),
+                State::Running(cf) => cf.view(global_state),
+            },
         };
 
-        let Some(popup) = &self.global_state.popup else {
+        let content = match self.global_state.popups.current() {
+            Some(popup) => {
+                let popup_container = container(popup.view())
+                    .width(Fill)
+                    .height(Fill)
+                    .align_x(Center)
+                    .align_y(Center)
+                    .padding([20, 30]);
+
+                stack![content, popup_container].into()
+            }
+            None => content,
+        };
+
+        let customer_display = self.global_state.options.customer_display;
+        let (Some(customer_display), State::Running(cf)) = (customer_display, &self.state) else {
             return content;
         };
 
-        let popup_container = container(popup.view())
-            .width(Fill)
-            .height(Fill)
-            .align_x(Center)
-            .align_y(Center)
-            .padding([20, 30]);
+        let customer_view = cf.customer_view(&self.global_state.texts);
+        match customer_display {
+            CustomerDisplay::Right => row![content, customer_view].into(),
+            CustomerDisplay::Bottom => column![content, customer_view].into(),
+        }
+    }
+}
 
-        stack![content, popup_container].into()
+/// The position of the customer-facing region of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CustomerDisplay {
+    /// To the right of the regular screen.
+    Right,
+    /// Below the regular screen.
+    Bottom,
+}
+
+impl CustomerDisplay {
+    /// The size of the whole window, given the size of the regular screen.
+    pub fn window_size(self, size: iced::Size) -> iced::Size {
+        match self {
+            CustomerDisplay::Right => iced::Size::new(size.width * 2., size.height),
+            CustomerDisplay::Bottom => iced::Size::new(size.width, size.height * 2.),
+        }
     }
 }
 
@@ -49,12 +91,18 @@ impl StartingClubFridge {
     pub fn view(&self) -> Element<'_, Message> {
         let title = text("ClubFridge neo").size(36).width(Fill).align_x(Center);
 
-        let status = if self.pool.is_none() {
-            "Connecting to database…"
+        let status = if self.database_corrupted {
+            "Database is corrupted, please restore a backup with `clubfridge-neo restore-backup`"
+                .to_string()
+        } else if self.pool.is_none() && self.failed_connection_attempts > 0 {
+            let attempt = self.failed_connection_attempts + 1;
+            format!("Connecting to database… (attempt {attempt})")
+        } else if self.pool.is_none() {
+            "Connecting to database…".to_string()
         } else if !self.migrations_finished {
-            "Running database migrations…"
+            "Running database migrations…".to_string()
         } else {
-            "Starting ClubFridge…"
+            "Starting ClubFridge…".to_string()
         };
 
         let status = text(status)
@@ -72,21 +120,66 @@ impl StartingClubFridge {
 }
 
 impl RunningClubFridge {
-    pub fn view(&self, global_state: &GlobalState) -> Element<'_, Message> {
+    pub fn view<'a>(&'a self, global_state: &'a GlobalState) -> Element<'a, Message> {
+        if let Some(confirmation) = &self.confirmation {
+            return admin::confirmation_view(confirmation);
+        }
+
+        if let Some(admin) = &self.admin {
+            let rate_limited_until = self.rate_limited_until.filter(|_| self.is_rate_limited());
+            return admin.view(
+                rate_limited_until,
+                &self.expiring_batches,
+                self.unseen_price_changes,
+            );
+        }
+
+        if self.door_alarm {
+            return door_alarm_view();
+        }
+
+        if let Some(input) = &self.open_price_input {
+            return open_price_view(input);
+        }
+
+        if self.confirming_payment {
+            return self.confirm_payment_view(&global_state.options);
+        }
+
+        if self.choosing_cost_center {
+            return cost_center_view(&global_state.options.cost_centers);
+        }
+
+        if self.card_payment_pending {
+            return self.card_payment_view();
+        }
+
+        if let Some(transfer_qr) = &self.transfer_qr {
+            return self.transfer_view(transfer_qr);
+        }
+
+        if let (None, Some(receipt)) = (&self.user, &self.receipt) {
+            return receipt_view(receipt);
+        }
+
         let title = self
             .user
             .as_ref()
             .map(|user| {
-                if user.nickname.is_empty() {
-                    text(format!(
-                        "{} {} – Produkte scannen bitte",
-                        user.firstname, user.lastname
-                    ))
+                let name = if user.nickname.is_empty() {
+                    format!("{} {}", user.firstname, user.lastname)
+                } else {
+                    user.nickname.clone()
+                };
+
+                let prompts = &global_state.texts;
+                if self.refund {
+                    text(texts::with_name(&prompts.refund_prompt, &name)).color(color!(0xffee12))
                 } else {
-                    text(format!("{} – Produkte scannen bitte", user.nickname))
+                    text(texts::with_name(&prompts.scan_prompt, &name))
                 }
             })
-            .unwrap_or(text("Bitte RFID Chip"));
+            .unwrap_or(text(&global_state.texts.login_prompt));
 
         let update_available: Option<Element<Message>> =
             global_state.self_updated.as_ref().map(|_| {
@@ -114,21 +207,89 @@ impl RunningClubFridge {
                 }
             });
 
-        let sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
-        let sum = text(format!("Summe: {sum:.2}€"))
-            .size(24)
-            .width(Fill)
-            .align_x(Right);
+        let round_up_amount = self.round_up_amount();
+        let is_member = self.user.as_ref().is_some_and(|user| !user.is_guest());
+        let show_round_up = global_state.options.round_up_article.is_some()
+            && is_member
+            && !self.refund
+            && !round_up_amount.is_zero();
+        let round_up: Option<Element<Message>> = show_round_up.then(|| {
+            let label = &global_state.options.round_up_label;
+            button(
+                text(format!("{label} (+{})", currency::format(round_up_amount)))
+                    .color(color!(0xffffff))
+                    .size(24),
+            )
+            .style(match self.round_up {
+                true => button::success,
+                false => button::secondary,
+            })
+            .padding([5, 10])
+            .on_press(Message::ToggleRoundUp)
+            .into()
+        });
+
+        let mut sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
+        if show_round_up && self.round_up {
+            sum += round_up_amount;
+        }
+        let sum = match self.refund {
+            true => text(format!("Erstattung: {}", currency::format(-sum))),
+            false => text(format!("Summe: {}", currency::format(sum))),
+        };
+        let sum = sum.size(24).width(Fill).align_x(Right);
+
+        let balance: Option<Element<Message>> = self
+            .balance
+            .filter(|balance| !balance.is_zero())
+            .map(|balance| {
+                text(format!("Guthaben: {}", currency::format(balance)))
+                    .size(24)
+                    .into()
+            });
+
+        let temperature: Option<Element<Message>> = self.temperature.map(|temperature| {
+            let color = match self.temperature_alert {
+                true => color!(0xff4444),
+                false => color!(0x888888),
+            };
+            text(format!("{temperature:.1}°C"))
+                .size(24)
+                .color(color)
+                .into()
+        });
+
+        let offline: Option<Element<Message>> =
+            (!self.online).then(|| text("Offline").size(24).color(color!(0xffee12)).into());
 
-        let status_row = Row::with_capacity(2).extend(update_available).push(sum);
+        let quantity: Option<Element<Message>> = self
+            .pending_quantity()
+            .map(|quantity| text(format!("Menge: {quantity}×")).size(24).into());
 
+        let status_row = Row::with_capacity(7)
+            .extend(offline)
+            .extend(quantity)
+            .extend(round_up)
+            .extend(update_available)
+            .extend(temperature)
+            .extend(balance)
+            .push(sum)
+            .spacing(20);
+
+        let clock_warning: Option<Element<Message>> = global_state.clock_skew.map(|skew| {
+            let minutes = skew.as_secs().abs() / 60;
+            text(format!(
+                "Achtung: Die Systemzeit weicht um {minutes} Minuten ab!"
+            ))
+            .color(color!(0xffee12))
+            .size(24)
+            .into()
+        });
+
+        // An empty cart is cancelled on timeout, otherwise it is paid
+        let options = &global_state.options;
         let mut cancel_label = "Abbruch".to_string();
-        if let Some(timeout) = self.interaction_timeout {
-            let secs_remaining = timeout.as_secs();
-            if self.sales.is_empty() && secs_remaining < 15 {
-                cancel_label.push_str(&format!(" ({secs_remaining}s)"));
-            }
-        }
+        cancel_label.extend(self.countdown(options, self.sales.is_empty()));
         let cancel_button = button(
             text(cancel_label)
                 .color(color!(0xffffff))
@@ -140,13 +301,12 @@ impl RunningClubFridge {
         .padding([10, 20])
         .on_press_maybe(self.user.as_ref().map(|_| Message::Cancel));
 
-        let mut pay_label = "Bezahlen".to_string();
-        if let Some(timeout) = self.interaction_timeout {
-            let secs_remaining = timeout.as_secs();
-            if !self.sales.is_empty() && secs_remaining < 15 {
-                pay_label.push_str(&format!(" ({secs_remaining}s)"));
-            }
-        }
+        let is_guest = self.user.as_ref().is_some_and(|user| user.is_guest());
+        let mut pay_label = match is_guest && self.sumup.is_some() {
+            true => "Mit Karte bezahlen".to_string(),
+            false => "Bezahlen".to_string(),
+        };
+        pay_label.extend(self.countdown(options, !self.sales.is_empty()));
         let pay_button = button(
             text(pay_label)
                 .color(color!(0xffffff))
@@ -158,14 +318,416 @@ impl RunningClubFridge {
         .padding([10, 20])
         .on_press_maybe(self.user.as_ref().map(|_| Message::Pay));
 
+        let open_price_button: Option<Element<Message>> = global_state
+            .options
+            .open_price_article
+            .as_ref()
+            .filter(|_| self.user.is_some())
+            .map(|_| {
+                button(
+                    text(OPEN_PRICE_DESIGNATION)
+                        .color(color!(0xffffff))
+                        .size(36)
+                        .align_x(Center),
+                )
+                .width(Fill)
+                .style(button::primary)
+                .padding([10, 20])
+                .on_press(Message::OpenPriceEntry)
+                .into()
+            });
+
+        let show_cash_button =
+            global_state.options.cash_payment && self.user.is_some() && !self.refund;
+        let cash_button: Option<Element<Message>> = show_cash_button.then(|| {
+            button(
+                text("Barzahlung")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press_maybe((!self.sales.is_empty()).then_some(Message::PayCash))
+            .into()
+        });
+
+        let show_guest_button = self.user.is_none() && self.guests_enabled();
+        let guest_button: Option<Element<Message>> = show_guest_button.then(|| {
+            button(
+                text("Gast")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::primary)
+            .padding([10, 20])
+            .on_press(Message::StartGuestSale)
+            .into()
+        });
+
+        let show_admin_button = self.user.is_none() && self.admin_enabled(options);
+        let admin_button: Option<Element<Message>> = show_admin_button.then(|| {
+            button(
+                text("Admin")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press(Message::OpenAdmin)
+            .into()
+        });
+
+        let show_limits_button = is_member
+            && !self.refund
+            && !self.limits_overridden
+            && RunningClubFridge::limits_enabled(options)
+            && self.admin_enabled(options);
+        let limits_button: Option<Element<Message>> = show_limits_button.then(|| {
+            button(
+                text("Limits aufheben")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press(Message::LiftLimits)
+            .into()
+        });
+
+        let show_cost_center_button =
+            self.cost_centers_enabled && self.user.is_some() && !is_guest && !self.refund;
+        let cost_center_button: Option<Element<Message>> = show_cost_center_button.then(|| {
+            button(
+                text("Kostenstelle")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press_maybe((!self.sales.is_empty()).then_some(Message::ChooseCostCenter))
+            .into()
+        });
+
+        let show_park_button = self.park_window.is_some() && self.user.is_some() && !is_guest;
+        let park_button: Option<Element<Message>> = show_park_button.then(|| {
+            button(
+                text("Parken")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::secondary)
+            .padding([10, 20])
+            .on_press_maybe((!self.sales.is_empty()).then_some(Message::ParkCart))
+            .into()
+        });
+
+        let show_repeat_button = self.user.is_some()
+            && self.sales.is_empty()
+            && !self.refund
+            && !self.last_purchase.is_empty();
+        let repeat_button: Option<Element<Message>> = show_repeat_button.then(|| {
+            button(
+                text("Wie letztes Mal")
+                    .color(color!(0xffffff))
+                    .size(36)
+                    .align_x(Center),
+            )
+            .width(Fill)
+            .style(button::primary)
+            .padding([10, 20])
+            .on_press(Message::RepeatLastPurchase)
+            .into()
+        });
+
+        let buttons = Row::with_capacity(9)
+            .extend(admin_button)
+            .extend(repeat_button)
+            .extend(open_price_button)
+            .extend(guest_button)
+            .push(cancel_button)
+            .extend(limits_button)
+            .extend(park_button)
+            .extend(cost_center_button)
+            .extend(cash_button)
+            .push(pay_button)
+            .spacing(10);
+
+        let announcement = self
+            .announcements
+            .get(self.announcement_index)
+            .filter(|_| self.user.is_none());
+        let content: Element<Message> = match announcement {
+            Some(announcement) => announcement_view(announcement),
+            None => scrollable(items(&self.sales, self.highlighted_row.as_ref()))
+                .id(CART_ID)
+                .height(Fill)
+                .width(Fill)
+                .anchor_bottom()
+                .into(),
+        };
+
+        let show_calendar = self.user.is_none() && !self.calendar_events.is_empty();
+        let calendar: Option<Element<Message>> =
+            show_calendar.then(|| calendar_view(&self.calendar_events));
+
+        let show_expiry_warning = self.user.is_none() && !self.expiring_batches.is_empty();
+        let expiry_warning: Option<Element<Message>> = show_expiry_warning.then(|| {
+            let batches = self.expiring_batches.iter().map(|batch| batch.label());
+            text(format!(
+                "Läuft bald ab: {}",
+                batches.collect::<Vec<_>>().join(", ")
+            ))
+            .color(color!(0xffee12))
+            .size(24)
+            .into()
+        });
+
+        let disk_space_warning: Option<Element<Message>> = self.low_disk_space.map(|free_space| {
+            text(format!(
+                "Wenig Speicherplatz: nur noch {} MB frei",
+                free_space / 1024 / 1024
+            ))
+            .color(color!(0xff4444))
+            .size(24)
+            .into()
+        });
+
+        let upload_warning: Option<Element<Message>> = (self.stuck_sales > 0).then(|| {
+            text(format!(
+                "{} Verkäufe konnten nicht zu Vereinsflieger hochgeladen werden",
+                self.stuck_sales
+            ))
+            .color(color!(0xff4444))
+            .size(24)
+            .into()
+        });
+
+        let card_payment_warning: Option<Element<Message>> =
+            (!self.unsaved_card_payments.is_empty()).then(|| {
+                text(format!(
+                    "Kartenzahlungen nicht gespeichert, bitte manuell buchen: {} (siehe {})",
+                    self.unsaved_card_payments.join(", "),
+                    paths::card_payment_log().display()
+                ))
+                .color(color!(0xff4444))
+                .size(24)
+                .into()
+            });
+
+        column![title.size(36), content]
+            .extend(calendar)
+            .extend(expiry_warning)
+            .extend(disk_space_warning)
+            .extend(upload_warning)
+            .extend(card_payment_warning)
+            .extend(clock_warning)
+            .push(status_row)
+            .push(buttons)
+            .spacing(10)
+            .padding([20, 30])
+            .into()
+    }
+}
+
+impl RunningClubFridge {
+    /// The cart and total in large type, for a display that faces the
+    /// customer.
+    pub fn customer_view<'a>(&'a self, texts: &'a Texts) -> Element<'a, Message> {
+        if self.user.is_none() {
+            return container(text(&texts.customer_welcome).size(64))
+                .width(Fill)
+                .height(Fill)
+                .align_x(Center)
+                .align_y(Center)
+                .into();
+        }
+
+        let items = column(self.sales.iter().map(|sale| {
+            let total_price = sale.total();
+            row![
+                text(format!("{}x", sale.amount)).size(36),
+                text(&sale.article.designation).size(36).width(Fill),
+                text(currency::format(total_price))
+                    .size(36)
+                    .wrapping(Wrapping::None),
+            ]
+            .spacing(20)
+            .into()
+        }))
+        .spacing(10);
+
+        let sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
+        let sum = match self.refund {
+            true => format!("Erstattung: {}", currency::format(-sum)),
+            false => format!("Summe: {}", currency::format(sum)),
+        };
+        let sum = text(sum).size(64).width(Fill).align_x(Right);
+
         column![
-            title.size(36),
-            scrollable(items(&self.sales))
+            scrollable(items).height(Fill).width(Fill).anchor_bottom(),
+            sum
+        ]
+        .spacing(20)
+        .padding([20, 30])
+        .into()
+    }
+
+    /// The countdown until the sale is automatically processed, to be appended
+    /// to a button label (e.g. ` (12s)`), if it should be shown on a button
+    /// that is triggered by the timeout or not.
+    fn countdown(&self, options: &Options, triggered: bool) -> Option<String> {
+        let secs_remaining = self.interaction_timeout?.as_secs();
+        let visible =
+            options.countdown_always || (triggered && secs_remaining < options.countdown_threshold);
+        visible.then(|| format!(" ({secs_remaining}s)"))
+    }
+
+    /// The dialog that summarizes the cart before it is booked.
+    fn confirm_payment_view(&self, options: &Options) -> Element<'_, Message> {
+        let title = text("Einkauf bestätigen").size(36).width(Fill);
+
+        let count = self
+            .sales
+            .iter()
+            .map(|item| item.amount as u32)
+            .sum::<u32>();
+        let count = text(format!("{count} Artikel"))
+            .size(36)
+            .width(Fill)
+            .align_x(Center);
+
+        let sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
+        let sum = match self.refund {
+            true => format!("Erstattung: {}", currency::format(-sum)),
+            false => format!("Summe: {}", currency::format(sum)),
+        };
+        let sum = text(sum).size(48).width(Fill).align_x(Center);
+
+        let mut back_label = "Zurück".to_string();
+        back_label.extend(self.countdown(options, false));
+        let back_button = button(
+            text(back_label)
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::danger)
+        .padding([10, 20])
+        .on_press(Message::CancelPayment);
+
+        let mut confirm_label = "Bestätigen".to_string();
+        confirm_label.extend(self.countdown(options, true));
+        let confirm_button = button(
+            text(confirm_label)
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::success)
+        .padding([10, 20])
+        .on_press(Message::Pay);
+
+        column![
+            title,
+            container(column![count, sum].spacing(20))
+                .height(Fill)
+                .align_y(Center),
+            row![back_button, confirm_button].spacing(10),
+        ]
+        .spacing(10)
+        .padding([20, 30])
+        .into()
+    }
+}
+
+impl RunningClubFridge {
+    /// The screen that is shown while the card terminal waits for the guest
+    /// to pay.
+    fn card_payment_view(&self) -> Element<'_, Message> {
+        let title = text("Kartenzahlung").size(36).width(Fill);
+
+        let sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
+        let sum = text(format!("Summe: {}", currency::format(sum)))
+            .size(48)
+            .width(Fill)
+            .align_x(Center);
+
+        let hint = text("Bitte Karte an das Terminal halten")
+            .size(36)
+            .color(color!(0xffee12))
+            .width(Fill)
+            .align_x(Center);
+
+        column![
+            title,
+            container(column![sum, hint].spacing(20))
                 .height(Fill)
+                .align_y(Center),
+        ]
+        .spacing(10)
+        .padding([20, 30])
+        .into()
+    }
+}
+
+impl RunningClubFridge {
+    /// The QR code that guests can scan to transfer their total via their
+    /// banking app or PayPal.
+    fn transfer_view<'a>(&'a self, transfer_qr: &'a qr_code::Data) -> Element<'a, Message> {
+        let title = text("Bezahlen per Überweisung").size(36).width(Fill);
+
+        let sum = self.sales.iter().map(|item| item.total()).sum::<Decimal>();
+        let sum = text(format!("Summe: {}", currency::format(sum))).size(36);
+
+        let hint = text("QR-Code mit der Banking- oder PayPal-App scannen")
+            .size(24)
+            .color(color!(0x888888));
+
+        let back_button = button(
+            text("Zurück")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::danger)
+        .padding([10, 20])
+        .on_press(Message::CancelTransfer);
+
+        let confirm_button = button(
+            text("Bezahlt")
+                .color(color!(0xffffff))
+                .size(36)
+                .align_x(Center),
+        )
+        .width(Fill)
+        .style(button::success)
+        .padding([10, 20])
+        .on_press(Message::ConfirmTransfer);
+
+        column![
+            title,
+            container(qr_code(transfer_qr).cell_size(4))
                 .width(Fill)
-                .anchor_bottom(),
-            status_row,
-            row![cancel_button, pay_button].spacing(10),
+                .height(Fill)
+                .align_x(Center)
+                .align_y(Center),
+            sum,
+            hint,
+            row![back_button, confirm_button].spacing(10),
         ]
         .spacing(10)
         .padding([20, 30])
@@ -173,11 +735,274 @@ impl RunningClubFridge {
     }
 }
 
-fn items(items: &[Sale]) -> Element<'_, Message> {
-    column(items.iter().map(sale_row)).spacing(10).into()
+/// The warning that is shown while the fridge door is open for too long.
+/// The release notes of the version that the app was updated to.
+fn release_notes_view(release_notes: &ReleaseNotes) -> Element<'_, Message> {
+    let title = text(format!("Neu in v{}", release_notes.version))
+        .size(36)
+        .width(Fill);
+
+    let notes = text(&release_notes.notes).size(18).width(Fill);
+
+    let close_button = button(
+        text("Schließen")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::CloseReleaseNotes);
+
+    column![
+        title,
+        scrollable(notes).height(Fill).width(Fill),
+        close_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// The error screen that is shown after the app was restarted because of
+/// a panic.
+fn crash_view(crash_report: &CrashReport) -> Element<'_, Message> {
+    let title = text("Unerwarteter Fehler")
+        .size(36)
+        .color(color!(0xff4444))
+        .width(Fill);
+
+    let hint = text("Die Anwendung wurde nach einem Absturz neu gestartet.").size(24);
+    let message = text(&crash_report.message).size(18).width(Fill);
+
+    let continue_button = button(
+        text("Weiter")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::CloseCrashReport);
+
+    column![
+        title,
+        hint,
+        scrollable(message).height(Fill).width(Fill),
+        continue_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+fn door_alarm_view() -> Element<'static, Message> {
+    let title = text("Kühlschranktür offen!")
+        .size(64)
+        .color(color!(0xff4444))
+        .align_x(Center);
+
+    let hint = text("Bitte die Tür schließen").size(36).align_x(Center);
+
+    container(column![title, hint].spacing(20).align_x(Center))
+        .width(Fill)
+        .height(Fill)
+        .align_x(Center)
+        .align_y(Center)
+        .into()
+}
+
+/// The list of cost centers to which the member can book their purchase.
+fn cost_center_view(cost_centers: &[CostCenter]) -> Element<'_, Message> {
+    let title = text("Kostenstelle wählen").size(36).width(Fill);
+
+    let cost_center_buttons =
+        column(cost_centers.iter().enumerate().map(|(index, cost_center)| {
+            button(text(&cost_center.name).color(color!(0xffffff)).size(36))
+                .width(Fill)
+                .style(button::primary)
+                .padding([10, 20])
+                .on_press(Message::PayToCostCenter(index))
+                .into()
+        }))
+        .spacing(10);
+
+    let back_button = button(
+        text("Zurück")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::danger)
+    .padding([10, 20])
+    .on_press(Message::CancelPayment);
+
+    column![
+        title,
+        scrollable(cost_center_buttons).height(Fill),
+        back_button
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// The QR code with the digital receipt of the last purchase.
+fn receipt_view(receipt: &qr_code::Data) -> Element<'_, Message> {
+    let title = text("Dein Beleg").size(36).width(Fill);
+
+    let hint = text("QR-Code scannen, um den Beleg zu speichern")
+        .size(24)
+        .color(color!(0x888888));
+
+    let close_button = button(
+        text("Schließen")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::primary)
+    .padding([10, 20])
+    .on_press(Message::CloseReceipt);
+
+    column![
+        title,
+        container(qr_code(receipt).cell_size(4))
+            .width(Fill)
+            .height(Fill)
+            .align_x(Center)
+            .align_y(Center),
+        hint,
+        close_button,
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+/// The numpad that is used to enter the price of the open-price article.
+fn open_price_view(input: &str) -> Element<'_, Message> {
+    let title = text(format!("{OPEN_PRICE_DESIGNATION} – Betrag eingeben"))
+        .size(36)
+        .width(Fill);
+
+    let display = if input.is_empty() { "0" } else { input };
+    let display = text(format!("{display}€"))
+        .size(48)
+        .width(Fill)
+        .align_x(Right);
+
+    let key = |label: &'static str, value: Option<String>| {
+        let value = value.filter(|value| parse_open_price(value).is_some());
+        button(text(label).size(36).width(Fill).align_x(Center))
+            .width(Fixed(120.))
+            .padding([10, 20])
+            .style(button::secondary)
+            .on_press_maybe(value.map(Message::SetOpenPrice))
+    };
+
+    let digit = |label: &'static str| key(label, Some(format!("{input}{label}")));
+
+    let mut backspace = input.chars();
+    backspace.next_back();
+
+    let numpad = column![
+        row![digit("7"), digit("8"), digit("9")].spacing(10),
+        row![digit("4"), digit("5"), digit("6")].spacing(10),
+        row![digit("1"), digit("2"), digit("3")].spacing(10),
+        row![
+            key(",", (!input.contains(',')).then(|| format!("{input},"))),
+            digit("0"),
+            key("⌫", Some(backspace.as_str().to_string())),
+        ]
+        .spacing(10),
+    ]
+    .spacing(10);
+
+    let valid = parse_open_price(input).is_some_and(|price| !price.is_zero());
+
+    let cancel_button = button(
+        text("Abbruch")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::danger)
+    .padding([10, 20])
+    .on_press(Message::CancelOpenPrice);
+
+    let confirm_button = button(
+        text("Hinzufügen")
+            .color(color!(0xffffff))
+            .size(36)
+            .align_x(Center),
+    )
+    .width(Fill)
+    .style(button::success)
+    .padding([10, 20])
+    .on_press_maybe(valid.then_some(Message::ConfirmOpenPrice));
+
+    column![
+        title,
+        display,
+        container(numpad).width(Fill).height(Fill).align_x(Center),
+        row![cancel_button, confirm_button].spacing(10),
+    ]
+    .spacing(10)
+    .padding([20, 30])
+    .into()
+}
+
+fn calendar_view(events: &[calendar::Event]) -> Element<'_, Message> {
+    let events = events.iter().map(|event| {
+        row![
+            text(event.format_start())
+                .size(24)
+                .color(color!(0x888888))
+                .width(Fixed(200.))
+                .wrapping(Wrapping::None),
+            text(&event.summary).size(24).width(Fill),
+        ]
+        .spacing(20)
+        .into()
+    });
+
+    column![text("Nächste Termine").size(24).color(color!(0xffee12))]
+        .extend(events)
+        .spacing(5)
+        .into()
+}
+
+fn announcement_view(announcement: &Announcement) -> Element<'_, Message> {
+    let content: Element<Message> = match announcement {
+        Announcement::Text(content) => text(content).size(36).align_x(Center).into(),
+        Announcement::Image(path) => image(path).content_fit(ContentFit::Contain).into(),
+    };
+
+    container(content)
+        .width(Fill)
+        .height(Fill)
+        .align_x(Center)
+        .align_y(Center)
+        .into()
+}
+
+/// Render the cart, highlighting the row of the given article.
+fn items<'a>(items: &'a [Sale], highlighted: Option<&(String, Instant)>) -> Element<'a, Message> {
+    let highlighted = highlighted.map(|(article_id, _)| article_id);
+    let rows = items.iter().map(|sale| {
+        let is_highlighted = !sale.open_price && highlighted == Some(&sale.article.id);
+        sale_row(sale, is_highlighted)
+    });
+    column(rows).spacing(10).into()
 }
 
-fn sale_row(sale: &Sale) -> Element<'_, Message> {
+fn sale_row(sale: &Sale, highlighted: bool) -> Element<'_, Message> {
     const AMOUNT_WIDTH: Length = Fixed(40.);
     const PRICE_WIDTH: Length = Fixed(80.);
 
@@ -189,8 +1014,8 @@ fn sale_row(sale: &Sale) -> Element<'_, Message> {
 
     let article_name = text(&sale.article.designation).size(24).width(Fill);
 
-    let unit_price = sale.article.current_price().unwrap_or_default();
-    let unit_price = text(format!("{unit_price:.2}€"))
+    let unit_price = sale.unit_price;
+    let unit_price = text(currency::format(unit_price))
         .width(PRICE_WIDTH)
         .size(24)
         .color(color!(0x888888))
@@ -198,13 +1023,15 @@ fn sale_row(sale: &Sale) -> Element<'_, Message> {
         .wrapping(Wrapping::None);
 
     let total_price = sale.total();
-    let total_price = text(format!("{total_price:.2}€"))
+    let total_price = text(currency::format(total_price))
         .width(PRICE_WIDTH)
         .size(24)
         .align_x(Right)
         .wrapping(Wrapping::None);
 
-    row![amount, article_name, unit_price, total_price]
-        .spacing(20)
-        .into()
+    let row = row![amount, article_name, unit_price, total_price].spacing(20);
+    match highlighted {
+        true => container(row).style(container::rounded_box).into(),
+        false => row.into(),
+    }
 }
@@ -0,0 +1,460 @@
+use crate::paths;
+use anyhow::Context;
+use self_update::update::ReleaseUpdate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::str::FromStr;
+use tracing::info;
+
+/// The source from which the application updates itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum UpdateBackend {
+    /// The releases of a GitHub repository.
+    #[default]
+    Github,
+    /// The releases of a repository on a Gitea (or Forgejo) server.
+    Gitea,
+    /// The release archives in an S3 bucket.
+    S3,
+    /// A JSON release index on any HTTPS server.
+    Index,
+}
+
+/// The repository whose releases are used for application updates.
+///
+/// This is parsed from `<owner>/<repo>`, e.g. `Turbo87/clubfridge-neo`.
+#[derive(Debug, Default, Clone)]
+pub struct UpdateRepo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl FromStr for UpdateRepo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((owner, name)) = s.split_once('/') else {
+            anyhow::bail!("Expected `<owner>/<repo>`");
+        };
+        if owner.is_empty() || name.is_empty() || name.contains('/') {
+            anyhow::bail!("Expected `<owner>/<repo>`");
+        }
+
+        Ok(Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Where and how to look for application updates.
+#[derive(Debug, Clone)]
+pub struct UpdateConfig {
+    pub backend: UpdateBackend,
+    pub repo: UpdateRepo,
+    pub bin_name: String,
+    /// The base URL of the Gitea server.
+    pub host: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    /// The key prefix of the release archives in the S3 bucket.
+    pub s3_prefix: Option<String>,
+    /// The URL of the JSON release index.
+    pub index_url: Option<String>,
+    /// The version that is installed instead of the latest version.
+    pub pin_version: Option<String>,
+    /// The versions that are not installed.
+    pub skip_versions: Vec<String>,
+}
+
+impl UpdateConfig {
+    /// The tag of the release of `version`. GitHub and Gitea releases are
+    /// tagged with a `v` prefix, while the S3 archives contain the plain
+    /// version in their file names.
+    fn release_tag(&self, version: &str) -> String {
+        match self.backend {
+            UpdateBackend::S3 => version.to_string(),
+            _ => format!("v{version}"),
+        }
+    }
+}
+
+/// The JSON release index of the `index` backend, e.g.:
+///
+/// ```json
+/// {
+///   "version": "1.2.3",
+///   "assets": {
+///     "aarch64-unknown-linux-gnu": "https://example.com/clubfridge-neo-1.2.3-aarch64"
+///   }
+/// }
+/// ```
+///
+/// The assets point to the uncompressed binaries for each target. An
+/// optional `notes` field contains the release notes of the version.
+#[derive(Debug, Deserialize)]
+struct ReleaseIndex {
+    version: String,
+    assets: HashMap<String, String>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+impl ReleaseIndex {
+    fn asset_url(&self, target: &str) -> anyhow::Result<&str> {
+        self.assets
+            .get(target)
+            .map(String::as_str)
+            .with_context(|| format!("No release asset for {target} in the update index"))
+    }
+}
+
+/// The result of a check for application updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateStatus {
+    /// No (other) version needs to be installed.
+    UpToDate,
+    /// The application was updated to the given version.
+    Updated(String),
+    /// A newer version is available, but was not installed because another
+    /// version is pinned.
+    Available(String),
+}
+
+/// The version that should be installed, given the `latest` available
+/// version and the pinned and skipped versions.
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    UpToDate,
+    Install(String),
+    Available(String),
+}
+
+fn decide(config: &UpdateConfig, current_version: &str, latest: &str) -> anyhow::Result<Decision> {
+    let is_newer = self_update::version::bump_is_greater(current_version, latest)?;
+
+    if let Some(pinned) = &config.pin_version {
+        let decision = if pinned != current_version {
+            Decision::Install(pinned.clone())
+        } else if is_newer {
+            Decision::Available(latest.to_string())
+        } else {
+            Decision::UpToDate
+        };
+        return Ok(decision);
+    }
+
+    if !is_newer {
+        return Ok(Decision::UpToDate);
+    }
+
+    if config.skip_versions.iter().any(|skipped| skipped == latest) {
+        info!("Skipping version {latest}");
+        return Ok(Decision::UpToDate);
+    }
+
+    Ok(Decision::Install(latest.to_string()))
+}
+
+/// Check for a newer release and replace the current executable with it,
+/// unless another version is pinned or the release is skipped.
+///
+/// `self_updated` is the version that the application was already updated
+/// to, since the running process still reports its original version.
+pub async fn run(
+    config: UpdateConfig,
+    self_updated: Option<String>,
+) -> anyhow::Result<UpdateStatus> {
+    let current_version = self_updated.unwrap_or(env!("CARGO_PKG_VERSION").to_string());
+
+    if config.backend == UpdateBackend::Index {
+        return update_from_index(&config, &current_version).await;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        info!("Checking for updates ({:?})…", config.backend);
+
+        let latest = updater(&config, &current_version, None)?.get_latest_release()?;
+        let version = match decide(&config, &current_version, &latest.version)? {
+            Decision::UpToDate => return Ok(UpdateStatus::UpToDate),
+            Decision::Available(version) => return Ok(UpdateStatus::Available(version)),
+            Decision::Install(version) => version,
+        };
+
+        let tag = config.release_tag(&version);
+        match updater(&config, &current_version, Some(&tag))?.update()? {
+            self_update::Status::Updated(version) => Ok(UpdateStatus::Updated(version)),
+            self_update::Status::UpToDate(_) => Ok(UpdateStatus::UpToDate),
+        }
+    })
+    .await?
+}
+
+/// Create the `self_update` updater of the GitHub, Gitea or S3 backend,
+/// which installs the release with the `target` tag, or the latest release.
+fn updater(
+    config: &UpdateConfig,
+    current_version: &str,
+    target: Option<&str>,
+) -> anyhow::Result<Box<dyn ReleaseUpdate>> {
+    let updater = match config.backend {
+        UpdateBackend::Github => {
+            let mut builder = self_update::backends::github::Update::configure();
+            builder
+                .repo_owner(&config.repo.owner)
+                .repo_name(&config.repo.name)
+                .bin_name(&config.bin_name)
+                .current_version(current_version)
+                .show_output(false)
+                .no_confirm(true);
+            if let Some(target) = target {
+                builder.target_version_tag(target);
+            }
+            builder.build()?
+        }
+        UpdateBackend::Gitea => {
+            let host = config.host.as_deref().context("Missing Gitea host")?;
+            let mut builder = self_update::backends::gitea::Update::configure();
+            builder
+                .with_host(host)
+                .repo_owner(&config.repo.owner)
+                .repo_name(&config.repo.name)
+                .bin_name(&config.bin_name)
+                .current_version(current_version)
+                .show_output(false)
+                .no_confirm(true);
+            if let Some(target) = target {
+                builder.target_version_tag(target);
+            }
+            builder.build()?
+        }
+        UpdateBackend::S3 => {
+            let bucket = config.s3_bucket.as_deref().context("Missing S3 bucket")?;
+            let prefix = config.s3_prefix.as_deref().unwrap_or_default();
+            let mut builder = self_update::backends::s3::Update::configure();
+            builder
+                .bucket_name(bucket)
+                .region(&config.s3_region)
+                .asset_prefix(prefix)
+                .bin_name(&config.bin_name)
+                .current_version(current_version)
+                .show_output(false)
+                .no_confirm(true);
+            if let Some(target) = target {
+                builder.target_version_tag(target);
+            }
+            builder.build()?
+        }
+        UpdateBackend::Index => anyhow::bail!("The update index has no `self_update` backend"),
+    };
+
+    Ok(updater)
+}
+
+/// The release notes of a version that are shown after an update.
+#[derive(Debug, Clone)]
+pub struct ReleaseNotes {
+    pub version: String,
+    pub notes: String,
+}
+
+/// Remember that the application was updated to `version`, so that its
+/// release notes are shown on the next start.
+pub async fn remember_update(version: &str) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(paths::data_dir()).await?;
+    tokio::fs::write(paths::release_notes_marker(), version).await
+}
+
+/// Load the release notes of the running version, if the application was
+/// updated to it since the last start.
+///
+/// The marker file is only removed once the release notes were loaded, so
+/// that they are loaded again on the next start if e.g. the network is not
+/// available yet.
+pub async fn pending_release_notes(config: UpdateConfig) -> anyhow::Result<Option<ReleaseNotes>> {
+    let marker = paths::release_notes_marker();
+    let version = match tokio::fs::read_to_string(&marker).await {
+        Ok(version) => version.trim().to_string(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    // The update might not have been applied, e.g. if the application was
+    // restarted from a different binary
+    if version != env!("CARGO_PKG_VERSION") {
+        tokio::fs::remove_file(&marker).await?;
+        return Ok(None);
+    }
+
+    info!("Loading release notes of version {version}…");
+    let notes = match config.backend {
+        UpdateBackend::Index => {
+            let url = config.index_url.context("Missing update index URL")?;
+            let index = load_index(&url).await?;
+            index.notes.filter(|_| index.version == version)
+        }
+        _ => {
+            let version = version.clone();
+            tokio::task::spawn_blocking(move || {
+                let tag = config.release_tag(&version);
+                let release = updater(&config, &version, None)?.get_release_version(&tag)?;
+                anyhow::Ok(release.body)
+            })
+            .await??
+        }
+    };
+
+    tokio::fs::remove_file(&marker).await?;
+
+    let notes = notes.filter(|notes| !notes.trim().is_empty());
+    Ok(notes.map(|notes| ReleaseNotes { version, notes }))
+}
+
+async fn load_index(url: &str) -> anyhow::Result<ReleaseIndex> {
+    reqwest::get(url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to read update index")
+}
+
+/// Download the binary for the current target from the release index, if
+/// the index lists a newer version.
+async fn update_from_index(
+    config: &UpdateConfig,
+    current_version: &str,
+) -> anyhow::Result<UpdateStatus> {
+    let url = config
+        .index_url
+        .as_deref()
+        .context("Missing update index URL")?;
+    info!("Checking for updates from {url}…");
+
+    let index = load_index(url).await?;
+
+    match decide(config, current_version, &index.version)? {
+        Decision::UpToDate => return Ok(UpdateStatus::UpToDate),
+        Decision::Available(version) => return Ok(UpdateStatus::Available(version)),
+        Decision::Install(version) if version != index.version => {
+            anyhow::bail!("Version {version} is not in the update index");
+        }
+        Decision::Install(_) => {}
+    }
+
+    let asset_url = index.asset_url(self_update::get_target())?;
+    info!("Downloading version {} from {asset_url}…", index.version);
+
+    let binary = reqwest::get(asset_url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .context("Failed to download update")?;
+
+    // Write the new binary next to the current one, so that it can be
+    // atomically renamed over it.
+    let exe = std::env::current_exe()?;
+    let new_exe = exe.with_extension("new");
+    tokio::fs::write(&new_exe, &binary).await?;
+    tokio::fs::set_permissions(&new_exe, std::fs::Permissions::from_mode(0o755)).await?;
+    tokio::fs::rename(&new_exe, &exe).await?;
+
+    Ok(UpdateStatus::Updated(index.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_repo() {
+        let repo: UpdateRepo = "my-club/clubfridge-neo".parse().unwrap();
+        assert_eq!(repo.owner, "my-club");
+        assert_eq!(repo.name, "clubfridge-neo");
+
+        assert!("clubfridge-neo".parse::<UpdateRepo>().is_err());
+        assert!("/clubfridge-neo".parse::<UpdateRepo>().is_err());
+        assert!("my-club/".parse::<UpdateRepo>().is_err());
+        assert!("a/b/c".parse::<UpdateRepo>().is_err());
+    }
+
+    fn update_config(pin_version: Option<&str>, skip_versions: &[&str]) -> UpdateConfig {
+        UpdateConfig {
+            backend: UpdateBackend::Github,
+            repo: "Turbo87/clubfridge-neo".parse().unwrap(),
+            bin_name: "clubfridge-neo".to_string(),
+            host: None,
+            s3_bucket: None,
+            s3_region: "eu-central-1".to_string(),
+            s3_prefix: None,
+            index_url: None,
+            pin_version: pin_version.map(str::to_string),
+            skip_versions: skip_versions.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let install = |version: &str| Decision::Install(version.to_string());
+        let available = |version: &str| Decision::Available(version.to_string());
+
+        let config = update_config(None, &[]);
+        assert_eq!(
+            decide(&config, "0.11.0", "0.11.0").unwrap(),
+            Decision::UpToDate
+        );
+        assert_eq!(
+            decide(&config, "0.11.0", "0.12.0").unwrap(),
+            install("0.12.0")
+        );
+
+        let config = update_config(None, &["0.12.0"]);
+        assert_eq!(
+            decide(&config, "0.11.0", "0.12.0").unwrap(),
+            Decision::UpToDate
+        );
+        assert_eq!(
+            decide(&config, "0.11.0", "0.12.1").unwrap(),
+            install("0.12.1")
+        );
+
+        let config = update_config(Some("0.11.0"), &[]);
+        assert_eq!(
+            decide(&config, "0.11.0", "0.11.0").unwrap(),
+            Decision::UpToDate
+        );
+        assert_eq!(
+            decide(&config, "0.11.0", "0.12.0").unwrap(),
+            available("0.12.0")
+        );
+        assert_eq!(
+            decide(&config, "0.12.0", "0.12.0").unwrap(),
+            install("0.11.0")
+        );
+        assert_eq!(
+            decide(&config, "0.10.0", "0.12.0").unwrap(),
+            install("0.11.0")
+        );
+    }
+
+    #[test]
+    fn test_release_index() {
+        let json = r#"{
+            "version": "1.2.3",
+            "assets": {
+                "aarch64-unknown-linux-gnu": "https://example.com/clubfridge-neo-aarch64"
+            }
+        }"#;
+
+        let index: ReleaseIndex = serde_json::from_str(json).unwrap();
+        assert_eq!(index.version, "1.2.3");
+        assert_eq!(index.notes, None);
+
+        let url = index.asset_url("aarch64-unknown-linux-gnu").unwrap();
+        assert_eq!(url, "https://example.com/clubfridge-neo-aarch64");
+
+        assert!(index.asset_url("x86_64-unknown-linux-gnu").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::database;
+use crate::logging::VF_DEBUG_TARGET;
+use anyhow::Context;
+use serde_json::Value;
+use tracing::debug;
+
+/// The base URL of the Vereinsflieger REST API.
+const BASE_URL: &str = "https://www.vereinsflieger.de/interface/rest";
+
+/// Checks whether uploaded sales were actually booked in Vereinsflieger.
+///
+/// The `vereinsflieger` client has no endpoint for listing sales, so this
+/// signs in with the same credentials and queries the sales directly.
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    http: reqwest::Client,
+    credentials: database::Credentials,
+}
+
+impl Verifier {
+    pub fn new(credentials: database::Credentials) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            credentials,
+        }
+    }
+
+    /// Sign in and return a session that can query the booked sales.
+    pub async fn sign_in(&self) -> anyhow::Result<Session<'_>> {
+        let access_token = vereinsflieger::get_access_token(&self.http).await?;
+
+        let credentials = self.credentials.clone().into();
+        vereinsflieger::authenticate(&self.http, &access_token, &credentials)
+            .await
+            .context("Failed to sign in")?;
+
+        Ok(Session {
+            verifier: self,
+            access_token,
+        })
+    }
+}
+
+/// A signed-in session of the [`Verifier`].
+pub struct Session<'a> {
+    verifier: &'a Verifier,
+    access_token: String,
+}
+
+impl Session<'_> {
+    /// Load the comments of all sales that were booked on the given date.
+    pub async fn booked_comments(&self, date: jiff::civil::Date) -> anyhow::Result<Vec<String>> {
+        let date = date.to_string();
+        let form = [
+            ("accesstoken", self.access_token.as_str()),
+            ("datefrom", date.as_str()),
+            ("dateto", date.as_str()),
+        ];
+
+        let url = format!("{BASE_URL}/sale/list");
+        let response = self.verifier.http.post(url).form(&form).send().await?;
+        let response: Value = response.error_for_status()?.json().await?;
+        debug!(target: VF_DEBUG_TARGET, %date, ?response, "list_sales");
+
+        Ok(comments(&response))
+    }
+}
+
+/// Extract the comments of the sales in a list response, which is an object
+/// with numeric keys and an additional status code.
+fn comments(response: &Value) -> Vec<String> {
+    let Some(object) = response.as_object() else {
+        return Vec::new();
+    };
+
+    object
+        .values()
+        .filter_map(|sale| sale.get("comment")?.as_str())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Check whether one of the comments belongs to the given sale.
+pub fn is_booked(comments: &[String], sale: &database::Sale) -> bool {
+    let sale_id = (*sale.id).to_string();
+    comments.iter().any(|comment| comment.contains(&sale_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_comments() {
+        let response = json!({
+            "0": { "articleid": "40822938", "comment": "clubfridge-neo 01ABC" },
+            "1": { "articleid": "40822938", "comment": "" },
+            "2": { "articleid": "40822938" },
+            "httpstatuscode": 200,
+        });
+
+        assert_eq!(comments(&response), ["clubfridge-neo 01ABC", ""]);
+        assert!(comments(&json!([])).is_empty());
+    }
+}